@@ -0,0 +1,32 @@
+//! Build script, which generates the C header of the `ffi` feature.
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "ffi")]
+    generate_header();
+}
+
+/// Generates `include/cccompress.h` from the functions of the `ffi` module.
+#[cfg(feature = "ffi")]
+fn generate_header() {
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR is not set");
+    let mut config = cbindgen::Config {
+        language: cbindgen::Language::C,
+        include_guard: Some(String::from("CCCOMPRESS_H")),
+        cpp_compat: true,
+        documentation: true,
+        usize_is_size_t: true,
+        ..Default::default()
+    };
+    // Only the interface, not the constants and types of the other modules.
+    config.export.item_types = vec![cbindgen::ItemType::Enums, cbindgen::ItemType::Functions];
+    config.enumeration.rename_variants = cbindgen::RenameRule::ScreamingSnakeCase;
+    config.enumeration.prefix_with_name = true;
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("generating the C header failed")
+        .write_to_file(format!("{crate_dir}/include/cccompress.h"));
+}
@@ -0,0 +1,926 @@
+//! Module contains the Huffman coding itself: the frequencies of all symbols, the Huffman tree
+//! built from them and the canonical prefix codes derived from the tree.
+//!
+//! Only the code length of every symbol determines a canonical code, so a compressed file just
+//! needs to store the code lengths to reconstruct the identical table for decoding.
+//!
+//! Building the tree and the table is deterministic: ties between equal frequencies are broken by
+//! the symbol values and the order, in which nodes were created, never by hashing, addresses or
+//! unstable sorting. So the same content always yields bit-identical output, on every run and
+//! platform, e.g. for reproducible builds or content-addressed storage.
+//!
+//! Symbols are bytes, not characters. Texts with umlauts, accents or CJK characters are coded by
+//! their UTF-8 bytes, so any content round-trips unchanged, whatever its encoding.
+
+use crate::{
+    bitstream::{BitStreamReader, BitStreamWriter},
+    io::{self, Read, Write},
+    CtError, Result,
+};
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt;
+
+/// Number of different symbols, one per byte value.
+pub const SYMBOLS: usize = 256;
+
+/// Maximum length of a code in bits, since codes are held in an `u64`.
+pub const MAX_CODE_LENGTH: u8 = 63;
+
+/// Frequencies of all symbols of a content.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CharSpectrum {
+    frequencies: [u64; SYMBOLS],
+}
+
+impl Default for CharSpectrum {
+    fn default() -> CharSpectrum {
+        CharSpectrum {
+            frequencies: [0; SYMBOLS],
+        }
+    }
+}
+
+impl CharSpectrum {
+    /// Spectrum of the given frequencies of all symbols, e.g. of another content.
+    pub fn from_frequencies(frequencies: [u64; SYMBOLS]) -> CharSpectrum {
+        CharSpectrum { frequencies }
+    }
+
+    /// Counts the frequencies of all symbols of the content.
+    pub fn from_bytes(content: &[u8]) -> CharSpectrum {
+        let mut spectrum = CharSpectrum::default();
+        spectrum.add(content);
+        spectrum
+    }
+
+    /// Counts the frequencies of all symbols of a reader, without holding its whole content.
+    pub fn from_reader(mut reader: impl Read) -> io::Result<CharSpectrum> {
+        let mut spectrum = CharSpectrum::default();
+        let mut buffer = vec![0; 1 << 16];
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => return Ok(spectrum),
+                Ok(n) => spectrum.add(&buffer[..n]),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Adds the symbols of another piece of content.
+    pub fn add(&mut self, content: &[u8]) {
+        content
+            .iter()
+            .for_each(|b| self.frequencies[*b as usize] += 1);
+    }
+
+    /// Frequency of the given symbol.
+    pub fn frequency(&self, symbol: u8) -> u64 {
+        self.frequencies[symbol as usize]
+    }
+
+    /// Number of all symbols, i.e. the length of the content.
+    pub fn total(&self) -> u64 {
+        self.frequencies.iter().sum()
+    }
+
+    /// All symbols, which occur at least once, with their frequencies. They are sorted ascending
+    /// by frequency, symbols of equal frequency ascending by their value. Every symbol occurs once,
+    /// so this order is total and does not depend on the sorting algorithm.
+    pub fn sort(&self) -> Vec<(u8, u64)> {
+        let mut symbols: Vec<(u8, u64)> = (0..=255u8)
+            .map(|s| (s, self.frequency(s)))
+            .filter(|(_, f)| *f > 0)
+            .collect();
+        symbols.sort_by_key(|(s, f)| (*f, *s));
+        symbols
+    }
+}
+
+/// One node of the Huffman tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CtTreeNode {
+    /// A symbol and its frequency.
+    Leaf { symbol: u8, weight: u64 },
+    /// Two subtrees and the sum of their frequencies.
+    Inner {
+        weight: u64,
+        left: Box<CtTreeNode>,
+        right: Box<CtTreeNode>,
+    },
+}
+
+impl CtTreeNode {
+    /// Sum of the frequencies of all symbols of this (sub-)tree.
+    pub fn weight(&self) -> u64 {
+        match self {
+            CtTreeNode::Leaf { weight, .. } | CtTreeNode::Inner { weight, .. } => *weight,
+        }
+    }
+
+    /// Position, at which this node has to be inserted into nodes sorted ascending by weight.
+    /// The node is placed behind all nodes of equal weight, so of equal weights, leaves and older
+    /// subtrees get merged first. Together with `CharSpectrum::sort()`, the tree only depends on
+    /// the frequencies.
+    pub fn find_position_in(&self, nodes: &[CtTreeNode]) -> usize {
+        nodes.partition_point(|node| node.weight() <= self.weight())
+    }
+
+    /// Appends the DOT statements of this subtree, its node gets the given id. Returns the next
+    /// free id.
+    fn write_dot(&self, id: usize, dot: &mut String) -> usize {
+        match self {
+            CtTreeNode::Leaf { symbol, weight } => {
+                let symbol = match *symbol {
+                    b'"' => String::from("\\\""),
+                    b'\\' => String::from("\\\\"),
+                    s if s.is_ascii_graphic() => (s as char).to_string(),
+                    s => format!("0x{s:02x}"),
+                };
+                dot.push_str(&format!(
+                    "  n{id} [shape=box, label=\"{symbol}\\n{weight}\"];\n"
+                ));
+                id + 1
+            }
+            CtTreeNode::Inner {
+                weight,
+                left,
+                right,
+            } => {
+                dot.push_str(&format!("  n{id} [label=\"{weight}\"];\n"));
+                let left_id = id + 1;
+                let right_id = left.write_dot(left_id, dot);
+                dot.push_str(&format!("  n{id} -> n{left_id} [label=\"0\"];\n"));
+                dot.push_str(&format!("  n{id} -> n{right_id} [label=\"1\"];\n"));
+                right.write_dot(right_id, dot)
+            }
+        }
+    }
+
+    /// Collects the code lengths of all leafs of this subtree in the given depth.
+    fn code_lengths(&self, depth: usize, lengths: &mut [usize; SYMBOLS]) {
+        match self {
+            CtTreeNode::Leaf { symbol, .. } => lengths[*symbol as usize] = depth,
+            CtTreeNode::Inner { left, right, .. } => {
+                left.code_lengths(depth + 1, lengths);
+                right.code_lengths(depth + 1, lengths);
+            }
+        }
+    }
+}
+
+/// Huffman tree, in which frequent symbols are close to the root.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CtBinaryTree {
+    root: CtTreeNode,
+}
+
+impl CtBinaryTree {
+    /// Code length of every symbol, zero for symbols not contained in the tree. A tree of a
+    /// single symbol still needs one bit per symbol.
+    pub fn code_lengths(&self) -> [u8; SYMBOLS] {
+        let mut depths = [0; SYMBOLS];
+        self.root.code_lengths(0, &mut depths);
+        if let CtTreeNode::Leaf { symbol, .. } = self.root {
+            depths[symbol as usize] = 1;
+        }
+        depths.map(|d| d as u8)
+    }
+
+    /// The tree in the DOT language of Graphviz, e.g. to render it by `dot -Tsvg`. Inner nodes
+    /// show the sum of their frequencies, leafs their symbol and frequency, edges the bit of the
+    /// code. The canonical codes of `PrefixCodeTable` have the same lengths, but may differ.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph huffman {\n");
+        self.root.write_dot(0, &mut dot);
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl TryFrom<&CharSpectrum> for CtBinaryTree {
+    type Error = CtError;
+
+    fn try_from(spectrum: &CharSpectrum) -> Result<CtBinaryTree> {
+        let mut nodes: Vec<CtTreeNode> = spectrum
+            .sort()
+            .into_iter()
+            .map(|(symbol, weight)| CtTreeNode::Leaf { symbol, weight })
+            .collect();
+        if nodes.is_empty() {
+            return Err("can not build a Huffman tree without any symbols".into());
+        }
+        while nodes.len() > 1 {
+            let left = nodes.remove(0);
+            let right = nodes.remove(0);
+            let node = CtTreeNode::Inner {
+                weight: left.weight() + right.weight(),
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+            let position = node.find_position_in(&nodes);
+            nodes.insert(position, node);
+        }
+
+        let tree = CtBinaryTree {
+            root: nodes.remove(0),
+        };
+        if tree.code_lengths().iter().any(|l| *l > MAX_CODE_LENGTH) {
+            return Err(format!("Huffman codes exceed {MAX_CODE_LENGTH} bits").into());
+        }
+        Ok(tree)
+    }
+}
+
+/// Optimal code lengths of all symbols of the spectrum, of which none exceeds `max_length` bits,
+/// found by the package-merge algorithm. Without the limit, Huffman codes of very skewed
+/// frequencies can get longer than fixed-width decode tables or other formats allow, e.g. DEFLATE
+/// limits them to 15 bits.
+///
+/// Every symbol is a coin of its frequency in each denomination 2^-1..2^-max_length. Starting at
+/// the smallest denomination, pairs of the cheapest coins get packaged and merged with the coins of
+/// the next larger one. The cheapest 2n-2 items of the largest denomination have a total value of
+/// n-1, the number of items containing a symbol is its code length.
+pub fn limited_code_lengths(spectrum: &CharSpectrum, max_length: u8) -> Result<[u8; SYMBOLS]> {
+    let symbols = spectrum.sort();
+    let n = symbols.len();
+    if n == 0 {
+        return Err("can not build prefix codes without any symbols".into());
+    }
+    if max_length == 0 || max_length > MAX_CODE_LENGTH || (n - 1) >> max_length > 0 {
+        return Err(format!("{n} symbols can not be coded with at most {max_length} bits").into());
+    }
+    let mut lengths = [0; SYMBOLS];
+    if n == 1 {
+        lengths[symbols[0].0 as usize] = 1;
+        return Ok(lengths);
+    }
+
+    // Items of a denomination as their weight and their symbols.
+    let coins: Vec<(u64, Vec<u8>)> = symbols.iter().map(|(s, w)| (*w, vec![*s])).collect();
+    let mut items = coins.clone();
+    for _ in 1..max_length {
+        let packages = items
+            .chunks_exact(2)
+            .map(|pair| (pair[0].0 + pair[1].0, [&pair[0].1[..], &pair[1].1].concat()));
+        items = coins.iter().cloned().chain(packages).collect();
+        // The sort is stable: of equal weights, coins stay in front of packages, both in their
+        // order, so ties are always broken the same way.
+        items.sort_by_key(|(weight, _)| *weight);
+    }
+    for (_, item_symbols) in &items[..2 * n - 2] {
+        item_symbols.iter().for_each(|s| lengths[*s as usize] += 1);
+    }
+    Ok(lengths)
+}
+
+/// Canonical prefix codes of all symbols. Codes are assigned in order of their lengths, codes of
+/// equal length in order of their symbols, so the code lengths define the table completely.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrefixCodeTable {
+    /// Code length of every symbol, zero for unused symbols.
+    lengths: [u8; SYMBOLS],
+    /// Code of every symbol, in the lowest `length` bits.
+    codes: [u64; SYMBOLS],
+}
+
+impl PrefixCodeTable {
+    /// Assigns the canonical codes for the given code lengths, which have to form a valid prefix
+    /// code.
+    pub fn from_lengths(lengths: [u8; SYMBOLS]) -> Result<PrefixCodeTable> {
+        let mut codes = [0; SYMBOLS];
+        let mut code: u64 = 0;
+        let mut previous = 0;
+        for (symbol, length) in canonical_order(&lengths) {
+            if length > MAX_CODE_LENGTH {
+                return Err(format!("code length {length} exceeds {MAX_CODE_LENGTH} bits").into());
+            }
+            code <<= length - previous;
+            if code >= 1 << length {
+                return Err("code lengths do not form a prefix code".into());
+            }
+            codes[symbol as usize] = code;
+            code += 1;
+            previous = length;
+        }
+        Ok(PrefixCodeTable { lengths, codes })
+    }
+
+    /// Codes of the spectrum, of which none exceeds `max_length` bits. With `MAX_CODE_LENGTH` they
+    /// are derived from the Huffman tree, otherwise from `limited_code_lengths()`. An empty
+    /// content gets the empty table.
+    pub fn build(spectrum: &CharSpectrum, max_length: u8) -> Result<PrefixCodeTable> {
+        if spectrum.total() == 0 {
+            return PrefixCodeTable::from_lengths([0; SYMBOLS]);
+        }
+        if max_length >= MAX_CODE_LENGTH {
+            return Ok(PrefixCodeTable::from(&CtBinaryTree::try_from(spectrum)?));
+        }
+        PrefixCodeTable::from_lengths(limited_code_lengths(spectrum, max_length)?)
+    }
+
+    /// Code and its length of the given symbol, if it is contained in the table.
+    pub fn code(&self, symbol: u8) -> Option<(u64, u8)> {
+        let length = self.lengths[symbol as usize];
+        (length > 0).then_some((self.codes[symbol as usize], length))
+    }
+
+    /// Number of bits of a content with the given spectrum, once it is encoded by this table.
+    pub fn encoded_bits(&self, spectrum: &CharSpectrum) -> u64 {
+        (0..=255u8)
+            .map(|s| spectrum.frequency(s) * self.lengths[s as usize] as u64)
+            .sum()
+    }
+
+    /// Length of the longest code.
+    pub fn max_length(&self) -> u8 {
+        self.lengths.iter().copied().max().unwrap_or_default()
+    }
+
+    /// The table together with the frequencies of the symbols, for printing them.
+    pub fn with_spectrum<'a>(&'a self, spectrum: &'a CharSpectrum) -> CodeTableView<'a> {
+        CodeTableView {
+            table: self,
+            spectrum: Some(spectrum),
+        }
+    }
+
+    /// All codes as comma separated values, one line per symbol, see `CodeTableView::to_csv()`.
+    pub fn to_csv(&self) -> String {
+        CodeTableView {
+            table: self,
+            spectrum: None,
+        }
+        .to_csv()
+    }
+}
+
+/// Aligned table of all symbols in canonical order with their code and its length.
+impl fmt::Display for PrefixCodeTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        CodeTableView {
+            table: self,
+            spectrum: None,
+        }
+        .fmt(f)
+    }
+}
+
+/// Prefix code table, optionally with the frequencies of its symbols, for printing it, see
+/// `PrefixCodeTable::with_spectrum()`.
+pub struct CodeTableView<'a> {
+    table: &'a PrefixCodeTable,
+    spectrum: Option<&'a CharSpectrum>,
+}
+
+impl CodeTableView<'_> {
+    /// All symbols as comma separated values with a header line: the byte value of the symbol,
+    /// its frequency if known, its code as bits and the code length.
+    pub fn to_csv(&self) -> String {
+        let mut csv = match self.spectrum {
+            Some(_) => String::from("symbol,frequency,code,length\n"),
+            None => String::from("symbol,code,length\n"),
+        };
+        for (symbol, length) in canonical_order(&self.table.lengths) {
+            csv.push_str(&format!("{symbol},"));
+            if let Some(spectrum) = self.spectrum {
+                csv.push_str(&format!("{},", spectrum.frequency(symbol)));
+            }
+            csv.push_str(&format!("{},{length}\n", self.code_bits(symbol)));
+        }
+        csv
+    }
+
+    /// Code of the symbol as bits.
+    fn code_bits(&self, symbol: u8) -> String {
+        let (code, length) = self.table.code(symbol).unwrap_or_default();
+        format!("{code:0width$b}", width = length as usize)
+    }
+}
+
+impl fmt::Display for CodeTableView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = (self.table.max_length() as usize).max(4);
+        write!(f, "{:<6}", "symbol")?;
+        if self.spectrum.is_some() {
+            write!(f, " {:>12}", "frequency")?;
+        }
+        writeln!(f, " {:<width$} {:>6}", "code", "length")?;
+        for (symbol, length) in canonical_order(&self.table.lengths) {
+            write!(f, "{:<6}", symbol_name(symbol))?;
+            if let Some(spectrum) = self.spectrum {
+                write!(f, " {:>12}", spectrum.frequency(symbol))?;
+            }
+            writeln!(f, " {:<width$} {length:>6}", self.code_bits(symbol))?;
+        }
+        Ok(())
+    }
+}
+
+/// Printable name of a symbol, its character if it is a graphic ASCII one, its value otherwise.
+pub fn symbol_name(symbol: u8) -> String {
+    match symbol {
+        s if s.is_ascii_graphic() => format!("'{}'", s as char),
+        s => format!("0x{s:02x}"),
+    }
+}
+
+/// All used symbols with their code lengths, sorted by length and symbol.
+fn canonical_order(lengths: &[u8; SYMBOLS]) -> Vec<(u8, u8)> {
+    let mut symbols: Vec<(u8, u8)> = (0..=255u8)
+        .map(|s| (s, lengths[s as usize]))
+        .filter(|(_, l)| *l > 0)
+        .collect();
+    symbols.sort_by_key(|(s, l)| (*l, *s));
+    symbols
+}
+
+impl From<&CtBinaryTree> for PrefixCodeTable {
+    fn from(tree: &CtBinaryTree) -> PrefixCodeTable {
+        PrefixCodeTable::from_lengths(tree.code_lengths())
+            .expect("Huffman tree yields a valid prefix code")
+    }
+}
+
+/// **Byte Representation**
+///
+/// 0               (1) maximum code length L, 0 for an empty table
+/// 1..2L+1         (2) number of symbols per code length 1..=L, 2 bytes u16 each
+/// 2L+1..          (3) all symbols in canonical order, one byte each
+///
+/// The table of an empty content is empty, just (1) as 0, and its encoded data has no bytes. A
+/// content of a single distinct symbol gets the code `0` of 1 bit, so its encoded data has one bit
+/// per byte of the content.
+impl From<&PrefixCodeTable> for Vec<u8> {
+    fn from(table: &PrefixCodeTable) -> Vec<u8> {
+        let max_length = table.max_length();
+        let mut data = vec![max_length];
+
+        // (2)
+        for length in 1..=max_length {
+            let count = table.lengths.iter().filter(|l| **l == length).count() as u16;
+            data.extend_from_slice(&count.to_le_bytes());
+        }
+
+        // (3)
+        data.extend(canonical_order(&table.lengths).iter().map(|(s, _)| *s));
+        data
+    }
+}
+
+impl TryFrom<&[u8]> for PrefixCodeTable {
+    type Error = CtError;
+
+    fn try_from(data: &[u8]) -> Result<PrefixCodeTable> {
+        let broken = |offset| CtError::CorruptHeader {
+            field: "prefix code table",
+            offset,
+        };
+
+        // (1) & (2)
+        let max_length = *data.first().ok_or_else(|| broken(0))? as usize;
+        let counts = data
+            .get(1..2 * max_length + 1)
+            .ok_or_else(|| broken(data.len()))?;
+        let counts = counts
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]) as usize);
+
+        // (3)
+        let mut symbols = data[2 * max_length + 1..].iter();
+        let mut lengths = [0; SYMBOLS];
+        for (length, count) in counts.enumerate() {
+            for _ in 0..count {
+                let symbol = symbols.next().ok_or_else(|| broken(data.len()))?;
+                lengths[*symbol as usize] = length as u8 + 1;
+            }
+        }
+        if symbols.next().is_some() {
+            return Err(broken(data.len() - symbols.len() - 1));
+        }
+        PrefixCodeTable::from_lengths(lengths)
+    }
+}
+
+/// Encodes the content by the codes of the table. Returns the encoded bytes and the number of
+/// unused bits in the last byte.
+pub fn encode(table: &PrefixCodeTable, content: &[u8]) -> Result<(Vec<u8>, u8)> {
+    let mut encoder = Encoder::new(table, Vec::new());
+    encoder.encode(content)?;
+    encoder.finish()
+}
+
+/// Encoder of a content, which arrives piece by piece. The encoded bytes are written into the
+/// given writer, as soon as they are complete.
+#[derive(Debug)]
+pub struct Encoder<'t, W> {
+    table: &'t PrefixCodeTable,
+    writer: BitStreamWriter<W>,
+    /// Number of bits encoded so far.
+    bits: u64,
+}
+
+impl<'t, W: Write> Encoder<'t, W> {
+    /// New type pattern: generates a new encoder, which uses the codes of the table.
+    pub fn new(table: &'t PrefixCodeTable, writer: W) -> Encoder<'t, W> {
+        Encoder {
+            table,
+            writer: BitStreamWriter::new(writer),
+            bits: 0,
+        }
+    }
+
+    /// Encodes the next piece of the content.
+    pub fn encode(&mut self, content: &[u8]) -> Result<()> {
+        for symbol in content {
+            let (code, length) = self
+                .table
+                .code(*symbol)
+                .ok_or(CtError::SymbolNotInTable { symbol: *symbol })?;
+            if length > 32 {
+                self.writer.add_bits((code >> 32) as u32, length - 32)?;
+                self.writer.add_bits(code as u32, 32)?;
+            } else {
+                self.writer.add_bits(code as u32, length)?;
+            }
+            self.bits += length as u64;
+        }
+        Ok(())
+    }
+
+    /// Number of bits encoded so far.
+    pub fn bits(&self) -> u64 {
+        self.bits
+    }
+
+    /// Writes the last, incomplete byte. Returns the writer and the number of unused bits in the
+    /// last byte.
+    pub fn finish(self) -> Result<(W, u8)> {
+        Ok(self.writer.finish()?)
+    }
+}
+
+/// Decodes data, which has been encoded by `encode()` with the same table.
+pub fn decode(table: &PrefixCodeTable, data: &[u8], unused_bits: u8) -> Result<Vec<u8>> {
+    let mut decoder = Decoder::new(table);
+    let mut content = Vec::new();
+    decoder.decode(data, unused_bits, &mut content)?;
+    decoder.finish()?;
+    Ok(content)
+}
+
+/// Decoder of encoded data, which arrives piece by piece. Canonical codes are looked up per code
+/// length, without walking a tree.
+#[derive(Debug)]
+pub struct Decoder {
+    /// First code of every length, index 0 is unused.
+    first: Vec<u64>,
+    /// Number of codes of every length.
+    counts: Vec<usize>,
+    /// Index of the first symbol of every length in `symbols`.
+    offsets: Vec<usize>,
+    /// All symbols in canonical order.
+    symbols: Vec<u8>,
+    /// Bits of an incomplete code, which continues in the next piece.
+    code: u64,
+    /// Number of bits in `code`.
+    length: usize,
+    /// Number of bits decoded so far.
+    position: u64,
+}
+
+impl Decoder {
+    /// New type pattern: generates a new decoder for the codes of the table.
+    pub fn new(table: &PrefixCodeTable) -> Decoder {
+        let max_length = table.max_length() as usize;
+        let order = canonical_order(&table.lengths);
+        let mut counts = vec![0; max_length + 1];
+        order.iter().for_each(|(_, l)| counts[*l as usize] += 1);
+
+        let mut first = vec![0; max_length + 1];
+        let mut offsets = vec![0; max_length + 1];
+        let mut code: u64 = 0;
+        let mut offset = 0;
+        for length in 1..=max_length {
+            code = (code + counts[length - 1] as u64) << 1;
+            first[length] = code;
+            offsets[length] = offset;
+            offset += counts[length];
+        }
+        Decoder {
+            first,
+            counts,
+            offsets,
+            symbols: order.into_iter().map(|(s, _)| s).collect(),
+            code: 0,
+            length: 0,
+            position: 0,
+        }
+    }
+
+    /// Decodes the next piece of data and appends the symbols to `content`. Unused bits can only
+    /// occur in the last byte of the last piece.
+    pub fn decode(&mut self, data: &[u8], unused_bits: u8, content: &mut Vec<u8>) -> Result<()> {
+        let mut reader = BitStreamReader::new(data, unused_bits);
+        while let Some(bit) = reader.next_bit()? {
+            if let Some(symbol) = self.push_bit(bit)? {
+                content.push(symbol);
+            }
+        }
+        Ok(())
+    }
+
+    /// Decodes the next bit. Returns the symbol, once its code is complete.
+    pub fn push_bit(&mut self, bit: bool) -> Result<Option<u8>> {
+        self.code = self.code << 1 | bit as u64;
+        self.length += 1;
+        self.position += 1;
+        if let Some(symbol) = self.symbol(self.code, self.length) {
+            self.code = 0;
+            self.length = 0;
+            Ok(Some(symbol))
+        } else if self.length >= self.max_length() {
+            let offset = (self.position - 1) / 8;
+            Err(CtError::InvalidCode { offset })
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Checks that the data has not ended within a code.
+    pub fn finish(&self) -> Result<()> {
+        if self.length > 0 {
+            return Err("encoded data ends within a code".into());
+        }
+        Ok(())
+    }
+
+    /// Symbol of the given code, if it is complete.
+    fn symbol(&self, code: u64, length: usize) -> Option<u8> {
+        let index = code.checked_sub(*self.first.get(length)?)? as usize;
+        (index < self.counts[length]).then(|| self.symbols[self.offsets[length] + index])
+    }
+
+    fn max_length(&self) -> usize {
+        self.first.len() - 1
+    }
+}
+
+/// Iterator over the symbols of encoded data, which decodes one after another instead of the
+/// whole content at once. After an error, it ends.
+pub struct DecodeIter<'d> {
+    decoder: Decoder,
+    reader: BitStreamReader<&'d [u8]>,
+    done: bool,
+}
+
+impl<'d> DecodeIter<'d> {
+    /// New type pattern: generates a new iterator over the data, which has been encoded by
+    /// `encode()` with the same table.
+    pub fn new(table: &PrefixCodeTable, data: &'d [u8], unused_bits: u8) -> DecodeIter<'d> {
+        DecodeIter {
+            decoder: Decoder::new(table),
+            reader: BitStreamReader::new(data, unused_bits),
+            done: false,
+        }
+    }
+}
+
+impl Iterator for DecodeIter<'_> {
+    type Item = Result<u8>;
+
+    fn next(&mut self) -> Option<Result<u8>> {
+        while !self.done {
+            let symbol = match self.reader.next_bit() {
+                Ok(Some(bit)) => self.decoder.push_bit(bit).transpose(),
+                Ok(None) => {
+                    self.done = true;
+                    self.decoder.finish().err().map(Err)
+                }
+                Err(err) => Some(Err(err.into())),
+            };
+            if let Some(symbol) = symbol {
+                self.done |= symbol.is_err();
+                return Some(symbol);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_table(content: &[u8]) -> PrefixCodeTable {
+        let spectrum = CharSpectrum::from_bytes(content);
+        let tree = CtBinaryTree::try_from(&spectrum).expect("building tree failed");
+        PrefixCodeTable::from(&tree)
+    }
+
+    #[test]
+    fn spectrum() {
+        let spectrum = CharSpectrum::from_bytes(b"abracadabra");
+        assert_eq!(spectrum.frequency(b'a'), 5);
+        assert_eq!(
+            spectrum.sort(),
+            vec![(b'c', 1), (b'd', 1), (b'b', 2), (b'r', 2), (b'a', 5)]
+        );
+    }
+
+    #[test]
+    fn deterministic_construction() {
+        // All frequencies are equal, so every choice is a tie.
+        let tree = CtBinaryTree::try_from(&CharSpectrum::from_bytes(b"edcba")).unwrap();
+        let lengths = tree.code_lengths();
+        assert_eq!(lengths[b'a' as usize..=b'e' as usize], [3, 3, 2, 2, 2]);
+        let table = PrefixCodeTable::from(&tree);
+        assert_eq!(table.code(b'c'), Some((0b00, 2)));
+        assert_eq!(table.code(b'a'), Some((0b110, 3)));
+
+        let lengths = limited_code_lengths(&CharSpectrum::from_bytes(b"edcba"), 3).unwrap();
+        assert_eq!(lengths[b'a' as usize..=b'e' as usize], [3, 3, 2, 2, 2]);
+
+        // Permutations of the same content yield identical encoded data.
+        let content = b"mississippi river banks";
+        let mut reversed = content.to_vec();
+        reversed.reverse();
+        let table = build_table(content);
+        assert_eq!(
+            Vec::<u8>::from(&table),
+            Vec::<u8>::from(&build_table(&reversed))
+        );
+        assert_eq!(
+            encode(&table, content).unwrap(),
+            encode(&table, content).unwrap()
+        );
+    }
+
+    #[test]
+    fn dot_export() {
+        let spectrum = CharSpectrum::from_bytes(b"aab\"");
+        let tree = CtBinaryTree::try_from(&spectrum).unwrap();
+        let dot = tree.to_dot();
+        assert!(dot.starts_with("digraph huffman {\n  n0 [label=\"4\"];\n"));
+        assert!(dot.contains("[shape=box, label=\"a\\n2\"]"));
+        assert!(dot.contains("[shape=box, label=\"\\\"\\n1\"]"));
+        assert_eq!(dot.matches(" -> ").count(), 4);
+        assert!(dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn canonical_codes() {
+        let table = build_table(b"abracadabra");
+        assert_eq!(table.code(b'a'), Some((0b0, 1)));
+        assert_eq!(table.code(b'b'), Some((0b100, 3)));
+        assert_eq!(table.code(b'c'), Some((0b101, 3)));
+        assert_eq!(table.code(b'd'), Some((0b110, 3)));
+        assert_eq!(table.code(b'r'), Some((0b111, 3)));
+        assert_eq!(table.code(b'x'), None);
+
+        let mut lengths = [0; SYMBOLS];
+        lengths[..3].copy_from_slice(&[1, 1, 1]);
+        assert!(PrefixCodeTable::from_lengths(lengths).is_err());
+    }
+
+    #[test]
+    fn table_serialization() {
+        let table = build_table(b"abracadabra");
+        let data = Vec::<u8>::from(&table);
+        assert_eq!(
+            data,
+            vec![3, 1, 0, 0, 0, 4, 0, b'a', b'b', b'c', b'd', b'r']
+        );
+        assert_eq!(PrefixCodeTable::try_from(&data[..]).unwrap(), table);
+        let err = PrefixCodeTable::try_from(&data[..data.len() - 1]).unwrap_err();
+        assert!(matches!(err, CtError::CorruptHeader { offset: 11, .. }));
+    }
+
+    #[test]
+    fn non_ascii_content() {
+        let content = "Grüße aus Köln, ça va? naïve café, 日本語のテキスト".as_bytes();
+        let table = build_table(content);
+        let data = Vec::<u8>::from(&table);
+        assert!(data.iter().any(|b| *b > 0x7f));
+        let table = PrefixCodeTable::try_from(&data[..]).unwrap();
+        let (data, unused_bits) = encode(&table, content).unwrap();
+        assert_eq!(decode(&table, &data, unused_bits).unwrap(), content);
+    }
+
+    #[test]
+    fn decode_pieces() {
+        let table = build_table(b"abracadabra");
+        let (data, unused_bits) = encode(&table, b"abracadabra").unwrap();
+        let mut decoder = Decoder::new(&table);
+        let mut content = Vec::new();
+        for (i, byte) in data.iter().enumerate() {
+            let unused = if i + 1 == data.len() { unused_bits } else { 0 };
+            decoder.decode(&[*byte], unused, &mut content).unwrap();
+        }
+        decoder.finish().unwrap();
+        assert_eq!(content, b"abracadabra");
+
+        let mut decoder = Decoder::new(&table);
+        decoder.decode(&[0b1000_0000], 6, &mut content).unwrap();
+        assert!(decoder.finish().is_err());
+    }
+
+    #[test]
+    fn length_limited_codes() {
+        // Fibonacci frequencies make the Huffman tree as deep as possible.
+        let mut spectrum = CharSpectrum::default();
+        let (mut a, mut b) = (1, 1);
+        let mut content = Vec::new();
+        for symbol in 0..20u8 {
+            content.extend(std::iter::repeat_n(symbol, a));
+            (a, b) = (b, a + b);
+        }
+        spectrum.add(&content);
+        let huffman = build_table(&content);
+        assert_eq!(huffman.max_length(), 19);
+
+        for max_length in [5, 7, 15, 19, 30] {
+            let table = PrefixCodeTable::build(&spectrum, max_length).unwrap();
+            assert!(table.max_length() <= max_length);
+            let kraft: f64 = (0..20u8)
+                .map(|s| 0.5f64.powi(table.code(s).unwrap().1 as i32))
+                .sum();
+            assert_eq!(kraft, 1.0);
+            let (data, unused_bits) = encode(&table, &content).unwrap();
+            assert_eq!(decode(&table, &data, unused_bits).unwrap(), content);
+            if max_length >= 19 {
+                assert_eq!(
+                    table.encoded_bits(&spectrum),
+                    huffman.encoded_bits(&spectrum)
+                );
+            }
+        }
+        assert!(limited_code_lengths(&spectrum, 4).is_err());
+        assert!(limited_code_lengths(&CharSpectrum::default(), 8).is_err());
+        let single = limited_code_lengths(&CharSpectrum::from_bytes(b"aa"), 1).unwrap();
+        assert_eq!(single[b'a' as usize], 1);
+    }
+
+    #[test]
+    fn table_formatting() {
+        let spectrum = CharSpectrum::from_bytes(b"abracadabra\n");
+        let table = PrefixCodeTable::from(&CtBinaryTree::try_from(&spectrum).unwrap());
+        let text = table.with_spectrum(&spectrum).to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[0], "symbol    frequency code length");
+        assert_eq!(lines[1], "'a'               5 0         1");
+        assert!(lines.contains(&"0x0a              1 1110      4"));
+        assert!(table
+            .to_string()
+            .starts_with("symbol code length\n'a'    0         1\n"));
+
+        let csv = table.with_spectrum(&spectrum).to_csv();
+        assert!(csv.starts_with("symbol,frequency,code,length\n97,5,0,1\n"));
+        assert!(table.to_csv().contains("\n10,1110,4\n"));
+    }
+
+    #[test]
+    fn decode_lazily() {
+        let table = build_table(b"abracadabra");
+        let (data, unused_bits) = encode(&table, b"abracadabra").unwrap();
+        let content: Result<Vec<u8>> = DecodeIter::new(&table, &data, unused_bits).collect();
+        assert_eq!(content.unwrap(), b"abracadabra");
+        let mut iter = DecodeIter::new(&table, &data, unused_bits);
+        assert_eq!(iter.position(|s| s.unwrap() == b'c'), Some(4));
+
+        let mut iter = DecodeIter::new(&table, &[0b1000_0000], 6);
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn encode_decode() {
+        let content = std::fs::read("../135-0.txt").unwrap();
+        let table = build_table(&content);
+        assert!(Vec::<u8>::from(&table).len() < 256);
+
+        let (data, unused_bits) = encode(&table, &content).unwrap();
+        assert!(data.len() < content.len());
+        let bits = table.encoded_bits(&CharSpectrum::from_bytes(&content));
+        assert_eq!(bits, data.len() as u64 * 8 - unused_bits as u64);
+        assert_eq!(decode(&table, &data, unused_bits).unwrap(), content);
+
+        let single = build_table(b"aaaa");
+        let (data, unused_bits) = encode(&single, b"aaaa").unwrap();
+        assert_eq!((&data[..], unused_bits), (&[0][..], 4));
+        // Only the used bits get decoded, each of them.
+        assert_eq!(decode(&single, &data, 5).unwrap(), b"aaa");
+        assert_eq!(decode(&single, &data, 0).unwrap(), b"aaaaaaaa");
+        assert!(decode(&single, &data, 8).is_err());
+        let err = encode(&single, b"ab").unwrap_err();
+        assert!(matches!(err, CtError::SymbolNotInTable { symbol: b'b' }));
+        assert!(CtBinaryTree::try_from(&CharSpectrum::default()).is_err());
+    }
+}
@@ -0,0 +1,300 @@
+//! Module contains the bit-level reading and writing of the encoded data, since prefix codes do
+//! not align with byte boundaries. Both types wrap an `io::Write` or `io::Read`, so the bits can
+//! be streamed from and to files directly. Byte-wise access is slow for unbuffered files, so wrap
+//! them into a `BufReader` or `BufWriter`. Without the `std` feature, see `crate::io`, they wrap
+//! byte slices and vectors.
+
+use crate::io::{self, Read, Write};
+use alloc::format;
+
+/// Writer, which collects single bits into bytes, most significant bit first, and writes the
+/// completed bytes into the wrapped writer.
+#[derive(Debug)]
+pub struct BitStreamWriter<W> {
+    /// Destination of the completed bytes.
+    writer: W,
+    /// The byte, which is currently filled.
+    current: u8,
+    /// Number of bits already used in `current`.
+    used: u8,
+}
+
+impl<W: Write> BitStreamWriter<W> {
+    /// New type pattern: generates a new writer, which writes into the given one.
+    pub fn new(writer: W) -> BitStreamWriter<W> {
+        BitStreamWriter {
+            writer,
+            current: 0,
+            used: 0,
+        }
+    }
+
+    /// Appends a single bit.
+    pub fn add_bit(&mut self, bit: bool) -> io::Result<()> {
+        self.current |= (bit as u8) << (7 - self.used);
+        self.used += 1;
+        if self.used == 8 {
+            self.writer.write_all(&[self.current])?;
+            self.current = 0;
+            self.used = 0;
+        }
+        Ok(())
+    }
+
+    /// Appends the lowest `count` bits of the value, most significant first. At most 32 bits can
+    /// be added at once.
+    pub fn add_bits(&mut self, value: u32, count: u8) -> io::Result<()> {
+        assert!(count <= 32, "at most 32 bits can be added at once");
+        let mut count = count;
+        while count > 0 {
+            let free = 8 - self.used;
+            let n = free.min(count);
+            let chunk = (value >> (count - n)) as u8 & (u8::MAX >> (8 - n));
+            self.current |= chunk << (free - n);
+            self.used += n;
+            count -= n;
+            if self.used == 8 {
+                self.writer.write_all(&[self.current])?;
+                self.current = 0;
+                self.used = 0;
+            }
+        }
+        Ok(())
+    }
+
+    /// The wrapped writer, which contains all completed bytes so far.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// The wrapped writer, e.g. to remove the completed bytes from a buffer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Completes the current byte with zeros and writes it, so the stream is byte-aligned again.
+    /// Returns the number of added zero bits.
+    pub fn pad_to_byte(&mut self) -> io::Result<u8> {
+        if self.used == 0 {
+            return Ok(0);
+        }
+        self.writer.write_all(&[self.current])?;
+        let padding = 8 - self.used;
+        self.current = 0;
+        self.used = 0;
+        Ok(padding)
+    }
+
+    /// Completes the last byte with zeros and writes it. Returns the wrapped writer, plus the
+    /// number of unused bits in the last byte.
+    pub fn finish(mut self) -> io::Result<(W, u8)> {
+        let unused = self.pad_to_byte()?;
+        Ok((self.writer, unused))
+    }
+}
+
+/// Byte-wise writing, e.g. of a header or of stored raw data in between the bit-level data. The
+/// stream has to be byte-aligned by `pad_to_byte()` beforehand.
+impl<W: Write> Write for BitStreamWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.used != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "bit stream is not byte-aligned, call pad_to_byte() first",
+            ));
+        }
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Reader, which returns the bits of the wrapped reader, most significant bit first.
+#[derive(Debug)]
+pub struct BitStreamReader<R> {
+    /// Source of the bits.
+    reader: R,
+    /// Number of unused bits in the last byte of the source.
+    unused_bits: u8,
+    /// The byte, whose bits are currently returned.
+    current: u8,
+    /// Number of bits left in `current`.
+    left: u8,
+    /// The byte after `current`, read ahead to recognize the last byte. `None` at the end.
+    next: Option<u8>,
+    /// Whether `next` has been read initially.
+    started: bool,
+    /// Number of bits read so far.
+    pos: u64,
+}
+
+impl<R: Read> BitStreamReader<R> {
+    /// New type pattern: generates a new reader, which ignores the given number of unused bits in
+    /// the last byte.
+    pub fn new(reader: R, unused_bits: u8) -> BitStreamReader<R> {
+        BitStreamReader {
+            reader,
+            unused_bits,
+            current: 0,
+            left: 0,
+            next: None,
+            started: false,
+            pos: 0,
+        }
+    }
+
+    /// Returns the next bit, or `None` at the end of the stream.
+    pub fn next_bit(&mut self) -> io::Result<Option<bool>> {
+        if !self.fill()? {
+            return Ok(None);
+        }
+        let bit = self.current >> 7 & 1;
+        self.current <<= 1;
+        self.left -= 1;
+        self.pos += 1;
+        Ok(Some(bit == 1))
+    }
+
+    /// Returns the next `count` bits as the lowest bits of the value, most significant first, or
+    /// `None` at the end of the stream. At most 32 bits can be taken at once. Fails, if the stream
+    /// ends within these bits.
+    pub fn take_bits(&mut self, count: u8) -> io::Result<Option<u32>> {
+        assert!(count <= 32, "at most 32 bits can be taken at once");
+        let mut value = 0u32;
+        let mut missing = count;
+        while missing > 0 {
+            if !self.fill()? {
+                if missing == count {
+                    return Ok(None);
+                }
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("bit stream ends within {count} bits"),
+                ));
+            }
+            let n = self.left.min(missing);
+            // Shifting by 32 is not allowed, but then `value` is still zero anyway.
+            value = value.checked_shl(n as u32).unwrap_or(0) | (self.current >> (8 - n)) as u32;
+            self.current = self.current.checked_shl(n as u32).unwrap_or(0);
+            self.left -= n;
+            self.pos += n as u64;
+            missing -= n;
+        }
+        Ok(Some(value))
+    }
+
+    /// Number of bits read so far.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Makes sure, that `current` has bits left. Returns false at the end of the stream. Fails, if
+    /// the unused bits do not fit into the last byte.
+    fn fill(&mut self) -> io::Result<bool> {
+        if !self.started {
+            self.next = self.read_byte()?;
+            self.started = true;
+        }
+        while self.left == 0 {
+            let Some(byte) = self.next else {
+                return Ok(false);
+            };
+            self.current = byte;
+            self.next = self.read_byte()?;
+            self.left = match self.next {
+                Some(_) => 8,
+                None if self.unused_bits < 8 => 8 - self.unused_bits,
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("{} unused bits exceed the last byte", self.unused_bits),
+                    ))
+                }
+            };
+        }
+        Ok(true)
+    }
+
+    /// Reads a single byte, `None` at the end of the source.
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        loop {
+            match self.reader.read(&mut byte) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(byte[0])),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_and_read_bits() {
+        let bits = [
+            true, false, true, true, false, false, false, true, true, true,
+        ];
+        let mut writer = BitStreamWriter::new(Vec::new());
+        bits.iter().for_each(|b| writer.add_bit(*b).unwrap());
+        assert_eq!(writer.get_ref(), &vec![0b1011_0001]);
+        let (data, unused) = writer.finish().unwrap();
+        assert_eq!(data, vec![0b1011_0001, 0b1100_0000]);
+        assert_eq!(unused, 6);
+
+        let mut reader = BitStreamReader::new(&data[..], unused);
+        let read: Vec<bool> = std::iter::from_fn(|| reader.next_bit().unwrap()).collect();
+        assert_eq!(read, bits);
+        assert_eq!(reader.position(), bits.len() as u64);
+
+        let mut reader = BitStreamReader::new(&[][..], 0);
+        assert_eq!(reader.next_bit().unwrap(), None);
+        let mut reader = BitStreamReader::new(&[0xff][..], 8);
+        assert!(reader.next_bit().is_err());
+    }
+
+    #[test]
+    fn write_and_read_bulk_bits() {
+        let mut writer = BitStreamWriter::new(Vec::new());
+        writer.add_bits(0b101, 3).unwrap();
+        writer.add_bits(u32::MAX, 32).unwrap();
+        writer.add_bits(0, 0).unwrap();
+        writer.add_bit(false).unwrap();
+        writer.add_bits(0x1234, 13).unwrap();
+        let (data, unused) = writer.finish().unwrap();
+        assert_eq!(data.len(), 7);
+        assert_eq!(unused, 7);
+
+        let mut reader = BitStreamReader::new(&data[..], unused);
+        assert_eq!(reader.take_bits(3).unwrap(), Some(0b101));
+        assert_eq!(reader.take_bits(32).unwrap(), Some(u32::MAX));
+        assert_eq!(reader.next_bit().unwrap(), Some(false));
+        assert_eq!(reader.take_bits(13).unwrap(), Some(0x1234));
+        assert_eq!(reader.position(), 49);
+        assert_eq!(reader.take_bits(1).unwrap(), None);
+
+        let mut reader = BitStreamReader::new(&data[..], unused);
+        assert!(reader.take_bits(32).is_ok());
+        assert!(reader.take_bits(32).is_err());
+    }
+
+    #[test]
+    fn mix_bytes_and_bits() {
+        let mut writer = BitStreamWriter::new(Vec::new());
+        writer.write_all(b"hd").unwrap();
+        writer.add_bits(0b11, 2).unwrap();
+        assert!(writer.write_all(b"x").is_err());
+        assert_eq!(writer.pad_to_byte().unwrap(), 6);
+        assert_eq!(writer.pad_to_byte().unwrap(), 0);
+        writer.write_all(b"raw").unwrap();
+        writer.add_bit(true).unwrap();
+        let (data, unused) = writer.finish().unwrap();
+        assert_eq!(data, b"hd\xc0raw\x80");
+        assert_eq!(unused, 7);
+    }
+}
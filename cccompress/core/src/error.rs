@@ -0,0 +1,189 @@
+//! Module contains the error type of cccompress, so that callers can match on failures instead of
+//! parsing messages. Failures without a variant of their own carry their message, see
+//! `CtError::Other`, so that any string converts into an error.
+
+use crate::io;
+use alloc::{boxed::Box, string::String};
+use core::fmt;
+
+/// Error of compressing or decompressing.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CtError {
+    /// Reading or writing failed.
+    Io(io::Error),
+    /// The input does not begin with the magic string of compressed files, so it is no
+    /// compressed file or one of another tool.
+    InvalidMagic,
+    /// The file has been written in a newer format version than the supported one.
+    UnsupportedVersion {
+        /// Format version of the file.
+        version: u8,
+        /// Newest supported format version.
+        supported: u8,
+    },
+    /// A field of a header can not be parsed.
+    CorruptHeader {
+        /// Name of the field.
+        field: &'static str,
+        /// Offset of the broken byte within the field.
+        offset: usize,
+    },
+    /// A symbol has no code in the prefix code table, by which it was to be encoded.
+    SymbolNotInTable {
+        /// The symbol without a code.
+        symbol: u8,
+    },
+    /// The encoded data contains an invalid code.
+    InvalidCode {
+        /// Offset of the byte of the encoded data, in which the code ends.
+        offset: u64,
+    },
+    /// The input ended, before all expected bytes were read.
+    Truncated {
+        /// Number of bytes, which were expected.
+        expected: u64,
+        /// Number of bytes, which were there.
+        got: u64,
+    },
+    /// The checksum of decoded content does not match the one of the original content.
+    ChecksumMismatch {
+        /// Checksum of the original content.
+        expected: u32,
+        /// Checksum of the decoded content.
+        got: u32,
+    },
+    /// An error concerning the named file.
+    File {
+        /// Name of the file.
+        name: String,
+        /// The error itself.
+        error: Box<CtError>,
+    },
+    /// Any other failure, described by its message.
+    Other(String),
+}
+
+impl CtError {
+    /// Error of any other type, described by its message.
+    pub fn other(err: impl fmt::Display) -> CtError {
+        CtError::Other(alloc::format!("{err}"))
+    }
+
+    /// The error concerning the named file.
+    pub fn in_file(self, name: impl Into<String>) -> CtError {
+        CtError::File {
+            name: name.into(),
+            error: Box::new(self),
+        }
+    }
+
+    /// The error itself, without the files it concerns, to match on it.
+    pub fn cause(&self) -> &CtError {
+        match self {
+            CtError::File { error, .. } => error.cause(),
+            err => err,
+        }
+    }
+}
+
+impl fmt::Display for CtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CtError::Io(err) => write!(f, "{err}"),
+            CtError::InvalidMagic => {
+                f.write_str("no magic string detected, maybe another file type?")
+            }
+            CtError::UnsupportedVersion { version, supported } => write!(
+                f,
+                "file format version {version} is not supported, only up to {supported}, the \
+                 file was written by a newer cccompress"
+            ),
+            CtError::CorruptHeader { field, offset } => {
+                write!(f, "{field} of the header is broken at byte {offset}")
+            }
+            CtError::SymbolNotInTable { symbol } => {
+                write!(f, "symbol {symbol:#04x} is not contained in the table")
+            }
+            CtError::InvalidCode { offset } => {
+                write!(f, "invalid code at byte {offset} of the encoded data")
+            }
+            CtError::Truncated { expected, got } => write!(
+                f,
+                "input is truncated, {} of {expected} bytes are missing",
+                expected.saturating_sub(*got)
+            ),
+            CtError::ChecksumMismatch { expected, got } => write!(
+                f,
+                "checksum mismatch, the compressed data is corrupted (expected {expected:08x}, \
+                 got {got:08x})"
+            ),
+            CtError::File { name, error } => write!(f, "'{name}': {error}"),
+            CtError::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl core::error::Error for CtError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            CtError::Io(err) => Some(err),
+            CtError::File { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CtError {
+    fn from(err: io::Error) -> CtError {
+        CtError::Io(err)
+    }
+}
+
+impl From<String> for CtError {
+    fn from(message: String) -> CtError {
+        CtError::Other(message)
+    }
+}
+
+impl From<&str> for CtError {
+    fn from(message: &str) -> CtError {
+        CtError::Other(message.into())
+    }
+}
+
+impl From<core::array::TryFromSliceError> for CtError {
+    fn from(err: core::array::TryFromSliceError) -> CtError {
+        CtError::other(err)
+    }
+}
+
+impl From<core::str::Utf8Error> for CtError {
+    fn from(err: core::str::Utf8Error) -> CtError {
+        CtError::other(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages() {
+        let err = CtError::Truncated {
+            expected: 10,
+            got: 4,
+        };
+        assert_eq!(
+            err.to_string(),
+            "input is truncated, 6 of 10 bytes are missing"
+        );
+        let err = err.in_file("a.cpd").in_file("b.cpd");
+        assert_eq!(
+            err.to_string(),
+            "'b.cpd': 'a.cpd': input is truncated, 6 of 10 bytes are missing"
+        );
+        assert!(matches!(err.cause(), CtError::Truncated { got: 4, .. }));
+        assert!(matches!(CtError::from("failed"), CtError::Other(m) if m == "failed"));
+    }
+}
@@ -0,0 +1,153 @@
+//! Module contains the input and output, which the coding in `algorithm` and `bitstream` needs.
+//! With the `std` feature, these are the traits and types of `std::io`, so files and buffers can
+//! be used directly. Without it, e.g. on embedded targets or in kernels, a minimal subset of the
+//! same interface is provided for byte slices and vectors.
+
+#[cfg(feature = "std")]
+pub use std::io::{Error, ErrorKind, Read, Result, Write};
+
+#[cfg(not(feature = "std"))]
+pub use self::core_io::*;
+
+#[cfg(not(feature = "std"))]
+mod core_io {
+    use alloc::{string::String, vec::Vec};
+    use core::fmt;
+
+    /// Result of reading or writing.
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// Category of an `Error`, the subset of `std::io::ErrorKind` used by the coding.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum ErrorKind {
+        /// A parameter was incorrect.
+        InvalidInput,
+        /// The data ended before it was complete.
+        UnexpectedEof,
+        /// The operation was interrupted and can be retried.
+        Interrupted,
+        /// Not all bytes could be written.
+        WriteZero,
+        /// Any other failure.
+        Other,
+    }
+
+    /// Error of reading or writing, with its category and a description.
+    #[derive(Debug)]
+    pub struct Error {
+        kind: ErrorKind,
+        message: String,
+    }
+
+    impl Error {
+        /// New type pattern: generates a new error of the given category.
+        pub fn new(kind: ErrorKind, message: impl Into<String>) -> Error {
+            Error {
+                kind,
+                message: message.into(),
+            }
+        }
+
+        /// Category of the error.
+        pub fn kind(&self) -> ErrorKind {
+            self.kind
+        }
+    }
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(&self.message)
+        }
+    }
+
+    impl core::error::Error for Error {}
+
+    /// Source of bytes, like `std::io::Read`.
+    pub trait Read {
+        /// Reads bytes into the buffer, returns how many. 0 at the end of the source.
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+    }
+
+    /// Destination of bytes, like `std::io::Write`.
+    pub trait Write {
+        /// Writes bytes of the buffer, returns how many.
+        fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+        /// Makes sure, that all written bytes reached their destination.
+        fn flush(&mut self) -> Result<()>;
+
+        /// Writes all bytes of the buffer.
+        fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.write(buf) {
+                    Ok(0) => return Err(Error::new(ErrorKind::WriteZero, "failed to write")),
+                    Ok(n) => buf = &buf[n..],
+                    Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl Read for &[u8] {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let n = buf.len().min(self.len());
+            let (head, tail) = self.split_at(n);
+            buf[..n].copy_from_slice(head);
+            *self = tail;
+            Ok(n)
+        }
+    }
+
+    impl<R: Read + ?Sized> Read for &mut R {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            (**self).read(buf)
+        }
+    }
+
+    impl Write for Vec<u8> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<W: Write + ?Sized> Write for &mut W {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            (**self).write(buf)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            (**self).flush()
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "std")))]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn slices_and_vectors() {
+        let mut written = Vec::new();
+        written.write_all(b"no_std").unwrap();
+        written.write_all(b" core").unwrap();
+
+        let mut source = &written[..];
+        let mut buf = [0; 8];
+        assert_eq!(source.read(&mut buf).unwrap(), 8);
+        assert_eq!(&buf, b"no_std c");
+        assert_eq!(source.read(&mut buf).unwrap(), 3);
+        assert_eq!(source.read(&mut buf).unwrap(), 0);
+
+        let err = Error::new(ErrorKind::UnexpectedEof, "ends early");
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+        assert_eq!(alloc::string::ToString::to_string(&err), "ends early");
+    }
+}
@@ -0,0 +1,17 @@
+//! Core of cccompress: the Huffman coding itself and the bit-level streams it needs. It only
+//! depends on `core` and `alloc`, so that the coder also runs on embedded targets or in kernels.
+//! Reading and writing files is gated behind the default `std` feature, see `io`.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+pub mod algorithm;
+pub mod bitstream;
+pub mod error;
+pub mod io;
+
+pub use error::CtError;
+
+/// Crate common default Result type.
+pub type Result<T> = core::result::Result<T, CtError>;
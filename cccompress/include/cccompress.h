@@ -0,0 +1,72 @@
+#ifndef CCCOMPRESS_H
+#define CCCOMPRESS_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Status returned by the functions of the C interface.
+ */
+typedef enum CcStatus {
+  /**
+   * Succeeded, the output is valid.
+   */
+  CC_STATUS_OK = 0,
+  /**
+   * A required pointer is null.
+   */
+  CC_STATUS_NULL_POINTER = 1,
+  /**
+   * Compressing or decompressing failed, e.g. the input is no compressed file.
+   */
+  CC_STATUS_FAILED = 2,
+} CcStatus;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Compresses `len` bytes at `content` by the algorithm of the given id, see `Header`, e.g. 0 for
+ * Huffman coding. On success, `*out` points to `*out_len` bytes of the header and the compressed
+ * data, like a file written by `--pack`.
+ *
+ * # Safety
+ *
+ * `content` must point to `len` readable bytes, `out` and `out_len` must be writable. The output
+ * has to be released by `cc_free()`.
+ */
+enum CcStatus cc_compress(const uint8_t *content,
+                          size_t len,
+                          uint8_t algorithm,
+                          uint8_t **out,
+                          size_t *out_len);
+
+/**
+ * Decompresses `len` bytes of a compressed file at `data`, as returned by `cc_compress()` or
+ * written by `--pack`. On success, `*out` points to the `*out_len` bytes of the original content.
+ *
+ * # Safety
+ *
+ * `data` must point to `len` readable bytes, `out` and `out_len` must be writable. The output
+ * has to be released by `cc_free()`.
+ */
+enum CcStatus cc_decompress(const uint8_t *data, size_t len, uint8_t **out, size_t *out_len);
+
+/**
+ * Releases an output of `cc_compress()` or `cc_decompress()`. Null is ignored.
+ *
+ * # Safety
+ *
+ * `data` and `len` must be an output and its length, which was not released yet.
+ */
+void cc_free(uint8_t *data, size_t len);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CCCOMPRESS_H */
@@ -0,0 +1,245 @@
+//! Module contains multi-file archives. A compressed directory is a sequence of compressed files,
+//! whose headers contain their paths including the name of the directory, like `dir/sub/a.txt`.
+//! Like tar, directories and symlinks get entries of their own, see `EntryType`, so unpacking
+//! recreates the tree exactly, also empty directories. The content of a symlink entry is the path,
+//! to which it points.
+
+use crate::Result;
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// Maximum number of bytes of an entry name, which fit into the header.
+const MAX_NAME_LEN: usize = u16::MAX as usize;
+
+/// Type of an entry, its id is stored in the header.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EntryType {
+    /// A regular file, also stdin.
+    #[default]
+    File,
+    /// A directory without content, its files follow as entries of their own.
+    Dir,
+    /// A symlink, whose content is the path, to which it points.
+    Symlink,
+}
+
+impl EntryType {
+    /// Id of the entry type in the header.
+    pub fn id(self) -> u8 {
+        match self {
+            EntryType::File => 0,
+            EntryType::Dir => 1,
+            EntryType::Symlink => 2,
+        }
+    }
+
+    /// Entry type of the given id in the header.
+    pub fn from_id(id: u8) -> Result<EntryType> {
+        match id {
+            0 => Ok(EntryType::File),
+            1 => Ok(EntryType::Dir),
+            2 => Ok(EntryType::Symlink),
+            _ => Err(format!("unknown entry type {id}").into()),
+        }
+    }
+
+    /// Entry type of the path without following symlinks. Anything else than a directory or a
+    /// symlink is a file, e.g. stdin.
+    pub fn of(path: &Path) -> EntryType {
+        match fs::symlink_metadata(path).map(|m| m.file_type()) {
+            Ok(file_type) if file_type.is_dir() => EntryType::Dir,
+            Ok(file_type) if file_type.is_symlink() => EntryType::Symlink,
+            _ => EntryType::File,
+        }
+    }
+}
+
+/// The directory itself, all files, directories and symlinks in it and its subdirectories with
+/// their entry names, sorted by name. Directories come in front of their content.
+pub fn entries(dir: &Path) -> Result<Vec<(PathBuf, String)>> {
+    let root = dir
+        .canonicalize()?
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(String::from)
+        .ok_or_else(|| format!("'{}' has no valid directory name", dir.display()))?;
+    check_name(&root)?;
+    let mut entries = vec![(dir.to_path_buf(), root.clone())];
+    collect(dir, &root, &mut entries)?;
+    entries.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok(entries)
+}
+
+/// Adds all entries of the directory recursively, their names prefixed by `prefix`. Symlinks to
+/// directories are not followed.
+fn collect(dir: &Path, prefix: &str, entries: &mut Vec<(PathBuf, String)>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| format!("'{}' is not a valid UTF-8 path", path.display()))?;
+        let name = format!("{prefix}/{name}");
+        check_name(&name)?;
+        entries.push((path.clone(), name.clone()));
+        if entry.file_type()?.is_dir() {
+            collect(&path, &name, entries)?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks, whether the entry name can be stored in the header.
+pub fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(format!("path '{name}' can not be stored in the header").into());
+    }
+    Ok(())
+}
+
+/// Files to be appended to an archive with their entry names, see `CtDirective::Append`. Files
+/// keep their relative path as name, directories get all their entries like in `entries()`.
+pub fn append_entries(paths: &[String]) -> Result<Vec<(PathBuf, String)>> {
+    let mut entries = Vec::new();
+    for path in paths {
+        let name = path.trim_start_matches("./").trim_end_matches('/');
+        entry_path(name)?;
+        check_name(name)?;
+        entries.push((PathBuf::from(path), name.to_string()));
+        if EntryType::of(Path::new(path)) == EntryType::Dir {
+            collect(Path::new(path), name, &mut entries)?;
+        }
+    }
+    Ok(entries)
+}
+
+/// Path, to which an entry gets restored. Entries must not leave the current directory.
+pub fn entry_path(name: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(name);
+    if path
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        Ok(path)
+    } else {
+        Err(format!("entry '{name}' points outside of the current directory").into())
+    }
+}
+
+/// Path, to which an entry gets restored without its first `strip` components, see
+/// `--strip-components`. Entries, which have no more components, are skipped, so they have none.
+/// Entries must not be restored through symlinks, which earlier entries created, see
+/// `check_parents()`.
+pub fn stripped_path(name: &str, strip: usize) -> Result<Option<PathBuf>> {
+    let path: PathBuf = entry_path(name)?
+        .components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .skip(strip)
+        .collect();
+    if path.as_os_str().is_empty() {
+        return Ok(None);
+    }
+    check_parents(&path)?;
+    Ok(Some(path))
+}
+
+/// Checks, that no directory on the way to the path is a symlink on disk. Otherwise a symlink
+/// entry could redirect the following entries outside of the current directory.
+fn check_parents(path: &Path) -> Result<()> {
+    for dir in path.ancestors().skip(1) {
+        if fs::symlink_metadata(dir).is_ok_and(|m| m.file_type().is_symlink()) {
+            return Err(format!(
+                "entry '{}' would be restored through the symlink '{}'",
+                path.display(),
+                dir.display()
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Checks, that a symlink entry restored to the path points into the current directory. Absolute
+/// targets and targets, whose `..` leave the current directory, are rejected.
+pub fn check_symlink(path: &Path, target: &str) -> Result<()> {
+    let mut depth = path.components().count().saturating_sub(1);
+    for component in Path::new(target).components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            _ => {
+                return Err(format!(
+                    "symlink '{}' points outside of the current directory to '{target}'",
+                    path.display()
+                )
+                .into())
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_names() {
+        fs::create_dir_all("entries_test/sub").unwrap();
+        fs::write("entries_test/b.txt", "b").unwrap();
+        fs::write("entries_test/sub/a.txt", "a").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("sub", "entries_test/link").unwrap();
+        let entries = entries(Path::new("entries_test/")).unwrap();
+        let types: Vec<(&str, EntryType)> = entries
+            .iter()
+            .map(|(path, name)| (&name[..], EntryType::of(path)))
+            .collect();
+        let mut expected = vec![
+            ("entries_test", EntryType::Dir),
+            ("entries_test/b.txt", EntryType::File),
+            ("entries_test/link", EntryType::Symlink),
+            ("entries_test/sub", EntryType::Dir),
+            ("entries_test/sub/a.txt", EntryType::File),
+        ];
+        if cfg!(not(unix)) {
+            expected.remove(2);
+        }
+        assert_eq!(types, expected);
+        fs::remove_dir_all("entries_test").unwrap();
+
+        for entry_type in [EntryType::File, EntryType::Dir, EntryType::Symlink] {
+            assert_eq!(EntryType::from_id(entry_type.id()).unwrap(), entry_type);
+        }
+        assert!(EntryType::from_id(3).is_err());
+
+        let stripped = |name, strip| stripped_path(name, strip).unwrap();
+        assert_eq!(
+            stripped("dir/sub/a.txt", 1),
+            Some(PathBuf::from("sub/a.txt"))
+        );
+        assert_eq!(stripped("./dir/a.txt", 1), Some(PathBuf::from("a.txt")));
+        assert_eq!(stripped("dir", 1), None);
+        assert!(stripped_path("../a.txt", 1).is_err());
+
+        assert!(check_symlink(Path::new("dir/link"), "sub/a.txt").is_ok());
+        assert!(check_symlink(Path::new("dir/link"), "../a.txt").is_ok());
+        assert!(check_symlink(Path::new("dir/link"), "../../a.txt").is_err());
+        assert!(check_symlink(Path::new("link"), "sub/../../a.txt").is_err());
+        assert!(check_symlink(Path::new("dir/link"), "/tmp/a.txt").is_err());
+
+        assert!(entry_path("dir/a.txt").is_ok());
+        assert!(entry_path("/etc/passwd").is_err());
+        assert!(entry_path("dir/../../a.txt").is_err());
+
+        assert!(check_name("dir/a.txt").is_ok());
+        assert!(check_name(&format!("dir/{}", "ä".repeat(200))).is_ok());
+        assert!(check_name("").is_err());
+        assert!(check_name(&"a".repeat(MAX_NAME_LEN + 1)).is_err());
+        assert!(append_entries(&[String::from("/etc/passwd")]).is_err());
+    }
+}
@@ -0,0 +1,285 @@
+//! Module contains an arithmetic coder as alternative entropy stage. In contrast to prefix codes,
+//! a symbol may take a fraction of a bit, which pays off for skewed distributions. The static
+//! model is derived from the `CharSpectrum` of the content.
+//!
+//! **Byte Representation** of the model, stored as table in the header
+//!
+//! 1. 4 bytes u32 (LE) number of encoded symbols
+//! 2. 2 bytes u16 (LE) number n of symbols in the model
+//! 3. n times 1 byte symbol and 2 bytes u16 (LE) its scaled frequency
+
+use crate::{
+    algorithm::{CharSpectrum, SYMBOLS},
+    bitstream::{BitStreamReader, BitStreamWriter},
+    codec::CompressionAlgorithm,
+    fs::{CompressedData, Header},
+    Result,
+};
+
+/// Number of bits of the coder's interval bounds.
+const CODE_BITS: u32 = 32;
+/// Upper bound of the interval.
+const TOP: u64 = (1 << CODE_BITS) - 1;
+const FIRST_QUARTER: u64 = 1 << (CODE_BITS - 2);
+const HALF: u64 = 2 * FIRST_QUARTER;
+const THIRD_QUARTER: u64 = 3 * FIRST_QUARTER;
+/// Maximum sum of all frequencies of the model, so that no interval collapses.
+const MAX_TOTAL: u64 = 1 << 16;
+
+/// Arithmetic coding with a static model of the byte frequencies.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Arith;
+
+impl CompressionAlgorithm for Arith {
+    fn compress(&self, content: &[u8]) -> Result<CompressedData> {
+        let model = Model::from(&CharSpectrum::from_bytes(content));
+        let (data, unused_bits) = encode(&model, content)?;
+        let mut table = (content.len() as u32).to_le_bytes().to_vec();
+        table.extend(Vec::<u8>::from(&model));
+
+        Ok(CompressedData {
+            header: Header {
+                prefix_table: table,
+                data_bytes: data.len() as u32,
+                unused_bits,
+                ..Default::default()
+            },
+            data,
+        })
+    }
+
+    fn decompress(&self, cdata: &CompressedData) -> Result<Vec<u8>> {
+        let table = &cdata.header.prefix_table;
+        if table.len() < 4 {
+            return Err("arithmetic coding model is broken".into());
+        }
+        let symbols = u32::from_le_bytes(table[..4].try_into()?) as usize;
+        let model = Model::try_from(&table[4..])?;
+        decode(&model, &cdata.data, cdata.header.unused_bits, symbols)
+    }
+}
+
+/// Static model: the cumulated, scaled frequencies of all symbols.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Model {
+    /// Symbol i covers the range `cumulated[i]..cumulated[i + 1]`.
+    cumulated: [u64; SYMBOLS + 1],
+}
+
+impl Model {
+    /// Sum of all frequencies.
+    pub fn total(&self) -> u64 {
+        self.cumulated[SYMBOLS]
+    }
+
+    /// Scaled frequency of the symbol.
+    pub fn frequency(&self, symbol: u8) -> u64 {
+        self.cumulated[symbol as usize + 1] - self.cumulated[symbol as usize]
+    }
+
+    /// Generates the model from the single frequencies.
+    fn from_frequencies(frequencies: &[u64; SYMBOLS]) -> Model {
+        let mut cumulated = [0; SYMBOLS + 1];
+        for (i, f) in frequencies.iter().enumerate() {
+            cumulated[i + 1] = cumulated[i] + f;
+        }
+        Model { cumulated }
+    }
+
+    /// Symbol, whose range contains the given cumulated frequency.
+    fn symbol_at(&self, cum: u64) -> usize {
+        self.cumulated.partition_point(|c| *c <= cum) - 1
+    }
+}
+
+impl From<&CharSpectrum> for Model {
+    /// Scales the frequencies down to `MAX_TOTAL`, if needed. Symbols, which occur, keep a
+    /// frequency of at least one.
+    fn from(spectrum: &CharSpectrum) -> Model {
+        let total = spectrum.total();
+        let mut frequencies = [0; SYMBOLS];
+        for (symbol, f) in frequencies.iter_mut().enumerate() {
+            let freq = spectrum.frequency(symbol as u8);
+            *f = if total <= MAX_TOTAL || freq == 0 {
+                freq
+            } else {
+                (freq * (MAX_TOTAL - SYMBOLS as u64) / total).max(1)
+            };
+        }
+        Model::from_frequencies(&frequencies)
+    }
+}
+
+impl From<&Model> for Vec<u8> {
+    fn from(model: &Model) -> Vec<u8> {
+        let symbols: Vec<u8> = (0..=u8::MAX).filter(|s| model.frequency(*s) > 0).collect();
+        let mut data = (symbols.len() as u16).to_le_bytes().to_vec();
+        for symbol in symbols {
+            data.push(symbol);
+            data.extend((model.frequency(symbol) as u16).to_le_bytes());
+        }
+        data
+    }
+}
+
+impl TryFrom<&[u8]> for Model {
+    type Error = crate::CtError;
+
+    fn try_from(data: &[u8]) -> Result<Model> {
+        let broken = || "arithmetic coding model is broken";
+        let n = u16::from_le_bytes(data.get(..2).ok_or_else(broken)?.try_into()?) as usize;
+        let entries = data.get(2..2 + 3 * n).ok_or_else(broken)?;
+        let mut frequencies = [0; SYMBOLS];
+        for entry in entries.chunks_exact(3) {
+            frequencies[entry[0] as usize] = u16::from_le_bytes([entry[1], entry[2]]) as u64;
+        }
+        let model = Model::from_frequencies(&frequencies);
+        if model.total() > MAX_TOTAL {
+            return Err(broken().into());
+        }
+        Ok(model)
+    }
+}
+
+/// Encodes the content with the model. Returns the encoded data and the number of unused bits
+/// in the last byte.
+pub fn encode(model: &Model, content: &[u8]) -> Result<(Vec<u8>, u8)> {
+    let mut writer = BitStreamWriter::new(Vec::new());
+    let (mut low, mut high) = (0, TOP);
+    let mut pending = 0;
+
+    for symbol in content {
+        let range = high - low + 1;
+        let symbol = *symbol as usize;
+        high = low + range * model.cumulated[symbol + 1] / model.total() - 1;
+        low += range * model.cumulated[symbol] / model.total();
+        loop {
+            if high < HALF {
+                emit(&mut writer, false, &mut pending)?;
+            } else if low >= HALF {
+                emit(&mut writer, true, &mut pending)?;
+                low -= HALF;
+                high -= HALF;
+            } else if low >= FIRST_QUARTER && high < THIRD_QUARTER {
+                pending += 1;
+                low -= FIRST_QUARTER;
+                high -= FIRST_QUARTER;
+            } else {
+                break;
+            }
+            low *= 2;
+            high = 2 * high + 1;
+        }
+    }
+
+    if !content.is_empty() {
+        // Two more bits select a value, which lies within the final interval.
+        pending += 1;
+        emit(&mut writer, low >= FIRST_QUARTER, &mut pending)?;
+    }
+    Ok(writer.finish()?)
+}
+
+/// Writes the bit, followed by the pending bits of the opposite value.
+fn emit(writer: &mut BitStreamWriter<Vec<u8>>, bit: bool, pending: &mut usize) -> Result<()> {
+    writer.add_bit(bit)?;
+    for _ in 0..*pending {
+        writer.add_bit(!bit)?;
+    }
+    *pending = 0;
+    Ok(())
+}
+
+/// Decodes the given number of symbols from the data, which has been encoded with the model.
+pub fn decode(model: &Model, data: &[u8], unused_bits: u8, symbols: usize) -> Result<Vec<u8>> {
+    if symbols > 0 && model.total() == 0 {
+        return Err("arithmetic coding model is empty".into());
+    }
+    let mut reader = BitStreamReader::new(data, unused_bits);
+    // Bits after the end of the data are zeros.
+    let mut next_bit = || -> Result<u64> { Ok(reader.next_bit()?.unwrap_or(false) as u64) };
+    let mut value = 0;
+    for _ in 0..CODE_BITS {
+        value = 2 * value + next_bit()?;
+    }
+    let (mut low, mut high) = (0, TOP);
+    let mut content = Vec::with_capacity(symbols);
+
+    for _ in 0..symbols {
+        let range = high - low + 1;
+        let cum = ((value - low + 1) * model.total() - 1) / range;
+        let symbol = model.symbol_at(cum);
+        if symbol >= SYMBOLS {
+            return Err("arithmetic coded data is broken".into());
+        }
+        content.push(symbol as u8);
+        high = low + range * model.cumulated[symbol + 1] / model.total() - 1;
+        low += range * model.cumulated[symbol] / model.total();
+        loop {
+            if high < HALF {
+                // Lower half, nothing to subtract.
+            } else if low >= HALF {
+                value -= HALF;
+                low -= HALF;
+                high -= HALF;
+            } else if low >= FIRST_QUARTER && high < THIRD_QUARTER {
+                value -= FIRST_QUARTER;
+                low -= FIRST_QUARTER;
+                high -= FIRST_QUARTER;
+            } else {
+                break;
+            }
+            low *= 2;
+            high = 2 * high + 1;
+            value = 2 * value + next_bit()?;
+        }
+    }
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::Huffman;
+
+    #[test]
+    fn model() {
+        let model = Model::from(&CharSpectrum::from_bytes(b"abbccc"));
+        assert_eq!(model.total(), 6);
+        assert_eq!(model.frequency(b'c'), 3);
+        assert_eq!(model.symbol_at(0), b'a' as usize);
+        assert_eq!(model.symbol_at(5), b'c' as usize);
+        let data = Vec::<u8>::from(&model);
+        assert_eq!(data.len(), 2 + 3 * 3);
+        assert_eq!(Model::try_from(&data[..]).unwrap(), model);
+        assert!(Model::try_from(&data[..5]).is_err());
+
+        let content = vec![0u8; 200_000];
+        let model = Model::from(&CharSpectrum::from_bytes(&[&content[..], b"x"].concat()));
+        assert!(model.total() <= MAX_TOTAL);
+        assert_eq!(model.frequency(b'x'), 1);
+    }
+
+    #[test]
+    fn beats_huffman_on_skewed_content() {
+        let content: Vec<u8> = (0..10_000u32)
+            .map(|i| if i % 20 == 0 { b'b' } else { b'a' })
+            .collect();
+        let arith = Arith.compress(&content).unwrap();
+        let huffman = Huffman::DEFAULT.compress(&content).unwrap();
+        assert!(arith.data.len() * 3 < huffman.data.len());
+        assert_eq!(Arith.decompress(&arith).unwrap(), content);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let content = std::fs::read("135-0.txt").unwrap();
+        let content = &content[..100_000];
+        let cdata = Arith.compress(content).unwrap();
+        assert_eq!(Arith.decompress(&cdata).unwrap(), content);
+
+        let cdata = Arith.compress(b"").unwrap();
+        assert!(cdata.data.is_empty());
+        assert_eq!(Arith.decompress(&cdata).unwrap(), b"");
+    }
+}
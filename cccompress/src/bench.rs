@@ -0,0 +1,123 @@
+//! Module contains the benchmark of all compression algorithms on a content, see `--bench`. Every
+//! algorithm and, where it has them, every compression level compresses and decompresses the
+//! content once, the comparison shows ratio and throughput to help picking the settings.
+
+use crate::{compressor::Compressor, fs, lz77, stats::Stats, Algorithm, Result};
+use clap::ValueEnum;
+use std::time::Instant;
+
+/// Result of one setting: the sizes and the duration of compressing, plus the duration of
+/// decompressing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Measurement {
+    /// The compression algorithm.
+    pub algo: Algorithm,
+    /// The compression level, `None` for algorithms without levels.
+    pub level: Option<u8>,
+    /// Sizes and duration of compressing.
+    pub encode: Stats,
+    /// Duration of decompressing, of the same sizes.
+    pub decode: Stats,
+}
+
+/// Compresses and decompresses the content by every algorithm and level. Fails, if any of them
+/// does not restore the content.
+pub fn run(content: &[u8]) -> Result<Vec<Measurement>> {
+    let mut measurements = Vec::new();
+    for &algo in Algorithm::value_variants() {
+        let levels: Vec<Option<u8>> = match algo {
+            Algorithm::Lz77 => (0..=lz77::MAX_LEVEL).map(Some).collect(),
+            _ => vec![None],
+        };
+        for level in levels {
+            measurements.push(measure(content, algo, level)?);
+        }
+    }
+    Ok(measurements)
+}
+
+/// Compresses and decompresses the content by one setting.
+fn measure(content: &[u8], algo: Algorithm, level: Option<u8>) -> Result<Measurement> {
+    let mut compressor = Compressor::new().algorithm(algo);
+    if let Some(level) = level {
+        compressor = compressor.level(level);
+    }
+    let start = Instant::now();
+    let cdata = compressor.compress(content)?;
+    let encode = Stats {
+        original_bytes: content.len() as u64,
+        compressed_bytes: fs::entry_size(&cdata.header)?,
+        elapsed: start.elapsed(),
+    };
+
+    let start = Instant::now();
+    let decompressed = crate::decompress(&cdata)?;
+    let decode = Stats {
+        elapsed: start.elapsed(),
+        ..encode
+    };
+    if decompressed != content {
+        return Err(format!("{} does not restore the content", name(algo, level)).into());
+    }
+    Ok(Measurement {
+        algo,
+        level,
+        encode,
+        decode,
+    })
+}
+
+/// Comparison of the measurements as aligned table, with the throughput in MB/s of the original
+/// content.
+pub fn table(measurements: &[Measurement]) -> String {
+    let mut lines = vec![format!(
+        "{:<12} {:>12} {:>7} {:>12} {:>12}",
+        "algorithm", "compressed", "ratio", "encode MB/s", "decode MB/s"
+    )];
+    for m in measurements {
+        lines.push(format!(
+            "{:<12} {:>12} {:>6.1}% {:>12.1} {:>12.1}",
+            name(m.algo, m.level),
+            m.encode.compressed_bytes,
+            m.encode.ratio(),
+            m.encode.throughput(),
+            m.decode.throughput()
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Name of the algorithm as on the command line, with the level if it has one.
+fn name(algo: Algorithm, level: Option<u8>) -> String {
+    let algo = algo
+        .to_possible_value()
+        .map_or_else(|| format!("{algo:?}"), |value| value.get_name().to_string());
+    match level {
+        Some(level) => format!("{algo} -{level}"),
+        None => algo,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_settings() {
+        let content = b"to be or not to be, that is the question. ".repeat(50);
+        let measurements = run(&content).unwrap();
+        let settings = Algorithm::value_variants().len() + lz77::MAX_LEVEL as usize;
+        assert_eq!(measurements.len(), settings);
+        assert!(measurements
+            .iter()
+            .all(|m| m.encode.original_bytes == content.len() as u64));
+
+        let table = table(&measurements);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), settings + 1);
+        assert!(lines[0].starts_with("algorithm"));
+        assert!(lines[1].starts_with("huffman "));
+        assert!(lines[2].starts_with("lz77 -0 "));
+        assert!(lines.iter().any(|line| line.starts_with("lz77 -9 ")));
+    }
+}
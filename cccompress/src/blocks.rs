@@ -0,0 +1,478 @@
+//! Module contains the seekable block format. The content is split into blocks, which are
+//! compressed independently of each other, so a range of the content can be decompressed without
+//! decoding everything in front of it. An index at the start of the data locates the blocks.
+//!
+//! **Byte Representation** of the data of a seekable file
+//!
+//! 1. 4 bytes u32 (LE) number n of blocks
+//! 2. n times 8 bytes u64 (LE) offset of the block, counted from the end of the index, 8 bytes
+//!    u64 (LE) start of its range in the original content and 4 bytes u32 (LE) length of the range
+//! 3. n blocks, each one a complete compressed file without filename, see `fs::write_header()`
+//!
+//! The number of bytes per block is stored in the header, so that decoding neither trusts the
+//! lengths of the index for allocating buffers nor accepts blocks larger than that. Files written
+//! before it was stored have blocks of `DEFAULT_BLOCK_SIZE`.
+//!
+//! The blocks are independent of each other, so they get decoded on several threads. The blocks
+//! are still read one after another, so the data needs no seekable reader, and their output gets
+//! written in their order.
+
+use crate::{
+    compressor::Compressor,
+    decompress_data,
+    fs::{self, CompressedData, Header},
+    Algorithm, CtError, Pipeline, Result,
+};
+use std::{
+    collections::BTreeMap,
+    io::{Read, Seek, SeekFrom, Write},
+    sync::{mpsc, Mutex},
+};
+
+/// Number of bytes of the original content per block, which is used if none is given.
+pub const DEFAULT_BLOCK_SIZE: u32 = 1 << 20;
+/// Minimum number of bytes per block, smaller blocks would be dominated by their headers.
+pub const MIN_BLOCK_SIZE: u32 = 1 << 12;
+/// Maximum number of bytes per block, whose buffers decoding allocates.
+pub const MAX_BLOCK_SIZE: u32 = 1 << 28;
+
+/// Number of bytes of one entry of the index.
+const ENTRY_BYTES: usize = 20;
+
+/// Location of one block in the data and its range of the original content.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BlockEntry {
+    /// Offset of the block, counted from the end of the index.
+    pub offset: u64,
+    /// Start of the range in the original content.
+    pub start: u64,
+    /// Length of the range in the original content.
+    pub len: u32,
+}
+
+/// Index of all blocks of a seekable file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlockIndex {
+    pub entries: Vec<BlockEntry>,
+}
+
+impl BlockIndex {
+    /// Reads the index from the start of the data.
+    pub fn read(reader: &mut (impl Read + ?Sized)) -> Result<BlockIndex> {
+        let mut count = [0; 4];
+        reader.read_exact(&mut count)?;
+        let count = u32::from_le_bytes(count) as usize;
+        let mut data = Vec::new();
+        reader
+            .take((count * ENTRY_BYTES) as u64)
+            .read_to_end(&mut data)?;
+        if data.len() != count * ENTRY_BYTES {
+            return Err("block index is truncated".into());
+        }
+        let entries = data
+            .chunks_exact(ENTRY_BYTES)
+            .map(|e| BlockEntry {
+                offset: u64::from_le_bytes(e[..8].try_into().unwrap()),
+                start: u64::from_le_bytes(e[8..16].try_into().unwrap()),
+                len: u32::from_le_bytes(e[16..].try_into().unwrap()),
+            })
+            .collect();
+        Ok(BlockIndex { entries })
+    }
+
+    /// Number of bytes of the serialized index.
+    pub fn size(&self) -> usize {
+        4 + self.entries.len() * ENTRY_BYTES
+    }
+
+    /// Total length of the original content.
+    pub fn content_len(&self) -> u64 {
+        self.entries.last().map_or(0, |e| e.start + e.len as u64)
+    }
+}
+
+impl From<&BlockIndex> for Vec<u8> {
+    fn from(index: &BlockIndex) -> Vec<u8> {
+        let mut data = Vec::with_capacity(index.size());
+        data.extend_from_slice(&(index.entries.len() as u32).to_le_bytes());
+        for entry in &index.entries {
+            data.extend_from_slice(&entry.offset.to_le_bytes());
+            data.extend_from_slice(&entry.start.to_le_bytes());
+            data.extend_from_slice(&entry.len.to_le_bytes());
+        }
+        data
+    }
+}
+
+/// Number of bytes per block of the seekable data of the header, see `MIN_BLOCK_SIZE` and
+/// `MAX_BLOCK_SIZE`.
+pub fn block_size(header: &Header) -> Result<usize> {
+    match header.seekable_block_size {
+        0 => Ok(DEFAULT_BLOCK_SIZE as usize),
+        size => check_block_size(size),
+    }
+}
+
+/// Checks, whether the number of bytes per block is within the limits.
+pub fn check_block_size(size: u32) -> Result<usize> {
+    if !(MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE).contains(&size) {
+        return Err(format!(
+            "block size {size} is not within {MIN_BLOCK_SIZE} and {MAX_BLOCK_SIZE} bytes"
+        )
+        .into());
+    }
+    Ok(size as usize)
+}
+
+/// Compresses the content from the reader block by block into the seekable format, blocks of
+/// `block_size` bytes each. Only the compressed blocks are held in memory.
+pub fn compress(
+    mut reader: impl Read,
+    algo: Algorithm,
+    pipeline: Pipeline,
+    level: u8,
+    block_size: u32,
+) -> Result<CompressedData> {
+    let size = check_block_size(block_size)?;
+    let mut index = BlockIndex::default();
+    let mut blocks = Vec::new();
+    let mut hasher = crc32fast::Hasher::new();
+    let mut block = Vec::with_capacity(size);
+    loop {
+        block.clear();
+        (&mut reader).take(size as u64).read_to_end(&mut block)?;
+        if block.is_empty() && !index.entries.is_empty() {
+            break;
+        }
+        hasher.update(&block);
+        let cdata = Compressor::new()
+            .algorithm(algo)
+            .pipeline(pipeline)
+            .level(level)
+            .compress(&block)?;
+        index.entries.push(BlockEntry {
+            offset: blocks.len() as u64,
+            start: index.content_len(),
+            len: block.len() as u32,
+        });
+        fs::write_header(&mut blocks, &cdata.header)?;
+        blocks.extend_from_slice(&cdata.data);
+        if block.len() < size {
+            break;
+        }
+    }
+
+    let mut data = Vec::<u8>::from(&index);
+    data.append(&mut blocks);
+    Ok(CompressedData {
+        header: Header {
+            data_bytes: u32::try_from(data.len()).map_err(|_| "compressed data exceeds 4 GiB")?,
+            checksum: hasher.finalize(),
+            algorithm: algo.id(),
+            pipeline: pipeline.id(),
+            block_size: pipeline.block_size(),
+            seekable: true,
+            seekable_block_size: block_size,
+            original_bytes: index.content_len(),
+            ..Default::default()
+        },
+        data,
+    })
+}
+
+/// Number of threads, which decode blocks: all available cores for 0.
+pub fn thread_count(threads: usize) -> usize {
+    match threads {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }
+}
+
+/// Decompresses all blocks of the data of the header from the reader into the writer, on up to
+/// `threads` threads, see `thread_count()`. Returns the number of written bytes and their
+/// checksum. Takes trait objects, because the blocks get decompressed by `decompress_data()`,
+/// which in turn calls this function for seekable files.
+pub fn decompress(
+    header: &Header,
+    reader: &mut dyn Read,
+    out: &mut dyn Write,
+    threads: usize,
+) -> Result<(u64, u32)> {
+    let size = block_size(header)?;
+    let index = BlockIndex::read(reader)?;
+    check_index(&index, size)?;
+    let threads = thread_count(threads).min(index.entries.len());
+    let mut hasher = crc32fast::Hasher::new();
+    let mut bytes = 0;
+    let mut append = |entry: &BlockEntry, checksum: u32, n: u64| {
+        if n != entry.len as u64 {
+            return Err(format!("block at {} does not match the index", entry.start));
+        }
+        // The block's checksum was verified while decoding it.
+        hasher.combine(&crc32fast::Hasher::new_with_initial_len(checksum, n));
+        bytes += n;
+        Ok(())
+    };
+    if threads <= 1 {
+        for entry in &index.entries {
+            let header = fs::read_header(reader)?;
+            let n = decompress_data(&header, &mut *reader, &mut *out, 1)?;
+            append(entry, header.checksum, n)?;
+        }
+    } else {
+        let blocks = index.entries.iter().map(|_| {
+            let header = fs::read_header(reader)?;
+            let data = crate::read_data(&header, &mut *reader)?;
+            Ok((header, data))
+        });
+        let decode = |(header, data): (Header, Vec<u8>)| {
+            let mut block = Vec::with_capacity(size);
+            decompress_data(&header, &data[..], &mut block, 1)?;
+            Ok((header.checksum, block))
+        };
+        let mut entries = index.entries.iter();
+        in_parallel(threads, blocks, decode, |(checksum, block)| {
+            out.write_all(&block)?;
+            append(entries.next().unwrap(), checksum, block.len() as u64)?;
+            Ok(())
+        })?;
+    }
+    Ok((bytes, hasher.finalize()))
+}
+
+/// Does the jobs on `threads` threads and hands their outputs to `done` in the order of the jobs.
+/// The jobs are taken one after another on the calling thread, at most two per thread are held in
+/// memory. The first error stops all threads.
+fn in_parallel<J: Send, T: Send>(
+    threads: usize,
+    jobs: impl Iterator<Item = Result<J>>,
+    work: impl Fn(J) -> Result<T> + Sync,
+    mut done: impl FnMut(T) -> Result<()>,
+) -> Result<()> {
+    let (sender, queue) = mpsc::channel::<(usize, J)>();
+    let (results, outputs) = mpsc::channel::<(usize, Result<T>)>();
+    let queue = Mutex::new(queue);
+    std::thread::scope(|scope| {
+        // Dropped on errors too, so that the threads stop.
+        let (sender, outputs) = (sender, outputs);
+        for _ in 0..threads {
+            let (queue, results, work) = (&queue, results.clone(), &work);
+            scope.spawn(move || loop {
+                // Bound first, so that the lock is released, before the job gets done.
+                let job = queue.lock().unwrap().recv();
+                let Ok((i, job)) = job else {
+                    break;
+                };
+                if results.send((i, work(job))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(results);
+        // Outputs, which wait for the ones in front of them.
+        let mut pending = BTreeMap::new();
+        let mut next = 0;
+        let mut receive = || -> Result<()> {
+            let (i, result) = outputs.recv().map_err(CtError::other)?;
+            pending.insert(i, result?);
+            while let Some(output) = pending.remove(&next) {
+                done(output)?;
+                next += 1;
+            }
+            Ok(())
+        };
+        let (mut sent, mut received) = (0, 0);
+        for job in jobs {
+            sender.send((sent, job?)).map_err(CtError::other)?;
+            sent += 1;
+            if sent - received >= 2 * threads {
+                receive()?;
+                received += 1;
+            }
+        }
+        drop(sender);
+        for _ in received..sent {
+            receive()?;
+        }
+        Ok(())
+    })
+}
+
+/// Decompresses `len` bytes of the original content, beginning at `start`, from a compressed file.
+/// Only the blocks, which overlap the range, are read and decoded.
+pub fn read_range(mut reader: impl Read + Seek, start: u64, len: u64) -> Result<Vec<u8>> {
+    let header = fs::read_header(&mut reader)?;
+    if !header.seekable {
+        return Err("random access needs a file in the seekable block format".into());
+    }
+    let size = block_size(&header)?;
+    let index = BlockIndex::read(&mut reader)?;
+    check_index(&index, size)?;
+    let base = reader.stream_position()?;
+    let end = start.checked_add(len).ok_or("invalid range")?;
+    if end > index.content_len() {
+        return Err(format!(
+            "range {start}..{end} exceeds the content of {} bytes",
+            index.content_len()
+        )
+        .into());
+    }
+
+    let mut content = Vec::with_capacity(len as usize);
+    for entry in index
+        .entries
+        .iter()
+        .filter(|e| e.start < end && e.start + e.len as u64 > start)
+    {
+        reader.seek(SeekFrom::Start(base + entry.offset))?;
+        let header = fs::read_header(&mut reader)?;
+        let mut block = Vec::with_capacity(size);
+        decompress_data(&header, &mut reader, &mut block, 1)?;
+        let from = start.saturating_sub(entry.start) as usize;
+        let to = ((end - entry.start) as usize).min(block.len());
+        let range = block
+            .get(from..to)
+            .ok_or("block does not match the index")?;
+        content.extend_from_slice(range);
+    }
+    Ok(content)
+}
+
+/// Checks, that no block of the index is larger than the block size.
+fn check_index(index: &BlockIndex, size: usize) -> Result<()> {
+    match index.entries.iter().find(|e| e.len as usize > size) {
+        Some(e) => Err(format!(
+            "block at {} has {} bytes, more than the block size of {size}",
+            e.start, e.len
+        )
+        .into()),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn random_access() {
+        const BLOCK_SIZE: usize = DEFAULT_BLOCK_SIZE as usize;
+        let content: Vec<u8> = (0..BLOCK_SIZE as u32 * 5 / 2)
+            .map(|i| (i % 251) as u8 ^ (i >> 12) as u8)
+            .collect();
+        let cdata = compress(
+            &content[..],
+            Algorithm::Huffman,
+            Pipeline::None,
+            0,
+            DEFAULT_BLOCK_SIZE,
+        )
+        .unwrap();
+        let index = BlockIndex::read(&mut &cdata.data[..]).unwrap();
+        assert_eq!(index.entries.len(), 3);
+        assert_eq!(index.entries[2].start, 2 * BLOCK_SIZE as u64);
+        assert_eq!(index.content_len(), content.len() as u64);
+
+        let mut file = Vec::new();
+        fs::write_header(&mut file, &cdata.header).unwrap();
+        file.extend_from_slice(&cdata.data);
+        for (start, len) in [(0, 10), (BLOCK_SIZE - 5, 10), (100, 2 * BLOCK_SIZE), (7, 0)] {
+            let range = read_range(Cursor::new(&file), start as u64, len as u64).unwrap();
+            assert_eq!(range, &content[start..start + len]);
+        }
+        assert!(read_range(Cursor::new(&file), content.len() as u64, 1).is_err());
+
+        let mut output = Vec::new();
+        let (bytes, checksum) =
+            decompress(&cdata.header, &mut &cdata.data[..], &mut output, 1).unwrap();
+        assert_eq!(output, content);
+        assert_eq!(
+            (bytes, checksum),
+            (content.len() as u64, cdata.header.checksum)
+        );
+    }
+
+    #[test]
+    fn block_sizes() {
+        let content = vec![7u8; 20_000];
+        let cdata = compress(&content[..], Algorithm::Rle, Pipeline::None, 0, 8192).unwrap();
+        assert_eq!(cdata.header.seekable_block_size, 8192);
+        let index = BlockIndex::read(&mut &cdata.data[..]).unwrap();
+        assert_eq!(index.entries.len(), 3);
+        let mut output = Vec::new();
+        decompress(&cdata.header, &mut &cdata.data[..], &mut output, 1).unwrap();
+        assert_eq!(output, content);
+
+        // Blocks larger than the header says are rejected.
+        let header = Header {
+            seekable_block_size: MIN_BLOCK_SIZE,
+            ..cdata.header.clone()
+        };
+        let err = decompress(&header, &mut &cdata.data[..], &mut Vec::new(), 1).unwrap_err();
+        assert!(err.to_string().contains("more than the block size"));
+        for size in [0, MIN_BLOCK_SIZE - 1, MAX_BLOCK_SIZE + 1] {
+            assert!(compress(&content[..], Algorithm::Rle, Pipeline::None, 0, size).is_err());
+        }
+        assert_eq!(
+            block_size(&Header::default()).unwrap(),
+            DEFAULT_BLOCK_SIZE as usize
+        );
+    }
+
+    #[test]
+    fn parallel() {
+        let content: Vec<u8> = (0..100_000u32).map(|i| (i % 97 * (i % 89)) as u8).collect();
+        let cdata = compress(
+            &content[..],
+            Algorithm::Huffman,
+            Pipeline::None,
+            0,
+            MIN_BLOCK_SIZE,
+        )
+        .unwrap();
+        let sequential = decompress(&cdata.header, &mut &cdata.data[..], &mut Vec::new(), 1);
+        for threads in [0, 2, 3, 64] {
+            let mut output = Vec::new();
+            let result = decompress(&cdata.header, &mut &cdata.data[..], &mut output, threads);
+            assert_eq!(result.unwrap(), *sequential.as_ref().unwrap());
+            assert_eq!(output, content);
+        }
+
+        // An error of any block fails the whole data.
+        let mut data = cdata.data.clone();
+        let last = data.len() - 1;
+        data[last] ^= 0xff;
+        assert!(decompress(&cdata.header, &mut &data[..], &mut Vec::new(), 3).is_err());
+        data.truncate(last - 100);
+        assert!(decompress(&cdata.header, &mut &data[..], &mut Vec::new(), 3).is_err());
+    }
+
+    #[test]
+    fn concurrent_jobs() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let (active, most) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        let work = |i: usize| {
+            let n = active.fetch_add(1, Ordering::SeqCst) + 1;
+            most.fetch_max(n, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            active.fetch_sub(1, Ordering::SeqCst);
+            Ok(i)
+        };
+        let mut order = Vec::new();
+        in_parallel(4, (0..12).map(Ok), work, |i| {
+            order.push(i);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(order, (0..12).collect::<Vec<_>>());
+        // The jobs overlap, the threads do not wait for each other.
+        assert!(most.load(Ordering::SeqCst) > 1);
+
+        let work = |i: usize| match i {
+            5 => Err("failed".into()),
+            i => Ok(i),
+        };
+        assert!(in_parallel(4, (0..12).map(Ok), work, |_| Ok(())).is_err());
+    }
+}
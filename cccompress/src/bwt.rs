@@ -0,0 +1,171 @@
+//! Module contains the Burrows-Wheeler transform and the move-to-front coding, a preprocessing
+//! pipeline in front of the compression algorithm. The transform sorts all rotations of a block,
+//! so equal contexts get grouped and their preceding bytes form long runs. The move-to-front
+//! coding turns these runs into mostly small numbers, which the entropy coders compress well.
+//!
+//! **Byte Representation**
+//!
+//! The content is split into blocks of the block size, the last one may be shorter. Each block is
+//! stored as 4 bytes u32 (LE) index of the original rotation, followed by the move-to-front coded
+//! last column of the sorted rotations.
+
+use crate::Result;
+
+/// Default size of the blocks, which get transformed separately.
+pub const DEFAULT_BLOCK_SIZE: u32 = 900_000;
+/// Minimum size of the blocks.
+pub const MIN_BLOCK_SIZE: u32 = 1 << 10;
+/// Maximum size of the blocks, limits the memory needed for sorting and restoring a block.
+pub const MAX_BLOCK_SIZE: u32 = 1 << 24;
+
+/// Transforms the content block by block, see the module documentation.
+pub fn forward(content: &[u8], block_size: u32) -> Vec<u8> {
+    let mut data = Vec::with_capacity(content.len() + content.len() / block_size as usize * 4 + 4);
+    for block in content.chunks(block_size as usize) {
+        let (mut last, primary) = transform(block);
+        move_to_front(&mut last);
+        data.extend_from_slice(&primary.to_le_bytes());
+        data.append(&mut last);
+    }
+    data
+}
+
+/// Restores the content from the output of `forward()` with the same block size.
+pub fn inverse(data: &[u8], block_size: u32) -> Result<Vec<u8>> {
+    let mut content = Vec::with_capacity(data.len());
+    for block in data.chunks(block_size as usize + 4) {
+        if block.len() <= 4 {
+            return Err("BWT data is broken".into());
+        }
+        let primary = u32::from_le_bytes(block[..4].try_into()?) as usize;
+        let mut last = block[4..].to_vec();
+        move_to_front_inverse(&mut last);
+        content.append(&mut untransform(&last, primary)?);
+    }
+    Ok(content)
+}
+
+/// Burrows-Wheeler transform of a single block. Returns the last column of the sorted rotations
+/// and the index of the original block among them.
+fn transform(block: &[u8]) -> (Vec<u8>, u32) {
+    let n = block.len();
+    let mut rank: Vec<u32> = block.iter().map(|b| *b as u32).collect();
+    let mut keyed: Vec<(u64, u32)> = Vec::with_capacity(n);
+    // Prefix doubling: after the round with offset k, the rotations are sorted by their first
+    // 2k bytes, until all ranks differ or the offset covers the whole block.
+    let mut k = 1;
+    loop {
+        keyed.clear();
+        keyed.extend((0..n).map(|i| {
+            let key = (rank[i] as u64) << 32 | rank[(i + k) % n] as u64;
+            (key, i as u32)
+        }));
+        keyed.sort_unstable();
+        let mut r = 0;
+        for w in 0..n {
+            if w > 0 && keyed[w].0 != keyed[w - 1].0 {
+                r += 1;
+            }
+            rank[keyed[w].1 as usize] = r;
+        }
+        if r as usize == n - 1 || k >= n {
+            break;
+        }
+        k *= 2;
+    }
+
+    let primary = keyed.iter().position(|(_, i)| *i == 0).unwrap_or(0);
+    let last = keyed
+        .iter()
+        .map(|(_, i)| block[(*i as usize + n - 1) % n])
+        .collect();
+    (last, primary as u32)
+}
+
+/// Restores a single block from the last column of the sorted rotations.
+fn untransform(last: &[u8], primary: usize) -> Result<Vec<u8>> {
+    let n = last.len();
+    if primary >= n {
+        return Err("BWT data is broken".into());
+    }
+    // Start of each byte in the first column, which is the sorted last column.
+    let mut starts = [0usize; 256];
+    last.iter().for_each(|b| starts[*b as usize] += 1);
+    let mut sum = 0;
+    for start in starts.iter_mut() {
+        (*start, sum) = (sum, sum + *start);
+    }
+    // Row of the rotation, which starts one byte earlier than the one in row i.
+    let mut previous = vec![0; n];
+    for (i, b) in last.iter().enumerate() {
+        previous[i] = starts[*b as usize];
+        starts[*b as usize] += 1;
+    }
+
+    let mut block = vec![0; n];
+    let mut row = primary;
+    for byte in block.iter_mut().rev() {
+        *byte = last[row];
+        row = previous[row];
+    }
+    Ok(block)
+}
+
+/// Replaces each byte by its position in a list of all bytes, which moves it to the front.
+fn move_to_front(data: &mut [u8]) {
+    let mut list: Vec<u8> = (0..=u8::MAX).collect();
+    for byte in data.iter_mut() {
+        let pos = list.iter().position(|b| b == byte).unwrap_or(0);
+        list[..=pos].rotate_right(1);
+        *byte = pos as u8;
+    }
+}
+
+/// Restores the bytes from the output of `move_to_front()`.
+fn move_to_front_inverse(data: &mut [u8]) {
+    let mut list: Vec<u8> = (0..=u8::MAX).collect();
+    for byte in data.iter_mut() {
+        let pos = *byte as usize;
+        *byte = list[pos];
+        list[..=pos].rotate_right(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transform_block() {
+        let (last, primary) = transform(b"banana");
+        assert_eq!((&last[..], primary), (&b"nnbaaa"[..], 3));
+        assert_eq!(untransform(&last, 3).unwrap(), b"banana");
+        assert!(untransform(&last, 6).is_err());
+
+        for block in [&b"abababab"[..], &[0; 10], b"x"] {
+            let (last, primary) = transform(block);
+            assert_eq!(untransform(&last, primary as usize).unwrap(), block);
+        }
+    }
+
+    #[test]
+    fn mtf() {
+        let mut data = b"aaabbbaaa".to_vec();
+        move_to_front(&mut data);
+        assert_eq!(data, vec![97, 0, 0, 98, 0, 0, 1, 0, 0]);
+        move_to_front_inverse(&mut data);
+        assert_eq!(data, b"aaabbbaaa");
+    }
+
+    #[test]
+    fn forward_inverse() {
+        let content = std::fs::read("135-0.txt").unwrap();
+        let content = &content[..100_000];
+        let data = forward(content, 1 << 15);
+        assert_eq!(data.len(), content.len() + 4 * 4);
+        // A large share of the transformed text are zeros.
+        assert!(data.iter().filter(|b| **b == 0).count() > content.len() / 3);
+        assert_eq!(inverse(&data, 1 << 15).unwrap(), content);
+        assert!(inverse(&data[..3 * ((1 << 15) + 4) + 3], 1 << 15).is_err());
+    }
+}
@@ -0,0 +1,318 @@
+//! Module contains the pluggable compression algorithms. Each algorithm implements
+//! `CompressionAlgorithm` and gets an id, which is stored in the header, so that decompressing
+//! selects the same algorithm again. An optional pipeline preprocesses the content in front of
+//! the algorithm.
+
+use crate::{
+    algorithm::{self, CharSpectrum, PrefixCodeTable, MAX_CODE_LENGTH},
+    arith::Arith,
+    bwt, dict,
+    fs::{CompressedData, Header},
+    lz77::{self, Lz77},
+    rle::Rle,
+    spectrum,
+    symbols::{self, SymbolModel},
+    PackOptions, Result,
+};
+use clap::ValueEnum;
+use std::borrow::Cow;
+
+/// A compression algorithm, which transforms a content into compressed data and back.
+pub trait CompressionAlgorithm {
+    /// Compresses the content. Only the fields of the header, which describe the encoded data,
+    /// are set: the prefix table, the number of data bytes and of unused bits.
+    fn compress(&self, content: &[u8]) -> Result<CompressedData>;
+
+    /// Restores the content from compressed data, as returned by `compress()`.
+    fn decompress(&self, cdata: &CompressedData) -> Result<Vec<u8>>;
+
+    /// Like `compress()`, but with a preset dictionary, see `dict`. Algorithms, which can not make
+    /// use of a dictionary, fail.
+    fn compress_with_dict(&self, _content: &[u8], _dict: &[u8]) -> Result<CompressedData> {
+        Err("the compression algorithm does not support dictionaries".into())
+    }
+
+    /// Restores the content from compressed data, as returned by `compress_with_dict()`.
+    fn decompress_with_dict(&self, _cdata: &CompressedData, _dict: &[u8]) -> Result<Vec<u8>> {
+        Err("the compression algorithm does not support dictionaries".into())
+    }
+}
+
+/// Available compression algorithms, the id of the used one is stored in the header.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Algorithm {
+    /// Huffman coding of the single bytes.
+    #[default]
+    Huffman,
+    /// Repeated sequences are replaced by LZ77 references, before the result is Huffman coded.
+    Lz77,
+    /// Runs of equal bytes are replaced by their length and the byte.
+    Rle,
+    /// Arithmetic coding of the single bytes.
+    Arith,
+}
+
+impl Algorithm {
+    /// Id of the algorithm in the header.
+    pub fn id(self) -> u8 {
+        match self {
+            Algorithm::Huffman => 0,
+            Algorithm::Lz77 => 1,
+            Algorithm::Rle => 2,
+            Algorithm::Arith => 3,
+        }
+    }
+
+    /// Algorithm of the given id in the header.
+    pub fn from_id(id: u8) -> Result<Algorithm> {
+        match id {
+            0 => Ok(Algorithm::Huffman),
+            1 => Ok(Algorithm::Lz77),
+            2 => Ok(Algorithm::Rle),
+            3 => Ok(Algorithm::Arith),
+            _ => Err(format!("unknown compression algorithm {id}").into()),
+        }
+    }
+
+    /// Name of the algorithm, as on the command line.
+    pub fn name(self) -> String {
+        self.to_possible_value()
+            .map_or_else(String::new, |v| v.get_name().to_string())
+    }
+
+    /// Implementation of the algorithm with its default configuration.
+    pub fn codec(self) -> &'static dyn CompressionAlgorithm {
+        match self {
+            Algorithm::Huffman => &Huffman::DEFAULT,
+            Algorithm::Lz77 => &Lz77::DEFAULT,
+            Algorithm::Rle => &Rle,
+            Algorithm::Arith => &Arith,
+        }
+    }
+
+    /// Implementation of the algorithm, configured by the compression level and the maximum code
+    /// length of the options, as far as the algorithm has them.
+    pub fn configured(self, options: &PackOptions) -> Box<dyn CompressionAlgorithm> {
+        let huffman = Huffman {
+            max_code_length: options.max_code_length,
+            spectrum: None,
+            symbols: SymbolModel::Bytes,
+        };
+        match self {
+            Algorithm::Huffman => Box::new(Huffman {
+                spectrum: options.spectrum.clone(),
+                symbols: options.symbols,
+                ..huffman
+            }),
+            Algorithm::Lz77 => Box::new(Lz77 {
+                level: options.level.min(lz77::MAX_LEVEL),
+                huffman,
+            }),
+            Algorithm::Rle => Box::new(Rle),
+            Algorithm::Arith => Box::new(Arith),
+        }
+    }
+}
+
+/// Preprocessing of the content in front of the compression algorithm, its id and block size are
+/// stored in the header.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Pipeline {
+    /// The content is compressed as it is.
+    #[default]
+    None,
+    /// Burrows-Wheeler transform and move-to-front coding of blocks of the given size, see `bwt`.
+    Bwt { block_size: u32 },
+}
+
+impl Pipeline {
+    /// Id of the pipeline in the header.
+    pub fn id(self) -> u8 {
+        match self {
+            Pipeline::None => 0,
+            Pipeline::Bwt { .. } => 1,
+        }
+    }
+
+    /// Block size of the pipeline in the header, 0 if it does not work on blocks.
+    pub fn block_size(self) -> u32 {
+        match self {
+            Pipeline::None => 0,
+            Pipeline::Bwt { block_size } => block_size,
+        }
+    }
+
+    /// Pipeline of the given id and block size in the header.
+    pub fn from_header(header: &Header) -> Result<Pipeline> {
+        match header.pipeline {
+            0 => Ok(Pipeline::None),
+            1 => {
+                let block_size = header.block_size;
+                if !(bwt::MIN_BLOCK_SIZE..=bwt::MAX_BLOCK_SIZE).contains(&block_size) {
+                    return Err(format!("invalid block size {block_size} of the pipeline").into());
+                }
+                Ok(Pipeline::Bwt { block_size })
+            }
+            id => Err(format!("unknown pipeline {id}").into()),
+        }
+    }
+
+    /// Preprocesses the content.
+    pub fn apply(self, content: &[u8]) -> Cow<'_, [u8]> {
+        match self {
+            Pipeline::None => Cow::Borrowed(content),
+            Pipeline::Bwt { block_size } => Cow::Owned(bwt::forward(content, block_size)),
+        }
+    }
+
+    /// Restores the content from the output of `apply()`.
+    pub fn revert(self, data: Vec<u8>) -> Result<Vec<u8>> {
+        match self {
+            Pipeline::None => Ok(data),
+            Pipeline::Bwt { block_size } => bwt::inverse(&data, block_size),
+        }
+    }
+}
+
+/// Huffman coding by canonical prefix codes, see `algorithm`. Codes longer than `max_code_length`
+/// bits are avoided by `algorithm::limited_code_lengths()`. An imported `spectrum` replaces the
+/// one of the content, see `spectrum`. Symbol models other than bytes code pairs of bytes or
+/// words instead, see `symbols`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Huffman {
+    pub max_code_length: u8,
+    pub spectrum: Option<CharSpectrum>,
+    pub symbols: SymbolModel,
+}
+
+impl Huffman {
+    /// Huffman coding without further limit of the code lengths.
+    pub const DEFAULT: Huffman = Huffman {
+        max_code_length: MAX_CODE_LENGTH,
+        spectrum: None,
+        symbols: SymbolModel::Bytes,
+    };
+}
+
+impl Default for Huffman {
+    fn default() -> Huffman {
+        Huffman::DEFAULT
+    }
+}
+
+impl CompressionAlgorithm for Huffman {
+    fn compress(&self, content: &[u8]) -> Result<CompressedData> {
+        if self.symbols != SymbolModel::Bytes {
+            return symbols::compress(content, self.symbols, self.max_code_length);
+        }
+        let table = match &self.spectrum {
+            Some(spectrum) => spectrum::table(spectrum, self.max_code_length)?,
+            None => {
+                PrefixCodeTable::build(&CharSpectrum::from_bytes(content), self.max_code_length)?
+            }
+        };
+        let (data, unused_bits) = algorithm::encode(&table, content)?;
+
+        Ok(CompressedData {
+            header: Header {
+                prefix_table: Vec::<u8>::from(&table),
+                data_bytes: data.len() as u32,
+                unused_bits,
+                ..Default::default()
+            },
+            data,
+        })
+    }
+
+    /// The symbol model is taken from the header, not from `symbols`.
+    fn decompress(&self, cdata: &CompressedData) -> Result<Vec<u8>> {
+        if SymbolModel::from_id(cdata.header.symbols)? != SymbolModel::Bytes {
+            return symbols::decompress(cdata);
+        }
+        let table = PrefixCodeTable::try_from(&cdata.header.prefix_table[..])?;
+        algorithm::decode(&table, &cdata.data, cdata.header.unused_bits)
+    }
+
+    /// The table is derived from the dictionary, so it is not stored in the header.
+    fn compress_with_dict(&self, content: &[u8], dict: &[u8]) -> Result<CompressedData> {
+        let (data, unused_bits) = algorithm::encode(&dict::table(dict)?, content)?;
+        Ok(CompressedData {
+            header: Header {
+                data_bytes: data.len() as u32,
+                unused_bits,
+                ..Default::default()
+            },
+            data,
+        })
+    }
+
+    fn decompress_with_dict(&self, cdata: &CompressedData, dict: &[u8]) -> Result<Vec<u8>> {
+        let table = dict::table(dict)?;
+        algorithm::decode(&table, &cdata.data, cdata.header.unused_bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids() {
+        for algo in Algorithm::value_variants() {
+            assert_eq!(Algorithm::from_id(algo.id()).unwrap(), *algo);
+        }
+        assert!(Algorithm::from_id(255).is_err());
+
+        let pipeline = Pipeline::Bwt { block_size: 4096 };
+        let header = Header {
+            pipeline: pipeline.id(),
+            block_size: pipeline.block_size(),
+            ..Default::default()
+        };
+        assert_eq!(Pipeline::from_header(&header).unwrap(), pipeline);
+        let header = Header {
+            block_size: 0,
+            ..header
+        };
+        assert!(Pipeline::from_header(&header).is_err());
+    }
+
+    #[test]
+    fn roundtrip_all_algorithms() {
+        let content = b"aaaaaaaabbbbbbbbbbbbcabcabcabc\n\n\n\0\0\0\0\0";
+        for algo in Algorithm::value_variants() {
+            let cdata = algo.codec().compress(content).unwrap();
+            assert_eq!(cdata.header.data_bytes as usize, cdata.data.len());
+            assert_eq!(algo.codec().decompress(&cdata).unwrap(), content);
+        }
+    }
+
+    #[test]
+    fn symbol_models() {
+        let content = b"the cat and the hat and the bat\n";
+        for symbols in SymbolModel::value_variants() {
+            let huffman = Huffman {
+                symbols: *symbols,
+                ..Default::default()
+            };
+            let cdata = huffman.compress(content).unwrap();
+            assert_eq!(cdata.header.symbols, symbols.id());
+            assert_eq!(Huffman::DEFAULT.decompress(&cdata).unwrap(), content);
+        }
+    }
+
+    #[test]
+    fn dictionaries() {
+        let dict = b"aaaabbbbcabc";
+        let content = b"abcabc\xff";
+        for algo in [Algorithm::Huffman, Algorithm::Lz77] {
+            let cdata = algo.codec().compress_with_dict(content, dict).unwrap();
+            let result = algo.codec().decompress_with_dict(&cdata, dict).unwrap();
+            assert_eq!(result, content);
+        }
+        let cdata = Huffman::DEFAULT.compress_with_dict(content, dict).unwrap();
+        assert_eq!(cdata.header.symbols, 0);
+        assert!(cdata.header.prefix_table.is_empty());
+        assert!(Rle.compress_with_dict(content, dict).is_err());
+    }
+}
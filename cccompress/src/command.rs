@@ -1,13 +1,173 @@
 //! Encapsules command line interface related implementations.
 
-use clap::Parser;
+use crate::{
+    algorithm::{CharSpectrum, MAX_CODE_LENGTH},
+    blocks, bwt,
+    fs::{FILE_EXTENSION, STDIO},
+    lz77,
+    recursive::Filter,
+    spectrum,
+    symbols::SymbolModel,
+    Algorithm, Pipeline,
+};
+use clap::{Parser, ValueEnum};
+
+/// Options, how files get compressed, see `Compressor`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackOptions {
+    /// Compression algorithm.
+    pub algo: Algorithm,
+    /// Preprocessing pipeline in front of the compression algorithm.
+    pub pipeline: Pipeline,
+    /// Compression level, see `Lz77`.
+    pub level: u8,
+    /// Maximum length of Huffman codes, see `algorithm::limited_code_lengths()`.
+    pub max_code_length: u8,
+    /// Whether the checksum of the original content gets stored.
+    pub checksum: bool,
+    /// Whether the seekable block format is written, see `blocks`.
+    pub seekable: bool,
+    /// Number of bytes per block of the seekable format, see `blocks`.
+    pub block_size: u32,
+    /// Passphrase, with which the compressed data gets encrypted, see `crypt`.
+    pub passphrase: Option<String>,
+    /// Content of the preset dictionary, see `dict`.
+    pub dict: Option<Vec<u8>>,
+    /// Imported spectrum, from which Huffman coding derives its table, see `spectrum`.
+    pub spectrum: Option<CharSpectrum>,
+    /// Symbols, which Huffman coding codes, see `symbols`.
+    pub symbols: SymbolModel,
+    /// Whether an existing output file gets overwritten.
+    pub force: bool,
+    /// Whether the output file only replaces its target once it is complete, see
+    /// `fs::OutputFile`.
+    pub atomic: bool,
+    /// Whether the source gets removed, once the packed file has been verified.
+    pub remove_source: bool,
+    /// Extension of compressed files, `FILE_EXTENSION` if not given.
+    pub suffix: Option<String>,
+    /// Name of a file, to which the Huffman tree of the source gets written in the DOT language.
+    pub dump_tree: Option<String>,
+    /// Name of a file, to which the prefix code table of the source gets written, as CSV if the
+    /// name ends with `.csv`.
+    pub dump_table: Option<String>,
+    /// Name of a file, to which the spectrum of the source gets written as JSON, see `spectrum`.
+    pub dump_spectrum: Option<String>,
+}
+
+impl PackOptions {
+    /// Extension of the compressed files.
+    pub fn extension(&self) -> &str {
+        self.suffix.as_deref().unwrap_or(FILE_EXTENSION)
+    }
+}
+
+impl Default for PackOptions {
+    fn default() -> PackOptions {
+        PackOptions {
+            algo: Algorithm::default(),
+            pipeline: Pipeline::default(),
+            level: lz77::DEFAULT_LEVEL,
+            max_code_length: MAX_CODE_LENGTH,
+            checksum: true,
+            seekable: false,
+            block_size: blocks::DEFAULT_BLOCK_SIZE,
+            passphrase: None,
+            dict: None,
+            spectrum: None,
+            symbols: SymbolModel::default(),
+            force: false,
+            atomic: true,
+            remove_source: false,
+            suffix: None,
+            dump_tree: None,
+            dump_table: None,
+            dump_spectrum: None,
+        }
+    }
+}
+
+/// Options, how files get decompressed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnpackOptions {
+    /// Whether the modification time and the permission bits get restored from the header.
+    pub restore_meta: bool,
+    /// Passphrase of encrypted files.
+    pub passphrase: Option<String>,
+    /// Content of the preset dictionary, which files packed with one need, see `dict`.
+    pub dict: Option<Vec<u8>>,
+    /// Whether existing output files get overwritten.
+    pub force: bool,
+    /// Whether output files only replace their targets once they are complete, see
+    /// `fs::OutputFile`.
+    pub atomic: bool,
+    /// Whether the compressed file gets removed, once all entries have been unpacked without
+    /// errors.
+    pub remove_source: bool,
+    /// Extension of compressed files, `FILE_EXTENSION` if not given.
+    pub suffix: Option<String>,
+    /// Whether all entries get written to stdout instead of files.
+    pub stdout: bool,
+    /// Whether as much as possible gets recovered from a truncated or corrupted file, instead of
+    /// failing at the first error.
+    pub salvage: bool,
+    /// Number of leading path components, which get removed from the names of entries.
+    pub strip_components: usize,
+    /// Number of threads, which decode the blocks of seekable files, all cores for 0, see
+    /// `blocks`.
+    pub threads: usize,
+}
+
+impl UnpackOptions {
+    /// Extension of the compressed files.
+    pub fn extension(&self) -> &str {
+        self.suffix.as_deref().unwrap_or(FILE_EXTENSION)
+    }
+}
+
+impl Default for UnpackOptions {
+    fn default() -> UnpackOptions {
+        UnpackOptions {
+            restore_meta: true,
+            passphrase: None,
+            dict: None,
+            force: false,
+            atomic: true,
+            remove_source: false,
+            suffix: None,
+            stdout: false,
+            salvage: false,
+            strip_components: 0,
+            threads: 0,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub enum CtDirective {
-    /// Compress text file from given filename and optional fixed output name.
-    Pack(String, Option<String>),
-    /// Decompress binary file from given filename.
-    Unpack(String),
+    /// Compress any file from given filename and optional fixed output name, with the given
+    /// options.
+    Pack(String, Option<String>, PackOptions),
+    /// Decompress binary file from given filename, all entries of a multi-file archive, with the
+    /// given options.
+    Unpack(String, UnpackOptions),
+    /// Decompress a single entry of a multi-file archive, from given filename and entry name,
+    /// with the given options.
+    Extract(String, String, UnpackOptions),
+    /// Print the metadata of all entries of a compressed file, without decompressing them.
+    List(String),
+    /// Decode all entries of a compressed file and verify them, without writing any output.
+    Test(String),
+    /// Append files or directories as new entries to an existing multi-file archive, with the
+    /// given options.
+    Append(String, Vec<String>, PackOptions),
+    /// Compress every file of a directory tree, which the filter selects, into a file of its own
+    /// next to it, with the given options.
+    Recursive(String, Filter, PackOptions),
+    /// Compress and decompress a file by every algorithm and level, and compare them.
+    Bench(String),
+    /// Show the Huffman coding of a file interactively in the terminal.
+    Inspect(String),
 }
 
 impl CtDirective {
@@ -17,22 +177,141 @@ impl CtDirective {
         let args = CtArgs::parse();
         CtDirective::try_from(args).map_err(|e| e.into())
     }
+
+    /// Whether the directive writes its output to stdout, so that nothing else may be printed
+    /// there.
+    pub fn writes_stdout(&self) -> bool {
+        match self {
+            CtDirective::Pack(source, of, _) => of.as_deref().unwrap_or(source) == STDIO,
+            CtDirective::Unpack(source, options) => source == STDIO || options.stdout,
+            CtDirective::Extract(_, _, options) => options.stdout,
+            CtDirective::List(_)
+            | CtDirective::Test(_)
+            | CtDirective::Append(..)
+            | CtDirective::Recursive(..)
+            | CtDirective::Bench(_)
+            | CtDirective::Inspect(_) => false,
+        }
+    }
 }
 
 impl TryFrom<CtArgs> for CtDirective {
     type Error = std::io::Error;
 
     fn try_from(args: CtArgs) -> Result<CtDirective, Self::Error> {
-        if args.pack.is_some() == args.unpack.is_some() {
-            let err = std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "argument error: specifiy either 'pack' or 'unpack'",
+        let sources = [
+            &args.pack,
+            &args.unpack,
+            &args.list,
+            &args.test,
+            &args.append,
+            &args.recursive,
+            &args.bench,
+            &args.inspect,
+        ];
+        let dict = args.dict.as_ref().map(std::fs::read).transpose()?;
+        let suffix = args
+            .suffix
+            .map(|suffix| suffix.trim_start_matches('.').to_string());
+        if sources.iter().filter(|s| s.is_some()).count() != 1 {
+            let err = std::io::Error::other(
+                "argument error: specifiy either 'pack', 'unpack', 'list', 'test', 'append', \
+                 'recursive', 'bench' or 'inspect'",
+            );
+            Err(err)
+        } else if let Some(source) = args.list {
+            Ok(CtDirective::List(source))
+        } else if let Some(source) = args.test {
+            Ok(CtDirective::Test(source))
+        } else if let Some(source) = args.bench {
+            Ok(CtDirective::Bench(source))
+        } else if let Some(source) = args.inspect {
+            Ok(CtDirective::Inspect(source))
+        } else if args.pack.as_deref() == Some(STDIO) && args.of.is_none() {
+            let err = std::io::Error::other(
+                "argument error: packing stdin needs '--of', which may be '-' for stdout",
             );
             Err(err)
-        } else if args.pack.is_some() {
-            Ok(CtDirective::Pack(args.pack.unwrap(), args.of))
+        } else if args.rm
+            && (args.stdout
+                || [&args.pack, &args.unpack, &args.of]
+                    .iter()
+                    .any(|name| name.as_deref() == Some(STDIO)))
+        {
+            let err = std::io::Error::other(
+                "argument error: '--rm' keeps sources, which are read from stdin or written to \
+                 stdout, use files instead",
+            );
+            Err(err)
+        } else if args.append.is_some() && args.files.is_empty() {
+            let err = std::io::Error::other("argument error: 'append' needs files to append");
+            Err(err)
+        } else if args.recursive.as_deref() == Some(STDIO) {
+            let err = std::io::Error::other("argument error: 'recursive' needs a directory");
+            Err(err)
+        } else if let Some(source) = args.pack.or(args.append.clone()).or(args.recursive.clone()) {
+            let pipeline = match args.pipeline {
+                PipelineArg::None => Pipeline::None,
+                PipelineArg::Bwt => Pipeline::Bwt {
+                    block_size: args.bwt_block_size,
+                },
+            };
+            let spectrum = match &args.use_spectrum {
+                Some(fname) => Some(
+                    spectrum::from_json(&std::fs::read_to_string(fname)?)
+                        .map_err(|err| std::io::Error::other(format!("'{fname}': {err}")))?,
+                ),
+                None => None,
+            };
+            let options = PackOptions {
+                algo: args.algo,
+                pipeline,
+                level: args.level,
+                max_code_length: args.max_code_length,
+                checksum: !args.no_checksum,
+                seekable: args.seekable,
+                block_size: args.block_size,
+                passphrase: args.passphrase.filter(|_| args.encrypt),
+                dict,
+                spectrum,
+                symbols: args.symbols,
+                force: args.force,
+                atomic: !args.no_atomic,
+                remove_source: args.rm,
+                suffix,
+                dump_tree: args.dump_tree,
+                dump_table: args.dump_table,
+                dump_spectrum: args.dump_spectrum,
+            };
+            if args.append.is_some() {
+                Ok(CtDirective::Append(source, args.files, options))
+            } else if args.recursive.is_some() {
+                let filter = Filter {
+                    include: args.include,
+                    exclude: args.exclude,
+                };
+                Ok(CtDirective::Recursive(source, filter, options))
+            } else {
+                Ok(CtDirective::Pack(source, args.of, options))
+            }
         } else {
-            Ok(CtDirective::Unpack(args.unpack.unwrap()))
+            let options = UnpackOptions {
+                restore_meta: !args.no_restore_meta,
+                passphrase: args.passphrase,
+                dict,
+                force: args.force,
+                atomic: !args.no_atomic,
+                remove_source: args.rm,
+                suffix,
+                stdout: args.stdout,
+                salvage: args.salvage,
+                strip_components: args.strip_components,
+                threads: args.threads,
+            };
+            match args.extract {
+                Some(name) => Ok(CtDirective::Extract(args.unpack.unwrap(), name, options)),
+                None => Ok(CtDirective::Unpack(args.unpack.unwrap(), options)),
+            }
         }
     }
 }
@@ -40,13 +319,379 @@ impl TryFrom<CtArgs> for CtDirective {
 #[derive(Debug, Parser)]
 #[clap(author, version, about)]
 pub struct CtArgs {
-    /// Name of file to be compressed (packed).
+    /// Name of file or directory to be compressed (packed), `-` for stdin. Compressed stdin gets
+    /// written to stdout, unless `--of` is given.
     #[clap(long, action)]
     pub pack: Option<String>,
-    /// Name of file to be decompressed (unpacked).
+    /// Name of file to be decompressed (unpacked), `-` for stdin. Decompressed stdin gets
+    /// written to stdout, except entries of multi-file archives.
     #[clap(long, action)]
     pub unpack: Option<String>,
-    /// Optional fixed output filename, after decompressing a compressed file.
+    /// Name of compressed file, whose entries get listed.
+    #[clap(long, action)]
+    pub list: Option<String>,
+    /// Name of compressed file, whose entries get decoded and verified.
+    #[clap(long, action)]
+    pub test: Option<String>,
+    /// Name of a multi-file archive, to which the given files get appended.
+    #[clap(long, action)]
+    pub append: Option<String>,
+    /// Name of a directory, whose files get compressed one by one into files next to them, in
+    /// parallel, instead of into one archive.
+    #[clap(long, action, conflicts_with = "of")]
+    pub recursive: Option<String>,
+    /// Compresses only files of `--recursive`, which match the glob, may be repeated. Globs with a
+    /// `/` match the relative path, others the file name, `**` matches any directories.
+    #[clap(long, action = clap::ArgAction::Append, requires = "recursive")]
+    pub include: Vec<String>,
+    /// Skips files and directories of `--recursive`, which match the glob, may be repeated.
+    #[clap(long, action = clap::ArgAction::Append, requires = "recursive")]
+    pub exclude: Vec<String>,
+    /// Name of a file, which gets compressed and decompressed by every algorithm and level, to
+    /// compare their ratio and throughput, `-` for stdin.
+    #[clap(long, action)]
+    pub bench: Option<String>,
+    /// Name of a text or compressed file, whose Huffman tree, prefix code table and encoded
+    /// stream get shown interactively, needs the `inspect` feature.
+    #[clap(long, action)]
+    pub inspect: Option<String>,
+    /// Files or directories to be appended, see `--append`.
+    #[clap(requires = "append")]
+    pub files: Vec<String>,
+    /// Compression algorithm used for packing.
+    #[clap(long, value_enum, default_value_t = Algorithm::Huffman)]
+    pub algo: Algorithm,
+    /// Compression level from 0 (fastest) to 9 (best), how thoroughly LZ77 searches repeated
+    /// sequences.
+    #[clap(
+        long,
+        default_value_t = lz77::DEFAULT_LEVEL,
+        value_parser = clap::value_parser!(u8).range(0..=lz77::MAX_LEVEL as i64)
+    )]
+    pub level: u8,
+    /// Maximum length of Huffman codes in bits, e.g. 15 like DEFLATE. Limited codes compress
+    /// slightly worse, if the limit is hit.
+    #[clap(
+        long,
+        default_value_t = MAX_CODE_LENGTH,
+        value_parser = clap::value_parser!(u8).range(1..=MAX_CODE_LENGTH as i64)
+    )]
+    pub max_code_length: u8,
+    /// Does not store the checksum of the original content, so that unpacking can not verify it.
+    #[clap(long, action)]
+    pub no_checksum: bool,
+    /// Preprocessing pipeline in front of the compression algorithm.
+    #[clap(long, value_enum, default_value_t = PipelineArg::None)]
+    pub pipeline: PipelineArg,
+    /// Size of the blocks, which the BWT pipeline transforms separately.
+    #[clap(
+        long,
+        default_value_t = bwt::DEFAULT_BLOCK_SIZE,
+        value_parser = clap::value_parser!(u32)
+            .range(bwt::MIN_BLOCK_SIZE as i64..=bwt::MAX_BLOCK_SIZE as i64)
+    )]
+    pub bwt_block_size: u32,
+    /// Writes independently compressed blocks with an index, which allows random access.
+    #[clap(long, action)]
+    pub seekable: bool,
+    /// Number of bytes of the original content per block of `--seekable`. Smaller blocks make
+    /// random access cheaper, larger ones compress better.
+    #[clap(
+        long,
+        default_value_t = blocks::DEFAULT_BLOCK_SIZE,
+        value_parser = clap::value_parser!(u32)
+            .range(blocks::MIN_BLOCK_SIZE as i64..=blocks::MAX_BLOCK_SIZE as i64),
+        requires = "seekable"
+    )]
+    pub block_size: u32,
+    /// Encrypts the compressed data with the passphrase, needs the `encrypt` feature.
+    #[clap(long, action, requires = "passphrase", conflicts_with = "seekable")]
+    pub encrypt: bool,
+    /// Preset dictionary, a file of content similar to the packed files. Unpacking needs the same
+    /// dictionary.
+    #[clap(long, action, conflicts_with_all = ["seekable", "pipeline"])]
+    pub dict: Option<String>,
+    /// Passphrase for `--encrypt` and for unpacking encrypted files.
+    #[clap(long, env = "CCCOMPRESS_PASSPHRASE", hide_env_values = true)]
+    pub passphrase: Option<String>,
+    /// Name of a single entry to be extracted from a multi-file archive.
+    #[clap(long, action, requires = "unpack")]
+    pub extract: Option<String>,
+    /// Keeps the current time and default permissions of unpacked files, instead of restoring the
+    /// original ones.
+    #[clap(long, action, requires = "unpack")]
+    pub no_restore_meta: bool,
+    /// Writes the unpacked content to stdout instead of files, all entries of an archive one after
+    /// another.
+    #[clap(long, short = 'c', action, requires = "unpack")]
+    pub stdout: bool,
+    /// Recovers as much as possible from a truncated or corrupted file and reports, where decoding
+    /// stopped.
+    #[clap(long, action, requires = "unpack")]
+    pub salvage: bool,
+    /// Removes the given number of leading directories from the paths of unpacked entries, like
+    /// tar. Entries without any path left are skipped.
+    #[clap(long, default_value_t = 0, requires = "unpack")]
+    pub strip_components: usize,
+    /// Number of threads, which decode the blocks of seekable files in parallel, all cores for 0.
+    #[clap(long, default_value_t = 0, requires = "unpack")]
+    pub threads: usize,
+    /// Overwrites existing output files.
+    #[clap(long, short, action)]
+    pub force: bool,
+    /// Writes output files directly to their names, instead of a temporary file, which is synced
+    /// to disk and renamed once it is complete. An interrupted run may leave a partial file.
+    #[clap(long, action)]
+    pub no_atomic: bool,
+    /// Removes the source after packing, once the compressed file has been verified, or the
+    /// compressed file after unpacking it without errors. Sources are kept by default, like by
+    /// `gzip -k`.
+    #[clap(long, action, conflicts_with_all = ["extract", "append"])]
+    pub rm: bool,
+    /// Extension of compressed files instead of `cpd`, for naming and checking them.
+    #[clap(long, action)]
+    pub suffix: Option<String>,
+    /// Writes the Huffman tree of the packed file in the DOT language of Graphviz to the given
+    /// file, e.g. to render it by `dot -Tsvg`.
+    #[clap(long, action, requires = "pack")]
+    pub dump_tree: Option<String>,
+    /// Writes the prefix code table of the packed file with the frequencies of all symbols to the
+    /// given file, as CSV if its name ends with `.csv`, as aligned table otherwise.
+    #[clap(long, action, requires = "pack")]
+    pub dump_table: Option<String>,
+    /// Writes the frequencies of all symbols of the packed file or directory as JSON to the given
+    /// file, to be reused by `--use-spectrum`.
+    #[clap(long, action, requires = "pack")]
+    pub dump_spectrum: Option<String>,
+    /// Huffman codes the packed files by the table of the frequencies in the given JSON file, as
+    /// written by `--dump-spectrum`, instead of their own.
+    #[clap(long, action, conflicts_with_all = ["seekable", "pipeline", "dict"])]
+    pub use_spectrum: Option<String>,
+    /// Symbols of Huffman coding: single bytes, pairs of bytes or words and the whitespace between
+    /// them, which compress natural-language text better.
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = SymbolModel::Bytes,
+        conflicts_with_all = ["seekable", "dict", "use_spectrum"]
+    )]
+    pub symbols: SymbolModel,
+    /// Prints statistics: sizes, ratio, elapsed time and throughput.
+    #[clap(long, action)]
+    pub stats: bool,
+    /// Prints the statistics as JSON object.
+    #[clap(long, action)]
+    pub stats_json: bool,
+    /// Disables the progress bar and all diagnostics on stderr except errors.
+    #[clap(long, short, action)]
+    pub quiet: bool,
+    /// Prints diagnostics on stderr instead of the progress bar, more of them if repeated: what
+    /// happens to every file with `-v`, details of the coding with `-vv` and `-vvv`.
+    #[clap(long, short, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+    /// Level of the diagnostics on stderr, one of off, error, warn, info, debug and trace,
+    /// overrides `-v` and `-q`.
+    #[clap(long)]
+    pub log_level: Option<log::LevelFilter>,
+    /// Optional fixed output filename, after decompressing a compressed file, `-` for stdout.
     #[clap(long, action)]
     pub of: Option<String>,
 }
+
+/// Pipelines selectable on the command line, see `Pipeline`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PipelineArg {
+    /// No preprocessing.
+    None,
+    /// Burrows-Wheeler transform and move-to-front coding.
+    Bwt,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directive(args: &[&str]) -> CtDirective {
+        let args = CtArgs::try_parse_from([&["cccompress"], args].concat()).unwrap();
+        CtDirective::try_from(args).unwrap()
+    }
+
+    #[test]
+    fn stdio() {
+        assert!(directive(&["--pack", "-", "--of", "-"]).writes_stdout());
+        let args = CtArgs::try_parse_from(["cccompress", "--pack", "-"]).unwrap();
+        assert!(CtDirective::try_from(args).is_err());
+        assert!(directive(&["--pack", "a.txt", "--of", "-"]).writes_stdout());
+        assert!(!directive(&["--pack", "-", "--of", "a.cpd"]).writes_stdout());
+        assert!(directive(&["--unpack", "-"]).writes_stdout());
+        assert!(!directive(&["--unpack", "a.cpd"]).writes_stdout());
+        assert!(directive(&["--unpack", "a.cpd", "--stdout"]).writes_stdout());
+        assert!(directive(&["--unpack", "a.cpd", "--extract", "a", "-c"]).writes_stdout());
+        assert!(CtArgs::try_parse_from(["cccompress", "--pack", "a.txt", "--stdout"]).is_err());
+        assert!(!directive(&["--list", "-"]).writes_stdout());
+    }
+
+    #[test]
+    fn exclusive_directives() {
+        let args = CtArgs::try_parse_from(["cccompress", "--list", "a.cpd", "--unpack", "a.cpd"]);
+        assert!(CtDirective::try_from(args.unwrap()).is_err());
+        assert!(matches!(
+            directive(&["--list", "a.cpd"]),
+            CtDirective::List(_)
+        ));
+        assert!(matches!(
+            directive(&["--test", "a.cpd"]),
+            CtDirective::Test(_)
+        ));
+        assert!(matches!(
+            directive(&["--recursive", "dir", "--include", "*.txt", "--include", "*.md"]),
+            CtDirective::Recursive(_, filter, _) if filter.include.len() == 2
+        ));
+        let args = CtArgs::try_parse_from(["cccompress", "--pack", "a", "--exclude", "*.txt"]);
+        assert!(args.is_err());
+        let args = CtArgs::try_parse_from(["cccompress", "--recursive", "-"]).unwrap();
+        assert!(CtDirective::try_from(args).is_err());
+        assert!(matches!(
+            directive(&["--bench", "a.txt"]),
+            CtDirective::Bench(_)
+        ));
+        assert!(matches!(
+            directive(&["--inspect", "a.txt"]),
+            CtDirective::Inspect(_)
+        ));
+    }
+
+    #[test]
+    fn verbosity() {
+        let args = CtArgs::try_parse_from(["cccompress", "--pack", "a.txt", "-vv"]).unwrap();
+        assert_eq!((args.verbose, args.log_level), (2, None));
+        let args = ["cccompress", "--pack", "a.txt", "--log-level", "debug"];
+        let args = CtArgs::try_parse_from(args).unwrap();
+        assert_eq!(args.log_level, Some(log::LevelFilter::Debug));
+        assert!(CtArgs::try_parse_from(["cccompress", "--pack", "a", "-v", "-q"]).is_err());
+        assert!(CtArgs::try_parse_from(["cccompress", "--pack", "a", "--log-level", "x"]).is_err());
+    }
+
+    #[test]
+    fn encryption() {
+        let passphrase = |args: &[&str]| match directive(args) {
+            CtDirective::Pack(_, _, options) => options.passphrase,
+            CtDirective::Unpack(_, options) => options.passphrase,
+            _ => panic!("no pack or unpack directive"),
+        };
+        let secret = Some(String::from("secret"));
+        let pack = ["--pack", "a.txt", "--passphrase", "secret"];
+        assert_eq!(passphrase(&[&pack[..], &["--encrypt"]].concat()), secret);
+        assert_eq!(passphrase(&pack), None);
+        assert_eq!(
+            passphrase(&["--unpack", "a.cpd", "--passphrase", "secret"]),
+            secret
+        );
+        let args = ["cccompress", "--pack", "a.txt", "--encrypt", "--seekable"];
+        assert!(CtArgs::try_parse_from([&args[..], &["--passphrase", "secret"]].concat()).is_err());
+    }
+
+    #[test]
+    fn append() {
+        let directive = directive(&["--append", "a.cpd", "b.txt", "dir", "--algo", "lz77"]);
+        let CtDirective::Append(archive, files, options) = directive else {
+            panic!("no append directive");
+        };
+        assert_eq!(
+            (archive.as_str(), files),
+            ("a.cpd", vec!["b.txt".into(), "dir".into()])
+        );
+        assert_eq!(options.algo, Algorithm::Lz77);
+        let args = CtArgs::try_parse_from(["cccompress", "--append", "a.cpd"]).unwrap();
+        assert!(CtDirective::try_from(args).is_err());
+        assert!(CtArgs::try_parse_from(["cccompress", "--pack", "a.txt", "b.txt"]).is_err());
+    }
+
+    #[test]
+    fn restore_meta() {
+        let restore = |args: &[&str]| match directive(args) {
+            CtDirective::Unpack(_, options) | CtDirective::Extract(_, _, options) => {
+                options.restore_meta
+            }
+            _ => panic!("no unpack directive"),
+        };
+        assert!(restore(&["--unpack", "a.cpd"]));
+        assert!(!restore(&["--unpack", "a.cpd", "--no-restore-meta"]));
+        assert!(!restore(&[
+            "--unpack",
+            "a.cpd",
+            "--extract",
+            "a",
+            "--no-restore-meta"
+        ]));
+        assert!(CtArgs::try_parse_from(["cccompress", "--no-restore-meta"]).is_err());
+    }
+
+    #[test]
+    fn level_and_checksum() {
+        let options = |args: &[&str]| match directive(args) {
+            CtDirective::Pack(_, _, options) => options,
+            _ => panic!("no pack directive"),
+        };
+        assert_eq!(options(&["--pack", "a.txt"]), PackOptions::default());
+        let options = options(&["--pack", "a.txt", "--level", "9", "--no-checksum"]);
+        assert_eq!((options.level, options.checksum), (9, false));
+        let options = directive(&["--pack", "a.txt", "--max-code-length", "15"]);
+        assert!(matches!(options, CtDirective::Pack(_, _, o) if o.max_code_length == 15));
+        let options = directive(&["--unpack", "a.cpd", "--no-atomic"]);
+        assert!(matches!(options, CtDirective::Unpack(_, o) if !o.atomic));
+        let options = directive(&["--unpack", "a.cpd", "--strip-components", "2"]);
+        assert!(matches!(options, CtDirective::Unpack(_, o) if o.strip_components == 2));
+        let options = directive(&["--unpack", "a.cpd", "--threads", "4"]);
+        assert!(matches!(options, CtDirective::Unpack(_, o) if o.threads == 4));
+        let options = directive(&["--pack", "a.txt", "--seekable", "--block-size", "65536"]);
+        assert!(matches!(options, CtDirective::Pack(_, _, o) if o.block_size == 1 << 16));
+        let args = [
+            "cccompress",
+            "--pack",
+            "a.txt",
+            "--seekable",
+            "--block-size",
+            "100",
+        ];
+        assert!(CtArgs::try_parse_from(args).is_err());
+        assert!(
+            CtArgs::try_parse_from(["cccompress", "--pack", "a", "--block-size", "4096"]).is_err()
+        );
+        let options = directive(&["--pack", "a.txt", "--symbols", "words"]);
+        assert!(matches!(options, CtDirective::Pack(_, _, o) if o.symbols == SymbolModel::Words));
+        let args = [
+            "cccompress",
+            "--pack",
+            "a.txt",
+            "--symbols",
+            "digrams",
+            "--seekable",
+        ];
+        assert!(CtArgs::try_parse_from(args).is_err());
+        let options = directive(&["--pack", "a.txt", "--rm"]);
+        assert!(matches!(options, CtDirective::Pack(_, _, o) if o.remove_source));
+        assert!(
+            matches!(directive(&["--pack", "a.txt"]), CtDirective::Pack(_, _, o) if !o.remove_source)
+        );
+        for args in [
+            &["--pack", "a.txt", "--of", "-", "--rm"][..],
+            &["--unpack", "-", "--rm"],
+            &["--unpack", "a.cpd", "-c", "--rm"],
+        ] {
+            let args = CtArgs::try_parse_from([&["cccompress"], args].concat()).unwrap();
+            assert!(CtDirective::try_from(args).is_err());
+        }
+        assert!(
+            CtArgs::try_parse_from(["cccompress", "--unpack", "a", "--extract", "b", "--rm"])
+                .is_err()
+        );
+        assert!(
+            CtArgs::try_parse_from(["cccompress", "--pack", "a", "--max-code-length", "0"])
+                .is_err()
+        );
+        assert!(
+            CtArgs::try_parse_from(["cccompress", "--pack", "a.txt", "--level", "10"]).is_err()
+        );
+    }
+}
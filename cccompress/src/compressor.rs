@@ -0,0 +1,299 @@
+//! Module contains the `Compressor`, which embeds the compression into applications without file
+//! paths or a `CtDirective`, e.g.
+//! `Compressor::new().algorithm(Algorithm::Lz77).level(6).compress_reader(reader, writer)`. Its
+//! output can be decompressed by `decompress_to()`. The command line tool compresses through it
+//! as well.
+
+use crate::{
+    algorithm::CharSpectrum, blocks, dict, stats::Stats, symbols::SymbolModel, Algorithm,
+    CompressedData, PackOptions, Pipeline,
+};
+use std::io::{Read, Write};
+
+/// Builder of the options of compressing, see `PackOptions`, which compresses contents or readers
+/// into one entry each.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Compressor {
+    options: PackOptions,
+}
+
+impl From<PackOptions> for Compressor {
+    fn from(options: PackOptions) -> Compressor {
+        Compressor { options }
+    }
+}
+
+impl Compressor {
+    /// New type pattern: generates a new compressor with the default options, Huffman coding
+    /// without a pipeline.
+    pub fn new() -> Compressor {
+        Compressor::default()
+    }
+
+    /// Sets the compression algorithm.
+    pub fn algorithm(mut self, algo: Algorithm) -> Compressor {
+        self.options.algo = algo;
+        self
+    }
+
+    /// Sets the preprocessing pipeline in front of the compression algorithm.
+    pub fn pipeline(mut self, pipeline: Pipeline) -> Compressor {
+        self.options.pipeline = pipeline;
+        self
+    }
+
+    /// Sets the compression level from 0 (fastest) to `lz77::MAX_LEVEL` (best), see `Lz77`. The
+    /// other algorithms have no levels.
+    pub fn level(mut self, level: u8) -> Compressor {
+        self.options.level = level;
+        self
+    }
+
+    /// Sets the maximum length of Huffman codes, see `algorithm::limited_code_lengths()`.
+    pub fn max_code_length(mut self, max_code_length: u8) -> Compressor {
+        self.options.max_code_length = max_code_length;
+        self
+    }
+
+    /// Sets whether the checksum of the content gets stored, so that decompressing verifies it.
+    pub fn checksum(mut self, checksum: bool) -> Compressor {
+        self.options.checksum = checksum;
+        self
+    }
+
+    /// Sets whether the seekable block format gets written, see `blocks`.
+    pub fn seekable(mut self, seekable: bool) -> Compressor {
+        self.options.seekable = seekable;
+        self
+    }
+
+    /// Sets the number of bytes per block of the seekable format, see `blocks`.
+    pub fn block_size(mut self, block_size: u32) -> Compressor {
+        self.options.block_size = block_size;
+        self
+    }
+
+    /// Encrypts the compressed data with the passphrase, needs the `encrypt` feature.
+    pub fn passphrase(mut self, passphrase: impl Into<String>) -> Compressor {
+        self.options.passphrase = Some(passphrase.into());
+        self
+    }
+
+    /// Compresses with the preset dictionary, see `dict`.
+    pub fn dictionary(mut self, dict: impl Into<Vec<u8>>) -> Compressor {
+        self.options.dict = Some(dict.into());
+        self
+    }
+
+    /// Huffman codes by the table of the imported spectrum instead of the content's, see
+    /// `spectrum`.
+    pub fn spectrum(mut self, spectrum: CharSpectrum) -> Compressor {
+        self.options.spectrum = Some(spectrum);
+        self
+    }
+
+    /// Sets the symbols of Huffman coding, see `symbols`.
+    pub fn symbols(mut self, symbols: SymbolModel) -> Compressor {
+        self.options.symbols = symbols;
+        self
+    }
+
+    /// The options, which have been set so far.
+    pub fn options(&self) -> &PackOptions {
+        &self.options
+    }
+
+    /// Fails, if the options can not be combined.
+    pub(crate) fn check(&self) -> crate::Result<()> {
+        let options = &self.options;
+        if options.seekable && options.passphrase.is_some() {
+            return Err("the seekable format can not be encrypted".into());
+        }
+        if options.dict.is_some() && (options.seekable || options.pipeline != Pipeline::None) {
+            return Err(
+                "a dictionary can not be combined with the seekable format or a pipeline".into(),
+            );
+        }
+        if options.spectrum.is_some()
+            && (options.algo != Algorithm::Huffman
+                || options.seekable
+                || options.pipeline != Pipeline::None
+                || options.dict.is_some())
+        {
+            return Err(
+                "a spectrum can only be used by Huffman coding without the seekable \
+                        format, a pipeline or a dictionary"
+                    .into(),
+            );
+        }
+        if options.symbols != SymbolModel::Bytes
+            && (options.algo != Algorithm::Huffman
+                || options.seekable
+                || options.dict.is_some()
+                || options.spectrum.is_some())
+        {
+            return Err(
+                "symbols other than bytes can only be coded by Huffman coding without the \
+                 seekable format, a dictionary or a spectrum"
+                    .into(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Compresses the content into the header and the data of one entry.
+    pub fn compress(&self, content: &[u8]) -> crate::Result<CompressedData> {
+        if self.options.seekable {
+            return self.compress_from(content);
+        }
+        self.check()?;
+        let PackOptions {
+            algo,
+            pipeline,
+            ref dict,
+            ..
+        } = self.options;
+        let codec = algo.configured(&self.options);
+        let mut cdata = match dict {
+            Some(dict) => {
+                let mut cdata = codec.compress_with_dict(content, dict)?;
+                cdata.header.dict_id = dict::id(dict);
+                cdata
+            }
+            None => {
+                let mut cdata = codec.compress(&pipeline.apply(content))?;
+                cdata.header.pipeline = pipeline.id();
+                cdata.header.block_size = pipeline.block_size();
+                cdata
+            }
+        };
+        cdata.header.checksum = crc32fast::hash(content);
+        cdata.header.algorithm = algo.id();
+        cdata.header.original_bytes = content.len() as u64;
+        self.seal(cdata)
+    }
+
+    /// Like `compress()`, but reads the content from the reader. The seekable format holds only
+    /// the compressed blocks in memory, otherwise the whole content gets read first.
+    pub fn compress_from(&self, mut reader: impl Read) -> crate::Result<CompressedData> {
+        self.check()?;
+        let options = &self.options;
+        if !options.seekable {
+            let mut content = Vec::new();
+            reader.read_to_end(&mut content)?;
+            return self.compress(&content);
+        }
+        let cdata = blocks::compress(
+            reader,
+            options.algo,
+            options.pipeline,
+            options.level,
+            options.block_size,
+        )?;
+        self.seal(cdata)
+    }
+
+    /// Compresses everything from the reader as one entry without a filename into the writer,
+    /// like a file packed by the command line tool, including its footer, see `footer`. Returns the sizes of the entry.
+    pub fn compress_reader(
+        &self,
+        reader: impl Read,
+        mut writer: impl Write,
+    ) -> crate::Result<Stats> {
+        let cdata = self.compress_from(reader)?;
+        crate::write_file(&mut writer, &cdata)
+    }
+
+    /// Removes the checksum and encrypts the compressed data, as far as the options say so.
+    fn seal(&self, mut cdata: CompressedData) -> crate::Result<CompressedData> {
+        if !self.options.checksum {
+            cdata.header.checksum = 0;
+            cdata.header.no_checksum = true;
+        }
+        if let Some(passphrase) = &self.options.passphrase {
+            cdata.data = crate::encrypt(passphrase, &cdata.data)?;
+            cdata.header.data_bytes =
+                u32::try_from(cdata.data.len()).map_err(|_| "encrypted data exceeds 4 GiB")?;
+            cdata.header.encrypted = true;
+        }
+        Ok(cdata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decompress, lz77};
+
+    #[test]
+    fn builder() {
+        let compressor = Compressor::new()
+            .algorithm(Algorithm::Lz77)
+            .level(lz77::MAX_LEVEL)
+            .checksum(false)
+            .seekable(true);
+        let options = compressor.options();
+        assert_eq!(options.algo, Algorithm::Lz77);
+        assert_eq!(options.level, lz77::MAX_LEVEL);
+        assert!(!options.checksum && options.seekable);
+        assert_eq!(Compressor::from(options.clone()), compressor);
+        assert_eq!(Compressor::new().options(), &PackOptions::default());
+    }
+
+    #[test]
+    fn compress_content() {
+        let content = crate::tests::testfile("135-0.txt");
+        let content = &content[..100_000];
+        for algo in [Algorithm::Huffman, Algorithm::Lz77] {
+            for seekable in [false, true] {
+                let cdata = Compressor::new()
+                    .algorithm(algo)
+                    .seekable(seekable)
+                    .compress(content)
+                    .unwrap();
+                assert_eq!(cdata.header.seekable, seekable);
+                assert_eq!(decompress(&cdata).unwrap(), content);
+            }
+        }
+        let fast = Compressor::new().algorithm(Algorithm::Lz77).level(0);
+        let fast = fast.compress(content).unwrap();
+        let default = Compressor::new().algorithm(Algorithm::Lz77);
+        assert!(fast.data.len() > default.compress(content).unwrap().data.len());
+
+        let limited = Compressor::new()
+            .max_code_length(8)
+            .compress(content)
+            .unwrap();
+        let table = crate::PrefixCodeTable::try_from(&limited.header.prefix_table[..]).unwrap();
+        assert_eq!(table.max_length(), 8);
+        assert_eq!(decompress(&limited).unwrap(), content);
+
+        let invalid = Compressor::new().dictionary(*b"dict").seekable(true);
+        assert!(invalid.compress(content).is_err());
+        let small = Compressor::new().seekable(true).block_size(1 << 12);
+        let cdata = small.compress(content).unwrap();
+        assert_eq!(cdata.header.seekable_block_size, 1 << 12);
+        assert_eq!(decompress(&cdata).unwrap(), content);
+        let words = Compressor::new().symbols(SymbolModel::Words);
+        assert_eq!(
+            decompress(&words.compress(content).unwrap()).unwrap(),
+            content
+        );
+        assert!(words.algorithm(Algorithm::Lz77).compress(content).is_err());
+    }
+
+    #[test]
+    fn without_checksum() {
+        let cdata = Compressor::new().checksum(false).compress(b"abc").unwrap();
+        assert!(cdata.header.no_checksum);
+        assert_eq!(cdata.header.checksum, 0);
+        assert_eq!(decompress(&cdata).unwrap(), b"abc");
+
+        let mut packed = Vec::new();
+        let stats = Compressor::new()
+            .compress_reader(&b"abc"[..], &mut packed)
+            .unwrap();
+        assert_eq!(stats.original_bytes, 3);
+        assert_eq!(stats.compressed_bytes, packed.len() as u64);
+    }
+}
@@ -0,0 +1,83 @@
+//! Module contains the optional encryption of the compressed data (feature `encrypt`). A 256 bit
+//! key gets derived from the passphrase by Argon2id with a random salt, the data gets encrypted
+//! and authenticated by AES-GCM with a random nonce. A wrong passphrase or modified data fail the
+//! authentication, before anything gets decompressed.
+//!
+//! **Byte Representation**
+//!
+//! 0..16           (1) salt of the key derivation
+//! 16..28          (2) nonce of AES-GCM
+//! 28..            (3) encrypted data, followed by the 16 bytes authentication tag
+
+use crate::Result;
+use aes_gcm::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+
+/// Number of bytes of the salt of the key derivation.
+const SALT_LEN: usize = 16;
+/// Number of bytes of the nonce of AES-GCM.
+const NONCE_LEN: usize = 12;
+
+/// Encrypts the data with a key derived from the passphrase.
+pub fn encrypt(passphrase: &str, data: &[u8]) -> Result<Vec<u8>> {
+    let mut salt = [0; SALT_LEN];
+    OsRng
+        .try_fill_bytes(&mut salt)
+        .map_err(|err| format!("no random salt available: {err}"))?;
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, &salt)?);
+    let encrypted = cipher
+        .encrypt(&nonce, data)
+        .map_err(|_| "encryption failed")?;
+
+    let mut output = Vec::with_capacity(SALT_LEN + NONCE_LEN + encrypted.len());
+    output.extend_from_slice(&salt);
+    output.extend_from_slice(&nonce);
+    output.extend_from_slice(&encrypted);
+    Ok(output)
+}
+
+/// Decrypts the output of `encrypt()`. Fails, if the passphrase is wrong or the data has been
+/// modified.
+pub fn decrypt(passphrase: &str, data: &[u8]) -> Result<Vec<u8>> {
+    if data.len() < SALT_LEN + NONCE_LEN {
+        return Err("encrypted data is truncated".into());
+    }
+    let (salt, data) = data.split_at(SALT_LEN);
+    let (nonce, data) = data.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, salt)?);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), data)
+        .map_err(|_| "decryption failed, wrong passphrase or corrupted data".into())
+}
+
+/// Derives the key of AES-GCM from the passphrase.
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key<Aes256Gcm>> {
+    let mut key = Key::<Aes256Gcm>::default();
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| format!("key derivation failed: {err}"))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt_decrypt() {
+        let data = b"compressed data";
+        let encrypted = encrypt("secret", data).unwrap();
+        assert_eq!(encrypted.len(), SALT_LEN + NONCE_LEN + data.len() + 16);
+        assert_ne!(encrypt("secret", data).unwrap(), encrypted);
+        assert_eq!(decrypt("secret", &encrypted).unwrap(), data);
+        assert!(decrypt("wrong", &encrypted).is_err());
+
+        let mut modified = encrypted.clone();
+        modified[SALT_LEN + NONCE_LEN] ^= 1;
+        assert!(decrypt("secret", &modified).is_err());
+        assert!(decrypt("secret", &encrypted[..20]).is_err());
+    }
+}
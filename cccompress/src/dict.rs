@@ -0,0 +1,55 @@
+//! Module contains preset dictionaries: a sample of content, which is shared by many small and
+//! similar files, e.g. logs or JSON records. Both packing and unpacking need the same dictionary,
+//! so its id gets stored in the header.
+//!
+//! Huffman coding derives its table from the dictionary instead of the content, so the table does
+//! not need to be stored with every file. LZ77 coding starts with the end of the dictionary in its
+//! window, so references can point into it.
+
+use crate::{
+    algorithm::{CharSpectrum, CtBinaryTree, PrefixCodeTable},
+    Result,
+};
+
+/// Id of a dictionary, its CRC32 checksum. The id 0 stands for no dictionary, so a checksum of 0
+/// becomes 1.
+pub fn id(dict: &[u8]) -> u32 {
+    crc32fast::hash(dict).max(1)
+}
+
+/// Prefix code table derived from the dictionary. Every symbol gets counted once more, so that
+/// contents with symbols missing in the dictionary can be encoded as well.
+pub fn table(dict: &[u8]) -> Result<PrefixCodeTable> {
+    let mut spectrum = CharSpectrum::from_bytes(dict);
+    spectrum.add(&(0..=u8::MAX).collect::<Vec<u8>>());
+    Ok(PrefixCodeTable::from(&CtBinaryTree::try_from(&spectrum)?))
+}
+
+/// Checks, whether the dictionary is the one of the given id.
+pub fn check(dict: &[u8], dict_id: u32) -> Result<()> {
+    if id(dict) != dict_id {
+        return Err(format!(
+            "wrong dictionary, the data needs the one with id {dict_id:08x}, not {:08x}",
+            id(dict)
+        )
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dictionary_table() {
+        let dict = b"{\"level\":\"info\",\"message\":\"started\"}";
+        assert_eq!(id(dict), crc32fast::hash(dict));
+        assert!(check(dict, id(dict)).is_ok());
+        assert!(check(b"other", id(dict)).is_err());
+
+        let table = table(dict).unwrap();
+        assert!(table.code(b'"').unwrap().1 < table.code(b'x').unwrap().1);
+        assert!((0..=u8::MAX).all(|symbol| table.code(symbol).is_some()));
+    }
+}
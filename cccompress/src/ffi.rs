@@ -0,0 +1,154 @@
+//! Module contains the C interface, so that the codec can be used by C or C++ tooling. The build
+//! generates the header `include/cccompress.h`, programs link against the shared library of this
+//! crate. Buffers returned by `cc_compress()` and `cc_decompress()` belong to Rust and have to be
+//! released by `cc_free()`, not by `free()`.
+
+use crate::{compressor::Compressor, Algorithm, Result};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+/// Status returned by the functions of the C interface.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CcStatus {
+    /// Succeeded, the output is valid.
+    Ok = 0,
+    /// A required pointer is null.
+    NullPointer = 1,
+    /// Compressing or decompressing failed, e.g. the input is no compressed file.
+    Failed = 2,
+}
+
+/// Compresses `len` bytes at `content` by the algorithm of the given id, see `Header`, e.g. 0 for
+/// Huffman coding. On success, `*out` points to `*out_len` bytes of the header and the compressed
+/// data, like a file written by `--pack`.
+///
+/// # Safety
+///
+/// `content` must point to `len` readable bytes, `out` and `out_len` must be writable. The output
+/// has to be released by `cc_free()`.
+#[no_mangle]
+pub unsafe extern "C" fn cc_compress(
+    content: *const u8,
+    len: usize,
+    algorithm: u8,
+    out: *mut *mut u8,
+    out_len: *mut usize,
+) -> CcStatus {
+    // Safety: guaranteed by the caller.
+    unsafe {
+        convert(content, len, out, out_len, |content| {
+            let cdata = Compressor::new()
+                .algorithm(Algorithm::from_id(algorithm)?)
+                .compress(content)?;
+            let mut bytes = Vec::new();
+            crate::write_file(&mut bytes, &cdata)?;
+            Ok(bytes)
+        })
+    }
+}
+
+/// Decompresses `len` bytes of a compressed file at `data`, as returned by `cc_compress()` or
+/// written by `--pack`. On success, `*out` points to the `*out_len` bytes of the original content.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, `out` and `out_len` must be writable. The output
+/// has to be released by `cc_free()`.
+#[no_mangle]
+pub unsafe extern "C" fn cc_decompress(
+    data: *const u8,
+    len: usize,
+    out: *mut *mut u8,
+    out_len: *mut usize,
+) -> CcStatus {
+    // Safety: guaranteed by the caller.
+    unsafe {
+        convert(data, len, out, out_len, |data| {
+            let mut content = Vec::new();
+            crate::decompress_to(data, &mut content)?;
+            Ok(content)
+        })
+    }
+}
+
+/// Releases an output of `cc_compress()` or `cc_decompress()`. Null is ignored.
+///
+/// # Safety
+///
+/// `data` and `len` must be an output and its length, which was not released yet.
+#[no_mangle]
+pub unsafe extern "C" fn cc_free(data: *mut u8, len: usize) {
+    if !data.is_null() {
+        // Safety: the output was a boxed slice of this length, see `convert()`.
+        drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)) });
+    }
+}
+
+/// Checks the pointers, converts the input and hands the output over to C. Panics are caught,
+/// they must not unwind into C.
+///
+/// # Safety
+///
+/// Same like `cc_compress()`.
+unsafe fn convert(
+    input: *const u8,
+    len: usize,
+    out: *mut *mut u8,
+    out_len: *mut usize,
+    conversion: impl FnOnce(&[u8]) -> Result<Vec<u8>>,
+) -> CcStatus {
+    if (input.is_null() && len > 0) || out.is_null() || out_len.is_null() {
+        return CcStatus::NullPointer;
+    }
+    let input = match len {
+        0 => &[][..],
+        // Safety: guaranteed by the caller.
+        _ => unsafe { slice::from_raw_parts(input, len) },
+    };
+    match panic::catch_unwind(AssertUnwindSafe(|| conversion(input))) {
+        Ok(Ok(output)) => {
+            let output = output.into_boxed_slice();
+            // Safety: guaranteed by the caller.
+            unsafe {
+                *out_len = output.len();
+                *out = Box::into_raw(output).cast();
+            }
+            CcStatus::Ok
+        }
+        _ => CcStatus::Failed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn c_round_trip() {
+        let content = b"Same like a file of the command line tool, but from C.";
+        let (mut data, mut data_len) = (ptr::null_mut(), 0);
+        let (mut out, mut out_len) = (ptr::null_mut(), 0);
+        unsafe {
+            let status = cc_compress(content.as_ptr(), content.len(), 1, &mut data, &mut data_len);
+            assert_eq!(status, CcStatus::Ok);
+            assert_eq!(slice::from_raw_parts(data, 4), b"CCPD");
+
+            let status = cc_decompress(data, data_len, &mut out, &mut out_len);
+            assert_eq!(status, CcStatus::Ok);
+            assert_eq!(slice::from_raw_parts(out, out_len), content);
+            cc_free(data, data_len);
+            cc_free(out, out_len);
+
+            let status = cc_decompress(content.as_ptr(), content.len(), &mut out, &mut out_len);
+            assert_eq!(status, CcStatus::Failed);
+            let status = cc_compress(content.as_ptr(), content.len(), 42, &mut out, &mut out_len);
+            assert_eq!(status, CcStatus::Failed);
+            let status = cc_compress(ptr::null(), 1, 0, &mut out, &mut out_len);
+            assert_eq!(status, CcStatus::NullPointer);
+            cc_free(ptr::null_mut(), 0);
+        }
+    }
+}
@@ -0,0 +1,373 @@
+//! Module contains the footer, which ends every compressed file since format version 3. It holds
+//! the number of bytes and the CRC32 checksum of everything in front of it, so that a truncated
+//! file is detected by walking its headers before decoding starts, and a corrupted one once all
+//! entries have been read, even if the damage is in a part, which decoding does not verify.
+//! Concatenated files, e.g. by `cat a.cpd b.cpd` or `--append`, are sections of their own, each
+//! one ends with a footer of the bytes since the previous footer. Every entry of an older version
+//! is a section without a footer.
+//!
+//! **Byte Representation**
+//!
+//! 0..4    (1) `FOOTER_MAGIC`
+//! 4..12   (2) 8 bytes u64, number of bytes of the section in front of the footer
+//! 12..16  (3) 4 bytes u32, CRC32 checksum of these bytes
+
+use crate::{fs::MAGIC, Result};
+use std::io::{self, BufRead, Read, Write};
+
+/// Magic string at the beginning of a footer.
+pub const FOOTER_MAGIC: &[u8; 4] = b"CCFT";
+
+/// Number of bytes of a footer.
+pub const FOOTER_SIZE: usize = FOOTER_MAGIC.len() + 12;
+
+/// First format version, whose files end with a footer.
+pub const FOOTER_VERSION: u8 = 3;
+
+/// Footer of a section of a compressed file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Footer {
+    /// Number of bytes of the section in front of the footer.
+    pub length: u64,
+    /// CRC32 checksum of these bytes.
+    pub checksum: u32,
+}
+
+impl Footer {
+    /// Footer at the beginning of the bytes, `None` if there is none.
+    pub fn read(bytes: &[u8]) -> Option<Footer> {
+        if bytes.len() < FOOTER_SIZE || !bytes.starts_with(FOOTER_MAGIC) {
+            return None;
+        }
+        Some(Footer {
+            length: u64::from_le_bytes(bytes[4..12].try_into().unwrap()),
+            checksum: u32::from_le_bytes(bytes[12..16].try_into().unwrap()),
+        })
+    }
+
+    /// Checks the footer against the length of its section and its checksum, if it is known.
+    pub fn verify(&self, length: u64, checksum: Option<u32>) -> Result<()> {
+        if length != self.length {
+            return Err(format!(
+                "the file is truncated or corrupted, its footer expects {} bytes in front of it, \
+                 but there are {length}",
+                self.length
+            )
+            .into());
+        }
+        match checksum {
+            Some(checksum) if checksum != self.checksum => Err(format!(
+                "checksum mismatch of the file, it is corrupted (expected {:08x}, got \
+                 {checksum:08x})",
+                self.checksum
+            )
+            .into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl From<&Footer> for Vec<u8> {
+    fn from(footer: &Footer) -> Vec<u8> {
+        let mut bytes = FOOTER_MAGIC.to_vec();
+        bytes.extend_from_slice(&footer.length.to_le_bytes());
+        bytes.extend_from_slice(&footer.checksum.to_le_bytes());
+        bytes
+    }
+}
+
+/// Whether a section, which begins with the given bytes, ends with a footer: the ones beginning
+/// with the `MAGIC` and a version from `FOOTER_VERSION` on.
+fn expects_footer(start: &[u8]) -> bool {
+    start.len() > MAGIC.len() && start.starts_with(MAGIC) && start[MAGIC.len()] >= FOOTER_VERSION
+}
+
+/// Length, checksum and beginning of the bytes of the current section.
+#[derive(Debug)]
+struct Section {
+    length: u64,
+    hasher: crc32fast::Hasher,
+    /// Whether all bytes went into the checksum, none were skipped.
+    hashed: bool,
+    /// The first bytes, to find out, whether the section ends with a footer.
+    start: Vec<u8>,
+}
+
+impl Section {
+    /// New type pattern: generates an empty section.
+    fn new() -> Section {
+        Section {
+            length: 0,
+            hasher: crc32fast::Hasher::new(),
+            hashed: true,
+            start: Vec::new(),
+        }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        let missing = (MAGIC.len() + 1).saturating_sub(self.start.len());
+        self.start
+            .extend_from_slice(&bytes[..missing.min(bytes.len())]);
+        self.length += bytes.len() as u64;
+        self.hasher.update(bytes);
+    }
+
+    fn footer(&self) -> Footer {
+        Footer {
+            length: self.length,
+            checksum: self.hasher.clone().finalize(),
+        }
+    }
+}
+
+/// Writer, which ends everything written through it with a footer, see `finish()`.
+#[derive(Debug)]
+pub struct FooterWriter<W: Write> {
+    writer: W,
+    section: Section,
+}
+
+impl<W: Write> FooterWriter<W> {
+    /// New type pattern: generates a new writer around the given one.
+    pub fn new(writer: W) -> FooterWriter<W> {
+        FooterWriter {
+            writer,
+            section: Section::new(),
+        }
+    }
+
+    /// Writes the footer of everything written so far and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer
+            .write_all(&Vec::<u8>::from(&self.section.footer()))?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> Write for FooterWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.writer.write(buf)?;
+        self.section.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Reader of the entries of compressed files, which verifies their footers, see `at_end()`.
+#[derive(Debug)]
+pub struct FooterReader<R> {
+    reader: R,
+    /// Bytes read ahead to find a footer, which are not consumed yet.
+    peeked: Vec<u8>,
+    section: Section,
+    /// Whether a bad or missing footer is an error, otherwise it only gets logged.
+    check: bool,
+}
+
+impl<R: BufRead> FooterReader<R> {
+    /// New type pattern: generates a new reader around the given one. Without `check`, e.g. when
+    /// salvaging, bad or missing footers are only logged.
+    pub fn new(reader: R, check: bool) -> FooterReader<R> {
+        FooterReader {
+            reader,
+            peeked: Vec::new(),
+            section: Section::new(),
+            check,
+        }
+    }
+
+    /// The inner reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Whether there is no further entry, to be called after each one. Reads and verifies the
+    /// footers following it, so that corrupted sections are reported, and fails at the end of
+    /// the input if the footer of a section is missing.
+    pub fn at_end(&mut self) -> Result<bool> {
+        loop {
+            self.peek(FOOTER_MAGIC.len())?;
+            if self.peeked.is_empty() {
+                if self.section.length > 0 && expects_footer(&self.section.start) {
+                    self.report(Err("the footer is missing, the file is truncated".into()))?;
+                }
+                return Ok(true);
+            }
+            if !self.peeked.starts_with(FOOTER_MAGIC) {
+                if !expects_footer(&self.section.start) {
+                    self.section = Section::new();
+                }
+                return Ok(false);
+            }
+            self.peek(FOOTER_SIZE)?;
+            let footer = Footer::read(&self.peeked).ok_or("the footer is truncated")?;
+            self.peeked.drain(..FOOTER_SIZE);
+            let checksum = self.section.hashed.then(|| self.section.footer().checksum);
+            self.report(footer.verify(self.section.length, checksum))?;
+            self.section = Section::new();
+        }
+    }
+
+    /// Skips up to the given number of bytes by the function of the inner reader, e.g. by
+    /// seeking. Returns how many were skipped. Their checksum is unknown, so the footer of the
+    /// section only gets checked by its length.
+    pub fn skip_by(
+        &mut self,
+        bytes: u64,
+        skip: impl FnOnce(&mut R, u64) -> io::Result<u64>,
+    ) -> io::Result<u64> {
+        let peeked = self.peeked.len().min(bytes as usize);
+        let consumed: Vec<u8> = self.peeked.drain(..peeked).collect();
+        self.section.update(&consumed);
+        let n = skip(&mut self.reader, bytes - peeked as u64)?;
+        if n > 0 {
+            self.section.length += n;
+            self.section.hashed = false;
+        }
+        Ok(peeked as u64 + n)
+    }
+
+    /// Reads ahead, until at least `n` bytes are peeked or the input ends.
+    fn peek(&mut self, n: usize) -> io::Result<()> {
+        while self.peeked.len() < n {
+            let buf = self.reader.fill_buf()?;
+            if buf.is_empty() {
+                break;
+            }
+            let amount = buf.len().min(n - self.peeked.len());
+            self.peeked.extend_from_slice(&buf[..amount]);
+            self.reader.consume(amount);
+        }
+        Ok(())
+    }
+
+    /// Fails with the error of a footer, or only logs it without `check`.
+    fn report(&self, result: Result<()>) -> Result<()> {
+        match result {
+            Err(err) if !self.check => {
+                log::warn!("{err}, ignored while salvaging");
+                Ok(())
+            }
+            result => result,
+        }
+    }
+}
+
+impl<R: BufRead> Read for FooterReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = match self.peeked.is_empty() {
+            true => self.reader.read(buf)?,
+            false => {
+                let n = self.peeked.len().min(buf.len());
+                buf[..n].copy_from_slice(&self.peeked[..n]);
+                self.peeked.drain(..n);
+                n
+            }
+        };
+        self.section.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for FooterReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self.peeked.is_empty() {
+            true => self.reader.fill_buf(),
+            false => Ok(&self.peeked),
+        }
+    }
+
+    fn consume(&mut self, amount: usize) {
+        if !self.peeked.is_empty() {
+            let consumed: Vec<u8> = self.peeked.drain(..amount).collect();
+            self.section.update(&consumed);
+            return;
+        }
+        // The bytes are still buffered, since `fill_buf()` returned them.
+        if let Ok(buf) = self.reader.fill_buf() {
+            self.section.update(&buf[..amount.min(buf.len())]);
+        }
+        self.reader.consume(amount);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(entries: &[&[u8]]) -> Vec<u8> {
+        let mut writer = FooterWriter::new(Vec::new());
+        for entry in entries {
+            writer.write_all(entry).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    /// Reads all bytes up to the end, as if they were one entry.
+    fn read_all(bytes: &[u8], check: bool) -> Result<Vec<u8>> {
+        let mut reader = FooterReader::new(bytes, check);
+        let mut content = Vec::new();
+        while !reader.at_end()? {
+            let mut entry = [0; 9];
+            reader.read_exact(&mut entry)?;
+            content.extend_from_slice(&entry);
+        }
+        Ok(content)
+    }
+
+    #[test]
+    fn sections() {
+        let mut bytes = file(&[b"CCPD\x03", b"abcd"]);
+        assert_eq!(bytes.len(), 9 + FOOTER_SIZE);
+        let footer = Footer::read(&bytes[9..]).unwrap();
+        assert_eq!(footer.length, 9);
+        assert_eq!(footer.checksum, crc32fast::hash(b"CCPD\x03abcd"));
+        assert_eq!(Footer::read(&bytes[..FOOTER_SIZE]), None);
+
+        // Concatenated files have a footer each, older ones none.
+        bytes.extend_from_slice(b"CCPD\x02efgh");
+        bytes.extend(file(&[b"CCPD\x03ijkl"]));
+        assert_eq!(
+            read_all(&bytes, true).unwrap(),
+            b"CCPD\x03abcdCCPD\x02efghCCPD\x03ijkl"
+        );
+
+        let mut corrupted = bytes.clone();
+        corrupted[7] ^= 1;
+        let err = read_all(&corrupted, true).unwrap_err();
+        assert!(err.to_string().contains("checksum mismatch of the file"));
+        assert!(read_all(&corrupted, false).is_ok());
+        let truncated = &bytes[..bytes.len() - FOOTER_SIZE];
+        let err = read_all(truncated, true).unwrap_err();
+        assert!(err.to_string().contains("footer is missing"));
+        assert!(read_all(&bytes[..bytes.len() - 1], true).is_err());
+        assert!(read_all(truncated, false).is_ok());
+    }
+
+    #[test]
+    fn skipped() {
+        let bytes = file(&[b"CCPD\x03abcd"]);
+        let mut reader = FooterReader::new(&bytes[..], true);
+        assert_eq!(
+            reader
+                .skip_by(9, |r, n| io::copy(&mut r.take(n), &mut io::sink()))
+                .unwrap(),
+            9
+        );
+        assert!(reader.at_end().unwrap());
+
+        let mut bytes = bytes;
+        bytes[7] ^= 1;
+        let mut reader = FooterReader::new(&bytes[..], true);
+        reader
+            .skip_by(9, |r, n| io::copy(&mut r.take(n), &mut io::sink()))
+            .unwrap();
+        assert!(
+            reader.at_end().is_ok(),
+            "skipped bytes are only checked by their length"
+        );
+    }
+}
@@ -1,81 +1,263 @@
 //! Module contains read and write operations related to files on harddisk, to simplify and
 //! generalize reading and writing from and to files.
 
-use crate::Result;
+use crate::{
+    algorithm::{DecodeIter, PrefixCodeTable},
+    footer::FooterReader,
+    Algorithm, CtError, Result,
+};
 use std::{
     fs::File,
     io::{prelude::*, BufReader},
+    ops::Range,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
 };
 
 /// File extension, file type specification.
 pub const FILE_EXTENSION: &str = "cpd";
 
-/// Some additional constant for additional validation, that we are reading the right file type.
-/// Based on Illuminati-style.
-pub const FILE_CONST: u8 = 23;
+/// Filename, which stands for stdin as source or stdout as target.
+pub const STDIO: &str = "-";
+
+/// Magic string at the beginning of every compressed file, followed by the format version.
+pub const MAGIC: &[u8; 4] = b"CCPD";
+
+/// Version of the file format, which gets written. Files of all versions up to this one can be
+/// read. Since version 3 every file ends with a footer, see `footer`.
+pub const FORMAT_VERSION: u8 = 4;
+
+/// First format version, whose headers store the filename in UTF-8 behind a 2 byte length, see
+/// `Header`.
+pub const UTF8_NAME_VERSION: u8 = 4;
+
+/// Single byte, which identified files of version 1 instead of `MAGIC` and a version. Based on
+/// Illuminati-style.
+pub const LEGACY_FILE_CONST: u8 = 23;
 
 /// TODO Header type
 ///
 /// **Byte Representation**
 ///
-/// 0               (1) number of bytes (n) for an optional filename, 0 when no filename specified
-/// 1..n+1          (2) optional filename
-/// n+3..n+259      (3) prefix code table, 256 bytes
-/// n+259..n+263    (5) 4 bytes u32, number of bytes of encoded data content
-/// n+263           (6) number of unused bits in the last byte
-#[derive(Debug, Default, PartialEq)]
+/// 0..2            (1) 2 bytes u16, number of bytes (n) of an optional filename, 0 when no filename
+///                     specified
+/// 2..n+2          (2) optional filename in UTF-8
+/// n+2..n+4        (3) 2 bytes u16, number of bytes (t) of the prefix code table
+/// n+4..n+t+4      (4) prefix code table, see `PrefixCodeTable`
+/// n+t+4..n+t+8    (5) 4 bytes u32, number of bytes of encoded data content
+/// n+t+8           (6) number of unused bits in the last byte
+/// n+t+9..n+t+13   (7) 4 bytes u32, CRC32 checksum of the original content
+/// n+t+13          (8) id of the compression algorithm, see `Algorithm`
+/// n+t+14          (9) id of the pipeline, see `Pipeline`
+/// n+t+15..n+t+19  (10) 4 bytes u32, block size of the pipeline
+/// n+t+19          (11) 1 if the data is in the seekable block format, see `blocks`, else 0
+/// n+t+20..n+t+28  (12) 8 bytes u64, number of bytes of the original content
+/// n+t+28..n+t+36  (13) 8 bytes u64, modification time in seconds since the Unix epoch
+/// n+t+36..n+t+40  (14) 4 bytes u32, Unix permission bits of the original file
+/// n+t+40          (15) flags, bit 0 is set if the data is encrypted, see `crypt`, bit 1 if (7) is
+///                      no checksum, see `--no-checksum`, bit 2 if (2) is the name of a single
+///                      file instead of a path in an archive
+/// n+t+41..n+t+45  (16) 4 bytes u32, id of the preset dictionary, 0 without one, see `dict`
+/// n+t+45          (17) id of the entry type, see `archive::EntryType`
+/// n+t+46          (18) id of the symbol model of Huffman coding, see `symbols::SymbolModel`
+/// n+t+47..n+t+51  (19) 4 bytes u32, number of bytes per block of the seekable block format
+///
+/// Headers of files, which were written before (9) to (19) existed, end earlier. The missing
+/// fields get read as 0. Files before `UTF8_NAME_VERSION` store (1) in a single byte and (2) one
+/// byte per character, see `Header::parse()`. A modification time or permission bits of 0 are
+/// unknown, e.g. of stdin, and do not get restored. A filename or table longer than 65535 bytes
+/// can not be written, its length does not fit into (1) or (3).
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Header {
     /// (Optional) specified filename.
     pub filename: String,
-    /// The prefix code table.
+    /// The serialized prefix code table.
     pub prefix_table: Vec<u8>,
     /// Number of bytes for the encoded data.
     pub data_bytes: u32,
+    /// Number of unused bits in the last byte of the encoded data.
+    pub unused_bits: u8,
+    /// CRC32 checksum of the original content.
+    pub checksum: u32,
+    /// Id of the compression algorithm, see `Algorithm`.
+    pub algorithm: u8,
+    /// Id of the preprocessing pipeline, see `Pipeline`.
+    pub pipeline: u8,
+    /// Block size of the preprocessing pipeline.
+    pub block_size: u32,
+    /// Whether the data is in the seekable block format.
+    pub seekable: bool,
+    /// Number of bytes of the original content.
+    pub original_bytes: u64,
+    /// Modification time of the original file in seconds since the Unix epoch.
+    pub modified: u64,
+    /// Unix permission bits of the original file.
+    pub mode: u32,
+    /// Whether the data is encrypted.
+    pub encrypted: bool,
+    /// Whether no checksum of the original content was computed, so that it can not be verified.
+    pub no_checksum: bool,
+    /// Whether the filename is the name of a single compressed file instead of a path in an
+    /// archive, see `Header::is_single_file()`.
+    pub single_file: bool,
+    /// Id of the preset dictionary, 0 without one.
+    pub dict_id: u32,
+    /// Id of the entry type, see `archive::EntryType`.
+    pub entry_type: u8,
+    /// Id of the symbol model of Huffman coding, see `symbols::SymbolModel`.
+    pub symbols: u8,
+    /// Number of bytes per block of the seekable block format, 0 if unknown, see `blocks`.
+    pub seekable_block_size: u32,
 }
 
-impl From<&[u8]> for Header {
-    fn from(data: &[u8]) -> Header {
+impl Header {
+    /// Parses the header of a file of the given format version. Filenames of files before
+    /// `UTF8_NAME_VERSION` have a single byte length and one byte per character, later ones must
+    /// be valid UTF-8. Fields (1) to (8), which do not fit into the data, fail with
+    /// `CtError::CorruptHeader`.
+    pub fn parse(data: &[u8], version: u8) -> Result<Header> {
         // (1) & (2)
-        let n = data[0] as usize;
-        let mut filename = String::new();
-        if n > 0 {
-            for &c in data.iter().skip(1).take(n) {
-                filename.push(c as char);
-            }
-        }
+        let (n, filename) = if version < UTF8_NAME_VERSION {
+            let n = field(data, 0..1, "filename length")?[0] as usize;
+            let filename = field(data, 1..n + 1, "filename")?;
+            (n + 1, filename.iter().map(|&c| c as char).collect())
+        } else {
+            let n = u16::from_le_bytes(field(data, 0..2, "filename length")?.try_into()?) as usize;
+            let filename = field(data, 2..n + 2, "filename")?;
+            let filename =
+                String::from_utf8(filename.to_vec()).map_err(|err| CtError::CorruptHeader {
+                    field: "filename",
+                    offset: err.utf8_error().valid_up_to(),
+                })?;
+            (n + 2, filename)
+        };
 
         // (3) & (4)
-        let prefix_table: Vec<u8> = data[n + 1..n + 257].to_vec();
+        let t = field(data, n..n + 2, "prefix code table length")?;
+        let t = u16::from_le_bytes(t.try_into()?) as usize;
+        let prefix_table = field(data, n + 2..n + t + 2, "prefix code table")?.to_vec();
 
         // (5)
-        let idx = n + 257;
-        let data_bytes = [data[idx], data[idx + 1], data[idx + 2], data[idx + 3]];
-        let data_bytes = u32::from_le_bytes(data_bytes);
+        let idx = n + t + 2;
+        let data_bytes = field(data, idx..idx + 4, "data length")?;
+        let data_bytes = u32::from_le_bytes(data_bytes.try_into()?);
+
+        // (6)
+        let unused_bits = field(data, idx + 4..idx + 5, "unused bits")?[0];
+
+        // (7)
+        let idx = idx + 5;
+        let checksum = u32::from_le_bytes(field(data, idx..idx + 4, "checksum")?.try_into()?);
+
+        // (8)
+        let algorithm = field(data, idx + 4..idx + 5, "algorithm")?[0];
+
+        // (9) & (10)
+        let idx = idx + 5;
+        let pipeline = data.get(idx).copied().unwrap_or(0);
+        let block_size = data
+            .get(idx + 1..idx + 5)
+            .map_or(0, |b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
 
-        Header {
+        // (11)
+        let seekable = data.get(idx + 5).is_some_and(|b| *b == 1);
+
+        // (12)
+        let original_bytes = data
+            .get(idx + 6..idx + 14)
+            .map_or(0, |b| u64::from_le_bytes(b.try_into().unwrap()));
+
+        // (13) & (14)
+        let modified = data
+            .get(idx + 14..idx + 22)
+            .map_or(0, |b| u64::from_le_bytes(b.try_into().unwrap()));
+        let mode = data
+            .get(idx + 22..idx + 26)
+            .map_or(0, |b| u32::from_le_bytes(b.try_into().unwrap()));
+
+        // (15)
+        let flags = data.get(idx + 26).copied().unwrap_or(0);
+
+        // (16)
+        let dict_id = data
+            .get(idx + 27..idx + 31)
+            .map_or(0, |b| u32::from_le_bytes(b.try_into().unwrap()));
+
+        // (17)
+        let entry_type = data.get(idx + 31).copied().unwrap_or(0);
+
+        // (18)
+        let symbols = data.get(idx + 32).copied().unwrap_or(0);
+
+        // (19)
+        let seekable_block_size = data
+            .get(idx + 33..idx + 37)
+            .map_or(0, |b| u32::from_le_bytes(b.try_into().unwrap()));
+
+        Ok(Header {
             filename,
             prefix_table,
             data_bytes,
-        }
+            unused_bits,
+            checksum,
+            algorithm,
+            pipeline,
+            block_size,
+            seekable,
+            original_bytes,
+            modified,
+            mode,
+            encrypted: flags & 1 == 1,
+            no_checksum: flags & 2 == 2,
+            single_file: flags & 4 == 4,
+            dict_id,
+            entry_type,
+            symbols,
+            seekable_block_size,
+        })
+    }
+
+    /// Whether this is the entry of a single compressed file, which has its original name or no
+    /// filename at all, e.g. of stdin, instead of an entry of an archive.
+    pub fn is_single_file(&self) -> bool {
+        self.filename.is_empty() || self.single_file
     }
 }
 
-impl From<&Header> for Vec<u8> {
-    fn from(hdr: &Header) -> Vec<u8> {
+/// Bytes of a field of the header, which must be contained in the data. Otherwise the field is
+/// broken at the first missing byte.
+fn field<'d>(data: &'d [u8], range: Range<usize>, field: &'static str) -> Result<&'d [u8]> {
+    data.get(range.clone()).ok_or(CtError::CorruptHeader {
+        field,
+        offset: data.len().saturating_sub(range.start),
+    })
+}
+
+impl TryFrom<&Header> for Vec<u8> {
+    type Error = CtError;
+
+    fn try_from(hdr: &Header) -> Result<Vec<u8>> {
         let mut data = Vec::<u8>::new();
 
         // (1) & (2)
-        if hdr.filename.is_empty() {
-            data.push(0);
-        } else {
-            assert!(hdr.filename.len() < 256);
-            data.push(hdr.filename.len() as u8);
-            hdr.filename.chars().for_each(|c| data.push(c as u8));
-        }
+        let name_len = u16::try_from(hdr.filename.len()).map_err(|_| {
+            format!(
+                "filename of {} bytes can not be stored in the header",
+                hdr.filename.len()
+            )
+        })?;
+        data.extend_from_slice(&name_len.to_le_bytes());
+        data.extend_from_slice(hdr.filename.as_bytes());
 
         // (3) & (4)
-        let mut table_data = hdr.prefix_table.clone();
-        data.append(&mut table_data);
+        let table_len = u16::try_from(hdr.prefix_table.len()).map_err(|_| {
+            format!(
+                "table of {} bytes can not be stored in the header",
+                hdr.prefix_table.len()
+            )
+        })?;
+        data.extend_from_slice(&table_len.to_le_bytes());
+        data.extend_from_slice(&hdr.prefix_table);
 
         // (5)
         let be_bytes = hdr.data_bytes.to_le_bytes();
@@ -84,34 +266,422 @@ impl From<&Header> for Vec<u8> {
         data.push(be_bytes[2]);
         data.push(be_bytes[3]);
 
-        data
+        // (6)
+        data.push(hdr.unused_bits);
+
+        // (7)
+        data.extend_from_slice(&hdr.checksum.to_le_bytes());
+
+        // (8)
+        data.push(hdr.algorithm);
+
+        // (9) & (10)
+        data.push(hdr.pipeline);
+        data.extend_from_slice(&hdr.block_size.to_le_bytes());
+
+        // (11)
+        data.push(hdr.seekable as u8);
+
+        // (12)
+        data.extend_from_slice(&hdr.original_bytes.to_le_bytes());
+
+        // (13) & (14)
+        data.extend_from_slice(&hdr.modified.to_le_bytes());
+        data.extend_from_slice(&hdr.mode.to_le_bytes());
+
+        // (15)
+        data.push(
+            hdr.encrypted as u8 | (hdr.no_checksum as u8) << 1 | (hdr.single_file as u8) << 2,
+        );
+
+        // (16)
+        data.extend_from_slice(&hdr.dict_id.to_le_bytes());
+
+        // (17)
+        data.push(hdr.entry_type);
+
+        // (18)
+        data.push(hdr.symbols);
+
+        // (19)
+        data.extend_from_slice(&hdr.seekable_block_size.to_le_bytes());
+
+        Ok(data)
     }
 }
 
+/// Switches between the names of an original and a compressed file. Text files swap their
+/// extension, e.g. `book.txt` becomes `book.cpd`, any other file gets the extension appended, e.g.
+/// `image.png.cpd`. The extension of a compressed file is removed only, e.g. `book.cpd` becomes
+/// `book`, the original name is restored from the header, see `unpacked_path()`.
 pub fn switch_file_type(name: &str) -> String {
-    let mut oname = String::from(name);
-    match oname.split_off(oname.len() - 3).as_str() {
-        "txt" => {
-            oname.push_str(FILE_EXTENSION);
-        }
-        FILE_EXTENSION => oname.push_str("txt"),
-        _ => {
-            panic!("switch_file_type(): not allowed this file ending!!!")
-        }
+    switch_file_type_with(name, FILE_EXTENSION)
+}
+
+/// Like `switch_file_type()`, but with another extension of compressed files than
+/// `FILE_EXTENSION`, see `--suffix`.
+pub fn switch_file_type_with(name: &str, extension: &str) -> String {
+    if let Some(stem) = name.strip_suffix(&format!(".{extension}")) {
+        stem.to_string()
+    } else if let Some(stem) = name.strip_suffix(".txt") {
+        format!("{stem}.{extension}")
+    } else {
+        format!("{name}.{extension}")
     }
-    oname
+}
+
+/// Path of a single compressed file, after unpacking it: its original name from the header next
+/// to the compressed file, or without a name the one of the compressed file without its extension,
+/// see `switch_file_type_with()`. A name, which is not a plain file name, fails.
+pub fn unpacked_path(source: &str, header: &Header, extension: &str) -> Result<PathBuf> {
+    if header.filename.is_empty() {
+        return Ok(switch_file_type_with(source, extension).into());
+    }
+    let name = Path::new(&header.filename);
+    if name.file_name() != Some(name.as_os_str()) || header.filename.contains(['/', '\\']) {
+        return Err(format!("'{}' of the header is not a file name", header.filename).into());
+    }
+    Ok(Path::new(source).with_file_name(name))
 }
 
 /// Method checks for a correct filename ending regarding the file type extension. Therefor, see
 /// the contant `FILE_EXTENSION`.
-fn check_filename(name: &str) -> Result<()> {
-    if name.ends_with(&format!(".{}", FILE_EXTENSION)) {
+pub(crate) fn check_filename(name: &str) -> Result<()> {
+    check_filename_with(name, FILE_EXTENSION)
+}
+
+/// Like `check_filename()`, but with another extension of compressed files.
+pub(crate) fn check_filename_with(name: &str, extension: &str) -> Result<()> {
+    if name.ends_with(&format!(".{extension}")) {
         Ok(())
     } else {
-        Err(format!("'{}' does not end with '.{}'", name, FILE_EXTENSION).into())
+        Err(format!("'{name}' does not end with '.{extension}'").into())
+    }
+}
+
+/// Creates a file for writing. An existing file only gets overwritten, if forced to, see
+/// `--force`.
+pub fn create_file(path: &Path, force: bool) -> Result<File> {
+    if force {
+        return Ok(File::create(path)?);
+    }
+    File::create_new(path).map_err(|err| match err.kind() {
+        std::io::ErrorKind::AlreadyExists => already_exists(path),
+        _ => err.into(),
+    })
+}
+
+/// Error of an output file, which already exists and must not be overwritten.
+pub(crate) fn already_exists(path: &Path) -> CtError {
+    let message = format!(
+        "'{}' already exists, use --force to overwrite it",
+        path.display()
+    );
+    std::io::Error::new(std::io::ErrorKind::AlreadyExists, message).into()
+}
+
+/// Output file of a directive, stdout for `-`. Unless written directly, see `--no-atomic`, the
+/// content goes to a temporary file in the directory of the target, which gets synced to disk and
+/// renamed to the target by `commit()`. So an interrupted run never leaves a half-written file
+/// behind, the temporary file gets removed when dropped without being committed.
+#[derive(Debug)]
+pub struct OutputFile {
+    target: PathBuf,
+    /// The written file, `None` for stdout.
+    file: Option<File>,
+    /// The temporary file, until it is renamed to the target.
+    temp: Option<PathBuf>,
+    force: bool,
+    written: u64,
+}
+
+impl OutputFile {
+    /// Creates the output file for writing, an existing target only gets overwritten, if forced
+    /// to, see `create_file()`.
+    pub fn create(target: &Path, force: bool, atomic: bool) -> Result<OutputFile> {
+        let mut output = OutputFile {
+            target: target.to_path_buf(),
+            file: None,
+            temp: None,
+            force,
+            written: 0,
+        };
+        if target == Path::new(STDIO) {
+            return Ok(output);
+        }
+        if !atomic {
+            output.file = Some(create_file(target, force)?);
+            return Ok(output);
+        }
+        if !force && target.exists() {
+            return Err(already_exists(target));
+        }
+        let name = target
+            .file_name()
+            .ok_or_else(|| format!("'{}' is no file name", target.display()))?;
+        let temp = target.with_file_name(format!(
+            ".{}.{}-{}.tmp",
+            name.to_string_lossy(),
+            std::process::id(),
+            unique_id()
+        ));
+        output.file = Some(File::create_new(&temp)?);
+        output.temp = Some(temp);
+        Ok(output)
+    }
+
+    /// Number of bytes written so far.
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Completes the output: the temporary file gets synced to disk and renamed to the target.
+    pub fn commit(mut self) -> Result<()> {
+        self.flush()?;
+        let (Some(file), Some(temp)) = (&self.file, &self.temp) else {
+            return Ok(());
+        };
+        file.sync_all()?;
+        // The target might have been created meanwhile, see `create()`.
+        if !self.force && self.target.exists() {
+            return Err(already_exists(&self.target));
+        }
+        std::fs::rename(temp, &self.target)?;
+        self.temp = None;
+        // The rename itself is only durable, once the directory is synced as well.
+        #[cfg(unix)]
+        if let Some(dir) = self.target.parent() {
+            let dir = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            };
+            File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+
+    /// Removes the output, also a directly written target, e.g. if it is corrupted.
+    pub fn discard(mut self) {
+        match (self.temp.take(), &self.file) {
+            (Some(temp), _) => {
+                let _ = std::fs::remove_file(temp);
+            }
+            (None, Some(_)) => {
+                let _ = std::fs::remove_file(&self.target);
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = match &mut self.file {
+            Some(file) => file.write(buf)?,
+            None => std::io::stdout().write(buf)?,
+        };
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => std::io::stdout().flush(),
+        }
+    }
+}
+
+impl Drop for OutputFile {
+    fn drop(&mut self) {
+        if let Some(temp) = &self.temp {
+            let _ = std::fs::remove_file(temp);
+        }
+    }
+}
+
+/// Temporary file, which gets removed when dropped, e.g. to read stdin twice.
+#[derive(Debug)]
+pub(crate) struct TempFile {
+    path: PathBuf,
+}
+
+impl TempFile {
+    /// Copies everything of the reader into a new temporary file.
+    pub fn from_reader(mut reader: impl Read) -> Result<TempFile> {
+        let name = format!("cccompress-{}-{}.tmp", std::process::id(), unique_id());
+        let temp = TempFile {
+            path: std::env::temp_dir().join(name),
+        };
+        let mut file = std::io::BufWriter::new(File::create_new(&temp.path)?);
+        std::io::copy(&mut reader, &mut file)?;
+        file.flush()?;
+        Ok(temp)
+    }
+
+    /// Path of the temporary file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
     }
 }
 
+/// Number, which differs for every call within the process.
+fn unique_id() -> u64 {
+    static COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+    COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+}
+
+/// Number of bytes in front of the header: the `MAGIC`, the format version and the length of the
+/// header. Files of version 1 only had a single byte in front of the length.
+pub const HEADER_PREFIX: usize = MAGIC.len() + 5;
+
+/// Modification time in seconds since the Unix epoch and Unix permission bits of a file, see
+/// `Header`. Both are 0 if they are unknown, the permission bits on other systems than Unix. The
+/// time is clamped to `SOURCE_DATE_EPOCH`, if set, so that archives can be reproduced.
+pub fn file_meta(path: &Path) -> (u64, u32) {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return (0, 0);
+    };
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map_or(0, |time| time.as_secs());
+    let modified = clamp_modified(modified, std::env::var("SOURCE_DATE_EPOCH").ok().as_deref());
+    #[cfg(unix)]
+    let mode = std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o7777;
+    #[cfg(not(unix))]
+    let mode = 0;
+    (modified, mode)
+}
+
+/// Modification time limited to the given `SOURCE_DATE_EPOCH`. Invalid values are ignored.
+fn clamp_modified(modified: u64, source_date_epoch: Option<&str>) -> u64 {
+    match source_date_epoch.and_then(|epoch| epoch.trim().parse::<u64>().ok()) {
+        Some(epoch) => modified.min(epoch),
+        None => modified,
+    }
+}
+
+/// Restores the modification time and the permission bits of the header to a decompressed file.
+/// Unknown ones are skipped, so are the permission bits on other systems than Unix.
+pub fn restore_meta(path: &Path, header: &Header) -> Result<()> {
+    if header.modified != 0 {
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(header.modified);
+        // Directories can not be opened for writing.
+        let file = match path.is_dir() {
+            true => File::open(path)?,
+            false => File::options().write(true).open(path)?,
+        };
+        file.set_modified(modified)?;
+    }
+    #[cfg(unix)]
+    if header.mode != 0 {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(header.mode))?;
+    }
+    Ok(())
+}
+
+/// Number of bytes of a whole entry of a compressed file: everything in front of the header, the
+/// header itself and the encoded data.
+pub fn entry_size(header: &Header) -> Result<u64> {
+    Ok((HEADER_PREFIX + Vec::<u8>::try_from(header)?.len()) as u64 + header.data_bytes as u64)
+}
+
+/// Writes everything in front of the encoded data: the `MAGIC`, the format version, the length of
+/// the header and the header itself. Returns the number of written bytes.
+pub fn write_header(writer: &mut impl Write, header: &Header) -> Result<usize> {
+    let mut buffer = Vec::<u8>::new();
+
+    // Initially we write the MAGIC as identifier of the correct file format and its version.
+    buffer.extend_from_slice(MAGIC);
+    buffer.push(FORMAT_VERSION);
+
+    // Followed by the length of the header (LE) and the header itself.
+    let mut hdr_data = Vec::<u8>::try_from(header)?;
+    let hdr_len = hdr_data.len() as u32;
+    hdr_len.to_le_bytes().iter().for_each(|b| buffer.push(*b));
+    buffer.append(&mut hdr_data);
+
+    writer.write_all(&buffer[..])?;
+    Ok(buffer.len())
+}
+
+/// Reader, which computes the CRC32 checksum of everything read through it.
+#[derive(Debug)]
+pub struct ChecksumReader<R> {
+    reader: R,
+    hasher: crc32fast::Hasher,
+}
+
+impl<R: Read> ChecksumReader<R> {
+    /// New type pattern: generates a new reader around the given one.
+    pub fn new(reader: R) -> ChecksumReader<R> {
+        ChecksumReader {
+            reader,
+            hasher: crc32fast::Hasher::new(),
+        }
+    }
+
+    /// Checksum of all bytes read so far.
+    pub fn checksum(&self) -> u32 {
+        self.hasher.clone().finalize()
+    }
+}
+
+impl<R: Read> Read for ChecksumReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Reads everything in front of the encoded data, as written by `write_header()`. The reader is
+/// left at the beginning of the encoded data.
+pub fn read_header(reader: &mut (impl Read + ?Sized)) -> Result<Header> {
+    // Same like above, files of version 1 begin with the LEGACY_FILE_CONST instead.
+    let mut magic = [0; 4];
+    let mut version = [1; 1];
+    reader.read_exact(&mut magic[..1])?;
+    if magic[0] != LEGACY_FILE_CONST {
+        reader.read_exact(&mut magic[1..])?;
+        if &magic != MAGIC {
+            return Err(CtError::InvalidMagic);
+        }
+        reader.read_exact(&mut version)?;
+        if version[0] > FORMAT_VERSION {
+            return Err(CtError::UnsupportedVersion {
+                version: version[0],
+                supported: FORMAT_VERSION,
+            });
+        }
+    }
+
+    // Same like above...
+    let mut hdr_le_bytes = [0; 4];
+    reader.read_exact(&mut hdr_le_bytes)?;
+    let hdr_len = u32::from_le_bytes(hdr_le_bytes) as u64;
+    // Not allocated up front, a broken length must not reserve gigabytes.
+    let mut hdr_data = Vec::new();
+    reader.take(hdr_len).read_to_end(&mut hdr_data)?;
+    if (hdr_data.len() as u64) != hdr_len {
+        return Err(CtError::Truncated {
+            expected: hdr_len,
+            got: hdr_data.len() as u64,
+        });
+    }
+    Header::parse(&hdr_data[..], version[0])
+}
+
 /// TODO
 #[derive(Debug, PartialEq)]
 pub struct CompressedData {
@@ -134,64 +704,81 @@ impl CompressedData {
             .into());
         }
 
-        let mut bytes = 0;
-        let mut file = File::create(filename)?;
-        let mut buffer = Vec::<u8>::new();
-
-        // Initially we write the FILE_CONST as identifier of the correct file format.
-        buffer.push(FILE_CONST);
-
-        // Followed by the length of the header (LE) and the header itself.
-        let mut hdr_data = Vec::<u8>::from(&self.header);
-        let hdr_len = hdr_data.len() as u32;
-        hdr_len.to_le_bytes().iter().for_each(|b| buffer.push(*b));
-        buffer.append(&mut hdr_data);
-
-        // Followed by the data content.
-        buffer.extend_from_slice(&self.data[..]);
-
-        bytes += file.write(&buffer[..])?;
-        file.flush()?;
-
-        Ok(bytes)
+        // The header, followed by the data content and the footer.
+        let stats = crate::write_file(File::create(filename)?, self)?;
+        Ok(stats.compressed_bytes as usize)
     }
 
-    /// TODO
+    /// Reads a compressed file: the header gets parsed from the beginning of the file, then
+    /// exactly the number of data bytes, which it expects, and the footer gets verified, see
+    /// `footer`.
     pub fn read(filename: &str) -> Result<CompressedData> {
         check_filename(filename)?;
 
         let file = File::open(filename)?;
-        let mut reader = BufReader::new(file);
-        let mut buffer = Vec::<u8>::new();
-
-        reader.read_to_end(&mut buffer)?;
+        let mut reader = FooterReader::new(BufReader::new(file), true);
+        let header = read_header(&mut reader)?;
 
-        // Same like above...
-        if buffer[0] != FILE_CONST {
-            return Err("no file constant detected, maybe another file type?"
-                .to_string()
-                .into());
+        let mut data = Vec::<u8>::new();
+        (&mut reader)
+            .take(header.data_bytes as u64)
+            .read_to_end(&mut data)?;
+        if (header.data_bytes as usize) != data.len() {
+            let err = CtError::Truncated {
+                expected: header.data_bytes as u64,
+                got: data.len() as u64,
+            };
+            return Err(err.in_file(filename));
         }
-
-        // Same like above...
-        let hdr_le_bytes = [buffer[1], buffer[2], buffer[3], buffer[4]];
-        let hdr_len = u32::from_le_bytes(hdr_le_bytes) as usize;
-        let header = Header::from(&buffer[5..5 + hdr_len]);
-
-        // Same like above...
-        if (header.data_bytes as usize) != buffer.len() - 5 - hdr_len {
+        let at_end = reader.at_end().map_err(|err| err.in_file(filename))?;
+        if !at_end {
             return Err(format!(
-                "'{filename}' seems to be broken, header expects {} data bytes, but only {} remain",
-                header.data_bytes,
-                buffer.len() - 5 - hdr_len
+                "'{filename}' seems to be broken, more than the {} data bytes of its header follow",
+                header.data_bytes
             )
             .into());
         }
-        let mut data = Vec::<u8>::new();
-        data.extend_from_slice(&buffer[5 + hdr_len..]);
 
         Ok(CompressedData { header, data })
     }
+
+    /// Iterator over the original content, which decodes it byte by byte, so that it can be
+    /// searched or streamed without decompressing all of it first. Only plain Huffman coded data
+    /// can be decoded like this. The checksum does not get verified.
+    pub fn decode_iter(&self) -> Result<DecodeIter<'_>> {
+        let header = &self.header;
+        if header.algorithm != Algorithm::Huffman.id()
+            || header.pipeline != 0
+            || header.seekable
+            || header.encrypted
+            || header.dict_id != 0
+            || header.symbols != 0
+        {
+            return Err("only plain Huffman coded data can be decoded byte by byte".into());
+        }
+        let table = PrefixCodeTable::try_from(&header.prefix_table[..])?;
+        Ok(DecodeIter::new(&table, &self.data, header.unused_bits))
+    }
+
+    /// Decompresses `len` bytes of the original content, beginning at `start`. Of the seekable
+    /// block format only the blocks overlapping the range get decoded, otherwise everything.
+    pub fn read_range(&self, start: u64, len: u64) -> Result<Vec<u8>> {
+        if self.header.seekable {
+            let mut file = Vec::new();
+            write_header(&mut file, &self.header)?;
+            file.extend_from_slice(&self.data);
+            return crate::blocks::read_range(std::io::Cursor::new(file), start, len);
+        }
+        let content = crate::decompress(self)?;
+        let end = start.saturating_add(len);
+        content
+            .get(start as usize..end as usize)
+            .map(|range| range.to_vec())
+            .ok_or_else(|| {
+                let n = content.len();
+                format!("range {start}..{end} exceeds the content of {n} bytes").into()
+            })
+    }
 }
 
 #[cfg(test)]
@@ -217,17 +804,76 @@ mod tests {
             filename: String::new(),
             prefix_table: table.clone(),
             data_bytes: 1,
+            unused_bits: 3,
+            checksum: 0x04030201,
+            algorithm: 1,
+            pipeline: 1,
+            block_size: 0x1000,
+            seekable: true,
+            original_bytes: 0x0102,
+            modified: 0x0a0b,
+            mode: 0o644,
+            encrypted: true,
+            no_checksum: false,
+            single_file: false,
+            dict_id: 0x0c0d0e0f,
+            entry_type: 2,
+            symbols: 1,
+            seekable_block_size: 0x2000,
         };
-        let output = Vec::<u8>::from(&header);
+        let output = Vec::<u8>::try_from(&header).unwrap();
 
-        let mut reference = vec![0u8];
+        let mut reference = vec![0u8, 0u8, 0u8, 1u8];
         reference.append(&mut table);
-        reference.append(&mut vec![1u8, 0u8, 0u8, 0u8]);
+        reference.append(&mut vec![1u8, 0u8, 0u8, 0u8, 3u8, 1u8, 2u8, 3u8, 4u8, 1u8]);
+        reference.append(&mut vec![1u8, 0u8, 0x10, 0u8, 0u8, 1u8]);
+        reference.append(&mut vec![2u8, 1u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8]);
+        reference.append(&mut vec![0x0b, 0x0a, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8]);
+        reference.append(&mut vec![
+            0xa4, 1u8, 0u8, 0u8, 1u8, 0x0f, 0x0e, 0x0d, 0x0c, 2u8, 1u8, 0u8, 0x20, 0u8, 0u8,
+        ]);
 
         assert_eq!(reference, output);
 
-        let hdr_out = Header::from(&output[..]);
+        let hdr_out = Header::parse(&output[..], FORMAT_VERSION).unwrap();
         assert_eq!(header, hdr_out);
+
+        // Headers without a block size from before.
+        let hdr_out = Header::parse(&output[..output.len() - 4], FORMAT_VERSION).unwrap();
+        assert_eq!(hdr_out.seekable_block_size, 0);
+        assert_eq!(hdr_out.symbols, header.symbols);
+
+        // Headers without a symbol model from before.
+        let hdr_out = Header::parse(&output[..output.len() - 5], FORMAT_VERSION).unwrap();
+        assert_eq!(hdr_out.symbols, 0);
+        assert_eq!(hdr_out.entry_type, header.entry_type);
+
+        // Headers without an entry type from before.
+        let hdr_out = Header::parse(&output[..output.len() - 6], FORMAT_VERSION).unwrap();
+        assert_eq!(hdr_out.entry_type, 0);
+        assert_eq!(hdr_out.dict_id, header.dict_id);
+
+        // Headers without a dictionary from before.
+        let hdr_out = Header::parse(&output[..output.len() - 10], FORMAT_VERSION).unwrap();
+        assert_eq!(hdr_out.dict_id, 0);
+        assert!(hdr_out.encrypted);
+
+        // Headers without flags from before.
+        let hdr_out = Header::parse(&output[..output.len() - 11], FORMAT_VERSION).unwrap();
+        assert!(!hdr_out.encrypted);
+        assert_eq!(hdr_out.mode, header.mode);
+
+        // Headers without metadata from before.
+        let hdr_out = Header::parse(&output[..output.len() - 23], FORMAT_VERSION).unwrap();
+        assert_eq!((hdr_out.modified, hdr_out.mode), (0, 0));
+        assert_eq!(hdr_out.original_bytes, header.original_bytes);
+
+        // Headers without a pipeline from before.
+        let hdr_out = Header::parse(&output[..output.len() - 37], FORMAT_VERSION).unwrap();
+        assert_eq!((hdr_out.pipeline, hdr_out.block_size), (0, 0));
+        assert!(!hdr_out.seekable);
+        assert_eq!(hdr_out.original_bytes, 0);
+        assert_eq!(hdr_out.checksum, header.checksum);
     }
 
     #[test]
@@ -237,17 +883,300 @@ mod tests {
             filename: "test".to_string(),
             prefix_table: table.clone(),
             data_bytes: 256,
+            unused_bits: 0,
+            checksum: 0,
+            algorithm: 0,
+            pipeline: 0,
+            block_size: 0,
+            seekable: false,
+            original_bytes: 0,
+            modified: 0,
+            mode: 0,
+            encrypted: false,
+            no_checksum: false,
+            single_file: false,
+            dict_id: 0,
+            entry_type: 0,
+            symbols: 0,
+            seekable_block_size: 0,
         };
-        let output = Vec::<u8>::from(&header);
+        let output = Vec::<u8>::try_from(&header).unwrap();
 
-        let mut reference = vec![4, 't' as u8, 'e' as u8, 's' as u8, 't' as u8];
+        let mut reference = vec![4, 0, b't', b'e', b's', b't', 0, 1];
         reference.append(&mut table);
-        reference.append(&mut vec![0u8, 1u8, 0u8, 0u8]);
+        reference.append(&mut vec![0u8, 1u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8]);
+        reference.append(&mut vec![0u8; 37]);
 
         assert_eq!(reference, output);
 
-        let hdr_out = Header::from(&output[..]);
+        let hdr_out = Header::parse(&output[..], FORMAT_VERSION).unwrap();
         assert_eq!(header, hdr_out);
+
+        // Files before version 4 have a single byte length.
+        let mut legacy = vec![4];
+        legacy.extend_from_slice(&output[2..]);
+        assert_eq!(Header::parse(&legacy, 3).unwrap(), header);
+        assert_eq!(Header::parse(&legacy, 1).unwrap(), header);
+    }
+
+    #[test]
+    fn header_with_long_filename() {
+        let header = Header {
+            filename: format!("{}/größe-🦀.txt", "ü".repeat(200)),
+            ..Default::default()
+        };
+        assert!(header.filename.len() > 255);
+        let output = Vec::<u8>::try_from(&header).unwrap();
+        assert_eq!(
+            output[..2],
+            (header.filename.len() as u16).to_le_bytes()[..]
+        );
+        assert_eq!(Header::parse(&output[..], FORMAT_VERSION).unwrap(), header);
+
+        let mut data = Vec::new();
+        write_header(&mut data, &header).unwrap();
+        assert_eq!(read_header(&mut &data[..]).unwrap(), header);
+
+        // A length of the filename beyond the end of the header.
+        let mut broken = output.clone();
+        broken[..2].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert!(matches!(
+            Header::parse(&broken, FORMAT_VERSION),
+            Err(CtError::CorruptHeader {
+                field: "filename",
+                ..
+            })
+        ));
+        assert!(matches!(
+            Header::parse(&[0xff, b'a'], 3),
+            Err(CtError::CorruptHeader {
+                field: "filename",
+                offset: 1
+            })
+        ));
+
+        // Filenames must be valid UTF-8, e.g. not a single byte of 'ü' in latin-1.
+        let mut latin1 = output.clone();
+        latin1[2] = 0xfc;
+        assert!(matches!(
+            Header::parse(&latin1, FORMAT_VERSION),
+            Err(CtError::CorruptHeader {
+                field: "filename",
+                offset: 0
+            })
+        ));
+
+        // Filenames beyond the length of (1) can not be written.
+        let header = Header {
+            filename: "a".repeat(u16::MAX as usize + 1),
+            ..Default::default()
+        };
+        assert!(Vec::<u8>::try_from(&header).is_err());
+        assert!(write_header(&mut Vec::new(), &header).is_err());
+        assert!(entry_size(&header).is_err());
+    }
+
+    #[test]
+    fn header_with_large_table() {
+        // Tables of other algorithms, e.g. the frequencies of arithmetic coding, reuse (4).
+        let mut header = Header {
+            prefix_table: vec![7; u16::MAX as usize],
+            ..Default::default()
+        };
+        let output = Vec::<u8>::try_from(&header).unwrap();
+        assert_eq!(Header::parse(&output, FORMAT_VERSION).unwrap(), header);
+
+        header.prefix_table.push(7);
+        assert!(Vec::<u8>::try_from(&header).is_err());
+        assert!(write_header(&mut Vec::new(), &header).is_err());
+    }
+
+    #[test]
+    fn truncated_headers() {
+        let header = Header {
+            filename: "test".to_string(),
+            prefix_table: vec![1, 2, 3],
+            ..Default::default()
+        };
+        let output = Vec::<u8>::try_from(&header).unwrap();
+        // Everything up to (8) is needed, the fields behind it are optional.
+        let required = 2 + 4 + 2 + 3 + 10;
+        for len in 0..required {
+            let err = Header::parse(&output[..len], FORMAT_VERSION).unwrap_err();
+            assert!(matches!(err, CtError::CorruptHeader { .. }), "{len}: {err}");
+        }
+        assert!(Header::parse(&output[..required], FORMAT_VERSION).is_ok());
+        assert!(matches!(
+            Header::parse(&output[..5], FORMAT_VERSION),
+            Err(CtError::CorruptHeader {
+                field: "filename",
+                offset: 3
+            })
+        ));
+        assert!(matches!(
+            Header::parse(&output[..7], FORMAT_VERSION),
+            Err(CtError::CorruptHeader {
+                field: "prefix code table length",
+                offset: 1
+            })
+        ));
+
+        // An empty header, as by `printf 'CCPD\x04\x00\x00\x00\x00'`.
+        let err = read_header(&mut &b"CCPD\x04\x00\x00\x00\x00"[..]).unwrap_err();
+        assert!(matches!(
+            err,
+            CtError::CorruptHeader {
+                field: "filename length",
+                offset: 0
+            }
+        ));
+    }
+
+    #[test]
+    fn header_versions() {
+        let header = Header {
+            filename: "test".to_string(),
+            checksum: 42,
+            no_checksum: true,
+            single_file: true,
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        let bytes = write_header(&mut data, &header).unwrap();
+        assert_eq!(bytes, data.len());
+        assert_eq!(&data[..5], b"CCPD\x04");
+        assert_eq!(read_header(&mut &data[..]).unwrap(), header);
+
+        // Version 1 files begin with a single byte, files before version 4 have a single byte
+        // length of the filename.
+        let mut hdr_data = Vec::<u8>::try_from(&header).unwrap();
+        hdr_data.remove(1);
+        let mut legacy = vec![LEGACY_FILE_CONST];
+        legacy.extend_from_slice(&(hdr_data.len() as u32).to_le_bytes());
+        legacy.extend_from_slice(&hdr_data);
+        assert_eq!(read_header(&mut &legacy[..]).unwrap(), header);
+        let mut legacy = b"CCPD\x03".to_vec();
+        legacy.extend_from_slice(&(hdr_data.len() as u32).to_le_bytes());
+        legacy.extend_from_slice(&hdr_data);
+        assert_eq!(read_header(&mut &legacy[..]).unwrap(), header);
+
+        data[4] = FORMAT_VERSION + 1;
+        let err = read_header(&mut &data[..]).unwrap_err();
+        assert!(err.to_string().contains("version 5 is not supported"));
+        assert!(matches!(
+            err,
+            CtError::UnsupportedVersion { version: 5, .. }
+        ));
+        let err = read_header(&mut &b"PK\x03\x04"[..]).unwrap_err();
+        assert!(matches!(err, CtError::InvalidMagic));
+
+        // A broken length neither allocates nor reads more than there is.
+        let err = read_header(&mut &b"CCPD\x02\xff\xff\xff\xff\x00"[..]).unwrap_err();
+        assert!(matches!(err, CtError::Truncated { got: 1, .. }));
+        assert!(err
+            .to_string()
+            .contains("4294967294 of 4294967295 bytes are missing"));
+    }
+
+    #[test]
+    fn temp_file() {
+        let temp = TempFile::from_reader(&b"spooled stdin"[..]).unwrap();
+        let path = temp.path().to_path_buf();
+        assert_eq!(std::fs::read(&path).unwrap(), b"spooled stdin");
+        drop(temp);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn source_date_epoch() {
+        assert_eq!(clamp_modified(1_700_000_000, None), 1_700_000_000);
+        assert_eq!(
+            clamp_modified(1_700_000_000, Some("1600000000")),
+            1_600_000_000
+        );
+        assert_eq!(
+            clamp_modified(1_500_000_000, Some("1600000000")),
+            1_500_000_000
+        );
+        assert_eq!(
+            clamp_modified(1_700_000_000, Some("yesterday")),
+            1_700_000_000
+        );
+    }
+
+    #[test]
+    fn atomic_output() {
+        let target = Path::new("atomic_output.cpd");
+        // Temporary files of the target, other tests write into the same directory.
+        let temps = || {
+            std::fs::read_dir(".")
+                .unwrap()
+                .filter(|entry| {
+                    let name = entry.as_ref().unwrap().file_name();
+                    name.to_string_lossy().starts_with(".atomic_output.cpd.")
+                })
+                .count()
+        };
+        let mut output = OutputFile::create(target, false, true).unwrap();
+        output.write_all(b"partial").unwrap();
+        assert!(!target.exists());
+        drop(output);
+        assert!(!target.exists());
+        assert_eq!(temps(), 0);
+
+        let mut output = OutputFile::create(target, false, true).unwrap();
+        output.write_all(b"complete").unwrap();
+        assert_eq!(output.written(), 8);
+        output.commit().unwrap();
+        assert_eq!(std::fs::read(target).unwrap(), b"complete");
+        assert_eq!(temps(), 0);
+        assert!(OutputFile::create(target, false, true).is_err());
+        assert!(OutputFile::create(target, false, false).is_err());
+
+        let mut output = OutputFile::create(target, true, false).unwrap();
+        output.write_all(b"direct").unwrap();
+        assert_eq!(std::fs::read(target).unwrap(), b"direct");
+        output.discard();
+        assert!(!target.exists());
+    }
+
+    #[test]
+    fn file_types() {
+        assert_eq!(switch_file_type("135-0.txt"), "135-0.cpd");
+        assert_eq!(switch_file_type("135-0.cpd"), "135-0");
+        assert_eq!(switch_file_type("image.png"), "image.png.cpd");
+        assert_eq!(switch_file_type("image.png.cpd"), "image.png");
+        assert_eq!(switch_file_type("archive"), "archive.cpd");
+        assert_eq!(switch_file_type("archive.cpd"), "archive");
+        assert_eq!(switch_file_type_with("135-0.txt", "hz"), "135-0.hz");
+        assert_eq!(switch_file_type_with("image.png.hz", "hz"), "image.png");
+        assert!(check_filename_with("135-0.hz", "hz").is_ok());
+        assert!(check_filename_with("135-0.cpd", "hz").is_err());
+    }
+
+    #[test]
+    fn unpacked_paths() {
+        let named = |filename: &str| Header {
+            filename: filename.to_string(),
+            single_file: true,
+            ..Default::default()
+        };
+        let unpacked = |source, header| unpacked_path(source, &header, FILE_EXTENSION);
+        assert_eq!(
+            unpacked("dir/135-0.cpd", named("135-0.txt")).unwrap(),
+            PathBuf::from("dir/135-0.txt")
+        );
+        assert_eq!(
+            unpacked("renamed.cpd", named("Makefile")).unwrap(),
+            PathBuf::from("Makefile")
+        );
+        assert_eq!(
+            unpacked("dir/135-0.cpd", Header::default()).unwrap(),
+            PathBuf::from("dir/135-0")
+        );
+        for name in ["../evil", "sub/evil", "/tmp/evil", "..", "."] {
+            assert!(unpacked("135-0.cpd", named(name)).is_err(), "{name}");
+        }
     }
 
     #[test]
@@ -259,14 +1188,30 @@ mod tests {
                 filename: "othername.txt".to_string(),
                 prefix_table: table,
                 data_bytes: data.len() as u32,
+                unused_bits: 5,
+                checksum: 42,
+                algorithm: 0,
+                pipeline: 0,
+                block_size: 0,
+                seekable: false,
+                original_bytes: 0,
+                modified: 0,
+                mode: 0,
+                encrypted: false,
+                no_checksum: false,
+                single_file: false,
+                dict_id: 0,
+                entry_type: 0,
+                symbols: 0,
+                seekable_block_size: 0,
             },
             data,
         };
 
-        cdata.write(&fname).expect("write() failed");
+        cdata.write(fname).expect("write() failed");
         assert!(std::path::Path::new(fname).exists());
 
-        let res_cdata = CompressedData::read(&fname).expect("read() failed");
+        let res_cdata = CompressedData::read(fname).expect("read() failed");
         assert_eq!(cdata, res_cdata);
 
         std::fs::remove_file(fname).expect("removing testfile failed");
@@ -0,0 +1,453 @@
+//! Module contains the interactive inspector of the Huffman coding of a file, see `--inspect`. It
+//! shows the code tree, the prefix code table and a hex view of the encoded stream in the
+//! terminal. Text files get encoded for it, compressed files get read and decoded.
+
+use crate::{
+    algorithm::{self, symbol_name, CharSpectrum, PrefixCodeTable, MAX_CODE_LENGTH},
+    fs, Algorithm, Result,
+};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListState, Paragraph, Row, Table},
+    Frame,
+};
+use std::{fs::File, io::BufReader};
+
+/// Number of bytes per line of the hex view.
+const HEX_BYTES_PER_LINE: usize = 16;
+
+/// Node of the code tree, as it gets displayed. The tree is built from the canonical codes, so
+/// that its paths match the encoded stream.
+#[derive(Debug)]
+struct CodeNode {
+    /// Bits of the path from the root to this node.
+    code: String,
+    /// Symbol of a leaf.
+    symbol: Option<u8>,
+    /// Frequency of the symbol of a leaf, sum of the frequencies of its subtree otherwise.
+    weight: u64,
+    /// Indices of the children for the bits 0 and 1.
+    children: [Option<usize>; 2],
+    /// Whether the children are shown.
+    expanded: bool,
+}
+
+/// Part of the screen, which receives the navigation keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Focus {
+    Tree,
+    Table,
+    Hex,
+}
+
+/// State of the inspector of one file.
+pub struct Inspector {
+    /// Name of the inspected file.
+    name: String,
+    /// All used symbols in canonical order with their frequency, code and code length.
+    codes: Vec<(u8, u64, u64, u8)>,
+    /// The encoded stream.
+    data: Vec<u8>,
+    /// Number of unused bits in the last byte of the stream.
+    unused_bits: u8,
+    /// Nodes of the code tree, the root first.
+    nodes: Vec<CodeNode>,
+    /// Row of the selected node among the visible ones.
+    selected: usize,
+    focus: Focus,
+    /// First shown row of the table.
+    table_offset: usize,
+    /// First shown line of the hex view.
+    hex_offset: usize,
+}
+
+impl Inspector {
+    /// Opens a file for inspection. Compressed files are recognized by their extension, only
+    /// Huffman coded ones can be inspected, of archives the first entry. Any other file gets
+    /// encoded.
+    pub fn open(source: &str) -> Result<Inspector> {
+        if fs::check_filename(source).is_err() {
+            return Inspector::from_content(source, &std::fs::read(source)?);
+        }
+        let mut reader = BufReader::new(File::open(source)?);
+        let header = fs::read_header(&mut reader)?;
+        if Algorithm::from_id(header.algorithm)? != Algorithm::Huffman
+            || header.seekable
+            || header.encrypted
+            || header.dict_id != 0
+            || header.symbols != 0
+        {
+            return Err(
+                "only plain Huffman coded files of bytes can be inspected, not seekable, \
+                        encrypted ones or ones with a dictionary"
+                    .into(),
+            );
+        }
+        let data = crate::read_data(&header, reader)?;
+        let table = PrefixCodeTable::try_from(&header.prefix_table[..])?;
+        let content = algorithm::decode(&table, &data, header.unused_bits)?;
+        let name = match header.filename.is_empty() {
+            true => source.to_string(),
+            false => header.filename,
+        };
+        let spectrum = CharSpectrum::from_bytes(&content);
+        Ok(Inspector::new(
+            name,
+            &table,
+            &spectrum,
+            data,
+            header.unused_bits,
+        ))
+    }
+
+    /// Encodes the given content for inspection.
+    pub fn from_content(name: &str, content: &[u8]) -> Result<Inspector> {
+        let spectrum = CharSpectrum::from_bytes(content);
+        let table = PrefixCodeTable::build(&spectrum, MAX_CODE_LENGTH)?;
+        let (data, unused_bits) = algorithm::encode(&table, content)?;
+        Ok(Inspector::new(
+            name.to_string(),
+            &table,
+            &spectrum,
+            data,
+            unused_bits,
+        ))
+    }
+
+    /// New type pattern: generates a new inspector, which shows the first two levels of the tree.
+    fn new(
+        name: String,
+        table: &PrefixCodeTable,
+        spectrum: &CharSpectrum,
+        data: Vec<u8>,
+        unused_bits: u8,
+    ) -> Inspector {
+        let mut codes: Vec<(u8, u64, u64, u8)> = (0..=255u8)
+            .filter_map(|s| table.code(s).map(|(c, l)| (s, spectrum.frequency(s), c, l)))
+            .collect();
+        codes.sort_by_key(|(symbol, _, _, length)| (*length, *symbol));
+
+        let mut nodes = vec![CodeNode {
+            code: String::new(),
+            symbol: None,
+            weight: 0,
+            children: [None; 2],
+            expanded: true,
+        }];
+        for &(symbol, weight, code, length) in &codes {
+            let mut node = 0;
+            nodes[node].weight += weight;
+            for i in (0..length).rev() {
+                let bit = (code >> i) as usize & 1;
+                node = match nodes[node].children[bit] {
+                    Some(child) => child,
+                    None => {
+                        let code = format!("{}{bit}", nodes[node].code);
+                        nodes.push(CodeNode {
+                            expanded: code.len() < 2,
+                            code,
+                            symbol: None,
+                            weight: 0,
+                            children: [None; 2],
+                        });
+                        nodes[node].children[bit] = Some(nodes.len() - 1);
+                        nodes.len() - 1
+                    }
+                };
+                nodes[node].weight += weight;
+            }
+            nodes[node].symbol = Some(symbol);
+        }
+
+        Inspector {
+            name,
+            codes,
+            data,
+            unused_bits,
+            nodes,
+            selected: 0,
+            focus: Focus::Tree,
+            table_offset: 0,
+            hex_offset: 0,
+        }
+    }
+
+    /// All visible nodes from top to bottom with their depth.
+    fn visible(&self) -> Vec<(usize, usize)> {
+        let mut rows = Vec::new();
+        let mut stack = vec![(0, 0)];
+        while let Some((node, depth)) = stack.pop() {
+            rows.push((node, depth));
+            if self.nodes[node].expanded {
+                for child in self.nodes[node].children.iter().rev().flatten() {
+                    stack.push((*child, depth + 1));
+                }
+            }
+        }
+        rows
+    }
+
+    /// Number of lines of the hex view.
+    fn hex_lines(&self) -> usize {
+        self.data.len().div_ceil(HEX_BYTES_PER_LINE)
+    }
+
+    /// Handles a pressed key. Returns false, if the inspector shall be closed.
+    fn handle_key(&mut self, key: KeyCode) -> bool {
+        let rows = self.visible();
+        let (node, depth) = rows[self.selected];
+        let is_inner = self.nodes[node].children.iter().any(Option::is_some);
+        match (self.focus, key) {
+            (_, KeyCode::Char('q') | KeyCode::Esc) => return false,
+            (_, KeyCode::Tab) => {
+                self.focus = match self.focus {
+                    Focus::Tree => Focus::Table,
+                    Focus::Table => Focus::Hex,
+                    Focus::Hex => Focus::Tree,
+                }
+            }
+            (Focus::Tree, KeyCode::Char('j') | KeyCode::Down) => {
+                self.selected = (self.selected + 1).min(rows.len() - 1)
+            }
+            (Focus::Tree, KeyCode::Char('k') | KeyCode::Up) => {
+                self.selected = self.selected.saturating_sub(1)
+            }
+            (Focus::Tree, KeyCode::Char('l') | KeyCode::Right) if is_inner => {
+                if self.nodes[node].expanded {
+                    self.selected += 1;
+                } else {
+                    self.nodes[node].expanded = true;
+                }
+            }
+            (Focus::Tree, KeyCode::Char('h') | KeyCode::Left) => {
+                if is_inner && self.nodes[node].expanded {
+                    self.nodes[node].expanded = false;
+                } else if let Some(parent) = rows[..self.selected]
+                    .iter()
+                    .rposition(|(_, d)| *d + 1 == depth)
+                {
+                    self.selected = parent;
+                }
+            }
+            (Focus::Tree, KeyCode::Enter | KeyCode::Char(' ')) if is_inner => {
+                self.nodes[node].expanded = !self.nodes[node].expanded
+            }
+            (Focus::Table, KeyCode::Char('j') | KeyCode::Down) => {
+                self.table_offset = (self.table_offset + 1).min(self.codes.len().saturating_sub(1))
+            }
+            (Focus::Table, KeyCode::Char('k') | KeyCode::Up) => {
+                self.table_offset = self.table_offset.saturating_sub(1)
+            }
+            (Focus::Hex, KeyCode::Char('j') | KeyCode::Down) => {
+                self.hex_offset = (self.hex_offset + 1).min(self.hex_lines().saturating_sub(1))
+            }
+            (Focus::Hex, KeyCode::Char('k') | KeyCode::Up) => {
+                self.hex_offset = self.hex_offset.saturating_sub(1)
+            }
+            _ => (),
+        }
+        true
+    }
+
+    /// Border of a part of the screen with the given title, highlighted if it has the focus.
+    fn block(&self, title: String, focus: Focus) -> Block<'static> {
+        let style = match self.focus == focus {
+            true => Style::new().fg(Color::Yellow),
+            false => Style::new(),
+        };
+        Block::bordered().title(title).border_style(style)
+    }
+
+    /// Draws the tree on the left, the table and the hex view on the right.
+    fn render(&self, frame: &mut Frame) {
+        let [main, help] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Fill(1)]).areas(main);
+        let [top, bottom] =
+            Layout::vertical([Constraint::Percentage(50), Constraint::Fill(1)]).areas(right);
+
+        let rows: Vec<Line> = self
+            .visible()
+            .into_iter()
+            .map(|(node, depth)| {
+                let node = &self.nodes[node];
+                let marker = match (node.symbol, node.expanded) {
+                    (Some(_), _) => "  ",
+                    (None, true) => "▾ ",
+                    (None, false) => "▸ ",
+                };
+                let code = match node.code.is_empty() {
+                    true => "root",
+                    false => &node.code,
+                };
+                let label = match node.symbol {
+                    Some(symbol) => format!("{code} {}", symbol_name(symbol)),
+                    None => code.to_string(),
+                };
+                let indent = "  ".repeat(depth);
+                Line::from(format!("{indent}{marker}{label} ({})", node.weight))
+            })
+            .collect();
+        let tree = List::new(rows)
+            .block(self.block(format!(" Huffman tree of '{}' ", self.name), Focus::Tree))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(tree, left, &mut state);
+
+        let rows = self.codes[self.table_offset..]
+            .iter()
+            .map(|&(symbol, weight, code, length)| {
+                Row::new([
+                    symbol_name(symbol),
+                    weight.to_string(),
+                    length.to_string(),
+                    format!("{code:0width$b}", width = length as usize),
+                ])
+            });
+        let widths = [
+            Constraint::Length(8),
+            Constraint::Length(10),
+            Constraint::Length(6),
+            Constraint::Fill(1),
+        ];
+        let table = Table::new(rows, widths)
+            .header(
+                Row::new(["symbol", "frequency", "length", "code"])
+                    .style(Style::new().add_modifier(Modifier::BOLD)),
+            )
+            .block(self.block(
+                format!(" Prefix code table, {} symbols ", self.codes.len()),
+                Focus::Table,
+            ));
+        frame.render_widget(table, top);
+
+        let lines: Vec<Line> = self
+            .data
+            .chunks(HEX_BYTES_PER_LINE)
+            .enumerate()
+            .skip(self.hex_offset)
+            .take(bottom.height as usize)
+            .map(|(i, chunk)| {
+                let bytes: Vec<String> = chunk.iter().map(|b| format!("{b:02x}")).collect();
+                Line::from(format!(
+                    "{:08x}  {}",
+                    i * HEX_BYTES_PER_LINE,
+                    bytes.join(" ")
+                ))
+            })
+            .collect();
+        let title = format!(
+            " Encoded stream, {} bytes, {} unused bits ",
+            self.data.len(),
+            self.unused_bits
+        );
+        frame.render_widget(
+            Paragraph::new(lines).block(self.block(title, Focus::Hex)),
+            bottom,
+        );
+
+        let keys = " q quit  tab switch view  j/k down/up  h/l collapse/expand  enter toggle";
+        frame.render_widget(Paragraph::new(keys), help);
+    }
+
+    /// Takes over the terminal, until the inspector gets closed.
+    pub fn run(mut self) -> Result<()> {
+        let mut terminal = ratatui::init();
+        let result = loop {
+            if let Err(err) = terminal.draw(|frame| self.render(frame)) {
+                break Err(err);
+            }
+            match event::read() {
+                Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                    if !self.handle_key(key.code) {
+                        break Ok(());
+                    }
+                }
+                Ok(_) => (),
+                Err(err) => break Err(err),
+            }
+        };
+        ratatui::restore();
+        Ok(result?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{backend::TestBackend, Terminal};
+
+    #[test]
+    fn code_tree() {
+        let inspector = Inspector::from_content("test", b"abracadabra").unwrap();
+        assert_eq!(inspector.codes.len(), 5);
+        assert_eq!(inspector.nodes[0].weight, 11);
+        // Five leafs need four inner nodes.
+        assert_eq!(inspector.nodes.len(), 9);
+        for (symbol, weight, code, length) in &inspector.codes {
+            let code = format!("{code:0width$b}", width = *length as usize);
+            let leaf = inspector.nodes.iter().find(|n| n.code == code).unwrap();
+            assert_eq!(leaf.symbol, Some(*symbol));
+            assert_eq!(leaf.weight, *weight);
+        }
+    }
+
+    #[test]
+    fn open_compressed() {
+        let cdata = crate::compress(b"abracadabra").unwrap();
+        cdata.write("inspect_test.cpd").unwrap();
+        let inspector = Inspector::open("inspect_test.cpd").unwrap();
+        let encoded = Inspector::from_content("test", b"abracadabra").unwrap();
+        assert_eq!(inspector.codes, encoded.codes);
+        assert_eq!(inspector.data, encoded.data);
+        std::fs::remove_file("inspect_test.cpd").unwrap();
+    }
+
+    #[test]
+    fn navigation() {
+        let mut inspector = Inspector::from_content("test", b"abracadabra").unwrap();
+        let rows = inspector.visible().len();
+        assert!(inspector.handle_key(KeyCode::Char('h')));
+        assert_eq!(inspector.visible().len(), 1);
+        inspector.handle_key(KeyCode::Char('l'));
+        assert_eq!(inspector.visible().len(), rows);
+        inspector.handle_key(KeyCode::Char('l'));
+        assert_eq!(inspector.selected, 1);
+        // The most frequent symbol 'a' gets the leaf "0", where 'h' moves back to the root.
+        assert_eq!(inspector.nodes[inspector.visible()[1].0].symbol, Some(b'a'));
+        inspector.handle_key(KeyCode::Char('h'));
+        assert_eq!(inspector.selected, 0);
+        for _ in 0..20 {
+            inspector.handle_key(KeyCode::Down);
+        }
+        assert_eq!(inspector.selected, rows - 1);
+
+        inspector.handle_key(KeyCode::Tab);
+        inspector.handle_key(KeyCode::Tab);
+        inspector.handle_key(KeyCode::Down);
+        assert_eq!(inspector.hex_offset, 0);
+        assert!(!inspector.handle_key(KeyCode::Char('q')));
+    }
+
+    #[test]
+    fn render() {
+        let inspector = Inspector::from_content("test", b"abracadabra").unwrap();
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|frame| inspector.render(frame)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("Huffman tree of 'test'"));
+        assert!(screen.contains("root (11)"));
+        assert!(screen.contains("Prefix code table, 5 symbols"));
+        assert!(screen.contains(&format!("00000000  {:02x}", inspector.data[0])));
+    }
+}
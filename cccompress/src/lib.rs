@@ -1,93 +1,1436 @@
 //! Library with functionality of compression-tool.
 
+pub mod archive;
+pub mod arith;
+pub mod bench;
+pub mod blocks;
+pub mod bwt;
+pub mod codec;
 mod command;
+pub mod compressor;
+#[cfg(feature = "encrypt")]
+pub mod crypt;
+pub mod dict;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod footer;
 pub mod fs;
+#[cfg(feature = "inspect")]
+pub mod inspect;
+pub mod logging;
+pub mod lz77;
+pub mod progress;
+pub mod recursive;
+pub mod rle;
+pub mod spectrum;
+pub mod stats;
+pub mod symbols;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
-use huffman_coding::{HuffmanReader, HuffmanTree, HuffmanWriter};
-use std::io::{Cursor, Read, Write};
+use algorithm::{CharSpectrum, CtBinaryTree, Decoder, Encoder, PrefixCodeTable};
+use archive::EntryType;
+use compressor::Compressor;
+use footer::{FooterReader, FooterWriter, FOOTER_SIZE};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
-pub use command::CtDirective;
-use fs::{CompressedData, Header};
+pub use cccompress_core::{algorithm, bitstream, CtError};
+pub use codec::{Algorithm, CompressionAlgorithm, Pipeline};
+pub use command::{CtArgs, CtDirective, PackOptions, UnpackOptions};
+use fs::{ChecksumReader, OutputFile};
+pub use fs::{CompressedData, Header};
+use progress::Progress;
+use stats::{Report, Stats};
+use symbols::SymbolModel;
 
 /// Crate common default Result type.
-pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+pub type Result<T> = std::result::Result<T, CtError>;
 
-/// Encoding method to transform text into encoded, compressed bit stream.
-fn compress(text: &str) -> Result<CompressedData> {
-    let tree = HuffmanTree::from_data(text.as_bytes());
-    let table = Vec::<u8>::from(tree.to_table());
+/// Encoding method to transform arbitrary bytes into encoded, compressed bit stream.
+pub fn compress(content: &[u8]) -> Result<CompressedData> {
+    compress_with(content, Algorithm::Huffman, Pipeline::None)
+}
 
-    let mut data = Vec::new();
-    {
-        let mut writer = HuffmanWriter::new(&mut data, &tree);
-        let _ = writer.write(text.as_bytes())?;
+/// Like `compress()`, but by the given algorithm, after preprocessing by the given pipeline.
+pub fn compress_with(
+    content: &[u8],
+    algo: Algorithm,
+    pipeline: Pipeline,
+) -> Result<CompressedData> {
+    Compressor::new()
+        .algorithm(algo)
+        .pipeline(pipeline)
+        .compress(content)
+}
+
+/// Like `compress_with()`, but with a preset dictionary instead of a pipeline, see `dict`.
+pub fn compress_with_dict(content: &[u8], algo: Algorithm, dict: &[u8]) -> Result<CompressedData> {
+    Compressor::new()
+        .algorithm(algo)
+        .dictionary(dict)
+        .compress(content)
+}
+
+/// Size of the pieces, in which a file gets encoded.
+const CHUNK_SIZE: usize = 1 << 16;
+
+/// Compresses a file into another file in two passes over the source: the first one counts the
+/// symbol frequencies, the second one encodes it piece by piece. The encoded size is known from
+/// the frequencies in advance, so the header can be written first and the memory use does not
+/// depend on the size of the file. The progress callback gets the number of processed and of all
+/// bytes of the source, which counts twice when it is read in two passes.
+pub fn compress_file(
+    source: &str,
+    target: &str,
+    options: PackOptions,
+    progress: &mut dyn FnMut(u64, u64),
+) -> Result<Stats> {
+    let start = Instant::now();
+    check_pack(&options)?;
+    let source = Path::new(source);
+    let total = input_size(source) * passes(source, &options);
+    let mut progress = Progress::new(total, progress);
+    // The original name gets restored on unpacking, stdin has none.
+    let name = match source == Path::new(fs::STDIO) {
+        true => None,
+        false => source.file_name().and_then(|name| name.to_str()),
+    };
+    let name = name.unwrap_or_default().to_string();
+    let mut writer = create_output(target, &options)?;
+    let mut stats = compress_entry(source, name, true, &options, &mut writer, &mut progress)?;
+    commit_output(writer)?;
+    stats.compressed_bytes += FOOTER_SIZE as u64;
+    stats.elapsed = start.elapsed();
+    Ok(stats)
+}
+
+/// Compresses a directory with all its files, subdirectories and symlinks into one archive, see
+/// `archive`. Returns the statistics and the number of compressed files. The progress covers all
+/// files, like `compress_file()`.
+pub fn compress_dir(
+    source: &str,
+    target: &str,
+    options: PackOptions,
+    progress: &mut dyn FnMut(u64, u64),
+) -> Result<(Stats, usize)> {
+    let start = Instant::now();
+    check_pack(&options)?;
+    let entries = archive::entries(Path::new(source))?;
+    let total = file_paths(&entries)
+        .map(|path| input_size(path) * passes(path, &options))
+        .sum();
+    let mut progress = Progress::new(total, progress);
+    let mut writer = create_output(target, &options)?;
+    let mut stats = Stats::default();
+    for (path, name) in &entries {
+        stats += compress_entry(
+            path,
+            name.clone(),
+            false,
+            &options,
+            &mut writer,
+            &mut progress,
+        )?;
+    }
+    commit_output(writer)?;
+    stats.compressed_bytes += FOOTER_SIZE as u64;
+    stats.elapsed = start.elapsed();
+    Ok((stats, file_paths(&entries).count()))
+}
+
+/// Appends files as new entries to an existing multi-file archive, see `archive`. The existing
+/// entries are neither decoded nor rewritten, the new ones get a footer of their own like a
+/// concatenated file, see `footer`. If anything fails, the archive gets truncated to its former
+/// size again, so it either contains all new entries or none. Returns the statistics of the new
+/// entries and the number of new files.
+pub fn append_files(
+    target: &str,
+    files: &[String],
+    options: PackOptions,
+    progress: &mut dyn FnMut(u64, u64),
+) -> Result<(Stats, usize)> {
+    let start = Instant::now();
+    fs::check_filename_with(target, options.extension())?;
+    let entries = archive::append_entries(files)?;
+    let names = entry_names(open_input(Path::new(target))?)?;
+    if names.iter().any(|name| name.is_empty()) {
+        return Err(format!("'{target}' is no multi-file archive").into());
+    }
+    if let Some((_, name)) = entries.iter().find(|(_, name)| names.contains(name)) {
+        return Err(format!("'{target}' already contains '{name}'").into());
     }
-    let len = data.len() as u32;
 
-    Ok(CompressedData {
-        header: Header {
-            filename: String::new(),
-            prefix_table: table,
-            data_bytes: len,
+    let total = file_paths(&entries)
+        .map(|path| input_size(path) * passes(path, &options))
+        .sum();
+    let mut progress = Progress::new(total, progress);
+    let file = File::options().append(true).open(target)?;
+    let size = file.metadata()?.len();
+    // The writer is dropped and flushed, before the archive gets truncated on failure.
+    let append = |progress: &mut Progress| -> Result<Stats> {
+        let mut writer = FooterWriter::new(BufWriter::new(&file));
+        let mut stats = Stats::default();
+        for (path, name) in &entries {
+            stats += compress_entry(path, name.clone(), false, &options, &mut writer, progress)?;
+        }
+        writer.finish()?.flush()?;
+        stats.compressed_bytes += FOOTER_SIZE as u64;
+        Ok(stats)
+    };
+    let mut stats = append(&mut progress).inspect_err(|_| {
+        let _ = file.set_len(size);
+    })?;
+    stats.elapsed = start.elapsed();
+    Ok((stats, file_paths(&entries).count()))
+}
+
+/// Paths of the entries, which are files, see `EntryType`.
+fn file_paths(entries: &[(PathBuf, String)]) -> impl Iterator<Item = &Path> {
+    entries
+        .iter()
+        .map(|(path, _)| path.as_path())
+        .filter(|path| EntryType::of(path) == EntryType::File)
+}
+
+/// Names of all entries of an archive. Only their headers get read, the data is skipped and the
+/// footers are checked by their lengths, see `footer`.
+fn entry_names(reader: Input) -> Result<Vec<String>> {
+    let mut reader = FooterReader::new(reader, true);
+    let mut names = Vec::new();
+    loop {
+        let header = fs::read_header(&mut reader)?;
+        let n = reader.skip_by(header.data_bytes as u64, Input::skip)?;
+        check_skipped(&header, n)?;
+        names.push(header.filename);
+        if reader.at_end()? {
+            return Ok(names);
+        }
+    }
+}
+
+/// Checks the structure of a compressed file by its headers and footers, before decoding it, so
+/// that a truncated file fails before anything gets written. Only regular files can be read
+/// twice, others are not checked.
+fn check_input(source: &Path) -> Result<()> {
+    match open_input(source)? {
+        input @ Input::File(..) => entry_names(input).map(|_| ()),
+        Input::Stream(_) => Ok(()),
+    }
+}
+
+/// Opens the source of a directive for reading, stdin for `-`.
+fn open_input(source: &Path) -> Result<Input> {
+    if source == Path::new(fs::STDIO) {
+        return Ok(Input::Stream(Box::new(std::io::stdin().lock())));
+    }
+    let file = File::open(source)?;
+    let metadata = file.metadata()?;
+    Ok(match metadata.is_file() {
+        true => Input::File(BufReader::new(file), metadata.len()),
+        false => Input::Stream(Box::new(BufReader::new(file))),
+    })
+}
+
+/// Source of a directive, see `open_input()`. Regular files can skip data by seeking, so that e.g.
+/// listing the entries of a large archive only reads their headers.
+enum Input {
+    /// A regular file with its length.
+    File(BufReader<File>, u64),
+    /// Stdin, a pipe or a device, which can only be read.
+    Stream(Box<dyn BufRead>),
+}
+
+impl Input {
+    /// Skips up to the given number of bytes, returns how many were skipped. Less are only
+    /// skipped at the end of the input.
+    fn skip(&mut self, bytes: u64) -> std::io::Result<u64> {
+        match self {
+            Input::File(reader, len) => {
+                let pos = std::io::Seek::stream_position(reader)?;
+                let bytes = bytes.min(len.saturating_sub(pos));
+                reader.seek_relative(bytes as i64)?;
+                Ok(bytes)
+            }
+            Input::Stream(reader) => std::io::copy(&mut reader.take(bytes), &mut std::io::sink()),
+        }
+    }
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Input::File(reader, _) => reader.read(buf),
+            Input::Stream(reader) => reader.read(buf),
+        }
+    }
+}
+
+impl BufRead for Input {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        match self {
+            Input::File(reader, _) => reader.fill_buf(),
+            Input::Stream(reader) => reader.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amount: usize) {
+        match self {
+            Input::File(reader, _) => reader.consume(amount),
+            Input::Stream(reader) => reader.consume(amount),
+        }
+    }
+}
+
+/// Content of the source mapped into memory, `None` for stdin. Very large files are neither copied
+/// into a buffer as a whole nor piece by piece, the operating system pages them in on demand.
+#[cfg(feature = "mmap")]
+fn map_input(source: &Path) -> Result<Option<memmap2::Mmap>> {
+    if source == Path::new(fs::STDIO) {
+        return Ok(None);
+    }
+    let file = File::open(source)?;
+    // Safety: the mapping is only read. If another process modifies the file meanwhile, the
+    // encoded size or the checksum does not match and `compress_entry()` fails.
+    Ok(Some(unsafe { memmap2::Mmap::map(&file)? }))
+}
+
+/// Without the `mmap` feature nothing gets mapped, sources are read through buffers.
+#[cfg(not(feature = "mmap"))]
+fn map_input(_source: &Path) -> Result<Option<Vec<u8>>> {
+    Ok(None)
+}
+
+/// Opens the source for reading, from its mapped content if there is one.
+fn open_mapped<'m>(source: &Path, mapped: Option<&'m [u8]>) -> Result<Box<dyn BufRead + 'm>> {
+    match mapped {
+        Some(content) => Ok(Box::new(content)),
+        None => Ok(Box::new(open_input(source)?)),
+    }
+}
+
+/// Encrypts the compressed data with the passphrase, see `crypt`.
+#[cfg(feature = "encrypt")]
+fn encrypt(passphrase: &str, data: &[u8]) -> Result<Vec<u8>> {
+    crypt::encrypt(passphrase, data)
+}
+
+/// Without the `encrypt` feature, nothing can be encrypted.
+#[cfg(not(feature = "encrypt"))]
+fn encrypt(_passphrase: &str, _data: &[u8]) -> Result<Vec<u8>> {
+    Err("encryption needs cccompress to be built with the 'encrypt' feature".into())
+}
+
+/// Checks the options in advance, so that no output gets created for packing, which must fail.
+fn check_pack(options: &PackOptions) -> Result<()> {
+    if options.passphrase.is_some() && cfg!(not(feature = "encrypt")) {
+        return Err("encryption needs cccompress to be built with the 'encrypt' feature".into());
+    }
+    Ok(())
+}
+
+/// Decrypts the compressed data with the passphrase, see `crypt`.
+#[cfg(feature = "encrypt")]
+fn decrypt(passphrase: &str, data: &[u8]) -> Result<Vec<u8>> {
+    crypt::decrypt(passphrase, data)
+}
+
+/// Without the `encrypt` feature, nothing can be decrypted.
+#[cfg(not(feature = "encrypt"))]
+fn decrypt(_passphrase: &str, _data: &[u8]) -> Result<Vec<u8>> {
+    Err("decryption needs cccompress to be built with the 'encrypt' feature".into())
+}
+
+/// Creates a symlink, which points to the target.
+#[cfg(unix)]
+fn symlink(target: &str, fname: &Path) -> Result<()> {
+    Ok(std::os::unix::fs::symlink(target, fname)?)
+}
+
+/// Symlinks are only restored on Unix.
+#[cfg(not(unix))]
+fn symlink(target: &str, _fname: &Path) -> Result<()> {
+    Err(format!("symlink to '{target}' can only be restored on Unix").into())
+}
+
+/// Shows the Huffman coding of the file interactively, see `inspect`.
+#[cfg(feature = "inspect")]
+fn inspect(source: &str) -> Result<()> {
+    inspect::Inspector::open(source)?.run()
+}
+
+/// Without the `inspect` feature, there is no terminal user interface.
+#[cfg(not(feature = "inspect"))]
+fn inspect(_source: &str) -> Result<()> {
+    Err("inspecting needs cccompress to be built with the 'inspect' feature".into())
+}
+
+/// Size of the source of a directive, 0 if it is unknown like for stdin.
+fn input_size(source: &Path) -> u64 {
+    if source == Path::new(fs::STDIO) {
+        return 0;
+    }
+    std::fs::metadata(source).map_or(0, |m| m.len())
+}
+
+/// Whether the source gets compressed piece by piece in two passes, see `compress_entry()`.
+fn streamed(options: &PackOptions) -> bool {
+    !options.seekable
+        && options.algo == Algorithm::Huffman
+        && options.pipeline == Pipeline::None
+        && options.passphrase.is_none()
+        && options.dict.is_none()
+        && options.symbols == SymbolModel::Bytes
+}
+
+/// Number of passes over the source, when compressing it, see `compress_entry()`.
+fn passes(source: &Path, options: &PackOptions) -> u64 {
+    if streamed(options) && source != Path::new(fs::STDIO) {
+        2
+    } else {
+        1
+    }
+}
+
+/// Creates the compressed target of a directive, stdout for `-`, see `fs::OutputFile`.
+fn create_output(
+    target: &str,
+    options: &PackOptions,
+) -> Result<FooterWriter<BufWriter<OutputFile>>> {
+    if target != fs::STDIO {
+        fs::check_filename_with(target, options.extension())?;
+    }
+    let file = OutputFile::create(Path::new(target), options.force, options.atomic)?;
+    Ok(FooterWriter::new(BufWriter::new(file)))
+}
+
+/// Completes the compressed target of a directive with its footer, once everything is written.
+fn commit_output(writer: FooterWriter<BufWriter<OutputFile>>) -> Result<()> {
+    writer
+        .finish()?
+        .into_inner()
+        .map_err(|err| err.into_error())?
+        .commit()
+}
+
+/// Writes the Huffman tree of a source file in the DOT language and its prefix code table into
+/// the targets of the options, see `--dump-tree` and `--dump-table`.
+fn dump_model(source: &Path, options: &PackOptions) -> Result<()> {
+    if source == Path::new(fs::STDIO) || source.is_dir() {
+        return Err("the tree and the table can only be dumped of a single file".into());
+    }
+    let spectrum = CharSpectrum::from_reader(open_input(source)?)?;
+    let tree = CtBinaryTree::try_from(&spectrum)?;
+    if let Some(target) = &options.dump_tree {
+        std::fs::write(target, tree.to_dot())?;
+    }
+    if let Some(target) = &options.dump_table {
+        let table = PrefixCodeTable::from(&tree);
+        let table = table.with_spectrum(&spectrum);
+        match target.ends_with(".csv") {
+            true => std::fs::write(target, table.to_csv())?,
+            false => std::fs::write(target, table.to_string())?,
+        }
+    }
+    Ok(())
+}
+
+/// Writes the spectrum of a source file, or of all files of a source directory together, as JSON
+/// to the target, see `--dump-spectrum`.
+fn dump_spectrum(source: &Path, target: &str) -> Result<()> {
+    if source == Path::new(fs::STDIO) {
+        return Err("the spectrum of stdin can not be dumped".into());
+    }
+    let spectrum = match source.is_dir() {
+        true => {
+            let mut frequencies = [0; algorithm::SYMBOLS];
+            for path in file_paths(&archive::entries(source)?) {
+                let spectrum = CharSpectrum::from_reader(open_input(path)?)?;
+                for (symbol, frequency) in frequencies.iter_mut().enumerate() {
+                    *frequency += spectrum.frequency(symbol as u8);
+                }
+            }
+            CharSpectrum::from_frequencies(frequencies)
+        }
+        false => CharSpectrum::from_reader(open_input(source)?)?,
+    };
+    std::fs::write(target, spectrum::to_json(&spectrum))?;
+    Ok(())
+}
+
+/// Compresses one file as the next entry into the writer, with the given name in its header: the
+/// original name of a single file or the path of an entry of an archive. Only pure Huffman coding
+/// without a pipeline is streamed, e.g. LZ77 references need the whole content. Neither is
+/// encrypted data nor data with a dictionary. Stdin can not be read twice, so it gets copied into
+/// a temporary file to be streamed. With the `mmap` feature, files are read from their mapping.
+/// Returns the sizes of the entry.
+fn compress_entry(
+    source: &Path,
+    filename: String,
+    single_file: bool,
+    options: &PackOptions,
+    writer: &mut impl Write,
+    progress: &mut Progress,
+) -> Result<Stats> {
+    let compressor = Compressor::from(options.clone());
+    compressor.check()?;
+    log::debug!(
+        "compressing '{}' by {} in {} passes",
+        source.display(),
+        options.algo.name(),
+        passes(source, options)
+    );
+    // Single files are compressed by their content, even if they are symlinks.
+    let entry_type = match single_file || source == Path::new(fs::STDIO) {
+        true => EntryType::File,
+        false => EntryType::of(source),
+    };
+    if entry_type != EntryType::File {
+        // Directories have no content, symlinks the path, to which they point.
+        let (content, (modified, mode)) = match entry_type {
+            EntryType::Symlink => {
+                let target = std::fs::read_link(source)?;
+                let target = target
+                    .to_str()
+                    .ok_or_else(|| format!("'{}' is not a valid UTF-8 path", target.display()))?;
+                (target.as_bytes().to_vec(), (0, 0))
+            }
+            _ => (Vec::new(), fs::file_meta(source)),
+        };
+        let mut cdata = compressor.compress(&content)?;
+        cdata.header.filename = filename;
+        cdata.header.modified = modified;
+        cdata.header.mode = mode;
+        cdata.header.entry_type = entry_type.id();
+        return write_entry(writer, &cdata);
+    }
+    let spooled;
+    let (source, (modified, mode)) = if source != Path::new(fs::STDIO) {
+        (source, fs::file_meta(source))
+    } else if streamed(options) {
+        spooled = fs::TempFile::from_reader(std::io::stdin().lock())?;
+        (spooled.path(), (0, 0))
+    } else {
+        (source, (0, 0))
+    };
+    let mapped = map_input(source)?;
+    let mapped = mapped.as_deref();
+    if passes(source, options) == 1 {
+        let mut cdata = match mapped {
+            Some(content) if !options.seekable => {
+                progress.advance(content.len() as u64);
+                compressor.compress(content)?
+            }
+            _ => compressor.compress_from(progress.reader(open_mapped(source, mapped)?))?,
+        };
+        cdata.header.filename = filename;
+        cdata.header.single_file = single_file;
+        cdata.header.modified = modified;
+        cdata.header.mode = mode;
+        return write_entry(writer, &cdata);
+    }
+
+    let mut reader = ChecksumReader::new(progress.reader(open_mapped(source, mapped)?));
+    let spectrum = CharSpectrum::from_reader(&mut reader)?;
+    let table = match &options.spectrum {
+        Some(imported) => spectrum::table(imported, options.max_code_length)?,
+        None => PrefixCodeTable::build(&spectrum, options.max_code_length)?,
+    };
+
+    let bits = table.encoded_bits(&spectrum);
+    log::trace!(
+        "'{}': {} symbols, codes of up to {} bits",
+        source.display(),
+        spectrum.total(),
+        table.max_length()
+    );
+    let header = Header {
+        filename,
+        prefix_table: Vec::<u8>::from(&table),
+        data_bytes: u32::try_from(bits.div_ceil(8)).map_err(|_| "encoded data exceeds 4 GiB")?,
+        unused_bits: ((8 - bits % 8) % 8) as u8,
+        checksum: if options.checksum {
+            reader.checksum()
+        } else {
+            0
         },
-        data,
+        algorithm: Algorithm::Huffman.id(),
+        no_checksum: !options.checksum,
+        single_file,
+        original_bytes: spectrum.total(),
+        modified,
+        mode,
+        ..Default::default()
+    };
+    let bytes = fs::write_header(writer, &header)?;
+
+    let mut reader = progress.reader(open_mapped(source, mapped)?);
+    let mut encoder = Encoder::new(&table, &mut *writer);
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        encoder.encode(&buffer[..n])?;
+    }
+    if encoder.bits() != bits {
+        let source = source.display();
+        return Err(format!("'{source}' changed while compressing it").into());
+    }
+    encoder.finish()?;
+
+    Ok(Stats {
+        original_bytes: header.original_bytes,
+        compressed_bytes: bytes as u64 + header.data_bytes as u64,
+        ..Default::default()
+    })
+}
+
+/// Writes the header and the data of an entry. Returns its sizes.
+fn write_entry(writer: &mut impl Write, cdata: &CompressedData) -> Result<Stats> {
+    let bytes = fs::write_header(writer, &cdata.header)?;
+    writer.write_all(&cdata.data)?;
+    Ok(Stats {
+        original_bytes: cdata.header.original_bytes,
+        compressed_bytes: (bytes + cdata.data.len()) as u64,
+        ..Default::default()
     })
 }
 
-/// Decoding method to transform encoded, compressed bit stream back to text.
-fn decompress(cdata: &CompressedData) -> Result<String> {
-    let tree = HuffmanTree::from_table(&cdata.header.prefix_table[..]);
-    let cursor = Cursor::new(&cdata.data[..]);
+/// Writes a compressed file of a single entry, like `--pack`: the entry and its footer, see
+/// `footer`. Returns its sizes.
+pub(crate) fn write_file(writer: impl Write, cdata: &CompressedData) -> Result<Stats> {
+    let mut writer = FooterWriter::new(writer);
+    let mut stats = write_entry(&mut writer, cdata)?;
+    writer.finish()?.flush()?;
+    stats.compressed_bytes += FOOTER_SIZE as u64;
+    Ok(stats)
+}
+
+/// Decoding method to transform encoded, compressed bit stream back to the original bytes.
+pub fn decompress(cdata: &CompressedData) -> Result<Vec<u8>> {
+    if cdata.header.dict_id != 0 {
+        return Err(NEEDS_DICT.into());
+    }
+    if cdata.header.seekable {
+        let mut content = Vec::new();
+        decompress_data(&cdata.header, &cdata.data[..], &mut content, 0)?;
+        return Ok(content);
+    }
+    let algo = Algorithm::from_id(cdata.header.algorithm)?;
+    let pipeline = Pipeline::from_header(&cdata.header)?;
+    let content = pipeline.revert(algo.codec().decompress(cdata)?)?;
+    verify_checksum(
+        &cdata.header,
+        crc32fast::hash(&content),
+        content.len() as u64,
+    )?;
+    Ok(content)
+}
+
+/// Like `decompress()`, but for data compressed by `compress_with_dict()` with the same dictionary.
+pub fn decompress_with_dict(cdata: &CompressedData, dict: &[u8]) -> Result<Vec<u8>> {
+    dict::check(dict, cdata.header.dict_id)?;
+    let algo = Algorithm::from_id(cdata.header.algorithm)?;
+    let content = algo.codec().decompress_with_dict(cdata, dict)?;
+    verify_checksum(
+        &cdata.header,
+        crc32fast::hash(&content),
+        content.len() as u64,
+    )?;
+    Ok(content)
+}
+
+/// Error of decompressing data, which has been compressed with a dictionary, without one.
+const NEEDS_DICT: &str =
+    "data has been compressed with a dictionary, which is needed to decompress it";
+
+/// Compares the checksum and the length of the decompressed content with the ones of the original
+/// content. The length is unknown for files written before it was stored in the header, the
+/// checksum of files written without one.
+fn verify_checksum(header: &Header, checksum: u32, bytes: u64) -> Result<()> {
+    if !header.no_checksum && checksum != header.checksum {
+        return Err(CtError::ChecksumMismatch {
+            expected: header.checksum,
+            got: checksum,
+        });
+    }
+    if header.original_bytes != 0 && bytes != header.original_bytes {
+        return Err(format!(
+            "decoded {bytes} bytes, but the original content has {} bytes",
+            header.original_bytes
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Decompresses a compressed file from a reader into a writer. The encoded data is decoded piece
+/// by piece, so the decompressed content is never held in memory completely. Like gzip, several
+/// concatenated compressed files get decompressed one after another into the writer. Returns the
+/// number of written bytes.
+pub fn decompress_to(cdata_reader: impl Read, out: impl Write) -> Result<u64> {
+    decompress_to_with_progress(cdata_reader, out, &mut |_, _| {})
+}
+
+/// Like `decompress_to()`, the progress callback gets the number of processed and of all bytes
+/// of the compressed data of each concatenated file.
+pub fn decompress_to_with_progress(
+    cdata_reader: impl Read,
+    mut out: impl Write,
+    progress: &mut dyn FnMut(u64, u64),
+) -> Result<u64> {
+    let mut reader = FooterReader::new(BufReader::new(cdata_reader), true);
+    let mut bytes = 0;
+    loop {
+        // The length fields of the header tell where the next file begins.
+        let header = fs::read_header(&mut reader)?;
+        let mut progress = Progress::new(header.data_bytes as u64, progress);
+        bytes += decompress_data(&header, progress.reader(&mut reader), &mut out, 0)?;
+        if reader.at_end()? {
+            return Ok(bytes);
+        }
+    }
+}
+
+/// Decodes the encoded data following the header from the reader into the writer. Only pure
+/// Huffman coding without a pipeline is decoded piece by piece, anything else gets the complete
+/// data. The blocks of seekable data are decoded on `threads` threads, see `blocks::decompress()`.
+pub(crate) fn decompress_data(
+    header: &Header,
+    reader: impl Read,
+    mut out: impl Write,
+    threads: usize,
+) -> Result<u64> {
+    if header.encrypted {
+        return Err("data is encrypted, a passphrase is needed to decompress it".into());
+    }
+    if header.dict_id != 0 {
+        return Err(NEEDS_DICT.into());
+    }
+    let algo = Algorithm::from_id(header.algorithm)?;
+    let pipeline = Pipeline::from_header(header)?;
+    let (bytes, checksum) = match (algo, pipeline) {
+        _ if header.seekable => {
+            let mut reader = reader.take(header.data_bytes as u64);
+            blocks::decompress(header, &mut reader, &mut out, threads)?
+        }
+        (Algorithm::Huffman, Pipeline::None) if header.symbols == 0 => {
+            decode_stream(header, reader, &mut out)?
+        }
+        _ => {
+            let cdata = CompressedData {
+                header: header.clone(),
+                data: read_data(header, reader)?,
+            };
+            let content = pipeline.revert(algo.codec().decompress(&cdata)?)?;
+            out.write_all(&content)?;
+            (content.len() as u64, crc32fast::hash(&content))
+        }
+    };
+    out.flush()?;
+    verify_checksum(header, checksum, bytes)?;
+    Ok(bytes)
+}
+
+/// Reads the complete data of an entry, which follows its header.
+fn read_data(header: &Header, reader: impl Read) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    reader
+        .take(header.data_bytes as u64)
+        .read_to_end(&mut data)?;
+    check_skipped(header, data.len() as u64)?;
+    Ok(data)
+}
+
+/// Decodes the Huffman coded data piece by piece from the reader into the writer. Returns the
+/// number of written bytes and their checksum.
+fn decode_stream(header: &Header, reader: impl Read, mut out: impl Write) -> Result<(u64, u32)> {
+    let table = PrefixCodeTable::try_from(&header.prefix_table[..])?;
+    let mut decoder = Decoder::new(&table);
+    let mut reader = reader.take(header.data_bytes as u64);
+    let mut remaining = header.data_bytes as usize;
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut content = Vec::new();
+    let mut hasher = crc32fast::Hasher::new();
+    let mut bytes = 0;
+    while remaining > 0 {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            return Err(CtError::Truncated {
+                expected: header.data_bytes as u64,
+                got: (header.data_bytes as usize - remaining) as u64,
+            });
+        }
+        remaining -= n;
+        let unused_bits = if remaining == 0 {
+            header.unused_bits
+        } else {
+            0
+        };
+        // Everything decoded in front of an invalid code is written, see `--salvage`.
+        let decoded = decoder.decode(&buffer[..n], unused_bits, &mut content);
+        out.write_all(&content)?;
+        hasher.update(&content);
+        bytes += content.len() as u64;
+        content.clear();
+        decoded?;
+    }
+    decoder.finish()?;
+    Ok((bytes, hasher.finalize()))
+}
+
+/// Creates the output file of an entry, or stdout for `-`, see `fs::OutputFile`.
+fn open_entry(fname: &Path, options: &UnpackOptions) -> Result<OutputFile> {
+    if let Some(dir) = fname.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    OutputFile::create(fname, options.force, options.atomic)
+}
+
+/// Completes the output file of an entry and restores the metadata of the header to it.
+fn finish_entry(
+    out: OutputFile,
+    fname: &Path,
+    header: &Header,
+    options: &UnpackOptions,
+) -> Result<()> {
+    out.commit()?;
+    if options.restore_meta && fname != Path::new(fs::STDIO) {
+        fs::restore_meta(fname, header)?;
+    }
+    Ok(())
+}
+
+/// Decompresses one entry of an archive into the output. Encrypted data gets decrypted as a whole
+/// in advance.
+fn decompress_entry(
+    header: &Header,
+    reader: impl Read,
+    out: &mut impl Write,
+    options: &UnpackOptions,
+) -> Result<u64> {
+    if header.encrypted {
+        let passphrase = options
+            .passphrase
+            .as_deref()
+            .ok_or("data is encrypted, a passphrase is needed to decompress it")?;
+        let data = decrypt(passphrase, &read_data(header, reader)?)?;
+        let header = Header {
+            data_bytes: data.len() as u32,
+            encrypted: false,
+            ..header.clone()
+        };
+        return decompress_entry(&header, &data[..], out, options);
+    }
+    decode_entry(header, reader, BufWriter::new(out), options)
+}
+
+/// Restores a directory or a symlink entry, see `EntryType`. The metadata of directories is
+/// restored afterwards, once their content is complete.
+fn unpack_special(
+    entry_type: EntryType,
+    header: &Header,
+    reader: &mut impl Read,
+    fname: &Path,
+    options: &UnpackOptions,
+) -> Result<()> {
+    if entry_type == EntryType::Dir {
+        skip_data(header, reader)?;
+        std::fs::create_dir_all(fname)?;
+        return Ok(());
+    }
+    let mut target = Vec::new();
+    decompress_entry(header, reader, &mut target, options)?;
+    let target = String::from_utf8(target).map_err(|_| "symlink target is no valid UTF-8")?;
+    archive::check_symlink(fname, &target)?;
+    if fname.symlink_metadata().is_ok() {
+        if !options.force {
+            return Err(fs::already_exists(fname));
+        }
+        std::fs::remove_file(fname)?;
+    }
+    if let Some(dir) = fname.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    symlink(&target, fname)
+}
+
+/// Decodes the data of an entry like `decompress_data()`, with the dictionary of the options if
+/// the data has been compressed with one.
+fn decode_entry(
+    header: &Header,
+    reader: impl Read,
+    mut out: impl Write,
+    options: &UnpackOptions,
+) -> Result<u64> {
+    if header.dict_id == 0 {
+        return decompress_data(header, reader, out, options.threads);
+    }
+    let dict = options.dict.as_deref().ok_or(NEEDS_DICT)?;
+    let cdata = CompressedData {
+        header: header.clone(),
+        data: read_data(header, reader)?,
+    };
+    let content = decompress_with_dict(&cdata, dict)?;
+    out.write_all(&content)?;
+    out.flush()?;
+    Ok(content.len() as u64)
+}
+
+/// Path, into which an entry gets restored: stdout, the original name of a single file next to
+/// the compressed file, see `fs::unpacked_path()`, or the path of an entry of an archive, see
+/// `--strip-components`. None, if nothing is left of the path.
+fn restore_path(header: &Header, source: &str, options: &UnpackOptions) -> Result<Option<PathBuf>> {
+    if options.stdout || (header.is_single_file() && source == fs::STDIO) {
+        Ok(Some(fs::STDIO.into()))
+    } else if header.is_single_file() {
+        fs::unpacked_path(source, header, options.extension()).map(Some)
+    } else {
+        archive::stripped_path(&header.filename, options.strip_components)
+    }
+}
+
+/// What became of an entry, see `restore_entry()`.
+enum Restored {
+    /// A directory was created, its metadata gets restored after its content, see
+    /// `restore_dirs()`.
+    Dir,
+    /// A symlink was created, or a directory or symlink was skipped when writing to stdout.
+    Special,
+    /// A file was decoded with the number of its bytes. Its output still needs to be finished, see
+    /// `finish_entry()`, as concatenated files may continue it.
+    File(OutputFile, u64),
+    /// Decoding a file failed when salvaging. Its output was kept with the number of bytes, which
+    /// could be decoded.
+    Salvaged(u64, CtError),
+}
+
+/// Restores one entry of a compressed file into the path: creates a directory or a symlink, see
+/// `unpack_special()`, or decodes a file into the given output or else a new one. The output of a
+/// file, which fails, gets discarded, or kept when salvaging.
+fn restore_entry(
+    header: &Header,
+    reader: &mut impl Read,
+    fname: &Path,
+    out: Option<OutputFile>,
+    options: &UnpackOptions,
+) -> Result<Restored> {
+    let entry_type = EntryType::from_id(header.entry_type)?;
+    if entry_type != EntryType::File {
+        if fname == Path::new(fs::STDIO) {
+            skip_data(header, reader)?;
+            return Ok(Restored::Special);
+        }
+        unpack_special(entry_type, header, reader, fname, options)?;
+        return Ok(match entry_type {
+            EntryType::Dir => Restored::Dir,
+            _ => Restored::Special,
+        });
+    }
+    let mut out = match out {
+        Some(out) => out,
+        None => open_entry(fname, options)?,
+    };
+    log::debug!("decompressing into '{}'", fname.display());
+    let before = out.written();
+    match decompress_entry(header, reader, &mut out, options) {
+        Ok(bytes) => Ok(Restored::File(out, bytes)),
+        Err(err) if options.salvage => {
+            let bytes = out.written() - before;
+            out.commit()?;
+            Ok(Restored::Salvaged(bytes, err))
+        }
+        Err(err) => {
+            out.discard();
+            Err(err)
+        }
+    }
+}
+
+/// Restores the metadata of unpacked directories with their headers, nested ones first.
+fn restore_dirs(dirs: &[(PathBuf, Header)], options: &UnpackOptions) -> Result<()> {
+    if options.restore_meta {
+        for (dir, header) in dirs.iter().rev() {
+            fs::restore_meta(dir, header)?;
+        }
+    }
+    Ok(())
+}
 
-    let mut data = Vec::<u8>::new();
-    let mut reader = HuffmanReader::new(cursor, tree);
+/// Decompresses all entries of a compressed file, see `CtDirective::Unpack`.
+fn unpack_file(
+    source: &str,
+    options: &UnpackOptions,
+    progress: &mut dyn FnMut(u64, u64),
+) -> Result<Report> {
+    if source != fs::STDIO {
+        fs::check_filename_with(source, options.extension())?;
+    }
+    let source_path = Path::new(source);
+    // Salvaging recovers as much as possible also of a truncated file.
+    if !options.salvage {
+        check_input(source_path).map_err(|err| err.in_file(source))?;
+    }
+    let mut progress = Progress::new(input_size(source_path), progress);
+    let input = progress.reader(open_input(source_path)?);
+    let mut reader = FooterReader::new(input, !options.salvage);
+    let mut fnames = Vec::new();
+    let mut bytes = 0;
+    // Position and error, where decoding stopped when salvaging.
+    let mut stopped = None;
+    // Output and header of the previous file, which concatenated compressed files continue, see
+    // `fs::OutputFile`.
+    let mut pending: Option<(OutputFile, Header)> = None;
+    // Directories and their headers, whose metadata gets restored after their content.
+    let mut dirs = Vec::new();
+    loop {
+        let header = match fs::read_header(&mut reader) {
+            Ok(header) => header,
+            Err(err) if options.salvage && !fnames.is_empty() => {
+                stopped = Some((reader.get_ref().done(), err));
+                break;
+            }
+            Err(err) => return Err(err),
+        };
+        // Single files, which follow each other, are concatenated compressed files like
+        // `cat a.cpd b.cpd`, and get decompressed into the file of the first one.
+        let append = header.is_single_file()
+            && matches!(&pending, Some((_, previous)) if previous.is_single_file());
+        let fname = match append {
+            true => fnames.last().cloned().unwrap(),
+            false => match restore_path(&header, source, options)? {
+                Some(fname) => fname,
+                None => {
+                    log::info!(
+                        "skipping '{}', nothing is left of its path",
+                        header.filename
+                    );
+                    skip_data(&header, &mut reader)?;
+                    if reader.at_end()? {
+                        break;
+                    }
+                    continue;
+                }
+            },
+        };
+        let out = match pending.take() {
+            Some((out, _)) if append => Some(out),
+            Some((out, previous)) => {
+                finish_entry(out, fnames.last().unwrap(), &previous, options)?;
+                None
+            }
+            None => None,
+        };
+        match restore_entry(&header, &mut reader, &fname, out, options)
+            .map_err(|err| err.in_file(source))?
+        {
+            Restored::Dir => dirs.push((fname, header)),
+            Restored::Special => (),
+            Restored::File(out, n) => {
+                bytes += n;
+                if !append {
+                    fnames.push(fname);
+                }
+                pending = Some((out, header));
+            }
+            Restored::Salvaged(n, err) => {
+                log::warn!("'{source}': {err}, salvaging what was decoded");
+                bytes += n;
+                if !append {
+                    fnames.push(fname);
+                }
+                stopped = Some((reader.get_ref().done(), err));
+                break;
+            }
+        }
+        if reader.at_end()? {
+            break;
+        }
+    }
+    drop(reader);
+    if let Some((out, header)) = pending {
+        finish_entry(out, fnames.last().unwrap(), &header, options)?;
+    }
+    restore_dirs(&dirs, options)?;
+    // Salvaged files are kept, they may still contain more than could be recovered.
+    let removed = options.remove_source && stopped.is_none();
+    if removed {
+        std::fs::remove_file(source_path)?;
+    }
 
-    let bytes = reader.read_to_end(&mut data)?;
-    println!("HuffmanReader read {bytes} Bytes");
+    let mut message = match (&fnames[..], stopped) {
+        (_, Some((offset, err))) => format!(
+            "Salvaged '{source}'. Wrote {bytes} bytes to {} files, decoding stopped at byte \
+             {offset}: {err}",
+            fnames.len()
+        ),
+        ([fname], None) => {
+            let fname = fname.display();
+            format!("Decompressed '{source}'. Wrote {bytes} bytes to '{fname}'")
+        }
+        (_, None) => format!(
+            "Decompressed '{source}'. Wrote {bytes} bytes to {} files",
+            fnames.len()
+        ),
+    };
+    if removed {
+        message += &format!(", removed '{source}'");
+    }
+    Ok(Report {
+        message,
+        stats: Stats {
+            original_bytes: bytes,
+            compressed_bytes: progress.done(),
+            ..Default::default()
+        },
+    })
+}
 
-    Ok(String::from_utf8(data)?)
+/// Decompresses a single entry of a multi-file archive by its name, see `CtDirective::Extract`.
+fn extract_entry(
+    source: &str,
+    name: &str,
+    options: &UnpackOptions,
+    progress: &mut dyn FnMut(u64, u64),
+) -> Result<Report> {
+    if source != fs::STDIO {
+        fs::check_filename_with(source, options.extension())?;
+    }
+    let source_path = Path::new(source);
+    if !options.salvage {
+        check_input(source_path).map_err(|err| err.in_file(source))?;
+    }
+    let mut progress = Progress::new(input_size(source_path), progress);
+    let input = progress.reader(open_input(source_path)?);
+    let mut reader = FooterReader::new(input, !options.salvage);
+    let header = loop {
+        let header = fs::read_header(&mut reader)?;
+        if header.filename == name {
+            break header;
+        }
+        skip_data(&header, &mut reader)?;
+        if reader.at_end()? {
+            return Err(format!("'{name}' is not contained in '{source}'").into());
+        }
+    };
+    let fname = restore_path(&header, source, options)?
+        .ok_or_else(|| format!("nothing is left of '{name}' to extract to"))?;
+    let restored = restore_entry(&header, &mut reader, &fname, None, options)
+        .map_err(|err| err.in_file(source))?;
+    let (message, bytes) = match restored {
+        Restored::Dir => {
+            restore_dirs(&[(fname, header.clone())], options)?;
+            (format!("Extracted '{name}' from '{source}'"), 0)
+        }
+        Restored::Special => (format!("Extracted '{name}' from '{source}'"), 0),
+        Restored::File(out, bytes) => {
+            finish_entry(out, &fname, &header, options)?;
+            let message = format!("Extracted '{name}' from '{source}'. Wrote {bytes} bytes");
+            (message, bytes)
+        }
+        Restored::Salvaged(_, err) => return Err(err.in_file(source)),
+    };
+    Ok(Report {
+        message,
+        stats: Stats {
+            original_bytes: bytes,
+            compressed_bytes: fs::entry_size(&header)?,
+            ..Default::default()
+        },
+    })
 }
 
 /// Main entry method for compression-tool use case, to be able to separate the code into library
 /// and not main module.
 pub fn compression_tool(directive: CtDirective) -> Result<String> {
-    Ok(match directive {
-        CtDirective::Pack(source, of) => {
-            let content = std::fs::read_to_string(&source)?;
+    Ok(compression_tool_with_progress(directive, &mut |_, _| {})?.message)
+}
+
+/// Like `compression_tool()`, but returns the statistics besides the message. The progress
+/// callback gets the number of processed and of all bytes of the source.
+pub fn compression_tool_with_progress(
+    directive: CtDirective,
+    progress: &mut dyn FnMut(u64, u64),
+) -> Result<Report> {
+    let start = Instant::now();
+    let mut report = match directive {
+        CtDirective::Pack(source, of, options) => {
             let fname = if let Some(ofname) = of {
                 ofname
+            } else if source == fs::STDIO {
+                source.clone()
             } else {
-                fs::switch_file_type(&source)
+                fs::switch_file_type_with(source.trim_end_matches('/'), options.extension())
             };
+            if options.dump_tree.is_some() || options.dump_table.is_some() {
+                dump_model(Path::new(&source), &options)?;
+            }
+            if let Some(target) = &options.dump_spectrum {
+                dump_spectrum(Path::new(&source), target)?;
+            }
 
-            let cdata = compress(&content)?;
-            let bytes = cdata.write(&fname)?;
-
-            format!("Compressed '{source}'. Wrote {bytes} bytes to '{fname}'")
-        }
-        CtDirective::Unpack(source) => {
-            let cdata = CompressedData::read(&source)?;
-            let fname = if cdata.header.filename.is_empty() {
-                fs::switch_file_type(&source)
+            let verify = options.remove_source.then(|| options.clone());
+            let mut report = if source != fs::STDIO && Path::new(&source).is_dir() {
+                let (stats, files) = compress_dir(&source, &fname, options, progress)?;
+                let bytes = stats.compressed_bytes;
+                Report {
+                    message: format!(
+                        "Compressed {files} files of '{source}'. Wrote {bytes} bytes to '{fname}'"
+                    ),
+                    stats,
+                }
             } else {
-                cdata.header.filename.clone()
+                let stats = compress_file(&source, &fname, options, progress)?;
+                let bytes = stats.compressed_bytes;
+                Report {
+                    message: format!("Compressed '{source}'. Wrote {bytes} bytes to '{fname}'"),
+                    stats,
+                }
             };
+            if let Some(options) = verify {
+                let names = verify_packed(&fname, &options)
+                    .map_err(|err| format!("'{fname}' is corrupt, '{source}' is kept: {err}"))?;
+                remove_sources(Path::new(&source), &names)?;
+                report.message += &format!(", removed '{source}'");
+            }
+            report
+        }
+        CtDirective::Unpack(source, options) => unpack_file(&source, &options, progress)?,
+        CtDirective::Extract(source, name, options) => {
+            extract_entry(&source, &name, &options, progress)?
+        }
+        CtDirective::List(source) => {
+            if source != fs::STDIO {
+                fs::check_filename(&source)?;
+            }
+            let mut reader = FooterReader::new(open_input(Path::new(&source))?, true);
+            let mut lines = vec![format!(
+                "{:<40} {:>12} {:>12} {:>7} {:>6} {:>8}  algorithm",
+                "name", "original", "compressed", "ratio", "table", "checksum"
+            )];
+            let mut stats = Stats::default();
+            loop {
+                let header = fs::read_header(&mut reader)?;
+                let n = reader.skip_by(header.data_bytes as u64, Input::skip)?;
+                check_skipped(&header, n)?;
+                let name = if header.filename.is_empty() && source != fs::STDIO {
+                    fs::switch_file_type(&source)
+                } else {
+                    header.filename.clone()
+                };
+                lines.push(list_entry(&name, &header)?);
+                stats.original_bytes += header.original_bytes;
+                stats.compressed_bytes += fs::entry_size(&header)?;
+                if reader.at_end()? {
+                    break;
+                }
+            }
+            Report {
+                message: lines.join("\n"),
+                stats,
+            }
+        }
+        CtDirective::Append(target, files, options) => {
+            let (stats, files) = append_files(&target, &files, options, progress)?;
+            let bytes = stats.compressed_bytes;
+            Report {
+                message: format!("Appended {files} files to '{target}'. Wrote {bytes} bytes"),
+                stats,
+            }
+        }
+        CtDirective::Recursive(dir, filter, options) => {
+            let summary = recursive::compress_tree(&dir, &filter, options, progress)?;
+            let message = summary.message(&dir);
+            if !summary.failed.is_empty() {
+                return Err(message.into());
+            }
+            Report {
+                message,
+                stats: summary.stats,
+            }
+        }
+        CtDirective::Test(source) => {
+            if source != fs::STDIO {
+                fs::check_filename(&source)?;
+            }
+            let source_path = Path::new(&source);
+            let mut progress = Progress::new(input_size(source_path), progress);
+            let (bytes, entries) = check_input(source_path)
+                .and_then(|_| test_entries(progress.reader(open_input(source_path)?)))
+                .map_err(|err| format!("'{source}' is corrupt: {err}"))?;
+            Report {
+                message: format!("'{source}' is OK. Verified {bytes} bytes of {entries} entries"),
+                stats: Stats {
+                    original_bytes: bytes,
+                    compressed_bytes: progress.done(),
+                    ..Default::default()
+                },
+            }
+        }
+        CtDirective::Bench(source) => {
+            let mut content = Vec::new();
+            open_input(Path::new(&source))?.read_to_end(&mut content)?;
+            let measurements = bench::run(&content)?;
+            Report {
+                message: format!("Benchmark of '{source}':\n{}", bench::table(&measurements)),
+                stats: Stats {
+                    original_bytes: content.len() as u64,
+                    ..Default::default()
+                },
+            }
+        }
+        CtDirective::Inspect(source) => {
+            inspect(&source)?;
+            Report {
+                message: format!("Inspected '{source}'"),
+                stats: Stats::default(),
+            }
+        }
+    };
+    report.stats.elapsed = start.elapsed();
+    Ok(report)
+}
 
-            let text = decompress(&cdata)?;
-            std::fs::write(&fname, &text)?;
-            let bytes = text.len();
+/// Decodes all entries from the reader and verifies them. Returns the number of decoded bytes and
+/// of entries.
+fn test_entries(reader: impl BufRead) -> Result<(u64, usize)> {
+    let mut reader = FooterReader::new(reader, true);
+    let mut bytes = 0;
+    let mut entries = 0;
+    loop {
+        let header = fs::read_header(&mut reader)?;
+        bytes += decompress_data(&header, &mut reader, std::io::sink(), 0)?;
+        entries += 1;
+        if reader.at_end()? {
+            return Ok((bytes, entries));
+        }
+    }
+}
 
-            format!("Decompressed '{source}'. Wrote {bytes} bytes to '{fname}'")
+/// Decodes all entries of a packed file and verifies them, before their sources get removed, see
+/// `PackOptions::remove_source`. Returns the names of the entries.
+fn verify_packed(fname: &str, options: &PackOptions) -> Result<Vec<String>> {
+    let options = UnpackOptions {
+        passphrase: options.passphrase.clone(),
+        dict: options.dict.clone(),
+        ..Default::default()
+    };
+    let mut reader = FooterReader::new(open_input(Path::new(fname))?, true);
+    let mut names = Vec::new();
+    loop {
+        let header = fs::read_header(&mut reader)?;
+        decompress_entry(&header, &mut reader, &mut std::io::sink(), &options)?;
+        names.push(header.filename);
+        if reader.at_end()? {
+            return Ok(names);
         }
-    })
+    }
+}
+
+/// Removes the sources of verified entries: a single file directly, a directory by the entry
+/// names, content in front of its directory. Anything else, e.g. a file created meanwhile, is kept
+/// and so is its directory.
+fn remove_sources(source: &Path, names: &[String]) -> Result<()> {
+    let Some(root) = names.first().filter(|_| source.is_dir()) else {
+        return Ok(std::fs::remove_file(source)?);
+    };
+    for name in names.iter().rev() {
+        let relative = match name.strip_prefix(root.as_str()) {
+            Some("") => "",
+            Some(rest) if rest.starts_with('/') => &rest[1..],
+            _ => continue,
+        };
+        let path = source.join(relative);
+        let removed = match EntryType::of(&path) {
+            EntryType::Dir => std::fs::remove_dir(&path),
+            EntryType::File | EntryType::Symlink => std::fs::remove_file(&path),
+        };
+        removed.map_err(|err| format!("'{}' could not be removed: {err}", path.display()))?;
+        log::debug!("removed '{}'", path.display());
+    }
+    Ok(())
+}
+
+/// Skips the data of an entry without decoding it.
+fn skip_data(header: &Header, reader: &mut impl Read) -> Result<()> {
+    let mut data = reader.take(header.data_bytes as u64);
+    check_skipped(header, std::io::copy(&mut data, &mut std::io::sink())?)
+}
+
+/// Checks, that all data of an entry was skipped or read, not less because the input is
+/// truncated.
+fn check_skipped(header: &Header, n: u64) -> Result<()> {
+    if n != header.data_bytes as u64 {
+        return Err(CtError::Truncated {
+            expected: header.data_bytes as u64,
+            got: n,
+        });
+    }
+    Ok(())
+}
+
+/// One line of the listing of a compressed file, see `CtDirective::List`.
+fn list_entry(name: &str, header: &Header) -> Result<String> {
+    let compressed = fs::entry_size(header)?;
+    let ratio = match header.original_bytes {
+        0 => String::from("-"),
+        original => format!("{:.1}%", compressed as f64 / original as f64 * 100.0),
+    };
+    let mut method = match Algorithm::from_id(header.algorithm) {
+        Ok(algo) => algo.name(),
+        Err(_) => format!("unknown ({})", header.algorithm),
+    };
+    match SymbolModel::from_id(header.symbols) {
+        Ok(SymbolModel::Bytes) => (),
+        Ok(symbols) => method = format!("{method} of {}", symbols.name()),
+        Err(_) => method = format!("{method} of unknown symbols ({})", header.symbols),
+    }
+    if let Ok(Pipeline::Bwt { block_size }) = Pipeline::from_header(header) {
+        method = format!("bwt ({block_size} bytes) + {method}");
+    }
+    if header.seekable {
+        match blocks::block_size(header) {
+            Ok(size) => method.push_str(&format!(", seekable ({size} bytes blocks)")),
+            Err(_) => method.push_str(", seekable"),
+        }
+    }
+    if header.encrypted {
+        method.push_str(", encrypted");
+    }
+    if header.dict_id != 0 {
+        method.push_str(&format!(", dictionary {:08x}", header.dict_id));
+    }
+    if header.no_checksum {
+        method.push_str(", no checksum");
+    }
+    let name = match EntryType::from_id(header.entry_type) {
+        Ok(EntryType::File) => name.to_string(),
+        Ok(EntryType::Dir) => format!("{name}/"),
+        Ok(EntryType::Symlink) => format!("{name} (symlink)"),
+        Err(_) => format!("{name} (unknown type {})", header.entry_type),
+    };
+    Ok(format!(
+        "{name:<40} {:>12} {compressed:>12} {ratio:>7} {:>6} {:08x}  {method}",
+        header.original_bytes,
+        header.prefix_table.len(),
+        header.checksum
+    ))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    pub(crate) fn testfile(name: &str) -> String {
-        std::fs::read_to_string(name).expect(&format!("could not open testfile '{name}'"))
+    pub(crate) fn testfile(name: &str) -> Vec<u8> {
+        std::fs::read(name).unwrap_or_else(|_| panic!("could not open testfile '{name}'"))
     }
 
     #[test]
@@ -98,14 +1441,855 @@ mod tests {
         assert_eq!(input, output);
     }
 
+    #[test]
+    fn encode_decode_binary() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        let mut input: Vec<u8> = (0..=255).collect();
+        input.extend((0..10_000).map(|_| rng.gen::<u8>()));
+        let cdata = compress(&input).expect("compress() failed");
+        let output = decompress(&cdata).expect("decompress() failed");
+        assert_eq!(input, output);
+    }
+
+    #[test]
+    fn streaming_compression() {
+        let mut cdata = compress(&testfile("135-0.txt")).expect("compress() failed");
+        // Only files carry their name and metadata, not contents in memory.
+        (cdata.header.modified, cdata.header.mode) = fs::file_meta(Path::new("135-0.txt"));
+        cdata.header.filename = "135-0.txt".into();
+        cdata.header.single_file = true;
+        cdata.write("135-0-memory.cpd").expect("write() failed");
+        let mut last = (0, 0);
+        let stats = compress_file(
+            "135-0.txt",
+            "135-0-stream.cpd",
+            PackOptions::default(),
+            &mut |done, total| last = (done, total),
+        )
+        .expect("compress_file() failed");
+        // Both passes over the source count.
+        let len = testfile("135-0.txt").len() as u64;
+        assert_eq!(last, (2 * len, 2 * len));
+
+        let expected = std::fs::read("135-0-memory.cpd").unwrap();
+        assert_eq!(std::fs::read("135-0-stream.cpd").unwrap(), expected);
+        assert_eq!(stats.compressed_bytes, expected.len() as u64);
+        assert_eq!(stats.original_bytes, len);
+
+        std::fs::remove_file("135-0-memory.cpd").expect("removing testfile failed");
+        std::fs::remove_file("135-0-stream.cpd").expect("removing testfile failed");
+    }
+
+    #[test]
+    fn lz77_algorithm() {
+        let input = testfile("135-0.txt");
+        let input = &input[..500_000];
+        let cdata =
+            compress_with(input, Algorithm::Lz77, Pipeline::None).expect("compress_with() failed");
+        let huffman = compress(input).expect("compress() failed");
+        assert!(cdata.data.len() < huffman.data.len() * 3 / 4);
+        assert_eq!(decompress(&cdata).expect("decompress() failed"), input);
+
+        let mut archive = Vec::new();
+        write_file(&mut archive, &cdata).unwrap();
+        let mut output = Vec::new();
+        decompress_to(&archive[..], &mut output).expect("decompress_to() failed");
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn bwt_pipeline() {
+        let input = std::fs::read("135-0.txt").unwrap();
+        let input = &input[..200_000];
+        let plain = compress_with(input, Algorithm::Huffman, Pipeline::None).unwrap();
+        let pipeline = Pipeline::Bwt {
+            block_size: 1 << 16,
+        };
+        let cdata = compress_with(input, Algorithm::Huffman, pipeline).unwrap();
+        assert!(cdata.data.len() < plain.data.len() * 3 / 4);
+        assert_eq!(decompress(&cdata).expect("decompress() failed"), input);
+
+        let mut archive = Vec::new();
+        write_file(&mut archive, &cdata).unwrap();
+        let mut output = Vec::new();
+        decompress_to(&archive[..], &mut output).expect("decompress_to() failed");
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn seekable_blocks() {
+        let options = PackOptions {
+            algo: Algorithm::Lz77,
+            seekable: true,
+            ..Default::default()
+        };
+        let directive = CtDirective::Pack(
+            String::from("135-0.txt"),
+            Some(String::from("135-0-seekable.cpd")),
+            options,
+        );
+        compression_tool(directive).expect("packing failed");
+
+        let input = testfile("135-0.txt");
+        let cdata = CompressedData::read("135-0-seekable.cpd").expect("read() failed");
+        assert!(cdata.header.seekable);
+        let start = 3 * blocks::DEFAULT_BLOCK_SIZE as usize - 1000;
+        let range = cdata
+            .read_range(start as u64, 5000)
+            .expect("read_range() failed");
+        assert_eq!(range, &input[start..start + 5000]);
+        assert_eq!(decompress(&cdata).expect("decompress() failed"), input);
+
+        let mut output = Vec::new();
+        let reader = BufReader::new(File::open("135-0-seekable.cpd").unwrap());
+        decompress_to(reader, &mut output).expect("decompress_to() failed");
+        assert_eq!(output, input);
+        std::fs::remove_file("135-0-seekable.cpd").unwrap();
+    }
+
+    #[test]
+    fn degenerate_inputs() {
+        for content in [&b""[..], b"a", b"aaaaaaaaaaaaaaaaaaaa"] {
+            for algo in <Algorithm as clap::ValueEnum>::value_variants() {
+                let cdata = compress_with(content, *algo, Pipeline::None).unwrap();
+                assert_eq!(decompress(&cdata).unwrap(), content, "{algo:?}");
+                let mut file = Vec::new();
+                write_file(&mut file, &cdata).unwrap();
+                let mut output = Vec::new();
+                decompress_to(&file[..], &mut output).unwrap();
+                assert_eq!(output, content, "{algo:?}");
+            }
+            let bwt = Pipeline::Bwt { block_size: 1024 };
+            let cdata = compress_with(content, Algorithm::Huffman, bwt).unwrap();
+            assert_eq!(decompress(&cdata).unwrap(), content);
+            let cdata = Compressor::new().seekable(true).compress(content).unwrap();
+            assert_eq!(decompress(&cdata).unwrap(), content);
+        }
+        let cdata = compress(b"").unwrap();
+        assert_eq!(cdata.header.prefix_table, vec![0]);
+        assert!(cdata.data.is_empty());
+        let cdata = compress(b"aaaaaaaaaaaaaaaaaaaa").unwrap();
+        assert_eq!((cdata.data, cdata.header.unused_bits), (vec![0, 0, 0], 4));
+
+        // Streamed in two passes.
+        std::fs::write("empty.txt", "").unwrap();
+        compression_tool(CtDirective::Pack(
+            "empty.txt".into(),
+            None,
+            Default::default(),
+        ))
+        .expect("packing failed");
+        std::fs::remove_file("empty.txt").unwrap();
+        compression_tool(CtDirective::Unpack("empty.cpd".into(), Default::default()))
+            .expect("unpacking failed");
+        assert!(testfile("empty.txt").is_empty());
+        std::fs::remove_file("empty.txt").unwrap();
+        std::fs::remove_file("empty.cpd").unwrap();
+    }
+
+    #[test]
+    fn lazy_decoding() {
+        let input = testfile("135-0.txt");
+        let cdata = compress(&input).expect("compress() failed");
+        let needle = b"Fantine";
+        let mut window = Vec::new();
+        let mut found = None;
+        for (i, byte) in cdata.decode_iter().unwrap().enumerate() {
+            window.push(byte.unwrap());
+            if window.ends_with(needle) {
+                found = Some(i + 1 - needle.len());
+                break;
+            }
+        }
+        let position = input.windows(needle.len()).position(|w| w == needle);
+        assert!(found.is_some());
+        assert_eq!(found, position);
+
+        let decoded: Result<Vec<u8>> = cdata.decode_iter().unwrap().collect();
+        assert_eq!(decoded.unwrap(), input);
+        let cdata = compress_with(&input[..1000], Algorithm::Lz77, Pipeline::None).unwrap();
+        assert!(cdata.decode_iter().is_err());
+    }
+
+    #[test]
+    fn rle_algorithm() {
+        let input: Vec<u8> = (0..100u8).flat_map(|b| [b; 50]).collect();
+        let cdata =
+            compress_with(&input, Algorithm::Rle, Pipeline::None).expect("compress_with() failed");
+        assert_eq!(cdata.data.len(), 200);
+
+        let mut archive = Vec::new();
+        write_file(&mut archive, &cdata).unwrap();
+        let mut output = Vec::new();
+        decompress_to(&archive[..], &mut output).expect("decompress_to() failed");
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn streaming_decompression() {
+        let input = testfile("135-0.txt");
+        let cdata = compress(&input).expect("compress() failed");
+        let mut archive = Vec::new();
+        write_file(&mut archive, &cdata).unwrap();
+
+        let mut output = Vec::new();
+        let bytes = decompress_to(&archive[..], &mut output).expect("decompress_to() failed");
+        assert_eq!(bytes, input.len() as u64);
+        assert_eq!(output, input);
+
+        let truncated = &archive[..archive.len() - 10];
+        assert!(decompress_to(truncated, std::io::sink()).is_err());
+    }
+
+    #[test]
+    fn concatenated_streams() {
+        let input = testfile("135-0.txt");
+        let (first, second) = input[..200_000].split_at(120_000);
+        let mut stream = Vec::new();
+        Compressor::new()
+            .compress_reader(first, &mut stream)
+            .unwrap();
+        Compressor::new()
+            .algorithm(Algorithm::Lz77)
+            .compress_reader(second, &mut stream)
+            .unwrap();
+        let mut output = Vec::new();
+        let bytes = decompress_to(&stream[..], &mut output).expect("decompress_to() failed");
+        assert_eq!(bytes, 200_000);
+        assert_eq!(output, &input[..200_000]);
+
+        std::fs::write("concat.cpd", &stream).unwrap();
+        let message =
+            compression_tool(CtDirective::Unpack("concat.cpd".into(), Default::default()));
+        let output = std::fs::read("concat");
+        std::fs::remove_file("concat.cpd").unwrap();
+        let _ = std::fs::remove_file("concat");
+        assert!(message.unwrap().ends_with("Wrote 200000 bytes to 'concat'"));
+        assert_eq!(output.unwrap(), &input[..200_000]);
+    }
+
+    #[test]
+    fn test_directive() {
+        let input = testfile("135-0.txt");
+        let mut cdata = compress(&input[..100_000]).expect("compress() failed");
+        cdata.write("test_directive.cpd").unwrap();
+        let directive = CtDirective::Test(String::from("test_directive.cpd"));
+        let result = compression_tool(directive).expect("testing failed");
+        assert_eq!(
+            result,
+            "'test_directive.cpd' is OK. Verified 100000 bytes of 1 entries"
+        );
+
+        cdata.header.original_bytes += 1;
+        cdata.write("test_directive.cpd").unwrap();
+        let directive = CtDirective::Test(String::from("test_directive.cpd"));
+        let err = compression_tool(directive).unwrap_err().to_string();
+        assert!(err.starts_with("'test_directive.cpd' is corrupt: decoded 100000 bytes"));
+        std::fs::remove_file("test_directive.cpd").unwrap();
+    }
+
+    #[test]
+    fn detect_corruption() {
+        let input = testfile("135-0.txt");
+        let mut cdata = compress(&input).expect("compress() failed");
+        assert_eq!(cdata.header.checksum, crc32fast::hash(&input));
+
+        // Swapping two bytes keeps all codes valid, but changes the content.
+        cdata.data.swap(1000, 2000);
+        let err = decompress(&cdata).expect_err("corruption not detected");
+        assert!(err.to_string().starts_with("checksum mismatch"));
+        assert!(matches!(err, CtError::ChecksumMismatch { .. }));
+    }
+
+    #[test]
+    fn pack_unpack_directory() {
+        std::fs::create_dir_all("archive_test/sub").unwrap();
+        std::fs::write("archive_test/a.txt", "first file").unwrap();
+        std::fs::write("archive_test/sub/b.bin", [0, 1, 2, 255]).unwrap();
+        let options = PackOptions {
+            algo: Algorithm::Lz77,
+            ..Default::default()
+        };
+        let directive = CtDirective::Pack(String::from("archive_test/"), None, options);
+        let result = compression_tool(directive).expect("packing failed");
+        assert!(result.starts_with("Compressed 2 files of 'archive_test/'"));
+        std::fs::remove_dir_all("archive_test").unwrap();
+
+        let directive = CtDirective::Unpack(String::from("archive_test.cpd"), Default::default());
+        compression_tool(directive).expect("unpacking failed");
+        assert_eq!(testfile("archive_test/a.txt"), b"first file");
+        assert_eq!(testfile("archive_test/sub/b.bin"), [0, 1, 2, 255]);
+        std::fs::remove_dir_all("archive_test").unwrap();
+
+        let name = String::from("archive_test/sub/b.bin");
+        let directive =
+            CtDirective::Extract(String::from("archive_test.cpd"), name, Default::default());
+        compression_tool(directive).expect("extracting failed");
+        assert!(!Path::new("archive_test/a.txt").exists());
+        assert_eq!(testfile("archive_test/sub/b.bin"), [0, 1, 2, 255]);
+        std::fs::remove_dir_all("archive_test").unwrap();
+
+        let name = String::from("missing.txt");
+        let directive =
+            CtDirective::Extract(String::from("archive_test.cpd"), name, Default::default());
+        assert!(compression_tool(directive).is_err());
+
+        let directive = CtDirective::List(String::from("archive_test.cpd"));
+        let listing = compression_tool(directive).expect("listing failed");
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].starts_with("archive_test/ "));
+        assert!(lines[2].starts_with("archive_test/a.txt "));
+        assert!(lines[2].contains(" 10 "));
+        assert!(lines[2].ends_with(&format!("{:08x}  lz77", crc32fast::hash(b"first file"))));
+        assert!(lines[3].starts_with("archive_test/sub/ "));
+        assert!(lines[4].starts_with("archive_test/sub/b.bin "));
+
+        // Listing seeks over the data, but still notices a truncated archive.
+        let archive = std::fs::read("archive_test.cpd").unwrap();
+        let truncated = &archive[..archive.len() - FOOTER_SIZE - 1];
+        std::fs::write("archive_test.cpd", truncated).unwrap();
+        let err = compression_tool(CtDirective::List(String::from("archive_test.cpd")));
+        assert!(err.unwrap_err().to_string().contains("1 of"));
+        std::fs::remove_file("archive_test.cpd").unwrap();
+    }
+
+    #[test]
+    fn bundle_tree() {
+        std::fs::create_dir_all("bundle_test/empty").unwrap();
+        std::fs::create_dir_all("bundle_test/sub").unwrap();
+        std::fs::write("bundle_test/sub/a.txt", "content").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("sub/a.txt", "bundle_test/link").unwrap();
+        let directive = CtDirective::Pack("bundle_test".into(), None, Default::default());
+        let packed = compression_tool(directive);
+        std::fs::remove_dir_all("bundle_test").unwrap();
+        assert!(packed
+            .expect("packing failed")
+            .starts_with("Compressed 1 files"));
+
+        let directive = CtDirective::Unpack("bundle_test.cpd".into(), Default::default());
+        let unpacked = compression_tool(directive);
+        let restored = (
+            Path::new("bundle_test/empty").is_dir(),
+            std::fs::read("bundle_test/sub/a.txt").ok(),
+            std::fs::read_link("bundle_test/link").ok(),
+        );
+        std::fs::remove_dir_all("bundle_test").unwrap();
+        unpacked.expect("unpacking failed");
+        assert!(restored.0);
+        assert_eq!(restored.1.unwrap(), b"content");
+        if cfg!(unix) {
+            assert_eq!(restored.2.unwrap(), Path::new("sub/a.txt"));
+        }
+
+        let options = UnpackOptions {
+            strip_components: 1,
+            ..Default::default()
+        };
+        let directive = CtDirective::Unpack("bundle_test.cpd".into(), options.clone());
+        let unpacked = compression_tool(directive);
+        let restored = std::fs::read("sub/a.txt");
+        let _ = std::fs::remove_dir_all("sub");
+        let _ = std::fs::remove_dir_all("empty");
+        let _ = std::fs::remove_file("link");
+        unpacked.expect("unpacking failed");
+        assert_eq!(restored.unwrap(), b"content");
+
+        let name = String::from("bundle_test/sub/a.txt");
+        let directive = CtDirective::Extract("bundle_test.cpd".into(), name, options);
+        let extracted = compression_tool(directive);
+        std::fs::remove_file("bundle_test.cpd").unwrap();
+        extracted.expect("extracting failed");
+        assert_eq!(std::fs::read("sub/a.txt").unwrap(), b"content");
+        std::fs::remove_dir_all("sub").unwrap();
+    }
+
+    #[test]
+    fn symbol_models() {
+        std::fs::write("symbols_test.txt", &testfile("135-0.txt")[..50_000]).unwrap();
+        let options = PackOptions {
+            symbols: SymbolModel::Digrams,
+            ..Default::default()
+        };
+        let directive = CtDirective::Pack("symbols_test.txt".into(), None, options);
+        compression_tool(directive).expect("packing failed");
+        let listing = compression_tool(CtDirective::List("symbols_test.cpd".into())).unwrap();
+        assert!(listing.contains("huffman of digrams"));
+        let options = UnpackOptions {
+            force: true,
+            ..Default::default()
+        };
+        compression_tool(CtDirective::Unpack("symbols_test.cpd".into(), options))
+            .expect("unpacking failed");
+        assert_eq!(
+            std::fs::read("symbols_test.txt").unwrap(),
+            &testfile("135-0.txt")[..50_000]
+        );
+        let mut packed = std::fs::File::open("symbols_test.cpd").unwrap();
+        assert_eq!(decompress_to(&mut packed, Vec::new()).unwrap(), 50_000);
+        std::fs::remove_file("symbols_test.txt").unwrap();
+        std::fs::remove_file("symbols_test.cpd").unwrap();
+    }
+
+    #[test]
+    fn remove_sources() {
+        std::fs::write("rm_test.txt", "removed after packing").unwrap();
+        let options = PackOptions {
+            remove_source: true,
+            ..Default::default()
+        };
+        let directive = CtDirective::Pack("rm_test.txt".into(), None, options.clone());
+        let packed = compression_tool(directive).expect("packing failed");
+        assert!(packed.ends_with(", removed 'rm_test.txt'"));
+        assert!(!Path::new("rm_test.txt").exists());
+
+        let unpack = UnpackOptions {
+            remove_source: true,
+            ..Default::default()
+        };
+        let directive = CtDirective::Unpack("rm_test.cpd".into(), unpack.clone());
+        compression_tool(directive).expect("unpacking failed");
+        assert!(!Path::new("rm_test.cpd").exists());
+        assert_eq!(
+            std::fs::read("rm_test.txt").unwrap(),
+            b"removed after packing"
+        );
+        std::fs::remove_file("rm_test.txt").unwrap();
+
+        std::fs::create_dir_all("rm_test/sub").unwrap();
+        std::fs::write("rm_test/sub/a.txt", "content").unwrap();
+        let directive = CtDirective::Pack("rm_test".into(), None, options);
+        compression_tool(directive).expect("packing failed");
+        assert!(!Path::new("rm_test").exists());
+        let directive = CtDirective::Unpack("rm_test.cpd".into(), unpack);
+        compression_tool(directive).expect("unpacking failed");
+        assert_eq!(std::fs::read("rm_test/sub/a.txt").unwrap(), b"content");
+        std::fs::remove_dir_all("rm_test").unwrap();
+
+        // Without a complete packed file, the source is kept.
+        std::fs::write("rm_kept.txt", "kept").unwrap();
+        std::fs::write("rm_kept.cpd", "").unwrap();
+        let options = PackOptions {
+            remove_source: true,
+            ..Default::default()
+        };
+        let directive = CtDirective::Pack("rm_kept.txt".into(), None, options);
+        assert!(compression_tool(directive).is_err());
+        assert!(Path::new("rm_kept.txt").exists());
+        std::fs::remove_file("rm_kept.txt").unwrap();
+        std::fs::remove_file("rm_kept.cpd").unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_escape() {
+        let outside = std::env::temp_dir().join("cccompress_slip_test");
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("x"), "outside").unwrap();
+        let pack = |link: &Path, file: &str| {
+            std::fs::create_dir_all("slip_test").unwrap();
+            std::os::unix::fs::symlink(link, "slip_test/link").unwrap();
+            let directive = CtDirective::Pack("slip_test".into(), None, PackOptions::default());
+            compression_tool(directive).expect("packing failed");
+            let files = vec![String::from(file)];
+            let directive = CtDirective::Append("slip_test.cpd".into(), files, Default::default());
+            compression_tool(directive).expect("appending failed");
+            std::fs::remove_dir_all("slip_test").unwrap();
+        };
+        let unpack = || {
+            let directive = CtDirective::Unpack("slip_test.cpd".into(), Default::default());
+            let err = compression_tool(directive).unwrap_err().to_string();
+            std::fs::remove_dir_all("slip_test").unwrap();
+            std::fs::remove_file("slip_test.cpd").unwrap();
+            err
+        };
+
+        // A symlink out of the tree, through which the following entry would be written.
+        pack(&outside, "slip_test/link/x");
+        std::fs::remove_file(outside.join("x")).unwrap();
+        assert!(unpack().contains("points outside"));
+        assert!(!outside.join("x").exists());
+        std::fs::remove_dir_all(&outside).unwrap();
+
+        // Entries are not written through symlinks inside the tree either.
+        std::fs::create_dir_all("slip_test/inside").unwrap();
+        std::fs::write("slip_test/inside/y", "inside").unwrap();
+        pack(Path::new("inside"), "slip_test/link/y");
+        assert!(unpack().contains("through the symlink"));
+    }
+
+    #[test]
+    fn append_entries() {
+        std::fs::create_dir_all("append_test").unwrap();
+        std::fs::write("append_test/a.txt", "first file").unwrap();
+        let directive = CtDirective::Pack("append_test/".into(), None, PackOptions::default());
+        compression_tool(directive).expect("packing failed");
+        std::fs::write("append_test/b.txt", "second file").unwrap();
+
+        let append = |files: &[&str]| {
+            let files = files.iter().map(|f| f.to_string()).collect();
+            let directive =
+                CtDirective::Append("append_test.cpd".into(), files, PackOptions::default());
+            compression_tool(directive)
+        };
+        let size = std::fs::metadata("append_test.cpd").unwrap().len();
+        assert!(append(&["append_test/a.txt"]).is_err());
+        assert!(append(&["append_test/b.txt", "append_test/missing.txt"]).is_err());
+        assert_eq!(std::fs::metadata("append_test.cpd").unwrap().len(), size);
+        let result = append(&["./append_test/b.txt"]).expect("appending failed");
+        assert!(result.starts_with("Appended 1 files to 'append_test.cpd'"));
+        std::fs::remove_dir_all("append_test").unwrap();
+
+        let directive = CtDirective::Unpack("append_test.cpd".into(), Default::default());
+        compression_tool(directive).expect("unpacking failed");
+        assert_eq!(testfile("append_test/a.txt"), b"first file");
+        assert_eq!(testfile("append_test/b.txt"), b"second file");
+        std::fs::remove_dir_all("append_test").unwrap();
+        std::fs::remove_file("append_test.cpd").unwrap();
+    }
+
+    #[test]
+    fn salvage_truncated() {
+        let input = testfile("135-0.txt")[..100_000].to_vec();
+        std::fs::write("salvage.txt", &input).unwrap();
+        let directive = CtDirective::Pack("salvage.txt".into(), None, PackOptions::default());
+        compression_tool(directive).expect("packing failed");
+        std::fs::remove_file("salvage.txt").unwrap();
+        let packed = std::fs::read("salvage.cpd").unwrap();
+        std::fs::write("salvage.cpd", &packed[..packed.len() / 2]).unwrap();
+
+        let unpack = |salvage| {
+            let options = UnpackOptions {
+                salvage,
+                ..Default::default()
+            };
+            compression_tool(CtDirective::Unpack("salvage.cpd".into(), options))
+        };
+        let err = unpack(false).unwrap_err();
+        assert!(matches!(err.cause(), CtError::Truncated { .. }), "{err}");
+        assert!(!Path::new("salvage.txt").exists());
+        let message = unpack(true).expect("salvaging failed");
+        let offset = format!("decoding stopped at byte {}", packed.len() / 2);
+        assert!(message.contains(&offset), "{message}");
+        let salvaged = testfile("salvage.txt");
+        assert!(salvaged.len() > input.len() / 3);
+        assert_eq!(salvaged, input[..salvaged.len()]);
+
+        std::fs::remove_file("salvage.txt").unwrap();
+        std::fs::remove_file("salvage.cpd").unwrap();
+    }
+
+    #[test]
+    fn integrity_footer() {
+        let input = testfile("135-0.txt")[..10_000].to_vec();
+        std::fs::write("footer.txt", &input).unwrap();
+        let directive = CtDirective::Pack("footer.txt".into(), None, PackOptions::default());
+        compression_tool(directive).expect("packing failed");
+        std::fs::remove_file("footer.txt").unwrap();
+        let packed = std::fs::read("footer.cpd").unwrap();
+        let (entry, footer) = packed.split_at(packed.len() - FOOTER_SIZE);
+        let footer = footer::Footer::read(footer).unwrap();
+        assert_eq!(footer.length, entry.len() as u64);
+        assert_eq!(footer.checksum, crc32fast::hash(entry));
+        let test = || compression_tool(CtDirective::Test("footer.cpd".into()));
+        let unpack = |salvage| {
+            let options = UnpackOptions {
+                salvage,
+                ..Default::default()
+            };
+            compression_tool(CtDirective::Unpack("footer.cpd".into(), options))
+        };
+
+        // Concatenated files have a footer each.
+        std::fs::write("footer.cpd", [&packed[..], &packed[..]].concat()).unwrap();
+        assert!(test().unwrap().contains("of 2 entries"));
+
+        // The permission bits are not covered by the checksum of the content, but by the footer.
+        let header = fs::read_header(&mut &packed[..]).unwrap();
+        let mode = fs::HEADER_PREFIX + Vec::<u8>::try_from(&header).unwrap().len() - 13;
+        let mut corrupted = packed.clone();
+        corrupted[mode] ^= 0x80;
+        std::fs::write("footer.cpd", &corrupted).unwrap();
+        let err = test().unwrap_err().to_string();
+        assert!(err.contains("checksum mismatch of the file"), "{err}");
+
+        // A missing footer fails before anything is written, unless salvaging.
+        std::fs::write("footer.cpd", entry).unwrap();
+        assert!(test()
+            .unwrap_err()
+            .to_string()
+            .contains("footer is missing"));
+        let err = unpack(false).unwrap_err().to_string();
+        assert!(err.contains("footer is missing"), "{err}");
+        assert!(!Path::new("footer.txt").exists());
+        assert!(unpack(true).unwrap().starts_with("Decompressed"));
+        assert_eq!(testfile("footer.txt"), input);
+
+        std::fs::remove_file("footer.txt").unwrap();
+        std::fs::remove_file("footer.cpd").unwrap();
+    }
+
+    #[test]
+    fn pack_unpack_binary_file() {
+        let input: Vec<u8> = (0..=255).cycle().take(5000).collect();
+        std::fs::write("binary.bin", &input).unwrap();
+        let modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        let file = File::options().write(true).open("binary.bin").unwrap();
+        file.set_modified(modified).unwrap();
+        drop(file);
+        let directive = CtDirective::Pack(String::from("binary.bin"), None, PackOptions::default());
+        compression_tool(directive).expect("packing failed");
+        std::fs::remove_file("binary.bin").unwrap();
+
+        let directive = CtDirective::Unpack(String::from("binary.bin.cpd"), Default::default());
+        compression_tool(directive).expect("unpacking failed");
+        assert_eq!(std::fs::read("binary.bin").unwrap(), input);
+        let metadata = std::fs::metadata("binary.bin").unwrap();
+        assert_eq!(metadata.modified().unwrap(), modified);
+        std::fs::remove_file("binary.bin").unwrap();
+
+        let options = UnpackOptions {
+            restore_meta: false,
+            ..Default::default()
+        };
+        let directive = CtDirective::Unpack(String::from("binary.bin.cpd"), options);
+        compression_tool(directive).expect("unpacking failed");
+        let metadata = std::fs::metadata("binary.bin").unwrap();
+        assert_ne!(metadata.modified().unwrap(), modified);
+
+        std::fs::remove_file("binary.bin").unwrap();
+        std::fs::remove_file("binary.bin.cpd").unwrap();
+    }
+
+    #[test]
+    fn restore_file_names() {
+        std::fs::create_dir_all("names_test").unwrap();
+        let files = [
+            ("names_test/Makefile", "names_test/Makefile.cpd"),
+            ("names_test/data.json", "names_test/data.json.cpd"),
+            ("names_test/notes.txt", "names_test/notes.cpd"),
+        ];
+        for (source, packed) in files {
+            std::fs::write(source, source).unwrap();
+            let directive = CtDirective::Pack(source.into(), None, PackOptions::default());
+            compression_tool(directive).expect("packing failed");
+            std::fs::remove_file(source).unwrap();
+            assert!(Path::new(packed).exists());
+
+            let directive = CtDirective::Unpack(packed.into(), Default::default());
+            let message = compression_tool(directive).expect("unpacking failed");
+            assert!(message.ends_with(&format!("to '{source}'")), "{message}");
+            assert_eq!(std::fs::read_to_string(source).unwrap(), source);
+            std::fs::remove_file(source).unwrap();
+        }
+
+        // Renamed compressed files still get unpacked into their original name.
+        std::fs::rename("names_test/Makefile.cpd", "names_test/renamed.cpd").unwrap();
+        let directive = CtDirective::Unpack("names_test/renamed.cpd".into(), Default::default());
+        compression_tool(directive).expect("unpacking failed");
+        assert_eq!(
+            std::fs::read_to_string("names_test/Makefile").unwrap(),
+            "names_test/Makefile"
+        );
+        std::fs::remove_dir_all("names_test").unwrap();
+    }
+
+    #[test]
+    fn encryption() {
+        std::fs::write("secret.txt", "confidential content").unwrap();
+        let options = PackOptions {
+            algo: Algorithm::Lz77,
+            passphrase: Some(String::from("secret")),
+            ..Default::default()
+        };
+        let result = compression_tool(CtDirective::Pack("secret.txt".into(), None, options));
+        if cfg!(not(feature = "encrypt")) {
+            assert!(result.is_err());
+            std::fs::remove_file("secret.txt").unwrap();
+            assert!(!Path::new("secret.cpd").exists());
+            return;
+        }
+        result.expect("packing failed");
+        std::fs::remove_file("secret.txt").unwrap();
+        let packed = std::fs::read("secret.cpd").unwrap();
+        assert!(!packed.windows(7).any(|w| w == b"content"));
+
+        let unpack = |passphrase: Option<&str>| {
+            let options = UnpackOptions {
+                passphrase: passphrase.map(String::from),
+                ..Default::default()
+            };
+            compression_tool(CtDirective::Unpack("secret.cpd".into(), options))
+        };
+        assert!(unpack(None).is_err());
+        assert!(unpack(Some("wrong")).is_err());
+        assert!(!Path::new("secret.txt").exists());
+        unpack(Some("secret")).expect("unpacking failed");
+        assert_eq!(testfile("secret.txt"), b"confidential content");
+
+        let listing = compression_tool(CtDirective::List("secret.cpd".into())).unwrap();
+        assert!(listing.ends_with("lz77, encrypted"));
+        std::fs::remove_file("secret.txt").unwrap();
+        std::fs::remove_file("secret.cpd").unwrap();
+    }
+
+    #[test]
+    fn dictionary() {
+        let dict = br#"{"level":"info","message":"request handled","status":200}"#.repeat(4);
+        std::fs::write("dict_test.json", br#"{"level":"info","message":"ok"}"#).unwrap();
+        let options = PackOptions {
+            dict: Some(dict.clone()),
+            ..Default::default()
+        };
+        compression_tool(CtDirective::Pack("dict_test.json".into(), None, options))
+            .expect("packing failed");
+        // Without the table, the header is small besides the stored name.
+        let packed = std::fs::metadata("dict_test.json.cpd").unwrap().len();
+        assert!(packed < 100 + "dict_test.json".len() as u64);
+        std::fs::remove_file("dict_test.json").unwrap();
+
+        let unpack = |dict: Option<&[u8]>| {
+            let options = UnpackOptions {
+                dict: dict.map(|d| d.to_vec()),
+                ..Default::default()
+            };
+            compression_tool(CtDirective::Unpack("dict_test.json.cpd".into(), options))
+        };
+        assert!(unpack(None).is_err());
+        assert!(unpack(Some(b"other dictionary")).is_err());
+        unpack(Some(&dict)).expect("unpacking failed");
+        assert_eq!(
+            testfile("dict_test.json"),
+            br#"{"level":"info","message":"ok"}"#
+        );
+        std::fs::remove_file("dict_test.json").unwrap();
+        std::fs::remove_file("dict_test.json.cpd").unwrap();
+
+        let cdata = compress_with_dict(b"info info", Algorithm::Lz77, &dict).unwrap();
+        assert!(decompress(&cdata).is_err());
+        assert_eq!(decompress_with_dict(&cdata, &dict).unwrap(), b"info info");
+    }
+
+    #[test]
+    fn overwrite_protection() {
+        std::fs::write("force.txt", "original").unwrap();
+        let pack = |force| {
+            let options = PackOptions {
+                force,
+                suffix: Some(String::from("hz")),
+                ..Default::default()
+            };
+            compression_tool(CtDirective::Pack("force.txt".into(), None, options))
+        };
+        pack(false).expect("packing failed");
+        assert!(Path::new("force.hz").exists());
+        assert!(pack(false).is_err());
+        pack(true).expect("packing failed");
+
+        let unpack = |force| {
+            let options = UnpackOptions {
+                force,
+                suffix: Some(String::from("hz")),
+                ..Default::default()
+            };
+            compression_tool(CtDirective::Unpack("force.hz".into(), options))
+        };
+        std::fs::write("force.txt", "modified").unwrap();
+        let err = unpack(false).unwrap_err();
+        assert!(err.to_string().contains("already exists"));
+        assert_eq!(testfile("force.txt"), b"modified");
+        unpack(true).expect("unpacking failed");
+        assert_eq!(testfile("force.txt"), b"original");
+
+        std::fs::remove_file("force.txt").unwrap();
+        std::fs::remove_file("force.hz").unwrap();
+    }
+
+    #[test]
+    fn tree_dump() {
+        std::fs::write("tree.txt", "abracadabra").unwrap();
+        let options = PackOptions {
+            dump_tree: Some(String::from("tree.dot")),
+            ..Default::default()
+        };
+        compression_tool(CtDirective::Pack("tree.txt".into(), None, options))
+            .expect("packing failed");
+        let dot = String::from_utf8(testfile("tree.dot")).unwrap();
+        assert!(dot.starts_with("digraph huffman {"));
+        assert!(dot.contains("label=\"a\\n5\""));
+        assert!(dot.contains("label=\"11\""));
+
+        let options = PackOptions {
+            dump_table: Some(String::from("tree.csv")),
+            force: true,
+            ..Default::default()
+        };
+        compression_tool(CtDirective::Pack("tree.txt".into(), None, options))
+            .expect("packing failed");
+        let csv = String::from_utf8(testfile("tree.csv")).unwrap();
+        assert!(csv.starts_with("symbol,frequency,code,length\n97,5,0,1\n"));
+        std::fs::remove_file("tree.csv").unwrap();
+
+        std::fs::remove_file("tree.txt").unwrap();
+        std::fs::remove_file("tree.cpd").unwrap();
+        std::fs::remove_file("tree.dot").unwrap();
+    }
+
+    #[test]
+    fn shared_spectrum() {
+        std::fs::create_dir_all("spectrum_test").unwrap();
+        std::fs::write("spectrum_test/a.txt", "abracadabra").unwrap();
+        std::fs::write("spectrum_test/b.txt", "alakazam").unwrap();
+        let options = PackOptions {
+            dump_spectrum: Some(String::from("spectrum_test.json")),
+            ..Default::default()
+        };
+        let packed = compression_tool(CtDirective::Pack("spectrum_test".into(), None, options));
+        let json = std::fs::read_to_string("spectrum_test.json");
+        std::fs::remove_dir_all("spectrum_test").unwrap();
+        std::fs::remove_file("spectrum_test.cpd").unwrap();
+        std::fs::remove_file("spectrum_test.json").unwrap();
+        packed.expect("packing failed");
+        let spectrum = spectrum::from_json(&json.unwrap()).unwrap();
+        assert_eq!((spectrum.total(), spectrum.frequency(b'a')), (19, 9));
+
+        // Symbols missing in the spectrum, like 'x', can be encoded as well.
+        let content = b"a banana, a bazaar and a box";
+        let compressor = Compressor::new().spectrum(spectrum.clone());
+        let cdata = compressor.compress(content).unwrap();
+        let table = PrefixCodeTable::try_from(&cdata.header.prefix_table[..]).unwrap();
+        assert!(table.code(b'a').unwrap().1 < table.code(b'x').unwrap().1);
+        assert_eq!(decompress(&cdata).unwrap(), content);
+        let compressor = compressor.algorithm(Algorithm::Lz77);
+        assert!(compressor.compress(content).is_err());
+
+        std::fs::write("spectrum.txt", content).unwrap();
+        let options = PackOptions {
+            spectrum: Some(spectrum),
+            ..Default::default()
+        };
+        let packed = compression_tool(CtDirective::Pack("spectrum.txt".into(), None, options));
+        std::fs::remove_file("spectrum.txt").unwrap();
+        packed.expect("packing failed");
+        let unpacked = compression_tool(CtDirective::Unpack(
+            "spectrum.cpd".into(),
+            Default::default(),
+        ));
+        std::fs::remove_file("spectrum.cpd").unwrap();
+        unpacked.expect("unpacking failed");
+        assert_eq!(std::fs::read("spectrum.txt").unwrap(), content);
+        std::fs::remove_file("spectrum.txt").unwrap();
+    }
+
     #[test]
     fn write_read_file() {
         let fname = "135-0.txt";
         let input = testfile(fname);
         let cdata = compress(&input).expect("compress() failed");
 
-        let fname = fs::switch_file_type(&fname);
-        println!("{fname:}");
+        let fname = fs::switch_file_type(fname);
         cdata.write(&fname).expect("CompressedData::write() failed");
 
         let cdata = CompressedData::read(&fname).expect("CompressedData::read() failed");
@@ -0,0 +1,67 @@
+//! Module contains the logger of the command line tool. The library emits its diagnostics by the
+//! `log` facade, so that programs using it decide, whether and where they appear. The tool writes
+//! them to stderr, filtered by `-v`, `-q` or `--log-level`. Only records of this tool are shown,
+//! not those of its dependencies.
+
+use crate::{CtError, Result};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// Logger, which writes every record to stderr.
+struct StderrLogger;
+
+static LOGGER: StderrLogger = StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level() && metadata.target().starts_with("cccompress")
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("{}", line(record.level(), &record.args().to_string()));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs the logger of the tool with the given level, once per process.
+pub fn init(level: LevelFilter) -> Result<()> {
+    log::set_logger(&LOGGER).map_err(CtError::other)?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+/// Level of the verbosity flags: warnings by default, each `-v` one level more up to trace, and
+/// only errors with `-q`.
+pub fn level(verbose: u8, quiet: bool) -> LevelFilter {
+    match (quiet, verbose) {
+        (true, _) => LevelFilter::Error,
+        (false, 0) => LevelFilter::Warn,
+        (false, 1) => LevelFilter::Info,
+        (false, 2) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    }
+}
+
+/// One line of the log on stderr.
+fn line(level: Level, message: &str) -> String {
+    format!("cccompress: {}: {message}", level.as_str().to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verbosity() {
+        assert_eq!(level(0, false), LevelFilter::Warn);
+        assert_eq!(level(2, false), LevelFilter::Debug);
+        assert_eq!(level(7, false), LevelFilter::Trace);
+        assert_eq!(level(0, true), LevelFilter::Error);
+        assert_eq!(
+            line(Level::Warn, "'a.txt' is skipped"),
+            "cccompress: warn: 'a.txt' is skipped"
+        );
+    }
+}
@@ -0,0 +1,268 @@
+//! Module contains the LZ77 stage of the deflate-style algorithm: repeated sequences are replaced
+//! by references to their previous occurrence, before the result gets Huffman coded.
+//!
+//! **Byte Representation**
+//!
+//! The tokens are stored in groups of up to eight. Each group starts with a flag byte, whose bit i
+//! (least significant first) tells whether token i is a reference. A literal is stored as its
+//! byte, a reference as 2 bytes u16 distance (LE) followed by 1 byte length minus `MIN_MATCH`.
+
+use crate::{
+    codec::{CompressionAlgorithm, Huffman},
+    fs::CompressedData,
+    Result,
+};
+
+/// Minimum length of a sequence to be replaced by a reference.
+pub const MIN_MATCH: usize = 3;
+/// Maximum length of a sequence, which is replaced by a single reference.
+pub const MAX_MATCH: usize = MIN_MATCH + u8::MAX as usize;
+/// Maximum distance of a reference to the previous occurrence.
+pub const WINDOW_SIZE: usize = u16::MAX as usize;
+
+/// Compression level, which is used if none is given.
+pub const DEFAULT_LEVEL: u8 = 6;
+/// Highest compression level.
+pub const MAX_LEVEL: u8 = 9;
+
+/// Number of bits of the hash of the next `MIN_MATCH` bytes.
+const HASH_BITS: usize = 15;
+
+/// LZ77 references, which are Huffman coded afterwards, like deflate does. The level from 0 to
+/// `MAX_LEVEL` trades speed for ratio: up to 2^level previous occurrences get compared to find the
+/// longest match.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lz77 {
+    /// Compression level.
+    pub level: u8,
+    /// Huffman coding of the tokens.
+    pub huffman: Huffman,
+}
+
+impl Lz77 {
+    /// LZ77 of the default level with unlimited Huffman codes.
+    pub const DEFAULT: Lz77 = Lz77 {
+        level: DEFAULT_LEVEL,
+        huffman: Huffman::DEFAULT,
+    };
+}
+
+impl Default for Lz77 {
+    fn default() -> Lz77 {
+        Lz77::DEFAULT
+    }
+}
+
+impl CompressionAlgorithm for Lz77 {
+    fn compress(&self, content: &[u8]) -> Result<CompressedData> {
+        self.huffman
+            .compress(&compress_with_level(&[], content, self.level))
+    }
+
+    fn decompress(&self, cdata: &CompressedData) -> Result<Vec<u8>> {
+        decompress(&self.huffman.decompress(cdata)?)
+    }
+
+    /// Only the LZ77 stage uses the dictionary, the tokens get their own Huffman table.
+    fn compress_with_dict(&self, content: &[u8], dict: &[u8]) -> Result<CompressedData> {
+        self.huffman
+            .compress(&compress_with_level(dict, content, self.level))
+    }
+
+    fn decompress_with_dict(&self, cdata: &CompressedData, dict: &[u8]) -> Result<Vec<u8>> {
+        decompress_with_dict(dict, &self.huffman.decompress(cdata)?)
+    }
+}
+
+/// One element of the LZ77 output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Token {
+    /// A single byte.
+    Literal(u8),
+    /// A copy of `length` bytes, starting `distance` bytes before the current position.
+    Reference { distance: u16, length: u8 },
+}
+
+/// Replaces repeated sequences of the content by references.
+pub fn compress(content: &[u8]) -> Vec<u8> {
+    compress_with_dict(&[], content)
+}
+
+/// Like `compress()`, but references may also point into the end of the preset dictionary, see
+/// `dict`.
+pub fn compress_with_dict(dict: &[u8], content: &[u8]) -> Vec<u8> {
+    compress_with_level(dict, content, DEFAULT_LEVEL)
+}
+
+/// Like `compress_with_dict()`, but with the given compression level, see `Lz77`.
+pub fn compress_with_level(dict: &[u8], content: &[u8], level: u8) -> Vec<u8> {
+    let window = window(dict);
+    let max_chain = 1 << level.min(MAX_LEVEL);
+    let input = [window, content].concat();
+    let mut output = Vec::with_capacity(content.len());
+    let mut group = Vec::new();
+    let mut flags = 0u8;
+    let mut count = 0;
+    for_each_token(&input, window.len(), max_chain, |token| {
+        match token {
+            Token::Literal(byte) => group.push(byte),
+            Token::Reference { distance, length } => {
+                flags |= 1 << count;
+                group.extend_from_slice(&distance.to_le_bytes());
+                group.push(length);
+            }
+        }
+        count += 1;
+        if count == 8 {
+            output.push(flags);
+            output.append(&mut group);
+            flags = 0;
+            count = 0;
+        }
+    });
+    if count > 0 {
+        output.push(flags);
+        output.append(&mut group);
+    }
+    output
+}
+
+/// Part of the dictionary, which fits into the window in front of the content.
+fn window(dict: &[u8]) -> &[u8] {
+    &dict[dict.len().saturating_sub(WINDOW_SIZE)..]
+}
+
+/// Finds the tokens of the content greedily, the longest match within the window wins. The first
+/// `start` bytes are not tokenized, they only serve as targets of references. At most `max_chain`
+/// previous occurrences get compared per position.
+fn for_each_token(content: &[u8], start: usize, max_chain: usize, mut f: impl FnMut(Token)) {
+    // Most recent position per hash and the previous position with the same hash per position.
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; content.len()];
+    let hash = |pos: usize| {
+        let bytes = &content[pos..pos + MIN_MATCH];
+        let value = (bytes[0] as usize) << 10 ^ (bytes[1] as usize) << 5 ^ bytes[2] as usize;
+        value & ((1 << HASH_BITS) - 1)
+    };
+    let insert = |pos: usize, head: &mut [usize], prev: &mut [usize]| {
+        if pos + MIN_MATCH <= content.len() {
+            let h = hash(pos);
+            prev[pos] = head[h];
+            head[h] = pos;
+        }
+    };
+
+    (0..start).for_each(|p| insert(p, &mut head, &mut prev));
+    let mut pos = start;
+    while pos < content.len() {
+        let (mut best_len, mut best_dist) = (0, 0);
+        if pos + MIN_MATCH <= content.len() {
+            let max_len = MAX_MATCH.min(content.len() - pos);
+            let mut candidate = head[hash(pos)];
+            let mut chain = 0;
+            while candidate != usize::MAX && pos - candidate <= WINDOW_SIZE && chain < max_chain {
+                let len = content[candidate..]
+                    .iter()
+                    .zip(&content[pos..pos + max_len])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if len > best_len {
+                    (best_len, best_dist) = (len, pos - candidate);
+                    if len == max_len {
+                        break;
+                    }
+                }
+                candidate = prev[candidate];
+                chain += 1;
+            }
+        }
+
+        if best_len >= MIN_MATCH {
+            f(Token::Reference {
+                distance: best_dist as u16,
+                length: (best_len - MIN_MATCH) as u8,
+            });
+            (pos..pos + best_len).for_each(|p| insert(p, &mut head, &mut prev));
+            pos += best_len;
+        } else {
+            f(Token::Literal(content[pos]));
+            insert(pos, &mut head, &mut prev);
+            pos += 1;
+        }
+    }
+}
+
+/// Restores the content from the output of `compress()`.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    decompress_with_dict(&[], data)
+}
+
+/// Restores the content from the output of `compress_with_dict()`, with the same dictionary.
+pub fn decompress_with_dict(dict: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let broken = || "LZ77 data is broken";
+    let window = window(dict);
+    let mut content = Vec::with_capacity(window.len() + data.len() * 2);
+    content.extend_from_slice(window);
+    let mut data = data.iter().copied();
+    while let Some(flags) = data.next() {
+        for i in 0..8 {
+            if flags & 1 << i == 0 {
+                match data.next() {
+                    Some(byte) => content.push(byte),
+                    None => break,
+                }
+                continue;
+            }
+            let mut next = || data.next().ok_or_else(broken);
+            let distance = u16::from_le_bytes([next()?, next()?]) as usize;
+            let length = next()? as usize + MIN_MATCH;
+            if distance == 0 || distance > content.len() {
+                return Err(broken().into());
+            }
+            // The sequence may overlap with the copied bytes, so it is copied byte by byte.
+            let start = content.len() - distance;
+            (start..start + length).for_each(|i| content.push(content[i]));
+        }
+    }
+    Ok(content.split_off(window.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references() {
+        let content = b"abcabcabcabcX";
+        let data = compress(content);
+        // Three literals, one overlapping reference of nine bytes and a final literal.
+        assert_eq!(data, vec![0b01000, b'a', b'b', b'c', 3, 0, 6, b'X']);
+        assert_eq!(decompress(&data).unwrap(), content);
+        assert!(decompress(&[0b1, 5, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn dictionary() {
+        let dict = b"the quick brown fox";
+        let data = compress_with_dict(dict, b"quick fox");
+        // One reference of six bytes and one of three bytes into the dictionary.
+        assert_eq!(data, vec![0b11, 15, 0, 3, 9, 0, 0]);
+        assert_eq!(decompress_with_dict(dict, &data).unwrap(), b"quick fox");
+        assert!(decompress(&data).is_err());
+    }
+
+    #[test]
+    fn compress_decompress() {
+        let content = std::fs::read("135-0.txt").unwrap();
+        let content = &content[..200_000];
+        let data = compress(content);
+        assert!(data.len() < content.len() * 3 / 4);
+        assert_eq!(decompress(&data).unwrap(), content);
+
+        let fast = compress_with_level(&[], content, 0);
+        let best = compress_with_level(&[], content, MAX_LEVEL);
+        assert!(fast.len() > data.len() && best.len() <= data.len());
+        assert_eq!(decompress(&fast).unwrap(), content);
+        assert_eq!(decompress(&best).unwrap(), content);
+    }
+}
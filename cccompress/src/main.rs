@@ -1,10 +1,68 @@
 //! Just a main for to create a binary out of this...
 
-use cccompress::CtDirective;
+use cccompress::{logging, progress::ProgressBar, CtArgs, CtDirective, CtError};
+use clap::Parser;
+use std::{io::IsTerminal, process::ExitCode};
 
-fn main() -> cccompress::Result<()> {
-    let args = CtDirective::parse_input()?;
-    let cli_out = cccompress::compression_tool(args)?;
-    println!("{}", cli_out);
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            if let Some(hint) = hint(&err) {
+                eprintln!("Hint: {hint}");
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Advice, how to get around the error, if there is any.
+fn hint(err: &CtError) -> Option<&'static str> {
+    match err.cause() {
+        CtError::InvalidMagic => Some("the input is no file compressed by cccompress"),
+        CtError::UnsupportedVersion { .. } => Some("a newer cccompress is needed to read it"),
+        CtError::Truncated { .. }
+        | CtError::CorruptHeader { .. }
+        | CtError::InvalidCode { .. }
+        | CtError::ChecksumMismatch { .. } => {
+            Some("--salvage recovers as much as possible of a damaged file")
+        }
+        CtError::SymbolNotInTable { .. } => {
+            Some("an imported spectrum (--use-spectrum) has to contain every byte of the input")
+        }
+        _ => None,
+    }
+}
+
+fn run() -> cccompress::Result<()> {
+    let args = CtArgs::parse();
+    logging::init(
+        args.log_level
+            .unwrap_or_else(|| logging::level(args.verbose, args.quiet)),
+    )?;
+    let show_bar = !args.quiet && args.verbose == 0 && std::io::stderr().is_terminal();
+    let mut bar = ProgressBar::new(show_bar);
+    let (stats, stats_json) = (args.stats, args.stats_json);
+    let args = CtDirective::try_from(args)?;
+    let to_stdout = args.writes_stdout();
+    let report = cccompress::compression_tool_with_progress(args, &mut |done, total| {
+        bar.update(done, total)
+    });
+    bar.finish();
+    let report = report?;
+
+    let mut cli_out = report.message;
+    if stats {
+        cli_out = format!("{cli_out}\n{}", report.stats.text());
+    }
+    if stats_json {
+        cli_out = format!("{cli_out}\n{}", report.stats.json());
+    }
+    if to_stdout {
+        eprintln!("{}", cli_out);
+    } else {
+        println!("{}", cli_out);
+    }
     Ok(())
 }
@@ -0,0 +1,177 @@
+//! Module contains the progress reporting of long running operations. A callback gets the number
+//! of processed and of all bytes, the command line tool draws a progress bar from it.
+
+use std::{
+    io::{self, BufRead, Read},
+    time::{Duration, Instant},
+};
+
+/// Progress of an operation, which is reported to a callback with the number of processed bytes
+/// and the number of all bytes. The latter is 0, if it is unknown, e.g. when reading stdin.
+pub struct Progress<'p> {
+    done: u64,
+    total: u64,
+    callback: &'p mut dyn FnMut(u64, u64),
+}
+
+impl<'p> Progress<'p> {
+    /// New type pattern: generates a new progress of the given number of all bytes.
+    pub fn new(total: u64, callback: &'p mut dyn FnMut(u64, u64)) -> Progress<'p> {
+        Progress {
+            done: 0,
+            total,
+            callback,
+        }
+    }
+
+    /// Number of processed bytes so far.
+    pub fn done(&self) -> u64 {
+        self.done
+    }
+
+    /// Adds processed bytes and reports the progress.
+    pub fn advance(&mut self, bytes: u64) {
+        self.done += bytes;
+        (self.callback)(self.done, self.total);
+    }
+
+    /// Wraps a reader, so that all bytes read through it count as processed.
+    pub fn reader<R>(&mut self, reader: R) -> ProgressReader<'_, 'p, R> {
+        ProgressReader {
+            reader,
+            progress: self,
+        }
+    }
+}
+
+/// Reader, which advances a progress by all bytes read through it.
+pub struct ProgressReader<'a, 'p, R> {
+    reader: R,
+    progress: &'a mut Progress<'p>,
+}
+
+impl<R> ProgressReader<'_, '_, R> {
+    /// Number of processed bytes so far, i.e. the position within the reader, if it has been read
+    /// from the beginning.
+    pub fn done(&self) -> u64 {
+        self.progress.done()
+    }
+}
+
+impl<R: Read> Read for ProgressReader<'_, '_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        self.progress.advance(n as u64);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for ProgressReader<'_, '_, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt);
+        self.progress.advance(amt as u64);
+    }
+}
+
+/// Delay, after which the progress bar appears, so that fast operations do not show it at all.
+const DELAY: Duration = Duration::from_millis(500);
+/// Minimum time between two updates of the progress bar.
+const INTERVAL: Duration = Duration::from_millis(100);
+/// Number of characters of the bar itself.
+const WIDTH: usize = 30;
+
+/// Progress bar with an estimated time of arrival, drawn on stderr.
+#[derive(Debug)]
+pub struct ProgressBar {
+    enabled: bool,
+    start: Instant,
+    last_draw: Option<Instant>,
+}
+
+impl ProgressBar {
+    /// New type pattern: generates a new progress bar, which draws nothing if not enabled.
+    pub fn new(enabled: bool) -> ProgressBar {
+        ProgressBar {
+            enabled,
+            start: Instant::now(),
+            last_draw: None,
+        }
+    }
+
+    /// Redraws the bar, but not more often than every `INTERVAL`.
+    pub fn update(&mut self, done: u64, total: u64) {
+        let now = Instant::now();
+        if !self.enabled
+            || now - self.start < DELAY
+            || self.last_draw.is_some_and(|last| now - last < INTERVAL)
+        {
+            return;
+        }
+        eprint!("\r{}", render(done, total, now - self.start));
+        self.last_draw = Some(now);
+    }
+
+    /// Removes the bar again, if it has been drawn.
+    pub fn finish(&mut self) {
+        if self.last_draw.take().is_some() {
+            eprint!("\r{:1$}\r", "", WIDTH + 40);
+        }
+    }
+}
+
+/// Text of the progress bar after the given time.
+fn render(done: u64, total: u64, elapsed: Duration) -> String {
+    let mb = |bytes: u64| bytes as f64 / 1_000_000.0;
+    if total == 0 || done > total {
+        return format!("{:.1} MB processed", mb(done));
+    }
+    let fraction = done as f64 / total as f64;
+    let filled = (fraction * WIDTH as f64) as usize;
+    let eta = if done == 0 {
+        String::from("?")
+    } else {
+        let remaining = elapsed.as_secs_f64() * (total - done) as f64 / done as f64;
+        format!("{}s", remaining.ceil() as u64)
+    };
+    format!(
+        "[{}{}] {:>3}% {:.1}/{:.1} MB, ETA {eta}",
+        "#".repeat(filled),
+        "-".repeat(WIDTH - filled),
+        (fraction * 100.0) as u64,
+        mb(done),
+        mb(total)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_reader() {
+        let mut reports = Vec::new();
+        let mut callback = |done, total| reports.push((done, total));
+        let mut progress = Progress::new(10, &mut callback);
+        let mut content = Vec::new();
+        progress
+            .reader(&b"0123456789"[..])
+            .read_to_end(&mut content)
+            .unwrap();
+        assert_eq!(content, b"0123456789");
+        assert_eq!(reports.first(), Some(&(10, 10)));
+    }
+
+    #[test]
+    fn bar() {
+        let bar = render(1_500_000, 3_000_000, Duration::from_secs(2));
+        assert_eq!(
+            bar,
+            "[###############---------------]  50% 1.5/3.0 MB, ETA 2s"
+        );
+        assert_eq!(render(2_000_000, 0, Duration::ZERO), "2.0 MB processed");
+    }
+}
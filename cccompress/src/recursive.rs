@@ -0,0 +1,299 @@
+//! Module contains the recursive compression of a directory tree: every file gets compressed into
+//! a file of its own next to it, like by `gzip -r`, instead of one archive of the whole tree, see
+//! `archive`. The files are compressed in parallel, one per thread, and selected by globs.
+//!
+//! Globs know `*` for any characters except `/`, `?` for one of them and `**` for any characters
+//! including `/`, where `**/` also matches no directory at all. A glob with a `/` gets matched
+//! against the path relative to the directory, any other one against the name only, so that
+//! `*.txt` selects text files in all subdirectories.
+
+use crate::{compress_file, input_size, passes, stats::Stats, verify_packed, PackOptions, Result};
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    time::Instant,
+};
+
+/// Selection of the files by globs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Filter {
+    /// Files are selected, if they match any of these globs, all files if there are none.
+    pub include: Vec<String>,
+    /// Files and directories are skipped, if they match any of these globs.
+    pub exclude: Vec<String>,
+}
+
+impl Filter {
+    /// Whether the file of the relative path gets compressed.
+    pub fn selects(&self, path: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|glob| matches(glob, path)))
+            && !self.excludes(path)
+    }
+
+    /// Whether the file or directory of the relative path is skipped.
+    pub fn excludes(&self, path: &str) -> bool {
+        self.exclude.iter().any(|glob| matches(glob, path))
+    }
+}
+
+/// Result of compressing a tree.
+#[derive(Debug, Default)]
+pub struct Summary {
+    /// Statistics of all compressed files.
+    pub stats: Stats,
+    /// Number of compressed files.
+    pub compressed: usize,
+    /// Number of selected files, which are compressed already, so they were skipped.
+    pub skipped: usize,
+    /// Files, which could not be compressed, with their error.
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+impl Summary {
+    /// Summary as message of the directive, one more line per failed file.
+    pub fn message(&self, dir: &str) -> String {
+        let mut message = format!(
+            "Compressed {} files in '{dir}'. Wrote {} bytes, skipped {} compressed files",
+            self.compressed, self.stats.compressed_bytes, self.skipped
+        );
+        if !self.failed.is_empty() {
+            message += &format!(", {} files failed:", self.failed.len());
+        }
+        for (path, err) in &self.failed {
+            message += &format!("\n  '{}': {err}", path.display());
+        }
+        message
+    }
+}
+
+/// Compresses every selected file of the tree into a file next to it, in parallel. A file, which
+/// can not be compressed, does not stop the others, it is reported in the summary. Files, which
+/// have the extension of compressed files already, are skipped. With
+/// `PackOptions::remove_source`, every file gets removed after its compressed file has been
+/// verified. The progress covers all files, like `compress_dir()`.
+pub fn compress_tree(
+    dir: &str,
+    filter: &Filter,
+    options: PackOptions,
+    progress: &mut dyn FnMut(u64, u64),
+) -> Result<Summary> {
+    let start = Instant::now();
+    let mut files = Vec::new();
+    collect(Path::new(dir), "", filter, &mut files)?;
+    files.sort();
+    let extension = format!(".{}", options.extension());
+    let (skipped, files): (Vec<PathBuf>, Vec<PathBuf>) = files
+        .into_iter()
+        .partition(|path| path.to_string_lossy().ends_with(&extension));
+    for path in &skipped {
+        log::info!("skipping '{}', it is compressed already", path.display());
+    }
+    let total = files
+        .iter()
+        .map(|path| input_size(path) * passes(path, &options))
+        .sum();
+
+    let mut summary = Summary {
+        skipped: skipped.len(),
+        ..Default::default()
+    };
+    let mut done = vec![0; files.len()];
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(files.len());
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            let sender = sender.clone();
+            let (files, next, options) = (&files, &next, &options);
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = files.get(i) else {
+                    break;
+                };
+                let result = compress_one(path, options, &mut |bytes, _| {
+                    let _ = sender.send(Message::Progress(i, bytes));
+                });
+                let _ = sender.send(Message::Done(i, result.map_err(|err| err.to_string())));
+            });
+        }
+        drop(sender);
+        for message in receiver {
+            match message {
+                Message::Progress(i, bytes) => done[i] = bytes,
+                Message::Done(i, Ok(stats)) => {
+                    log::info!("compressed '{}'", files[i].display());
+                    summary.stats += stats;
+                    summary.compressed += 1;
+                }
+                Message::Done(i, Err(err)) => {
+                    log::warn!("'{}' could not be compressed: {err}", files[i].display());
+                    summary.failed.push((files[i].clone(), err));
+                }
+            }
+            progress(done.iter().sum(), total);
+        }
+    });
+    summary.failed.sort();
+    summary.stats.elapsed = start.elapsed();
+    Ok(summary)
+}
+
+/// Message of a worker thread to the one, which collects the results.
+enum Message {
+    /// Number of processed bytes of the file of the index.
+    Progress(usize, u64),
+    /// Statistics of the compressed file of the index or its error.
+    Done(usize, std::result::Result<Stats, String>),
+}
+
+/// Compresses one file next to it, verifies the compressed file and removes the source, if the
+/// options say so.
+fn compress_one(
+    path: &Path,
+    options: &PackOptions,
+    progress: &mut dyn FnMut(u64, u64),
+) -> Result<Stats> {
+    let source = path.to_str().ok_or("path is not valid UTF-8")?;
+    let target = crate::fs::switch_file_type_with(source, options.extension());
+    let stats = compress_file(source, &target, options.clone(), progress)?;
+    if options.remove_source {
+        verify_packed(&target, options)
+            .map_err(|err| format!("'{target}' is corrupt, the source is kept: {err}"))?;
+        std::fs::remove_file(path)?;
+    }
+    Ok(stats)
+}
+
+/// Adds all selected files of the directory recursively, their relative paths prefixed by
+/// `prefix`. Symlinks are not followed, excluded directories are skipped as a whole.
+fn collect(dir: &Path, prefix: &str, filter: &Filter, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let relative = format!("{prefix}{}", entry.file_name().to_string_lossy());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() && !filter.excludes(&relative) {
+            collect(&path, &format!("{relative}/"), filter, files)?;
+        } else if file_type.is_file() && filter.selects(&relative) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Whether the glob matches the relative path, or its name, if the glob has no `/`.
+pub fn matches(glob: &str, path: &str) -> bool {
+    let path = match glob.contains('/') {
+        true => path,
+        false => path.rsplit('/').next().unwrap_or(path),
+    };
+    let glob: Vec<char> = glob.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    matches_chars(&glob, &path)
+}
+
+/// Matches the characters of a glob against those of a path.
+fn matches_chars(glob: &[char], path: &[char]) -> bool {
+    match glob {
+        [] => path.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            matches_chars(rest, path)
+                || (0..path.len()).any(|i| path[i] == '/' && matches_chars(rest, &path[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=path.len()).any(|i| matches_chars(rest, &path[i..])),
+        ['*', rest @ ..] => {
+            let name = path.iter().take_while(|c| **c != '/').count();
+            (0..=name).any(|i| matches_chars(rest, &path[i..]))
+        }
+        ['?', rest @ ..] => {
+            path.first().is_some_and(|c| *c != '/') && matches_chars(rest, &path[1..])
+        }
+        [c, rest @ ..] => path.first() == Some(c) && matches_chars(rest, &path[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs() {
+        assert!(matches("*.txt", "a.txt"));
+        assert!(matches("*.txt", "sub/dir/a.txt"));
+        assert!(!matches("*.txt", "a.txt.cpd"));
+        assert!(matches("a?c", "abc"));
+        assert!(!matches("a?c", "ac"));
+        assert!(matches("sub/*.txt", "sub/a.txt"));
+        assert!(!matches("sub/*.txt", "sub/dir/a.txt"));
+        assert!(matches("sub/**", "sub/dir/a.txt"));
+        assert!(matches("**/a.txt", "a.txt"));
+        assert!(matches("**/a.txt", "sub/dir/a.txt"));
+        assert!(!matches("**/a.txt", "sub/ba.txt"));
+        assert!(matches("target", "sub/target"));
+
+        let filter = Filter {
+            include: vec!["*.txt".into(), "*.md".into()],
+            exclude: vec!["skip*".into()],
+        };
+        assert!(filter.selects("dir/a.md"));
+        assert!(!filter.selects("dir/a.rs"));
+        assert!(!filter.selects("dir/skip.txt"));
+        assert!(Filter::default().selects("a.rs"));
+    }
+
+    #[test]
+    fn tree() {
+        for file in ["a.txt", "b.log", "sub/c.txt", "sub/d.txt.cpd", "skip/e.txt"] {
+            let path = Path::new("recursive_test").join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, format!("content of {file}")).unwrap();
+        }
+        let filter = Filter {
+            include: vec!["*.txt".into(), "*.cpd".into()],
+            exclude: vec!["skip".into()],
+        };
+        let mut updates = 0;
+        let summary = compress_tree(
+            "recursive_test",
+            &filter,
+            PackOptions::default(),
+            &mut |done, total| {
+                assert!(done <= total);
+                updates += 1;
+            },
+        )
+        .unwrap();
+        assert_eq!((summary.compressed, summary.skipped), (2, 1));
+        assert!(summary.failed.is_empty() && updates > 0);
+        for (file, exists) in [
+            ("a.cpd", true),
+            ("b.cpd", false),
+            ("sub/c.cpd", true),
+            ("skip/e.cpd", false),
+        ] {
+            assert_eq!(Path::new("recursive_test").join(file).exists(), exists);
+        }
+
+        // Existing compressed files are kept, the others are still compressed.
+        std::fs::remove_file("recursive_test/sub/c.cpd").unwrap();
+        let options = PackOptions {
+            remove_source: true,
+            ..Default::default()
+        };
+        let summary = compress_tree("recursive_test", &filter, options, &mut |_, _| {}).unwrap();
+        assert_eq!(summary.compressed, 1);
+        assert_eq!(summary.failed.len(), 1);
+        let message = summary.message("recursive_test");
+        assert!(
+            message.contains("1 files failed:\n  'recursive_test/a.txt': 'recursive_test/a.cpd'")
+        );
+        assert!(Path::new("recursive_test/a.txt").exists());
+        assert!(!Path::new("recursive_test/sub/c.txt").exists());
+        std::fs::remove_dir_all("recursive_test").unwrap();
+    }
+}
@@ -0,0 +1,61 @@
+//! Module contains a simple run-length encoding, which replaces runs of equal bytes by their
+//! length and the byte. It only pays off for contents with long runs, like simple images.
+//!
+//! **Byte Representation**
+//!
+//! Pairs of 1 byte run length (1..=255) and the repeated byte.
+
+use crate::{codec::CompressionAlgorithm, fs::CompressedData, fs::Header, Result};
+
+/// Run-length encoding of bytes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Rle;
+
+impl CompressionAlgorithm for Rle {
+    fn compress(&self, content: &[u8]) -> Result<CompressedData> {
+        let mut data = Vec::new();
+        let mut rest = content;
+        while let Some(&byte) = rest.first() {
+            let run = rest
+                .iter()
+                .take(u8::MAX as usize)
+                .take_while(|b| **b == byte)
+                .count();
+            data.push(run as u8);
+            data.push(byte);
+            rest = &rest[run..];
+        }
+
+        Ok(CompressedData {
+            header: Header {
+                data_bytes: data.len() as u32,
+                ..Default::default()
+            },
+            data,
+        })
+    }
+
+    fn decompress(&self, cdata: &CompressedData) -> Result<Vec<u8>> {
+        if !cdata.data.len().is_multiple_of(2) {
+            return Err("run-length encoded data is broken".into());
+        }
+        let mut content = Vec::new();
+        for pair in cdata.data.chunks_exact(2) {
+            content.extend(std::iter::repeat_n(pair[1], pair[0] as usize));
+        }
+        Ok(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs() {
+        let content: Vec<u8> = [vec![7; 300], vec![1, 2]].concat();
+        let cdata = Rle.compress(&content).unwrap();
+        assert_eq!(cdata.data, vec![255, 7, 45, 7, 1, 1, 1, 2]);
+        assert_eq!(Rle.decompress(&cdata).unwrap(), content);
+    }
+}
@@ -0,0 +1,234 @@
+//! Module contains shared frequency tables: the `CharSpectrum` of a corpus gets exported as JSON
+//! by `--dump-spectrum` and imported by `--use-spectrum`, so that many small files get Huffman
+//! coded by the table of the corpus instead of counting their own symbols. The table is still
+//! stored in the header of every file, so unpacking does not need the spectrum.
+//!
+//! The JSON object holds the total number and the frequencies of all symbols, e.g.
+//! `{"total": 5, "frequencies": [0, 0, ..., 3, 2, ...]}`. Reading only supports members of
+//! unsigned integers and arrays of them, which is all this format needs.
+
+use crate::{
+    algorithm::{CharSpectrum, PrefixCodeTable, SYMBOLS},
+    CtError, Result,
+};
+
+/// Number of frequencies per line of the JSON array.
+const PER_LINE: usize = 16;
+
+/// Frequencies above this limit could overflow the weights of the Huffman tree.
+const MAX_TOTAL: u64 = 1 << 56;
+
+/// Serializes the spectrum as JSON object.
+pub fn to_json(spectrum: &CharSpectrum) -> String {
+    let frequencies: Vec<String> = (0..=u8::MAX)
+        .map(|symbol| spectrum.frequency(symbol).to_string())
+        .collect();
+    let lines: Vec<String> = frequencies
+        .chunks(PER_LINE)
+        .map(|line| format!("    {}", line.join(", ")))
+        .collect();
+    format!(
+        "{{\n  \"total\": {},\n  \"frequencies\": [\n{}\n  ]\n}}\n",
+        spectrum.total(),
+        lines.join(",\n")
+    )
+}
+
+/// Deserializes a spectrum from a JSON object, as written by `to_json()`.
+pub fn from_json(json: &str) -> Result<CharSpectrum> {
+    let members = Parser::new(json).object()?;
+    let member = |name: &str| members.iter().find(|(n, _)| n == name).map(|(_, v)| v);
+    let frequencies = match member("frequencies") {
+        Some(Value::Array(frequencies)) => <[u64; SYMBOLS]>::try_from(&frequencies[..])
+            .map_err(|_| format!("spectrum needs {SYMBOLS} frequencies"))?,
+        _ => return Err("spectrum needs an array of frequencies".into()),
+    };
+    let total = frequencies
+        .iter()
+        .try_fold(0u64, |total, f| total.checked_add(*f))
+        .filter(|total| *total <= MAX_TOTAL)
+        .ok_or("frequencies of the spectrum are too large")?;
+    match member("total") {
+        Some(Value::Number(n)) if *n != total => Err(format!(
+            "spectrum has a total of {n}, but its frequencies sum up to {total}"
+        )
+        .into()),
+        Some(Value::Array(_)) => Err("total of the spectrum is no number".into()),
+        _ => Ok(CharSpectrum::from_frequencies(frequencies)),
+    }
+}
+
+/// Prefix code table derived from an imported spectrum. Every symbol gets counted once more, like
+/// by `dict::table()`, so that contents with symbols missing in the spectrum can be encoded as
+/// well.
+pub fn table(spectrum: &CharSpectrum, max_length: u8) -> Result<PrefixCodeTable> {
+    let mut spectrum = spectrum.clone();
+    spectrum.add(&(0..=u8::MAX).collect::<Vec<u8>>());
+    PrefixCodeTable::build(&spectrum, max_length)
+}
+
+/// Value of a member of the JSON object.
+#[derive(Debug, PartialEq)]
+enum Value {
+    Number(u64),
+    Array(Vec<u64>),
+}
+
+/// Minimal JSON parser of an object, whose members are unsigned integers or arrays of them.
+struct Parser<'j> {
+    json: &'j [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    /// New type pattern: generates a new parser at the beginning of the JSON source.
+    fn new(json: &str) -> Parser<'_> {
+        Parser {
+            json: json.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    /// Parses the whole source as one object, returns its members.
+    fn object(&mut self) -> Result<Vec<(String, Value)>> {
+        let mut members = Vec::new();
+        self.expect(b'{')?;
+        if !self.next_if(b'}') {
+            loop {
+                let name = self.string()?;
+                self.expect(b':')?;
+                members.push((name, self.value()?));
+                if self.next_if(b'}') {
+                    break;
+                }
+                self.expect(b',')?;
+            }
+        }
+        match self.peek() {
+            None => Ok(members),
+            Some(_) => Err(self.error("end of the JSON source")),
+        }
+    }
+
+    /// Parses an unsigned integer or an array of them.
+    fn value(&mut self) -> Result<Value> {
+        if !self.next_if(b'[') {
+            return Ok(Value::Number(self.number()?));
+        }
+        let mut array = Vec::new();
+        if !self.next_if(b']') {
+            loop {
+                array.push(self.number()?);
+                if self.next_if(b']') {
+                    break;
+                }
+                self.expect(b',')?;
+            }
+        }
+        Ok(Value::Array(array))
+    }
+
+    /// Parses a string without escape sequences, as member names are.
+    fn string(&mut self) -> Result<String> {
+        self.expect(b'"')?;
+        let start = self.pos;
+        while let Some(&b) = self.json.get(self.pos) {
+            match b {
+                b'"' => {
+                    let name = String::from_utf8_lossy(&self.json[start..self.pos]);
+                    self.pos += 1;
+                    return Ok(name.into_owned());
+                }
+                b'\\' => return Err(self.error("a member name without escape sequences")),
+                _ => self.pos += 1,
+            }
+        }
+        Err(self.error("'\"'"))
+    }
+
+    /// Parses an unsigned integer.
+    fn number(&mut self) -> Result<u64> {
+        self.peek();
+        let digits = self.json[self.pos..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        let number = std::str::from_utf8(&self.json[self.pos..self.pos + digits])?
+            .parse()
+            .map_err(|_| self.error("an unsigned integer"))?;
+        self.pos += digits;
+        Ok(number)
+    }
+
+    /// Skips the whitespace and consumes the given character.
+    fn expect(&mut self, c: u8) -> Result<()> {
+        match self.next_if(c) {
+            true => Ok(()),
+            false => Err(self.error(&format!("'{}'", c as char))),
+        }
+    }
+
+    /// Skips the whitespace and consumes the given character, if it is the next one.
+    fn next_if(&mut self, c: u8) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    /// Skips the whitespace and returns the next character without consuming it.
+    fn peek(&mut self) -> Option<u8> {
+        while self.json.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            self.pos += 1;
+        }
+        self.json.get(self.pos).copied()
+    }
+
+    /// Error of the current position, where something else was expected.
+    fn error(&self, expected: &str) -> CtError {
+        format!(
+            "invalid spectrum JSON at byte {}, expected {expected}",
+            self.pos
+        )
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_round_trip() {
+        let spectrum = CharSpectrum::from_bytes(b"abracadabra\n");
+        let json = to_json(&spectrum);
+        assert!(json.starts_with("{\n  \"total\": 12,\n  \"frequencies\": [\n    0, 0,"));
+        assert_eq!(from_json(&json).unwrap(), spectrum);
+        assert_eq!(
+            from_json(&to_json(&CharSpectrum::default()))
+                .unwrap()
+                .total(),
+            0
+        );
+
+        let zeros = vec!["0"; SYMBOLS].join(",");
+        assert!(from_json(&format!("{{\"frequencies\":[{zeros}]}}")).is_ok());
+        assert!(from_json(&format!("{{\"total\": 1, \"frequencies\":[{zeros}]}}")).is_err());
+        assert!(from_json("{\"frequencies\": [1, 2]}").is_err());
+        assert!(from_json("{\"frequencies\": [-1]}").is_err());
+        assert!(from_json("{\"frequencies\": [").is_err());
+        assert!(from_json("{").is_err());
+        assert!(from_json("").is_err());
+        assert!(from_json(&json.replace('}', "}}")).is_err());
+    }
+
+    #[test]
+    fn imported_table() {
+        let spectrum = CharSpectrum::from_bytes(b"aaaaaaaabbbbc");
+        let table = table(&spectrum, 12).unwrap();
+        assert!(table.code(b'a').unwrap().1 < table.code(b'c').unwrap().1);
+        assert!((0..=u8::MAX).all(|symbol| table.code(symbol).is_some()));
+        assert!(table.max_length() <= 12);
+    }
+}
@@ -0,0 +1,100 @@
+//! Module contains the statistics of a directive, like the sizes before and after compressing
+//! and the throughput.
+
+use std::{ops::AddAssign, time::Duration};
+
+/// Sizes and duration of compressing or decompressing.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// Number of bytes of the original content.
+    pub original_bytes: u64,
+    /// Number of bytes of the compressed file.
+    pub compressed_bytes: u64,
+    /// Duration of the whole operation.
+    pub elapsed: Duration,
+}
+
+impl Stats {
+    /// Size of the compressed file in percent of the original size.
+    pub fn ratio(&self) -> f64 {
+        if self.original_bytes == 0 {
+            return 0.0;
+        }
+        self.compressed_bytes as f64 / self.original_bytes as f64 * 100.0
+    }
+
+    /// Processed megabytes of the original content per second.
+    pub fn throughput(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs == 0.0 {
+            return 0.0;
+        }
+        self.original_bytes as f64 / 1_000_000.0 / secs
+    }
+
+    /// Human readable summary.
+    pub fn text(&self) -> String {
+        format!(
+            "Original {} bytes, compressed {} bytes ({:.1}%), {:.3} s, {:.1} MB/s",
+            self.original_bytes,
+            self.compressed_bytes,
+            self.ratio(),
+            self.elapsed.as_secs_f64(),
+            self.throughput()
+        )
+    }
+
+    /// Machine readable summary as JSON object.
+    pub fn json(&self) -> String {
+        format!(
+            "{{\"original_bytes\":{},\"compressed_bytes\":{},\"ratio\":{:.2},\"elapsed_s\":{:.6},\
+             \"throughput_mb_s\":{:.3}}}",
+            self.original_bytes,
+            self.compressed_bytes,
+            self.ratio(),
+            self.elapsed.as_secs_f64(),
+            self.throughput()
+        )
+    }
+}
+
+impl AddAssign for Stats {
+    fn add_assign(&mut self, other: Stats) {
+        self.original_bytes += other.original_bytes;
+        self.compressed_bytes += other.compressed_bytes;
+        self.elapsed += other.elapsed;
+    }
+}
+
+/// Result of a directive: the message for the user and the statistics.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    pub message: String,
+    pub stats: Stats,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summaries() {
+        let stats = Stats {
+            original_bytes: 4_000_000,
+            compressed_bytes: 1_000_000,
+            elapsed: Duration::from_millis(500),
+        };
+        assert_eq!(stats.ratio(), 25.0);
+        assert_eq!(stats.throughput(), 8.0);
+        assert_eq!(
+            stats.text(),
+            "Original 4000000 bytes, compressed 1000000 bytes (25.0%), 0.500 s, 8.0 MB/s"
+        );
+        assert_eq!(
+            stats.json(),
+            "{\"original_bytes\":4000000,\"compressed_bytes\":1000000,\"ratio\":25.00,\
+             \"elapsed_s\":0.500000,\"throughput_mb_s\":8.000}"
+        );
+        assert_eq!(Stats::default().ratio(), 0.0);
+    }
+}
@@ -0,0 +1,322 @@
+//! Module contains higher-order symbol models of Huffman coding: instead of single bytes, the
+//! spectrum and the tree are built over pairs of bytes or over words, which improves the ratio of
+//! natural-language text. The id of the model is stored in the header.
+//!
+//! The alphabet of these models is not known in advance, so the symbols with the lengths of their
+//! codes are stored in front of the encoded data instead of the prefix code table of the header.
+//! The codes are canonical like those of `algorithm`, so that only their lengths are needed.
+//!
+//! **Byte Representation**
+//!
+//! 0..4            (1) 4 bytes u32, number of symbols (m)
+//! 4..             (2) m times: 1 byte number of leading bytes (p), which the symbol shares with
+//!                     the one before, 1 byte number of the other bytes (l), these l bytes and
+//!                     1 byte length of its code, the symbols sorted by their bytes
+//! ...             (3) Huffman coded symbols, the last byte has the unused bits of the header
+//!
+//! Sorted words share many leading bytes, so (2) stores only the rest of them.
+
+use crate::{
+    bitstream::{BitStreamReader, BitStreamWriter},
+    fs::{CompressedData, Header},
+    Result,
+};
+use clap::ValueEnum;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap},
+    io::Write,
+};
+
+/// Maximum length of codes of these models, so that every code fits into `add_bits()` at once.
+const MAX_LENGTH: u8 = 32;
+
+/// Maximum number of bytes of a symbol, longer words get split.
+const MAX_SYMBOL_LEN: usize = u8::MAX as usize;
+
+/// Symbol models of Huffman coding, the id of the used one is stored in the header.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum SymbolModel {
+    /// Every byte is a symbol.
+    #[default]
+    Bytes,
+    /// Every pair of bytes is a symbol, an odd last byte one on its own.
+    Digrams,
+    /// Every run of whitespace and every word between them is a symbol.
+    Words,
+}
+
+impl SymbolModel {
+    /// Id of the symbol model in the header.
+    pub fn id(self) -> u8 {
+        match self {
+            SymbolModel::Bytes => 0,
+            SymbolModel::Digrams => 1,
+            SymbolModel::Words => 2,
+        }
+    }
+
+    /// Symbol model of the given id in the header.
+    pub fn from_id(id: u8) -> Result<SymbolModel> {
+        match id {
+            0 => Ok(SymbolModel::Bytes),
+            1 => Ok(SymbolModel::Digrams),
+            2 => Ok(SymbolModel::Words),
+            _ => Err(format!("unknown symbol model {id}").into()),
+        }
+    }
+
+    /// Name of the symbol model, as on the command line.
+    pub fn name(self) -> String {
+        self.to_possible_value()
+            .map_or_else(String::new, |v| v.get_name().to_string())
+    }
+
+    /// Splits the content into its symbols, whose concatenation is the content again.
+    pub fn symbols(self, content: &[u8]) -> Vec<&[u8]> {
+        match self {
+            SymbolModel::Bytes => content.chunks(1).collect(),
+            SymbolModel::Digrams => content.chunks(2).collect(),
+            SymbolModel::Words => {
+                let mut symbols = Vec::new();
+                let mut rest = content;
+                while let Some(first) = rest.first() {
+                    let len = rest
+                        .iter()
+                        .take(MAX_SYMBOL_LEN)
+                        .take_while(|b| b.is_ascii_whitespace() == first.is_ascii_whitespace())
+                        .count();
+                    let (symbol, tail) = rest.split_at(len);
+                    symbols.push(symbol);
+                    rest = tail;
+                }
+                symbols
+            }
+        }
+    }
+}
+
+/// Huffman codes the symbols of the content by the model, with codes of at most `max_length` bits.
+/// Only the fields of the header, which describe the encoded data, are set, like by
+/// `CompressionAlgorithm::compress()`.
+pub fn compress(content: &[u8], model: SymbolModel, max_length: u8) -> Result<CompressedData> {
+    let symbols = model.symbols(content);
+    let mut frequencies = BTreeMap::new();
+    symbols
+        .iter()
+        .for_each(|symbol| *frequencies.entry(*symbol).or_insert(0u64) += 1);
+    let (alphabet, frequencies): (Vec<&[u8]>, Vec<u64>) = frequencies.into_iter().unzip();
+    let lengths = code_lengths(&frequencies, max_length.min(MAX_LENGTH))?;
+    let codes = canonical_codes(&lengths);
+    let index: BTreeMap<&[u8], usize> = alphabet.iter().enumerate().map(|(i, s)| (*s, i)).collect();
+
+    let mut writer = BitStreamWriter::new(Vec::new());
+    writer.write_all(&(alphabet.len() as u32).to_le_bytes())?;
+    let mut previous: &[u8] = &[];
+    for (symbol, length) in alphabet.iter().zip(&lengths) {
+        let shared = previous
+            .iter()
+            .zip(symbol.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        writer.write_all(&[shared as u8, (symbol.len() - shared) as u8])?;
+        writer.write_all(&symbol[shared..])?;
+        writer.write_all(&[*length])?;
+        previous = symbol;
+    }
+    for symbol in &symbols {
+        let i = index[symbol];
+        writer.add_bits(codes[i], lengths[i])?;
+    }
+    let (data, unused_bits) = writer.finish()?;
+    Ok(CompressedData {
+        header: Header {
+            data_bytes: u32::try_from(data.len()).map_err(|_| "encoded data exceeds 4 GiB")?,
+            unused_bits,
+            symbols: model.id(),
+            ..Default::default()
+        },
+        data,
+    })
+}
+
+/// Restores the content from data, as returned by `compress()`.
+pub fn decompress(cdata: &CompressedData) -> Result<Vec<u8>> {
+    let mut data = &cdata.data[..];
+    let (alphabet, lengths): (Vec<Vec<u8>>, Vec<u8>) =
+        read_alphabet(&mut data)?.into_iter().unzip();
+    let codes = canonical_codes(&lengths);
+    let max_length = lengths.iter().copied().max().unwrap_or(0);
+    // Symbols by their code length and code, like `algorithm::Decoder` looks them up.
+    let codes: BTreeMap<(u8, u32), &[u8]> = (0..alphabet.len())
+        .map(|i| ((lengths[i], codes[i]), &alphabet[i][..]))
+        .collect();
+
+    let mut content = Vec::new();
+    let mut reader = BitStreamReader::new(data, cdata.header.unused_bits);
+    let (mut code, mut length) = (0u32, 0u8);
+    while let Some(bit) = reader.next_bit()? {
+        code = code << 1 | bit as u32;
+        length += 1;
+        if let Some(symbol) = codes.get(&(length, code)) {
+            content.extend_from_slice(symbol);
+            (code, length) = (0, 0);
+        } else if length >= max_length {
+            let offset = (reader.position() - 1) / 8;
+            return Err(format!("invalid code at byte {offset} of the encoded symbols").into());
+        }
+    }
+    if length > 0 {
+        return Err("encoded data ends within a code".into());
+    }
+    Ok(content)
+}
+
+/// Reads the symbols with the lengths of their codes, the data is left at the encoded symbols.
+fn read_alphabet(data: &mut &[u8]) -> Result<Vec<(Vec<u8>, u8)>> {
+    const TRUNCATED: &str = "symbol table is truncated";
+    let count = data.get(..4).ok_or(TRUNCATED)?;
+    let count = u32::from_le_bytes(count.try_into()?) as usize;
+    *data = &data[4..];
+    let mut alphabet: Vec<(Vec<u8>, u8)> = Vec::new();
+    for _ in 0..count {
+        let (shared, len) = match data {
+            [shared, len, ..] => (*shared as usize, *len as usize),
+            _ => return Err(TRUNCATED.into()),
+        };
+        let rest = data.get(2..len + 2).ok_or(TRUNCATED)?;
+        let length = *data.get(len + 2).ok_or(TRUNCATED)?;
+        let previous = alphabet.last().map_or(&[][..], |(symbol, _)| &symbol[..]);
+        let mut symbol = previous.get(..shared).ok_or(TRUNCATED)?.to_vec();
+        symbol.extend_from_slice(rest);
+        if !(1..=MAX_LENGTH).contains(&length) {
+            return Err(format!("invalid code length {length} in the symbol table").into());
+        }
+        alphabet.push((symbol, length));
+        *data = &data[len + 3..];
+    }
+    let kraft: u64 = alphabet.iter().map(|(_, l)| 1u64 << (MAX_LENGTH - l)).sum();
+    if kraft > 1 << MAX_LENGTH {
+        return Err("code lengths of the symbol table are no prefix code".into());
+    }
+    Ok(alphabet)
+}
+
+/// Lengths of the Huffman codes of the frequencies. Codes longer than `max_length` are avoided by
+/// halving the frequencies, until the tree is flat enough.
+fn code_lengths(frequencies: &[u64], max_length: u8) -> Result<Vec<u8>> {
+    if frequencies.len() as u64 > 1 << max_length {
+        return Err(format!(
+            "{} symbols do not fit into codes of {max_length} bits",
+            frequencies.len()
+        )
+        .into());
+    }
+    let mut frequencies = frequencies.to_vec();
+    loop {
+        let lengths = huffman_lengths(&frequencies);
+        if lengths.iter().all(|l| *l <= max_length) {
+            return Ok(lengths);
+        }
+        frequencies.iter_mut().for_each(|f| *f = f.div_ceil(2));
+    }
+}
+
+/// Depth of every symbol in the Huffman tree of the frequencies, at least 1. Equal weights get
+/// merged in the order of the symbols, so the lengths are deterministic.
+fn huffman_lengths(frequencies: &[u64]) -> Vec<u8> {
+    let n = frequencies.len();
+    let mut parents = vec![0; n];
+    let mut heap: BinaryHeap<Reverse<(u64, usize)>> = frequencies
+        .iter()
+        .enumerate()
+        .map(|(i, f)| Reverse((*f, i)))
+        .collect();
+    while let (Some(Reverse((w1, a))), Some(Reverse((w2, b)))) = (heap.pop(), heap.pop()) {
+        let node = parents.len();
+        parents.push(node);
+        parents[a] = node;
+        parents[b] = node;
+        heap.push(Reverse((w1 + w2, node)));
+    }
+    (0..n)
+        .map(|mut node| {
+            let mut depth = 0;
+            while parents[node] != node {
+                node = parents[node];
+                depth += 1;
+            }
+            depth.max(1)
+        })
+        .collect()
+}
+
+/// Canonical codes of the lengths: shorter codes first, equal lengths in the order of the symbols.
+fn canonical_codes(lengths: &[u8]) -> Vec<u32> {
+    let mut order: Vec<usize> = (0..lengths.len()).collect();
+    order.sort_by_key(|i| (lengths[*i], *i));
+    let mut codes = vec![0; lengths.len()];
+    let (mut code, mut previous) = (0u64, 0);
+    for (k, i) in order.into_iter().enumerate() {
+        if k > 0 {
+            code = (code + 1) << (lengths[i] - previous);
+        }
+        codes[i] = code as u32;
+        previous = lengths[i];
+    }
+    codes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_and_symbols() {
+        for model in SymbolModel::value_variants() {
+            assert_eq!(SymbolModel::from_id(model.id()).unwrap(), *model);
+        }
+        assert!(SymbolModel::from_id(3).is_err());
+        assert_eq!(
+            SymbolModel::Digrams.symbols(b"abcde"),
+            [b"ab", b"cd", &b"e"[..]]
+        );
+        assert_eq!(
+            SymbolModel::Words.symbols(b"to be,  or\n"),
+            [&b"to"[..], b" ", b"be,", b"  ", b"or", b"\n"]
+        );
+        let long = vec![b'x'; 300];
+        assert_eq!(SymbolModel::Words.symbols(&long).len(), 2);
+    }
+
+    #[test]
+    fn canonical_lengths() {
+        let lengths = code_lengths(&[10, 1, 1, 5], 32).unwrap();
+        assert_eq!(lengths, [1, 3, 3, 2]);
+        assert_eq!(canonical_codes(&lengths), [0b0, 0b110, 0b111, 0b10]);
+        assert_eq!(code_lengths(&[7], 32).unwrap(), [1]);
+        let skewed: Vec<u64> = (0..20).map(|i| 1 << i).collect();
+        assert!(code_lengths(&skewed, 6).unwrap().iter().all(|l| *l <= 6));
+        assert!(code_lengths(&[1; 5], 2).is_err());
+    }
+
+    #[test]
+    fn roundtrip() {
+        let content = crate::tests::testfile("135-0.txt");
+        let content = &content[..200_000];
+        let bytes = crate::compress(content).unwrap();
+        for model in [SymbolModel::Digrams, SymbolModel::Words] {
+            let cdata = compress(content, model, 32).unwrap();
+            assert_eq!(cdata.header.symbols, model.id());
+            assert_eq!(decompress(&cdata).unwrap(), content);
+            assert!(cdata.data.len() < bytes.data.len());
+        }
+        for content in [&b""[..], b"a", b"aaaa", b"ab ab ab"] {
+            let cdata = compress(content, SymbolModel::Words, 32).unwrap();
+            assert_eq!(decompress(&cdata).unwrap(), content);
+        }
+        let mut cdata = compress(b"to be or not to be", SymbolModel::Words, 32).unwrap();
+        cdata.data.truncate(10);
+        assert!(decompress(&cdata).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+//! Module contains a harness for property tests of the compression algorithms: generators of
+//! random inputs and `assert_roundtrip()`, which checks that an algorithm restores them exactly.
+//! New implementations of `CompressionAlgorithm` get their correctness coverage from it, e.g. by
+//! `proptest! { #[test] fn round_trip(input in bytes()) { assert_roundtrip(&MyCodec, &input) } }`.
+//!
+//! `corpus()` holds inputs, which are known to be hard for the algorithms, like empty content or
+//! long runs, every algorithm is checked against all of them. Failures found by proptest are
+//! stored in `proptest-regressions/` and replayed first by later runs. Outside of this crate, the
+//! module needs the `testing` feature.
+
+use crate::CompressionAlgorithm;
+use proptest::{collection::vec, prelude::*};
+
+/// Maximum length of the generated inputs.
+const MAX_LENGTH: usize = 4096;
+
+/// Arbitrary bytes, e.g. of binary files.
+pub fn bytes() -> impl Strategy<Value = Vec<u8>> {
+    vec(any::<u8>(), 0..MAX_LENGTH)
+}
+
+/// UTF-8 bytes of arbitrary text, including umlauts, CJK characters and emojis.
+pub fn unicode() -> impl Strategy<Value = Vec<u8>> {
+    vec(any::<char>(), 0..MAX_LENGTH / 4).prop_map(|chars| {
+        let text: String = chars.into_iter().collect();
+        text.into_bytes()
+    })
+}
+
+/// Runs and repeated phrases of few different bytes, which give long matches and very unequal
+/// frequencies, i.e. long codes.
+pub fn repetitive() -> impl Strategy<Value = Vec<u8>> {
+    let piece = (vec(0..4u8, 1..16), 1..64usize);
+    vec(piece, 0..32).prop_map(|pieces| {
+        pieces
+            .into_iter()
+            .flat_map(|(phrase, repeats)| phrase.repeat(repeats))
+            .collect()
+    })
+}
+
+/// Inputs, which are known to be hard for the algorithms, by name.
+pub fn corpus() -> Vec<(&'static str, Vec<u8>)> {
+    let mut fibonacci = vec![0u8];
+    let (mut a, mut b) = (1, 1);
+    for symbol in 1..24 {
+        fibonacci.extend(std::iter::repeat_n(symbol, a));
+        (a, b) = (b, a + b);
+    }
+    vec![
+        ("empty", Vec::new()),
+        ("single byte", vec![b'a']),
+        ("single symbol", vec![0; 1000]),
+        ("two symbols", b"ab".repeat(500)),
+        ("all symbols", (0..=u8::MAX).collect()),
+        (
+            "all symbols twice",
+            (0..=u8::MAX).chain(0..=u8::MAX).collect(),
+        ),
+        ("long run", vec![0xff; 70_000]),
+        ("fibonacci frequencies", fibonacci),
+        ("utf-8", "Größe 大きさ 📦 ".repeat(50).into_bytes()),
+    ]
+}
+
+/// Asserts that the algorithm compresses the input, that the header matches the compressed data
+/// and that decompressing restores the input exactly.
+pub fn assert_roundtrip(algo: &dyn CompressionAlgorithm, input: &[u8]) {
+    let len = input.len();
+    let cdata = algo
+        .compress(input)
+        .unwrap_or_else(|err| panic!("compressing {len} bytes failed: {err}"));
+    assert_eq!(
+        cdata.header.data_bytes as usize,
+        cdata.data.len(),
+        "header does not match the compressed data of {len} bytes"
+    );
+    let output = algo
+        .decompress(&cdata)
+        .unwrap_or_else(|err| panic!("decompressing {len} bytes failed: {err}"));
+    assert!(
+        output == input,
+        "round trip changed the content of {len} bytes"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Algorithm;
+    use clap::ValueEnum;
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn arbitrary_bytes(input in bytes()) {
+            for algo in Algorithm::value_variants() {
+                assert_roundtrip(algo.codec(), &input);
+            }
+        }
+
+        #[test]
+        fn unicode_text(input in unicode()) {
+            for algo in Algorithm::value_variants() {
+                assert_roundtrip(algo.codec(), &input);
+            }
+        }
+
+        #[test]
+        fn repetitive_content(input in repetitive()) {
+            for algo in Algorithm::value_variants() {
+                assert_roundtrip(algo.codec(), &input);
+            }
+        }
+    }
+
+    #[test]
+    fn regression_corpus() {
+        for (name, input) in corpus() {
+            for algo in Algorithm::value_variants() {
+                let result = std::panic::catch_unwind(|| assert_roundtrip(algo.codec(), &input));
+                assert!(result.is_ok(), "{algo:?} failed on the input '{name}'");
+            }
+        }
+    }
+}
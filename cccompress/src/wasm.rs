@@ -0,0 +1,85 @@
+//! Module contains the bindings for WebAssembly, so that the tool runs in the browser, e.g. on the
+//! demo page of the coding challenge. `wasm-pack build --target web -- --features wasm` generates
+//! the JavaScript module, whose `compress_bytes()` and `decompress_bytes()` take and return
+//! `Uint8Array`s. Their results are the same bytes like the files of the command line tool.
+
+use crate::{compressor::Compressor, Algorithm, CtError};
+use wasm_bindgen::prelude::*;
+
+/// Error thrown to JavaScript, instead of a Rust error, which can not cross the boundary. Like a
+/// JavaScript `Error`, it has a `message` and gets shown by `toString()`.
+#[wasm_bindgen]
+#[derive(Debug, PartialEq)]
+pub struct CompressError {
+    message: String,
+}
+
+#[wasm_bindgen]
+impl CompressError {
+    /// Description of what failed.
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+
+    /// Same like `message`, prefixed by the name of the error.
+    #[wasm_bindgen(js_name = toString)]
+    pub fn describe(&self) -> String {
+        format!("CompressError: {}", self.message)
+    }
+}
+
+impl From<CtError> for CompressError {
+    fn from(err: CtError) -> CompressError {
+        CompressError {
+            message: err.to_string(),
+        }
+    }
+}
+
+/// Compresses the content by the named algorithm, see `--algorithm`, by Huffman coding if none
+/// is given. Returns the header and the compressed data, like a file written by `--pack`.
+#[wasm_bindgen]
+pub fn compress_bytes(content: &[u8], algorithm: Option<String>) -> Result<Vec<u8>, CompressError> {
+    let algo = match algorithm {
+        Some(name) => {
+            <Algorithm as clap::ValueEnum>::from_str(&name, true).map_err(|_| CompressError {
+                message: format!("unknown algorithm '{name}'"),
+            })?
+        }
+        None => Algorithm::Huffman,
+    };
+    let cdata = Compressor::new().algorithm(algo).compress(content)?;
+    let mut bytes = Vec::new();
+    crate::write_file(&mut bytes, &cdata)?;
+    Ok(bytes)
+}
+
+/// Decompresses the bytes of a compressed file, as returned by `compress_bytes()` or written by
+/// `--pack`. Concatenated files are decompressed one after another.
+#[wasm_bindgen]
+pub fn decompress_bytes(data: &[u8]) -> Result<Vec<u8>, CompressError> {
+    let mut content = Vec::new();
+    crate::decompress_to(data, &mut content)?;
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_round_trip() {
+        let content = b"Like a file of the command line tool, but in the browser.";
+        let bytes = compress_bytes(content, None).unwrap();
+        assert_eq!(&bytes[..4], b"CCPD");
+        assert_eq!(decompress_bytes(&bytes).unwrap(), content);
+
+        let bytes = compress_bytes(content, Some("lz77".into())).unwrap();
+        assert_eq!(decompress_bytes(&bytes).unwrap(), content);
+
+        let err = compress_bytes(content, Some("zip".into())).unwrap_err();
+        assert_eq!(err.describe(), "CompressError: unknown algorithm 'zip'");
+        assert!(decompress_bytes(b"no compressed file").is_err());
+    }
+}
@@ -92,7 +92,7 @@ type MidLexerOutput = Option<(JLexerToken, usize)>;
 
 impl<'s> JLexer<'s> {
     /// New type pattern: Generates a new lexer with given source string slice.
-    pub fn new(source: &str) -> JLexer {
+    pub fn new(source: &str) -> JLexer<'_> {
         JLexer {
             source,
             iter: source.char_indices(),
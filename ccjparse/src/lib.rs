@@ -17,7 +17,7 @@ mod tests {
 
     #[inline]
     fn expect_file(file: &str) -> String {
-        std::fs::read_to_string(file).expect(&format!("missing test file {}", file))
+        std::fs::read_to_string(file).unwrap_or_else(|_| panic!("missing test file {}", file))
     }
 
     #[test]
@@ -0,0 +1,84 @@
+//! Module encapsules counting of asynchronous readers, for use in async applications like a web
+//! server, which must not block a runtime thread.
+
+use crate::{
+    encoding::Encoding,
+    iterators::{split_position, CHUNK_SIZE},
+    options::{CcWcOptions, Counts},
+};
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Counts lines, words, bytes, characters and grapheme clusters of a UTF-8 encoded asynchronous
+/// reader within a single pass. The data is processed piece by piece, split behind whitespace, so
+/// only one piece is held in memory at once.
+pub async fn count_async(mut reader: impl AsyncRead + Unpin) -> io::Result<Counts> {
+    let options = CcWcOptions::new()
+        .lines()
+        .words()
+        .bytes()
+        .chars()
+        .graphemes();
+    let mut counts = Counts::default();
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut raw = Vec::<u8>::new();
+    let mut text = String::new();
+
+    loop {
+        let n = reader.read(&mut buf).await?;
+        let eof = n == 0;
+        raw.extend_from_slice(&buf[..n]);
+        let (decoded, consumed) = Encoding::Utf8.decode(&raw, eof)?;
+        raw.drain(..consumed);
+        text.push_str(&decoded);
+
+        let split = if eof {
+            Some(text.len())
+        } else {
+            split_position(&text)
+        };
+        if let Some(split) = split {
+            let rest = text.split_off(split);
+            options.add_piece(&mut counts, &text);
+            text = rest;
+        }
+        if eof {
+            return Ok(counts);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn count_reader() {
+        let text: &[u8] = "Some text\nfrom an\u{a0}async reader\n".as_bytes();
+        let counts = count_async(text).await.unwrap();
+        assert_eq!(
+            counts,
+            Counts {
+                lines: 2,
+                words: 6,
+                bytes: 32,
+                chars: 31,
+                graphemes: 31,
+                ..Default::default()
+            }
+        );
+
+        let invalid: &[u8] = &[b'a', b' ', 0xFF];
+        assert!(count_async(invalid).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn count_file() {
+        let file = tokio::fs::File::open("test.txt").await.unwrap();
+        let counts = count_async(file).await.unwrap();
+        assert_eq!(
+            (counts.lines, counts.words, counts.bytes),
+            (7145, 58164, 342190)
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! Module encapsules the detection of binary files, whose words and lines are meaningless.
+
+use crate::{
+    command::Content, encoding::Encoding, gzip::GzipFile, iterators::Newline, CcWcArgs, Result,
+};
+use clap::ValueEnum;
+use std::{
+    fs,
+    io::{self, Read},
+};
+
+/// Number of bytes at the beginning of an input, which are probed for NUL bytes.
+pub const PROBE_SIZE: usize = 8192;
+
+/// Handling of binary inputs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum BinaryMode {
+    /// Skips binary files with a warning.
+    Skip,
+    /// Counts only the bytes of binary files, all other counters are zero.
+    #[default]
+    Bytes,
+    /// Counts binary files like text files.
+    Force,
+}
+
+/// Checks whether the data looks like binary data, i.e. contains a NUL byte.
+pub fn is_binary(probe: &[u8]) -> bool {
+    probe.contains(&0)
+}
+
+/// Checks whether binary detection is enabled. Text encoded in UTF-16 naturally contains NUL
+/// bytes, as well as NUL-terminated lines, so they are never considered binary.
+fn enabled(args: &CcWcArgs) -> bool {
+    args.binary != BinaryMode::Force
+        && !matches!(args.encoding, Encoding::Utf16le | Encoding::Utf16be)
+        && args.terminator() != Newline::Nul
+}
+
+/// Checks whether the file is a binary file, by probing its beginning. Compressed files are
+/// probed after decompression.
+pub fn detect_file(args: &CcWcArgs, file: &str) -> Result<bool> {
+    if !enabled(args) {
+        return Ok(false);
+    }
+    let reader: Box<dyn Read> = if args.is_compressed(file) {
+        Box::new(GzipFile::open(file)?)
+    } else {
+        Box::new(fs::File::open(file)?)
+    };
+    let mut probe = Vec::with_capacity(PROBE_SIZE);
+    reader.take(PROBE_SIZE as u64).read_to_end(&mut probe)?;
+    Ok(is_binary(&probe))
+}
+
+/// Checks whether the content is binary data, by probing its beginning. Only content, which has
+/// been read in completely, can be probed.
+pub fn detect_content(args: &CcWcArgs, content: &Content) -> bool {
+    match content {
+        Content::SmallFile(text, _) if enabled(args) => {
+            is_binary(&text.as_bytes()[..text.len().min(PROBE_SIZE)])
+        }
+        _ => false,
+    }
+}
+
+/// Number of raw bytes of the file, after decompression.
+pub fn raw_bytes(args: &CcWcArgs, file: &str) -> Result<usize> {
+    if args.is_compressed(file) {
+        let mut reader = GzipFile::open(file)?;
+        Ok(io::copy(&mut reader, &mut io::sink())? as usize)
+    } else {
+        Ok(fs::metadata(file)?.len() as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_binary() {
+        assert!(is_binary(b"ELF\0\x01"));
+        assert!(!is_binary("plain text\n".as_bytes()));
+
+        let args = CcWcArgs::from("ccwc test.txt");
+        assert!(!detect_file(&args, "test.txt").unwrap());
+        let content = Content::SmallFile(String::from("a\0b"), true);
+        assert!(detect_content(&args, &content));
+
+        let args = CcWcArgs::from("ccwc --encoding utf16le test.txt");
+        assert!(!detect_content(&args, &content));
+        let args = CcWcArgs::from("ccwc --binary force test.txt");
+        assert!(!detect_content(&args, &content));
+        let args = CcWcArgs::from("ccwc -z test.txt");
+        assert!(!detect_content(&args, &content));
+    }
+}
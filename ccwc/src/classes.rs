@@ -0,0 +1,91 @@
+//! Module encapsules the classification of characters into letters, digits, whitespace,
+//! punctuation and other characters, e.g. for corpus analysis.
+
+use crate::options::Counts;
+
+/// Class of a single character.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CharClass {
+    Letter,
+    Digit,
+    Whitespace,
+    Punctuation,
+    Other,
+}
+
+impl CharClass {
+    /// Class of the given character. Punctuation covers the ASCII punctuation characters like
+    /// POSIX `ispunct()`, and the common punctuation of Latin-1, general punctuation and CJK.
+    pub fn of(c: char) -> CharClass {
+        if c.is_alphabetic() {
+            CharClass::Letter
+        } else if c.is_numeric() {
+            CharClass::Digit
+        } else if c.is_whitespace() {
+            CharClass::Whitespace
+        } else if is_punctuation(c) {
+            CharClass::Punctuation
+        } else {
+            CharClass::Other
+        }
+    }
+}
+
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation()
+        || matches!(
+            c,
+            '\u{a1}'
+                | '\u{a7}'
+                | '\u{ab}'
+                | '\u{b6}'
+                | '\u{b7}'
+                | '\u{bb}'
+                | '\u{bf}'
+                | '\u{2010}'..='\u{2027}'
+                | '\u{2030}'..='\u{205e}'
+                | '\u{3001}'..='\u{3003}'
+                | '\u{3008}'..='\u{3011}'
+        )
+}
+
+/// Counts the characters of all classes within a single pass, all other counters are zero.
+pub fn count_classes(text: &str) -> Counts {
+    let mut counts = Counts::default();
+    for c in text.chars() {
+        match CharClass::of(c) {
+            CharClass::Letter => counts.letters += 1,
+            CharClass::Digit => counts.digits += 1,
+            CharClass::Whitespace => counts.whitespace += 1,
+            CharClass::Punctuation => counts.punctuation += 1,
+            CharClass::Other => counts.other += 1,
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify() {
+        assert_eq!(CharClass::of('\u{e4}'), CharClass::Letter);
+        assert_eq!(CharClass::of('7'), CharClass::Digit);
+        assert_eq!(CharClass::of('\u{a0}'), CharClass::Whitespace);
+        assert_eq!(CharClass::of('\u{201c}'), CharClass::Punctuation);
+        assert_eq!(CharClass::of('\u{1F600}'), CharClass::Other);
+
+        let counts = count_classes("Hello, world 42!\r\n\u{20ac}");
+        assert_eq!(
+            (
+                counts.letters,
+                counts.digits,
+                counts.whitespace,
+                counts.punctuation,
+                counts.other
+            ),
+            (10, 2, 4, 2, 1)
+        );
+    }
+}
@@ -1,13 +1,51 @@
 //! Encapsules command line interface related implementations.
 
-use clap::Parser;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+use crate::uring::UringFile;
+use crate::{
+    binary::BinaryMode,
+    encoding::{Encoding, Invalid},
+    format::{OutputFormat, Template, Total},
+    gzip::GzipFile,
+    iterators::{ChunkIterator, Newline},
+    normalize::{Normalization, WordForm},
+    pattern::Pattern,
+    progress::ProgressBar,
+};
+use clap::{CommandFactory, Parser};
+use clap_complete::Shell;
+use flate2::read::MultiGzDecoder;
 use std::{
     error, fs,
-    io::{self, BufReader, IsTerminal, Read, Seek},
+    io::{self, BufReader, IsTerminal, Read},
 };
 
+/// Filename, which stands for stdin among the input files, like in wc.
+pub const STDIN: &str = "-";
+
 /// This threshold affects whether a file will be read in completely or iterated vai buffer.
-const FILE_SIZE_THRESHOLD: usize = 10_000_000;
+pub const FILE_SIZE_THRESHOLD: u64 = 10_000_000;
+
+/// Strategy, how the content of a reader gets buffered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Buffering {
+    /// Read in the full content, it can be iterated multiple times.
+    #[default]
+    Memory,
+    /// Read the content piece by piece, it can be iterated only once.
+    Stream,
+}
+
+impl Buffering {
+    /// Buffering of a file of the given size: files larger than the threshold are streamed.
+    pub fn for_size(size: u64, threshold: u64) -> Buffering {
+        if size > threshold {
+            Buffering::Stream
+        } else {
+            Buffering::Memory
+        }
+    }
+}
 
 /// Content management system for providing either the full content as String, or in case of larger
 /// files piece by piece.
@@ -15,29 +53,194 @@ const FILE_SIZE_THRESHOLD: usize = 10_000_000;
 pub enum Content {
     /// Small file, we read in the full content.
     SmallFile(String, bool),
-    /// Large file, we read the content piece by piece.
-    LargeFile(BufReader<fs::File>),
+    /// Large file, we read the content piece by piece, optionally with a progress bar.
+    LargeFile(ChunkIterator<BufReader<fs::File>>, Option<ProgressBar>),
+    /// Arbitrary reader, we read the content piece by piece, but only once.
+    Stream(ChunkIterator<Box<dyn Read>>),
+    /// Gzip-compressed file, we decompress and read the content piece by piece.
+    Compressed(ChunkIterator<GzipFile>),
+    /// Large file, we read ahead the content via io_uring and iterate it piece by piece.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    Uring(ChunkIterator<UringFile>),
 }
 
 impl Content {
-    /// Renews the iterator, because it will be consumed multiple times.
+    /// Renews the iterator, because it will be consumed multiple times. Streamed content can not
+    /// be renewed, once it has been consumed.
     pub fn rewind(&mut self) -> crate::Result<()> {
         match self {
             Content::SmallFile(_, flag) => *flag = true,
-            Content::LargeFile(reader) => reader.rewind()?,
+            Content::LargeFile(chunks, _) => chunks.rewind()?,
+            Content::Compressed(chunks) => chunks.rewind()?,
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Content::Uring(chunks) => chunks.rewind()?,
+            Content::Stream(chunks) => {
+                if chunks.consumed() > 0 {
+                    return Err("streamed content can not be rewound, use Buffering::Memory".into());
+                }
+            }
         }
         Ok(())
     }
 
+    /// Returns the error, which stopped the last iteration, if there was one.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        match self {
+            Content::SmallFile(..) => None,
+            Content::LargeFile(chunks, _) => chunks.take_error(),
+            Content::Stream(chunks) => chunks.take_error(),
+            Content::Compressed(chunks) => chunks.take_error(),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Content::Uring(chunks) => chunks.take_error(),
+        }
+    }
+
+    /// Total size of the content in bytes, if known.
+    pub fn total_size(&self) -> Option<u64> {
+        match self {
+            Content::SmallFile(content, _) => Some(content.len() as u64),
+            Content::LargeFile(chunks, _) => {
+                chunks.get_ref().get_ref().metadata().ok().map(|m| m.len())
+            }
+            Content::Stream(_) | Content::Compressed(_) => None,
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Content::Uring(_) => None,
+        }
+    }
+
+    /// Number of bytes, which have been consumed by the current iteration.
+    pub fn consumed(&self) -> u64 {
+        match self {
+            Content::SmallFile(content, flag) => {
+                if *flag {
+                    0
+                } else {
+                    content.len() as u64
+                }
+            }
+            Content::LargeFile(chunks, _) => chunks.consumed(),
+            Content::Stream(chunks) => chunks.consumed(),
+            Content::Compressed(chunks) => chunks.consumed(),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Content::Uring(chunks) => chunks.consumed(),
+        }
+    }
+
+    /// Shows a progress bar on stderr while iterating, in case of a large file.
+    pub fn enable_progress(&mut self) {
+        let total = self.total_size();
+        if let (Content::LargeFile(_, progress @ None), Some(total)) = (self, total) {
+            *progress = Some(ProgressBar::new(total));
+        }
+    }
+
     /// Pendant-method to fs::read_to_string().
     pub fn read_to_string(file: &str) -> crate::Result<Content> {
-        let file_size = fs::metadata(file)?.len() as usize;
-        if file_size > FILE_SIZE_THRESHOLD {
-            let file = fs::File::open(file)?;
-            let reader = BufReader::new(file);
-            Ok(Content::LargeFile(reader))
-        } else {
-            Ok(Content::SmallFile(fs::read_to_string(file)?, true))
+        Content::read_with_encoding(file, Encoding::Utf8)
+    }
+
+    /// Reads a file, whose text is encoded in the given encoding.
+    pub fn read_with_encoding(file: &str, encoding: Encoding) -> crate::Result<Content> {
+        Content::read_with_decoding(file, encoding, Invalid::Strict)
+    }
+
+    /// Reads a file, whose text is encoded in the given encoding. Invalid data is handled as
+    /// defined by `invalid`.
+    pub fn read_with_decoding(
+        file: &str,
+        encoding: Encoding,
+        invalid: Invalid,
+    ) -> crate::Result<Content> {
+        let buffering = Buffering::for_size(fs::metadata(file)?.len(), FILE_SIZE_THRESHOLD);
+        Content::read_with_buffering(file, encoding, invalid, buffering)
+    }
+
+    /// Reads a file like `read_with_decoding()`, but either completely or piece by piece as
+    /// defined by `buffering`. A streamed file can still be rewound.
+    pub fn read_with_buffering(
+        file: &str,
+        encoding: Encoding,
+        invalid: Invalid,
+        buffering: Buffering,
+    ) -> crate::Result<Content> {
+        match buffering {
+            Buffering::Stream => {
+                let reader = BufReader::new(fs::File::open(file)?);
+                Ok(Content::LargeFile(
+                    ChunkIterator::new(reader, encoding).with_invalid(invalid),
+                    None,
+                ))
+            }
+            Buffering::Memory => {
+                let raw = fs::read(file)?;
+                let (text, _) = encoding.decode_with(&raw, true, invalid)?;
+                Ok(Content::from_string(text))
+            }
+        }
+    }
+
+    /// Reads a gzip-compressed file, which gets decompressed while iterating.
+    pub fn read_gzip(file: &str, encoding: Encoding, invalid: Invalid) -> crate::Result<Content> {
+        let file = GzipFile::open(file)?;
+        let chunks = ChunkIterator::new(file, encoding).with_invalid(invalid);
+        Ok(Content::Compressed(chunks))
+    }
+
+    /// Reads a file piece by piece, which is read ahead via io_uring.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub fn read_uring(file: &str, encoding: Encoding, invalid: Invalid) -> crate::Result<Content> {
+        let file = UringFile::open(file)?;
+        let chunks = ChunkIterator::new(file, encoding).with_invalid(invalid);
+        Ok(Content::Uring(chunks))
+    }
+
+    /// Content of a text, which is already held in memory.
+    pub fn from_string(text: String) -> Content {
+        Content::SmallFile(text, true)
+    }
+
+    /// Content of a UTF-8 encoded buffer, which is already held in memory.
+    pub fn from_bytes(bytes: &[u8]) -> crate::Result<Content> {
+        let (text, _) = Encoding::Utf8.decode(bytes, true)?;
+        Ok(Content::from_string(text))
+    }
+
+    /// Content of an arbitrary reader, e.g. a network stream or an in-memory cursor.
+    pub fn from_reader(
+        reader: impl Read + 'static,
+        buffering: Buffering,
+    ) -> crate::Result<Content> {
+        Content::from_reader_with_encoding(reader, Encoding::Utf8, buffering)
+    }
+
+    /// Content of an arbitrary reader, whose text is encoded in the given encoding.
+    pub fn from_reader_with_encoding(
+        reader: impl Read + 'static,
+        encoding: Encoding,
+        buffering: Buffering,
+    ) -> crate::Result<Content> {
+        Content::from_reader_with_decoding(reader, encoding, Invalid::Strict, buffering)
+    }
+
+    /// Content of an arbitrary reader, whose text is encoded in the given encoding. Invalid data
+    /// is handled as defined by `invalid`.
+    pub fn from_reader_with_decoding(
+        mut reader: impl Read + 'static,
+        encoding: Encoding,
+        invalid: Invalid,
+        buffering: Buffering,
+    ) -> crate::Result<Content> {
+        match buffering {
+            Buffering::Memory => {
+                let mut raw = Vec::<u8>::new();
+                reader.read_to_end(&mut raw)?;
+                let (text, _) = encoding.decode_with(&raw, true, invalid)?;
+                Ok(Content::from_string(text))
+            }
+            Buffering::Stream => {
+                let chunks = ChunkIterator::new(Box::new(reader) as Box<dyn Read>, encoding);
+                Ok(Content::Stream(chunks.with_invalid(invalid)))
+            }
         }
     }
 }
@@ -55,16 +258,20 @@ impl Iterator for Content {
                     None
                 }
             }
-            Content::LargeFile(reader) => {
-                let mut content = String::new();
-                unsafe {
-                    if reader.read(content.as_bytes_mut()).is_ok() {
-                        Some(content)
-                    } else {
-                        None
+            Content::LargeFile(chunks, progress) => {
+                let piece = chunks.next();
+                if let Some(bar) = progress {
+                    match piece {
+                        Some(_) => bar.update(chunks.consumed()),
+                        None => bar.finish(),
                     }
                 }
+                piece
             }
+            Content::Stream(chunks) => chunks.next(),
+            Content::Compressed(chunks) => chunks.next(),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Content::Uring(chunks) => chunks.next(),
         }
     }
 }
@@ -74,41 +281,56 @@ impl Iterator for Content {
 pub struct CcWcInput {
     /// CLI parameters.
     pub args: CcWcArgs,
-    /// Content to be analyzed.
-    pub content: Content,
+    /// Content provided via stdin, `None` if the files in `args` shall be analyzed.
+    pub content: Option<Content>,
 }
 
 impl CcWcInput {
-    /// Default method to process user input from command line. Method checks whether stdin was used to
-    /// path a text to be analyzed or a filename was passed to be read in.
+    /// Default method to process user input from command line. Like wc, the given files are
+    /// counted, and only without any file or with `-` among them the text to be analyzed is read
+    /// from stdin.
     pub fn parse_input() -> crate::Result<CcWcInput> {
-        let (args, content) = if io::stdin().is_terminal() {
-            // No usage of stdin, a filename should be provided.
-            let args = CcWcArgs::parse();
-            let content = if let Some(file) = &args.file {
-                // Check file size and decide for reading in completely or buffered.
-                Content::read_to_string(file)?
-            } else {
-                return Err(String::from("No input file or data was provided").into());
-            };
-            (args, content)
+        let args = CcWcArgs::parse();
+        if args.generate_completions.is_some() || (!args.files.is_empty() && !args.reads_stdin()) {
+            // The files will be read on demand, maybe it is not necessary at all.
+            return Ok(CcWcInput {
+                args,
+                content: None,
+            });
+        }
+        if args.files.is_empty() && io::stdin().is_terminal() {
+            return Err(String::from("No input file or data was provided").into());
+        }
+        CcWcInput::from_reader(args, io::stdin())
+    }
+
+    /// Input, whose content is read from the reader like from stdin. Files in `args` are
+    /// ignored, unless `-` among them stands for the reader.
+    pub fn from_reader(
+        mut args: CcWcArgs,
+        reader: impl Read + 'static,
+    ) -> crate::Result<CcWcInput> {
+        let content = CcWcInput::read_content(&args, reader)?;
+        if !args.reads_stdin() {
+            args.files.clear();
+        }
+        Ok(CcWcInput {
+            args,
+            content: Some(content),
+        })
+    }
+
+    /// Content of the reader like stdin, decompressed with `--decompress`.
+    pub(crate) fn read_content(
+        args: &CcWcArgs,
+        reader: impl Read + 'static,
+    ) -> crate::Result<Content> {
+        let reader: Box<dyn Read> = if args.decompress {
+            Box::new(MultiGzDecoder::new(reader))
         } else {
-            // Stdin provides content input, no filename should be provided.
-            let mut content = String::new();
-            let mut reader = BufReader::new(io::stdin());
-            reader.read_to_string(&mut content)?;
-            let mut args = CcWcArgs::parse();
-            if let Some(file) = args.file {
-                println!(
-                    "Warning: file `{}` will be ignored because stdin-input was provided",
-                    file
-                );
-                args.file = None;
-            }
-            (args, Content::SmallFile(content, true))
+            Box::new(reader)
         };
-
-        Ok(CcWcInput { args, content })
+        Content::from_reader_with_decoding(reader, args.encoding, args.invalid, Buffering::Memory)
     }
 }
 
@@ -117,11 +339,13 @@ impl TryFrom<&str> for CcWcInput {
 
     fn try_from(cmd: &str) -> Result<CcWcInput, Self::Error> {
         let args = CcWcArgs::parse_from(CcWcArgsCommand::from(cmd));
-        if args.file.is_none() {
-            return Err(io::Error::new(io::ErrorKind::Other, "no file has been specified").into());
+        if args.files.is_empty() {
+            return Err(io::Error::other("no file has been specified").into());
         }
-        let content = Content::SmallFile(fs::read_to_string(args.file.as_ref().unwrap())?, true);
-        Ok(CcWcInput { args, content })
+        Ok(CcWcInput {
+            args,
+            content: None,
+        })
     }
 }
 
@@ -138,14 +362,175 @@ pub struct CcWcArgs {
     /// Outputs the number of characters.
     #[clap(short('m'), long, action)]
     pub chars: bool,
+    /// Outputs the number of grapheme clusters (user-perceived characters).
+    #[clap(short('g'), long, action)]
+    pub graphemes: bool,
     /// Outputs the number of lines.
     #[clap(short('l'), long, action)]
     pub lines: bool,
     /// Outputs the number of words.
     #[clap(short('w'), long, action)]
     pub words: bool,
-    /// Filename of file to be counted.
-    pub file: Option<String>,
+    /// Outputs the number of distinct words.
+    #[clap(short('u'), long, action)]
+    pub unique: bool,
+    /// Outputs the number of letters, digits, whitespace, punctuation and other characters.
+    #[clap(long, action)]
+    pub classes: bool,
+    /// Outputs the N most frequent words with their counts, instead of the counters.
+    #[clap(long, value_name = "N")]
+    pub top: Option<usize>,
+    /// Writes every distinct word with its number of occurrences as CSV to FILE, in addition to
+    /// the normal output.
+    #[clap(long, value_name = "FILE")]
+    pub histogram: Option<String>,
+    /// Counts only lines and words, which match the regular expression REGEX, like `grep -c`.
+    #[clap(long = "match", value_name = "REGEX")]
+    pub pattern: Option<Pattern>,
+    /// Compares words case-insensitively in the word frequency modes and for distinct words.
+    #[clap(long, action)]
+    pub fold_case: bool,
+    /// Unicode normalization of words in the word frequency modes and for distinct words, so that
+    /// e.g. composed and decomposed characters are equal.
+    #[clap(long, value_enum, default_value_t = Normalization::None)]
+    pub normalize: Normalization,
+    /// Outputs statistics about the lengths of lines and words, instead of the counters.
+    #[clap(long, action)]
+    pub stats: bool,
+    /// Line terminator used for counting lines.
+    #[clap(long, value_enum, default_value_t = Newline::Lf)]
+    pub newline: Newline,
+    /// Lines are terminated by NUL characters, short for `--newline nul`.
+    #[clap(short('z'), long, action)]
+    pub zero_terminated: bool,
+    /// Counts a final line without terminator as a line, unlike POSIX wc.
+    #[clap(long, action)]
+    pub count_partial_line: bool,
+    /// Text encoding of the input.
+    #[clap(long, value_enum, default_value_t = Encoding::Utf8)]
+    pub encoding: Encoding,
+    /// Handling of binary files, which are detected by NUL bytes at their beginning.
+    #[clap(long, value_enum, default_value_t = BinaryMode::Bytes)]
+    pub binary: BinaryMode,
+    /// Handling of input data, which is invalid in the given encoding.
+    #[clap(long, value_enum, default_value_t = Invalid::Strict)]
+    pub invalid: Invalid,
+    /// Decompresses gzip input. Files ending with `.gz` are always decompressed.
+    #[clap(long, action)]
+    pub decompress: bool,
+    /// Keeps the file open and reprints the counts, whenever data has been appended.
+    #[clap(long, action)]
+    pub follow: bool,
+    /// Counts the files again and reprints the counts, whenever one of them changes on disk.
+    #[clap(long, action)]
+    pub watch: bool,
+    /// Reads large files via io_uring, which reads ahead while counting.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    #[clap(long, action)]
+    pub io_uring: bool,
+    /// Runs the system's wc on the same files and reports every difference of the counts.
+    #[cfg(unix)]
+    #[clap(long, action)]
+    pub verify: bool,
+    /// Files larger than BYTES are read piece by piece, smaller ones are read in completely.
+    #[clap(long, value_name = "BYTES", env = "CCWC_THRESHOLD", default_value_t = FILE_SIZE_THRESHOLD)]
+    pub buffer_threshold: u64,
+    /// Reads all files piece by piece, regardless of their size.
+    #[clap(long, action, conflicts_with = "force_memory")]
+    pub force_stream: bool,
+    /// Reads all files in completely, regardless of their size.
+    #[clap(long, action)]
+    pub force_memory: bool,
+    /// Disables the progress bar, which is shown on stderr for large files.
+    #[clap(long, action)]
+    pub no_progress: bool,
+    /// Output format of the results.
+    #[clap(long, value_enum, default_value_t = OutputFormat::Plain)]
+    pub output: OutputFormat,
+    /// Prints the values of the requested counters unaligned, separated by single spaces and
+    /// without filenames, in a fixed order which stays stable across versions.
+    #[clap(long, action, conflicts_with_all = ["output", "format", "width", "top", "stats"])]
+    pub porcelain: bool,
+    /// Prints byte counts in KiB/MiB/GiB and all other counts with thousands separators.
+    #[clap(short('H'), long, action, conflicts_with_all = ["output", "format", "porcelain"])]
+    pub human: bool,
+    /// Minimum width of the columns in plain output, by default computed like GNU wc.
+    #[clap(long, value_name = "N")]
+    pub width: Option<usize>,
+    /// Output template like `{lines}\t{words}\t{file}`, which replaces the output format.
+    /// Placeholders are the counter names and `file`.
+    #[clap(long, value_name = "TEMPLATE")]
+    pub format: Option<Template>,
+    /// When to print the row with the total counts of all files.
+    #[clap(long, value_enum, default_value_t = Total::Auto)]
+    pub total: Total,
+    /// Prints a completion script for the given shell and exits, used for packaging.
+    #[clap(long, value_enum, value_name = "SHELL", hide = true)]
+    pub generate_completions: Option<Shell>,
+    /// Filenames of files to be counted.
+    pub files: Vec<String>,
+}
+
+impl CcWcArgs {
+    /// Whether stdin is among the input files as `-`.
+    pub fn reads_stdin(&self) -> bool {
+        self.files.iter().any(|file| file == STDIN)
+    }
+
+    /// Line terminator used for counting lines, considering `--zero-terminated`.
+    pub fn terminator(&self) -> Newline {
+        if self.zero_terminated {
+            Newline::Nul
+        } else {
+            self.newline
+        }
+    }
+
+    /// Checks whether a progress bar shall be shown for large files.
+    pub fn progress(&self) -> bool {
+        !self.no_progress && io::stderr().is_terminal()
+    }
+
+    /// Checks whether the given file shall be decompressed before counting.
+    pub fn is_compressed(&self, file: &str) -> bool {
+        self.decompress || crate::gzip::is_gzip(file)
+    }
+
+    /// Buffering of a file of the given size, as forced or by the buffer threshold.
+    pub fn buffering(&self, size: u64) -> Buffering {
+        if self.force_stream {
+            Buffering::Stream
+        } else if self.force_memory {
+            Buffering::Memory
+        } else {
+            Buffering::for_size(size, self.buffer_threshold)
+        }
+    }
+
+    /// Definition of when two words are equal, for distinct words and word frequencies.
+    pub fn word_form(&self) -> WordForm {
+        WordForm::new(self.fold_case, self.normalize)
+    }
+
+    /// Checks whether files shall be read via io_uring.
+    pub fn uses_io_uring(&self) -> bool {
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        return self.io_uring;
+        #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+        false
+    }
+
+    /// Checks whether the number of bytes is the only requested output.
+    pub fn only_bytes(&self) -> bool {
+        crate::Counter::selected(self) == [crate::Counter::Bytes]
+            && self.top.is_none()
+            && !self.stats
+    }
+}
+
+/// Writes the completion script of the given shell, derived from the argument definitions.
+pub fn generate_completions(shell: Shell, out: &mut impl io::Write) {
+    clap_complete::generate(shell, &mut CcWcArgs::command(), "ccwc", out);
 }
 
 impl From<&str> for CcWcArgs {
@@ -188,31 +573,61 @@ mod tests {
     #[test]
     fn args_from_only_filename() {
         let args = CcWcArgs::from("ccwc test.txt");
-        assert_eq!(args.bytes, false);
-        assert_eq!(args.chars, false);
-        assert_eq!(args.lines, false);
-        assert_eq!(args.words, false);
-        assert_eq!(args.file, Some(String::from("test.txt")));
+        assert!(!args.bytes);
+        assert!(!args.chars);
+        assert!(!args.lines);
+        assert!(!args.words);
+        assert_eq!(args.files, vec![String::from("test.txt")]);
     }
 
     #[test]
     fn args_from_flags() {
         let args = CcWcArgs::from("ccwc -w test.txt");
-        assert_eq!(args.bytes, false);
-        assert_eq!(args.chars, false);
-        assert_eq!(args.lines, false);
-        assert_eq!(args.words, true);
+        assert!(!args.bytes);
+        assert!(!args.chars);
+        assert!(!args.lines);
+        assert!(args.words);
 
         let args = CcWcArgs::from("ccwc -l test.txt");
-        assert_eq!(args.bytes, false);
-        assert_eq!(args.chars, false);
-        assert_eq!(args.lines, true);
-        assert_eq!(args.words, false);
-
-        let args = CcWcArgs::from("ccwc -cw test.txt");
-        assert_eq!(args.bytes, true);
-        assert_eq!(args.chars, false);
-        assert_eq!(args.lines, false);
-        assert_eq!(args.words, true);
+        assert!(!args.bytes);
+        assert!(!args.chars);
+        assert!(args.lines);
+        assert!(!args.words);
+
+        let args = CcWcArgs::from("ccwc -cw test.txt other.txt");
+        assert_eq!(args.files.len(), 2);
+        assert!(args.bytes);
+        assert!(!args.chars);
+        assert!(!args.lines);
+        assert!(args.words);
+    }
+
+    #[test]
+    fn buffering() {
+        let args = CcWcArgs::from("ccwc test.txt");
+        assert_eq!(args.buffering(FILE_SIZE_THRESHOLD), Buffering::Memory);
+        assert_eq!(args.buffering(FILE_SIZE_THRESHOLD + 1), Buffering::Stream);
+
+        let args = CcWcArgs::from("ccwc --buffer-threshold 100 test.txt");
+        assert_eq!(args.buffering(101), Buffering::Stream);
+        let args = CcWcArgs::from("ccwc --buffer-threshold 100 --force-memory test.txt");
+        assert_eq!(args.buffering(101), Buffering::Memory);
+        let args = CcWcArgs::from("ccwc --force-stream test.txt");
+        assert_eq!(args.buffering(1), Buffering::Stream);
+
+        let both = ["ccwc", "--force-stream", "--force-memory", "test.txt"];
+        assert!(CcWcArgs::try_parse_from(both).is_err());
+    }
+
+    #[test]
+    fn completions() {
+        let args = CcWcArgs::from("ccwc --generate-completions bash");
+        assert_eq!(args.generate_completions, Some(Shell::Bash));
+
+        let mut script = Vec::new();
+        generate_completions(Shell::Fish, &mut script);
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("complete -c ccwc"));
+        assert!(script.contains("unique"));
     }
 }
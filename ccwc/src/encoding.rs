@@ -0,0 +1,232 @@
+//! Module encapsules the decoding of input data in different text encodings.
+
+use clap::ValueEnum;
+use std::{char::REPLACEMENT_CHARACTER, error, fmt, io};
+
+/// Supported text encodings of input data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Encoding {
+    /// UTF-8, the default.
+    #[default]
+    Utf8,
+    /// UTF-16, little endian.
+    Utf16le,
+    /// UTF-16, big endian.
+    Utf16be,
+    /// ISO 8859-1, every byte is one character.
+    Latin1,
+}
+
+/// Handling of input data, which is invalid in its encoding.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Invalid {
+    /// Rejects the input with an error, which names the byte offset of the invalid data.
+    #[default]
+    Strict,
+    /// Replaces invalid data by the replacement character U+FFFD.
+    Lossy,
+    /// Counts only the bytes of files with invalid data, all other counters are zero. Standard
+    /// input is rejected like in strict mode.
+    Bytes,
+}
+
+/// Error about invalid input data, with the byte offset of the invalid data.
+#[derive(Debug)]
+pub struct DecodeError {
+    /// Byte offset of the invalid data.
+    pub offset: u64,
+    /// Description of the problem.
+    message: String,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at byte offset {})", self.message, self.offset)
+    }
+}
+
+impl error::Error for DecodeError {}
+
+/// Shifts the byte offset of a decode error by `base`, for data which has been decoded in pieces.
+pub(crate) fn shift_offset(mut err: io::Error, base: u64) -> io::Error {
+    if let Some(decode_err) = err.get_mut().and_then(|e| e.downcast_mut::<DecodeError>()) {
+        decode_err.offset += base;
+    }
+    err
+}
+
+/// Checks whether the error has been caused by invalid input data.
+pub fn is_decode_error(err: &(dyn error::Error + 'static)) -> bool {
+    err.downcast_ref::<io::Error>()
+        .and_then(|e| e.get_ref())
+        .is_some_and(|e| e.is::<DecodeError>())
+}
+
+impl Encoding {
+    /// Decodes the longest decodable prefix of `raw` and returns the decoded text and the number
+    /// of consumed bytes. Bytes of an incomplete character at the end remain unconsumed, unless
+    /// `last` signals that no more data follows, which turns them into an error.
+    pub fn decode(&self, raw: &[u8], last: bool) -> io::Result<(String, usize)> {
+        self.decode_with(raw, last, Invalid::Strict)
+    }
+
+    /// Like `decode()`, but invalid data is handled as defined by `invalid`.
+    pub fn decode_with(
+        &self,
+        raw: &[u8],
+        last: bool,
+        invalid: Invalid,
+    ) -> io::Result<(String, usize)> {
+        let lossy = invalid == Invalid::Lossy;
+        let (mut text, consumed) = match self {
+            Encoding::Utf8 => decode_utf8(raw, lossy)?,
+            Encoding::Utf16le => decode_utf16(raw, u16::from_le_bytes, lossy)?,
+            Encoding::Utf16be => decode_utf16(raw, u16::from_be_bytes, lossy)?,
+            Encoding::Latin1 => (raw.iter().map(|&b| b as char).collect(), raw.len()),
+        };
+        if last && consumed < raw.len() {
+            if !lossy {
+                return Err(invalid_data(
+                    consumed,
+                    &format!("incomplete {self:?} character at end of input"),
+                ));
+            }
+            text.push(REPLACEMENT_CHARACTER);
+            return Ok((text, raw.len()));
+        }
+        Ok((text, consumed))
+    }
+
+    /// Number of bytes, the given text occupies in this encoding.
+    pub fn encoded_len(&self, text: &str) -> usize {
+        match self {
+            Encoding::Utf8 => text.len(),
+            Encoding::Utf16le | Encoding::Utf16be => text.encode_utf16().count() * 2,
+            Encoding::Latin1 => text.chars().count(),
+        }
+    }
+}
+
+fn decode_utf8(raw: &[u8], lossy: bool) -> io::Result<(String, usize)> {
+    let mut text = String::new();
+    let mut rest = raw;
+    loop {
+        match std::str::from_utf8(rest) {
+            Ok(valid) => {
+                text.push_str(valid);
+                return Ok((text, raw.len()));
+            }
+            Err(err) => {
+                let (valid, invalid) = rest.split_at(err.valid_up_to());
+                text.push_str(std::str::from_utf8(valid).expect("validated prefix"));
+                match err.error_len() {
+                    Some(len) if lossy => {
+                        text.push(REPLACEMENT_CHARACTER);
+                        rest = &invalid[len..];
+                    }
+                    Some(_) => {
+                        let offset = raw.len() - invalid.len();
+                        return Err(invalid_data(offset, "invalid UTF-8 sequence"));
+                    }
+                    // Incomplete character at the end, which may be completed by the next chunk.
+                    None => return Ok((text, raw.len() - invalid.len())),
+                }
+            }
+        }
+    }
+}
+
+fn decode_utf16(raw: &[u8], f: fn([u8; 2]) -> u16, lossy: bool) -> io::Result<(String, usize)> {
+    let mut units: Vec<u16> = raw.chunks_exact(2).map(|b| f([b[0], b[1]])).collect();
+    // Keep a leading surrogate at the end for the next chunk.
+    if matches!(units.last(), Some(0xD800..=0xDBFF)) {
+        units.pop();
+    }
+
+    let mut text = String::with_capacity(units.len());
+    let mut offset = 0;
+    for c in char::decode_utf16(units.iter().copied()) {
+        match c {
+            Ok(c) => text.push(c),
+            Err(_) if lossy => text.push(REPLACEMENT_CHARACTER),
+            Err(_) => return Err(invalid_data(offset, "unpaired UTF-16 surrogate")),
+        }
+        offset += c.map_or(2, |c| c.len_utf16() * 2);
+    }
+    Ok((text, units.len() * 2))
+}
+
+fn invalid_data(offset: usize, msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        DecodeError {
+            offset: offset as u64,
+            message: msg.to_string(),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf8_incomplete_char() {
+        let raw = "Grüße".as_bytes();
+        let (text, consumed) = Encoding::Utf8.decode(&raw[..3], false).unwrap();
+        assert_eq!(text, "Gr");
+        assert_eq!(consumed, 2);
+        assert!(Encoding::Utf8.decode(&raw[..3], true).is_err());
+    }
+
+    #[test]
+    fn utf16_both_endians() {
+        let text = "a€\u{1F600}\n";
+        let le: Vec<u8> = text.encode_utf16().flat_map(|u| u.to_le_bytes()).collect();
+        let be: Vec<u8> = text.encode_utf16().flat_map(|u| u.to_be_bytes()).collect();
+        assert_eq!(Encoding::Utf16le.decode(&le, true).unwrap().0, text);
+        assert_eq!(Encoding::Utf16be.decode(&be, true).unwrap().0, text);
+        assert_eq!(Encoding::Utf16le.encoded_len(text), le.len());
+
+        // Split in the middle of the surrogate pair.
+        let (part, consumed) = Encoding::Utf16le.decode(&le[..6], false).unwrap();
+        assert_eq!(part, "a€");
+        assert_eq!(consumed, 4);
+    }
+
+    #[test]
+    fn invalid_data_strict_and_lossy() {
+        let raw = [b'a', 0xFF, b'b', 0xC3];
+        let err = Encoding::Utf8.decode(&raw, true).unwrap_err();
+        assert_eq!(err.to_string(), "invalid UTF-8 sequence (at byte offset 1)");
+        assert_eq!(
+            shift_offset(err, 10).to_string(),
+            "invalid UTF-8 sequence (at byte offset 11)"
+        );
+
+        let (text, consumed) = Encoding::Utf8
+            .decode_with(&raw, false, Invalid::Lossy)
+            .unwrap();
+        assert_eq!((text.as_str(), consumed), ("a\u{FFFD}b", 3));
+        let (text, consumed) = Encoding::Utf8
+            .decode_with(&raw, true, Invalid::Lossy)
+            .unwrap();
+        assert_eq!((text.as_str(), consumed), ("a\u{FFFD}b\u{FFFD}", 4));
+
+        let raw = [b'a', 0, 0x00, 0xDC, b'b', 0];
+        assert!(Encoding::Utf16le.decode(&raw, true).is_err());
+        let (text, _) = Encoding::Utf16le
+            .decode_with(&raw, true, Invalid::Lossy)
+            .unwrap();
+        assert_eq!(text, "a\u{FFFD}b");
+    }
+
+    #[test]
+    fn latin1() {
+        let raw = [b'G', b'r', 0xFC, 0xDF, b'e'];
+        let (text, consumed) = Encoding::Latin1.decode(&raw, true).unwrap();
+        assert_eq!(text, "Grüße");
+        assert_eq!(consumed, 5);
+        assert_eq!(Encoding::Latin1.encoded_len(&text), 5);
+    }
+}
@@ -0,0 +1,153 @@
+//! Module encapsules the follow mode, which keeps a file open and counts appended data, like
+//! `tail -f` does.
+
+use crate::{encoding, format, CcWcArgs, CcWcInput, Content, Counter, Result};
+use std::{
+    fs,
+    io::{Read, Seek, SeekFrom},
+    thread,
+    time::Duration,
+};
+
+/// Interval between two checks for appended data.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Incremental counter for a growing file.
+#[derive(Debug)]
+pub struct Follower {
+    /// The followed file, kept open.
+    file: fs::File,
+    /// Number of bytes read so far.
+    position: u64,
+    /// Raw bytes, which could not be decoded yet.
+    raw: Vec<u8>,
+    /// Decoded text behind the last line feed, which may still be continued.
+    pending: String,
+    /// Counted values of all text before `pending`.
+    committed: Vec<usize>,
+}
+
+impl Follower {
+    /// Opens the file, nothing has been counted yet.
+    pub fn new(file: &str, counters: &[Counter]) -> Result<Follower> {
+        Ok(Follower {
+            file: fs::File::open(file)?,
+            position: 0,
+            raw: Vec::new(),
+            pending: String::new(),
+            committed: vec![0; counters.len()],
+        })
+    }
+
+    /// Reads and counts all data, which has been appended since the last call. Returns whether
+    /// there was new data. A truncated file will be counted again from its beginning.
+    pub fn poll(&mut self, args: &CcWcArgs, counters: &[Counter]) -> Result<bool> {
+        if self.file.metadata()?.len() < self.position {
+            self.file.seek(SeekFrom::Start(0))?;
+            self.position = 0;
+            self.raw.clear();
+            self.pending.clear();
+            self.committed.iter_mut().for_each(|v| *v = 0);
+        }
+
+        let n = self.file.read_to_end(&mut self.raw)?;
+        if n == 0 {
+            return Ok(false);
+        }
+        self.position += n as u64;
+
+        let base = self.position - self.raw.len() as u64;
+        let (text, consumed) = args
+            .encoding
+            .decode_with(&self.raw, false, args.invalid)
+            .map_err(|err| encoding::shift_offset(err, base))?;
+        self.raw.drain(..consumed);
+        self.pending.push_str(&text);
+
+        if let Some(i) = self.pending.rfind('\n') {
+            let rest = self.pending.split_off(i + 1);
+            let complete = std::mem::replace(&mut self.pending, rest);
+            let values = count_text(args, counters, complete)?;
+            self.committed
+                .iter_mut()
+                .zip(values)
+                .for_each(|(c, v)| *c += v);
+        }
+        Ok(true)
+    }
+
+    /// Current values of all counters, including the unterminated last line.
+    pub fn values(&self, args: &CcWcArgs, counters: &[Counter]) -> Result<Vec<usize>> {
+        let pending = count_text(args, counters, self.pending.clone())?;
+        Ok(self
+            .committed
+            .iter()
+            .zip(pending)
+            .map(|(c, p)| c + p)
+            .collect())
+    }
+}
+
+fn count_text(args: &CcWcArgs, counters: &[Counter], text: String) -> Result<Vec<usize>> {
+    let mut content = Content::SmallFile(text, true);
+    crate::count(args, counters, &mut content)
+}
+
+/// Follows the single input file of `input` and reports the formatted counts initially and after
+/// every change. This function only returns in case of an error.
+pub fn follow(input: &CcWcInput, mut report: impl FnMut(&str)) -> Result<()> {
+    let args = &input.args;
+    let file = match (&input.content, args.files.as_slice()) {
+        (None, [file]) => file,
+        _ => return Err("follow mode requires exactly one input file".into()),
+    };
+
+    let counters = Counter::selected(args);
+    if counters.contains(&Counter::Unique) {
+        return Err("follow mode can not count distinct words".into());
+    }
+    let mut follower = Follower::new(file, &counters)?;
+    let mut changed = true;
+    follower.poll(args, &counters)?;
+    loop {
+        if changed {
+            let values = follower.values(args, &counters)?;
+            let rows = [format::Row::new(values, Some(file.clone()))];
+            report(&crate::format_output(args, &counters, &rows));
+        }
+        thread::sleep(POLL_INTERVAL);
+        changed = follower.poll(args, &counters)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn follow_appended_data() {
+        let fname = "test_follow.txt";
+        fs::write(fname, "a b\nc").expect("writing testfile failed");
+
+        let args = CcWcArgs::from("ccwc -lwc test_follow.txt");
+        let counters = Counter::selected(&args);
+        let mut follower = Follower::new(fname, &counters).unwrap();
+        assert!(follower.poll(&args, &counters).unwrap());
+        assert_eq!(follower.values(&args, &counters).unwrap(), vec![1, 3, 5]);
+        assert!(!follower.poll(&args, &counters).unwrap());
+
+        let mut file = fs::OpenOptions::new().append(true).open(fname).unwrap();
+        file.write_all(b"d e\n")
+            .expect("appending to testfile failed");
+        assert!(follower.poll(&args, &counters).unwrap());
+        assert_eq!(follower.values(&args, &counters).unwrap(), vec![2, 4, 9]);
+
+        // Truncation restarts counting.
+        fs::write(fname, "x\n").expect("writing testfile failed");
+        assert!(follower.poll(&args, &counters).unwrap());
+        assert_eq!(follower.values(&args, &counters).unwrap(), vec![1, 1, 2]);
+
+        fs::remove_file(fname).expect("removing testfile failed");
+    }
+}
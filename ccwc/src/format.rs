@@ -0,0 +1,573 @@
+//! Module encapsules the formatting of counting results for the command line output.
+
+use crate::{stats::TextStats, Counter};
+use clap::ValueEnum;
+use std::{borrow::Cow, str::FromStr};
+
+/// Name of the column, which contains the filename in table-like output formats.
+const FILE_COLUMN: &str = "file";
+/// Label of the row with the total counts of all inputs.
+const TOTAL_ROW: &str = "total";
+/// Minimum column width of GNU wc, if the size of an input is unknown, e.g. for stdin.
+pub const UNKNOWN_SIZE_WIDTH: usize = 7;
+
+/// Supported output formats of ccwc.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Right-aligned columns like the original wc.
+    #[default]
+    Plain,
+    /// Comma separated values with a header row.
+    Csv,
+    /// Tab separated values with a header row.
+    Tsv,
+}
+
+/// Controls when the row with the total counts of all inputs gets printed, like GNU wc's
+/// `--total` option.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Total {
+    /// Only if more than one file is given.
+    #[default]
+    Auto,
+    /// Always, even for a single input.
+    Always,
+    /// Only the total counts without a label, the rows of the individual inputs are suppressed.
+    Only,
+    /// Never.
+    Never,
+}
+
+/// One row of output: the counted values of one input and its optional filename.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    /// Counted values, in the same order as the header.
+    pub values: Vec<usize>,
+    /// Filename of the counted input, `None` in case of stdin.
+    pub file: Option<String>,
+}
+
+impl Row {
+    /// New type pattern: generates a new row from values and an optional filename.
+    pub fn new(values: Vec<usize>, file: Option<String>) -> Row {
+        Row { values, file }
+    }
+}
+
+/// One part of an output template.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    /// Literal text.
+    Text(String),
+    /// Placeholder for the value of a counter.
+    Value(Counter),
+    /// Placeholder for the filename.
+    File,
+}
+
+/// Custom output template like `{lines}\t{words}\t{file}`, rendered once per row. Placeholders
+/// are the counter names and `file`, literal braces are written as `{{` and `}}`. The escape
+/// sequences `\t`, `\n` and `\\` are supported as well.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    /// Parsed parts of the template.
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    /// All counters used by the template, in order of their first appearance.
+    pub fn counters(&self) -> Vec<Counter> {
+        let mut counters = Vec::new();
+        for segment in &self.segments {
+            if let Segment::Value(counter) = segment {
+                if !counters.contains(counter) {
+                    counters.push(*counter);
+                }
+            }
+        }
+        counters
+    }
+
+    /// Renders one row, whose values belong to the given counters.
+    pub fn render(&self, counters: &[Counter], row: &Row) -> String {
+        let mut line = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => line.push_str(text),
+                Segment::Value(counter) => {
+                    let value = counters
+                        .iter()
+                        .zip(&row.values)
+                        .find_map(|(c, v)| (c == counter).then_some(*v))
+                        .unwrap_or_default();
+                    line.push_str(&value.to_string());
+                }
+                Segment::File => line.push_str(row.file.as_deref().unwrap_or_default()),
+            }
+        }
+        line
+    }
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(template: &str) -> Result<Template, String> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.next_if_eq(&'{').is_some() => text.push('{'),
+                '}' if chars.next_if_eq(&'}').is_some() => text.push('}'),
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(format!("unterminated placeholder `{{{name}`")),
+                        }
+                    }
+                    let segment = match name.as_str() {
+                        FILE_COLUMN => Segment::File,
+                        _ => match Counter::from_name(&name) {
+                            Some(counter) => Segment::Value(counter),
+                            None => return Err(format!("unknown placeholder `{{{name}}}`")),
+                        },
+                    };
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(segment);
+                }
+                '}' => return Err(String::from("unmatched `}`, use `}}` for a literal brace")),
+                '\\' => match chars.next_if(|c| ['t', 'n', '\\'].contains(c)) {
+                    Some('t') => text.push('\t'),
+                    Some('n') => text.push('\n'),
+                    _ => text.push('\\'),
+                },
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Ok(Template { segments })
+    }
+}
+
+/// Adds the row with the total counts of all rows, as requested by `total`. `columns` is the number
+/// of values per row and `files` the number of input files. Returns whether the row has been
+/// added, it is the last row then.
+pub fn apply_total(total: Total, columns: usize, files: usize, rows: &mut Vec<Row>) -> bool {
+    let mut values = vec![0; columns];
+    for row in rows.iter() {
+        values
+            .iter_mut()
+            .zip(&row.values)
+            .for_each(|(t, v)| *t += v);
+    }
+    match total {
+        Total::Auto if files > 1 => rows.push(Row::new(values, Some(TOTAL_ROW.to_string()))),
+        Total::Always => rows.push(Row::new(values, Some(TOTAL_ROW.to_string()))),
+        Total::Only => *rows = vec![Row::new(values, None)],
+        Total::Auto | Total::Never => return false,
+    }
+    true
+}
+
+/// Default column width like GNU wc: a single value of a single input is not padded at all,
+/// otherwise the columns are as wide as the total size of all inputs in bytes. Inputs of unknown
+/// size, given as `None`, require a width of at least `UNKNOWN_SIZE_WIDTH`.
+pub fn default_width(counters: usize, sizes: &[Option<u64>]) -> usize {
+    if counters == 1 && sizes.len() == 1 {
+        return 1;
+    }
+    let total: u64 = sizes.iter().flatten().sum();
+    let width = total.to_string().len();
+    if sizes.contains(&None) {
+        width.max(UNKNOWN_SIZE_WIDTH)
+    } else {
+        width
+    }
+}
+
+/// Formats all rows in the given output format. The header contains the column names of the
+/// values and is only used by table-like formats. Columns of the plain format are right-aligned
+/// to at least `width` characters.
+pub fn format_rows(format: OutputFormat, header: &[&str], rows: &[Row], width: usize) -> String {
+    match format {
+        OutputFormat::Plain => format_plain(rows, width),
+        OutputFormat::Csv => format_separated(',', csv_field, header, rows),
+        OutputFormat::Tsv => format_separated('\t', tsv_field, header, rows),
+    }
+}
+
+/// Formats a list of words and their number of occurrences in the given output format.
+pub fn format_frequencies(format: OutputFormat, entries: &[(&str, usize)]) -> String {
+    match format {
+        OutputFormat::Plain => {
+            let digits = entries
+                .iter()
+                .map(|(_, cnt)| cnt.to_string().len())
+                .max()
+                .unwrap_or(1);
+            entries
+                .iter()
+                .map(|(word, cnt)| format!("{cnt:>digits$} {word}"))
+                .collect::<Vec<String>>()
+                .join("\n")
+        }
+        OutputFormat::Csv => format_word_table(',', csv_field, entries),
+        OutputFormat::Tsv => format_word_table('\t', tsv_field, entries),
+    }
+}
+
+/// Formats text statistics in the given output format.
+pub fn format_stats(format: OutputFormat, stats: &TextStats) -> String {
+    let mut entries = vec![
+        ("lines", stats.lines.to_string()),
+        ("words", stats.words.to_string()),
+        (
+            "average word length",
+            format!("{:.2}", stats.average_word_length()),
+        ),
+        (
+            "average line length",
+            format!("{:.2}", stats.average_line_length()),
+        ),
+        ("longest line", optional(stats.longest_line)),
+        ("shortest line", optional(stats.shortest_line)),
+    ];
+    let distribution: Vec<(String, String)> = stats
+        .words_per_line
+        .iter()
+        .map(|(words, lines)| (format!("lines with {words} words"), lines.to_string()))
+        .collect();
+    entries.extend(distribution.iter().map(|(k, v)| (k.as_str(), v.clone())));
+
+    match format {
+        OutputFormat::Plain => {
+            let width = entries.iter().map(|(k, _)| k.len()).max().unwrap_or(0) + 1;
+            entries
+                .iter()
+                .map(|(k, v)| format!("{:<width$} {v}", format!("{k}:")))
+                .collect::<Vec<String>>()
+                .join("\n")
+        }
+        OutputFormat::Csv => format_stat_table(',', csv_field, &entries),
+        OutputFormat::Tsv => format_stat_table('\t', tsv_field, &entries),
+    }
+}
+
+fn optional(value: Option<usize>) -> String {
+    value.map_or(String::from("-"), |v| v.to_string())
+}
+
+/// Header row and one row per statistic, fields separated by `sep` and escaped by `escape`.
+fn format_stat_table(
+    sep: char,
+    escape: fn(&str) -> Cow<'_, str>,
+    entries: &[(&str, String)],
+) -> String {
+    let mut lines = vec![format!("statistic{sep}value")];
+    for (name, value) in entries {
+        lines.push(format!("{}{sep}{value}", escape(name)));
+    }
+    lines.join("\n")
+}
+
+/// Header row and one row per word, fields separated by `sep` and escaped by `escape`.
+fn format_word_table(
+    sep: char,
+    escape: fn(&str) -> Cow<'_, str>,
+    entries: &[(&str, usize)],
+) -> String {
+    let mut lines = vec![format!("word{sep}count")];
+    for (word, cnt) in entries {
+        lines.push(format!("{}{sep}{cnt}", escape(word)));
+    }
+    lines.join("\n")
+}
+
+/// Right-aligned columns of at least `width` characters, separated by a single space.
+fn format_plain(rows: &[Row], width: usize) -> String {
+    let cells = rows
+        .iter()
+        .map(|row| row.values.iter().map(|v| v.to_string()).collect())
+        .collect();
+    format_aligned(rows, cells, width)
+}
+
+/// Plain output for humans: byte counts in binary units and all other values with thousands
+/// separators. Columns are right-aligned to the widest value, but at least `width` characters.
+pub fn format_human(counters: &[Counter], rows: &[Row], width: usize) -> String {
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            counters
+                .iter()
+                .zip(&row.values)
+                .map(|(counter, value)| match counter {
+                    Counter::Bytes => human_bytes(*value),
+                    _ => thousands(*value),
+                })
+                .collect()
+        })
+        .collect();
+    let width = cells
+        .iter()
+        .flatten()
+        .map(|cell| cell.len())
+        .max()
+        .unwrap_or_default()
+        .max(width);
+    format_aligned(rows, cells, width)
+}
+
+/// Number of bytes in binary units, e.g. `334.2 KiB`. Less than 1 KiB is printed exactly.
+pub fn human_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+/// Value with a comma between every group of three digits, e.g. `58,164`.
+pub fn thousands(value: usize) -> String {
+    let digits = value.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Rows of already formatted values, right-aligned to at least `width` characters.
+fn format_aligned(rows: &[Row], cells: Vec<Vec<String>>, width: usize) -> String {
+    let lines: Vec<String> = rows
+        .iter()
+        .zip(cells)
+        .map(|(row, cells)| {
+            let mut line = cells
+                .iter()
+                .map(|cell| format!("{cell:>width$}"))
+                .collect::<Vec<String>>()
+                .join(" ");
+            if let Some(file) = &row.file {
+                line.push(' ');
+                line.push_str(file);
+            }
+            line
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// One line per row with the values separated by single spaces, without any filename.
+pub fn format_porcelain(rows: &[Row]) -> String {
+    rows.iter()
+        .map(|row| {
+            row.values
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<String>>()
+                .join(" ")
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Header row and one row per input, fields separated by `sep` and escaped by `escape`.
+fn format_separated(
+    sep: char,
+    escape: fn(&str) -> Cow<'_, str>,
+    header: &[&str],
+    rows: &[Row],
+) -> String {
+    let sep = sep.to_string();
+    let mut lines = Vec::<String>::with_capacity(rows.len() + 1);
+
+    let mut names: Vec<&str> = header.to_vec();
+    names.push(FILE_COLUMN);
+    lines.push(names.join(&sep));
+
+    for row in rows {
+        let mut fields: Vec<String> = row.values.iter().map(|v| v.to_string()).collect();
+        fields.push(escape(row.file.as_deref().unwrap_or_default()).into_owned());
+        lines.push(fields.join(&sep));
+    }
+    lines.join("\n")
+}
+
+/// Quotes a CSV field according to RFC 4180, if necessary.
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// TSV does not support quoting, tabs and line breaks are escaped instead.
+fn tsv_field(field: &str) -> Cow<'_, str> {
+    if field.contains(['\t', '\n', '\r', '\\']) {
+        Cow::Owned(
+            field
+                .replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r"),
+        )
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows() -> Vec<Row> {
+        vec![
+            Row::new(vec![12, 345], Some(String::from("a.txt"))),
+            Row::new(vec![6, 78], None),
+        ]
+    }
+
+    #[test]
+    fn plain_alignment() {
+        let output = format_rows(OutputFormat::Plain, &["lines", "words"], &rows(), 3);
+        assert_eq!(output, " 12 345 a.txt\n  6  78");
+        let output = format_rows(OutputFormat::Plain, &["lines", "words"], &rows(), 1);
+        assert_eq!(output, "12 345 a.txt\n6 78");
+    }
+
+    #[test]
+    fn human_units() {
+        assert_eq!(human_bytes(512), "512 B");
+        assert_eq!(human_bytes(342190), "334.2 KiB");
+        assert_eq!(human_bytes(5 << 30), "5.0 GiB");
+        assert_eq!(thousands(999), "999");
+        assert_eq!(thousands(58164), "58,164");
+        assert_eq!(thousands(1234567), "1,234,567");
+
+        let rows = [Row::new(vec![7145, 342190], Some(String::from("test.txt")))];
+        let output = format_human(&[Counter::Lines, Counter::Bytes], &rows, 1);
+        assert_eq!(output, "    7,145 334.2 KiB test.txt");
+    }
+
+    #[test]
+    fn porcelain_rows() {
+        assert_eq!(format_porcelain(&rows()), "12 345\n6 78");
+    }
+
+    #[test]
+    fn gnu_widths() {
+        assert_eq!(default_width(1, &[Some(342190)]), 1);
+        assert_eq!(default_width(1, &[None]), 1);
+        assert_eq!(default_width(3, &[Some(6)]), 1);
+        assert_eq!(default_width(3, &[None]), 7);
+        assert_eq!(default_width(1, &[Some(6), Some(342190)]), 6);
+        assert_eq!(default_width(2, &[Some(6), None]), 7);
+    }
+
+    #[test]
+    fn total_row() {
+        let mut auto = rows();
+        assert!(!apply_total(Total::Auto, 2, 1, &mut auto));
+        assert_eq!(auto, rows());
+        assert!(apply_total(Total::Auto, 2, 2, &mut auto));
+        assert_eq!(
+            auto.last(),
+            Some(&Row::new(vec![18, 423], Some(String::from("total"))))
+        );
+
+        let mut always = rows();
+        apply_total(Total::Always, 2, 1, &mut always);
+        assert_eq!(always.len(), 3);
+
+        let mut only = rows();
+        apply_total(Total::Only, 2, 2, &mut only);
+        assert_eq!(only, vec![Row::new(vec![18, 423], None)]);
+
+        let mut never = rows();
+        apply_total(Total::Never, 2, 2, &mut never);
+        assert_eq!(never, rows());
+    }
+
+    #[test]
+    fn template() {
+        let template: Template = "{words}\\t{lines} {{{file}}}".parse().unwrap();
+        assert_eq!(template.counters(), vec![Counter::Words, Counter::Lines]);
+        let output = template.render(&[Counter::Lines, Counter::Words], &rows()[0]);
+        assert_eq!(output, "345\t12 {a.txt}");
+        let output = template.render(&[Counter::Lines, Counter::Words], &rows()[1]);
+        assert_eq!(output, "78\t6 {}");
+
+        assert!("{lines} {unknown}".parse::<Template>().is_err());
+        assert!("{lines".parse::<Template>().is_err());
+        assert!("lines}".parse::<Template>().is_err());
+    }
+
+    #[test]
+    fn csv_header_and_rows() {
+        let output = format_rows(OutputFormat::Csv, &["lines", "words"], &rows(), 1);
+        assert_eq!(output, "lines,words,file\n12,345,a.txt\n6,78,");
+    }
+
+    #[test]
+    fn tsv_header_and_rows() {
+        let output = format_rows(OutputFormat::Tsv, &["lines", "words"], &rows(), 1);
+        assert_eq!(output, "lines\twords\tfile\n12\t345\ta.txt\n6\t78\t");
+    }
+
+    #[test]
+    fn frequencies() {
+        let entries = [("the", 120), ("a,b", 7)];
+        let output = format_frequencies(OutputFormat::Plain, &entries);
+        assert_eq!(output, "120 the\n  7 a,b");
+        let output = format_frequencies(OutputFormat::Csv, &entries);
+        assert_eq!(output, "word,count\nthe,120\n\"a,b\",7");
+    }
+
+    #[test]
+    fn stats() {
+        let mut stats = TextStats::new(crate::Newline::Lf);
+        stats.add("a bb\nccc\n");
+        stats.finish();
+        let output = format_stats(OutputFormat::Plain, &stats);
+        assert_eq!(
+            output,
+            "lines:               2\n\
+             words:               3\n\
+             average word length: 2.00\n\
+             average line length: 3.50\n\
+             longest line:        4\n\
+             shortest line:       3\n\
+             lines with 1 words:  1\n\
+             lines with 2 words:  1"
+        );
+        let output = format_stats(OutputFormat::Csv, &stats);
+        assert!(output.starts_with("statistic,value\nlines,2\nwords,3\n"));
+    }
+
+    #[test]
+    fn field_escaping() {
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("plain.txt"), "plain.txt");
+        assert_eq!(tsv_field("a\tb"), "a\\tb");
+    }
+}
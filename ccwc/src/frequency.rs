@@ -0,0 +1,106 @@
+//! Module encapsules the analysis of word frequencies in a text.
+
+use crate::{command::Content, iterators, normalize::WordForm};
+use std::collections::HashMap;
+
+/// Frequency of every distinct word of a text, collected in one streaming pass.
+#[derive(Clone, Debug, Default)]
+pub struct WordFrequency {
+    /// Definition of when two words are equal.
+    form: WordForm,
+    /// Number of occurrences per word.
+    map: HashMap<String, usize>,
+}
+
+impl WordFrequency {
+    /// New type pattern: generates a new and empty frequency table.
+    pub fn new(form: WordForm) -> WordFrequency {
+        WordFrequency {
+            form,
+            map: HashMap::new(),
+        }
+    }
+
+    /// Counts all words of the content within a single pass.
+    pub fn from_content(content: &mut Content, form: WordForm) -> crate::Result<WordFrequency> {
+        let mut freq = WordFrequency::new(form);
+        freq.add_content(content)?;
+        Ok(freq)
+    }
+
+    /// Adds all words of the content within a single pass.
+    pub fn add_content(&mut self, content: &mut Content) -> crate::Result<()> {
+        content.rewind()?;
+        for piece in &mut *content {
+            self.add(&piece);
+        }
+        match content.take_error() {
+            Some(err) => Err(err.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds all words of the given piece of text.
+    pub fn add(&mut self, piece: &str) {
+        for word in iterators::words(piece) {
+            let key = self.form.key(word);
+            if let Some(cnt) = self.map.get_mut(key.as_ref()) {
+                *cnt += 1;
+            } else {
+                self.map.insert(key.into_owned(), 1);
+            }
+        }
+    }
+
+    /// Number of distinct words.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Checks whether no word has been counted so far.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the n most frequent words, ordered by descending count. Words with equal counts
+    /// are ordered alphabetically to get a stable output.
+    pub fn top(&self, n: usize) -> Vec<(&str, usize)> {
+        let mut entries: Vec<(&str, usize)> =
+            self.map.iter().map(|(w, c)| (w.as_str(), *c)).collect();
+        entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        entries.truncate(n);
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalize::Normalization;
+
+    const TEXT: &str = "the cat and The dog and the bird\n";
+
+    #[test]
+    fn top_words() {
+        let mut freq = WordFrequency::new(WordForm::default());
+        freq.add(TEXT);
+        assert_eq!(freq.len(), 6);
+        assert_eq!(freq.top(2), vec![("and", 2), ("the", 2)]);
+    }
+
+    #[test]
+    fn top_words_fold_case() {
+        let mut content = Content::SmallFile(TEXT.to_string(), true);
+        let form = WordForm::new(true, Normalization::None);
+        let freq = WordFrequency::from_content(&mut content, form).unwrap();
+        assert_eq!(freq.len(), 5);
+        assert_eq!(freq.top(2), vec![("the", 3), ("and", 2)]);
+    }
+
+    #[test]
+    fn top_words_normalized() {
+        let mut freq = WordFrequency::new(WordForm::new(true, Normalization::Nfc));
+        freq.add("Cafe\u{301} caf\u{e9} CAF\u{c9}");
+        assert_eq!(freq.top(2), vec![("caf\u{e9}", 3)]);
+    }
+}
@@ -0,0 +1,87 @@
+//! Module encapsules the transparent decompression of gzip-compressed input files.
+
+use flate2::read::MultiGzDecoder;
+use std::{
+    fs,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+};
+
+/// File extension of gzip-compressed files.
+pub const GZIP_EXTENSION: &str = ".gz";
+
+/// Checks whether a file is gzip-compressed, based on its extension.
+pub fn is_gzip(file: &str) -> bool {
+    file.ends_with(GZIP_EXTENSION)
+}
+
+/// Reader, which decompresses a gzip file on the fly. Seeking is only supported to the
+/// beginning of the file, which restarts the decompression.
+pub struct GzipFile {
+    /// Decoder, which also handles multiple concatenated gzip members.
+    decoder: MultiGzDecoder<BufReader<fs::File>>,
+}
+
+impl GzipFile {
+    /// Opens a gzip-compressed file.
+    pub fn open(file: &str) -> io::Result<GzipFile> {
+        let file = fs::File::open(file)?;
+        let decoder = MultiGzDecoder::new(BufReader::new(file));
+        Ok(GzipFile { decoder })
+    }
+}
+
+impl std::fmt::Debug for GzipFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GzipFile").finish_non_exhaustive()
+    }
+}
+
+impl Read for GzipFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.decoder.read(buf)
+    }
+}
+
+impl Seek for GzipFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        if pos != SeekFrom::Start(0) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "gzip input can only be rewound to its beginning",
+            ));
+        }
+        let mut file = self.decoder.get_ref().get_ref().try_clone()?;
+        file.rewind()?;
+        self.decoder = MultiGzDecoder::new(BufReader::new(file));
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    #[test]
+    fn read_and_rewind() {
+        let fname = "test_gzip.txt.gz";
+        let mut encoder = GzEncoder::new(fs::File::create(fname).unwrap(), Compression::fast());
+        encoder.write_all(b"compressed text\n").unwrap();
+        encoder.finish().unwrap();
+        assert!(is_gzip(fname));
+
+        let mut gzip = GzipFile::open(fname).unwrap();
+        let mut text = String::new();
+        gzip.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "compressed text\n");
+
+        gzip.rewind().unwrap();
+        text.clear();
+        gzip.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "compressed text\n");
+        assert!(gzip.seek(SeekFrom::Start(3)).is_err());
+
+        fs::remove_file(fname).expect("removing testfile failed");
+    }
+}
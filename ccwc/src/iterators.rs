@@ -1,52 +1,345 @@
 //! Module encapsules individual iterator implementations.
 
-/// Iterator for extracting words out of a text properly.
+use crate::encoding::{self, Encoding, Invalid};
+use clap::ValueEnum;
+use std::{
+    io::{self, Read, Seek},
+    ops::Range,
+};
+
+/// Default number of bytes, the `ChunkIterator` reads at once.
+pub const CHUNK_SIZE: usize = 1 << 20;
+
+/// Definition of what terminates a line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Newline {
+    /// Line feed `\n`, like GNU wc.
+    #[default]
+    Lf,
+    /// Carriage return followed by line feed `\r\n`.
+    Crlf,
+    /// Carriage return `\r`.
+    Cr,
+    /// Any of `\r\n`, `\n` or `\r`.
+    Any,
+    /// NUL character `\0`, like `wc -z` for `find -print0` output.
+    Nul,
+}
+
+impl Newline {
+    /// Length of the terminator at the end of the line, 0 if the line is not terminated.
+    pub fn terminator_len(&self, line: &str) -> usize {
+        match self {
+            Newline::Lf => line.ends_with('\n') as usize,
+            Newline::Crlf => 2 * line.ends_with("\r\n") as usize,
+            Newline::Cr => line.ends_with('\r') as usize,
+            Newline::Any if line.ends_with("\r\n") => 2,
+            Newline::Any => line.ends_with(['\n', '\r']) as usize,
+            Newline::Nul => line.ends_with('\0') as usize,
+        }
+    }
+
+    /// Whether the character separates words: any whitespace and the terminator of lines, e.g.
+    /// the NUL character of `--zero-terminated` input.
+    pub fn separates_words(&self, c: char) -> bool {
+        c.is_whitespace() || (*self == Newline::Nul && c == '\0')
+    }
+
+    /// Position behind the first terminator in the text, if there is one.
+    fn find_end(&self, text: &str) -> Option<usize> {
+        match self {
+            Newline::Lf => text.find('\n').map(|i| i + 1),
+            Newline::Crlf => text.find("\r\n").map(|i| i + 2),
+            Newline::Cr => text.find('\r').map(|i| i + 1),
+            Newline::Any => text.find(['\n', '\r']).map(|i| {
+                if text[i..].starts_with("\r\n") {
+                    i + 2
+                } else {
+                    i + 1
+                }
+            }),
+            Newline::Nul => text.find('\0').map(|i| i + 1),
+        }
+    }
+}
+
+/// Iterator for extracting words out of any iterator over characters and their byte positions,
+/// like `str::char_indices()`. It yields the byte range of every word, so no string has to be
+/// materialized, e.g. when iterating decoded or chained chunks.
 #[derive(Clone, Debug)]
-pub struct WordIterator<'r> {
+pub struct WordIterator<I> {
+    /// Internal iterator.
+    chars: I,
+    /// Definition of line terminators, which separate words as well.
+    newline: Newline,
+}
+
+impl<I: Iterator<Item = (usize, char)>> WordIterator<I> {
+    pub fn new(chars: I) -> WordIterator<I> {
+        WordIterator::with_newline(chars, Newline::default())
+    }
+
+    /// New type pattern: generates a new iterator, whose words are also separated by the line
+    /// terminators of `newline`.
+    pub fn with_newline(chars: I, newline: Newline) -> WordIterator<I> {
+        WordIterator { chars, newline }
+    }
+}
+
+impl<I: Iterator<Item = (usize, char)>> Iterator for WordIterator<I> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Range<usize>> {
+        // Step 1: Search for next beginning word.
+        let newline = self.newline;
+        let (start, first) = self.chars.find(|(_, c)| !newline.separates_words(*c))?;
+
+        // Step 2: Search for end of this word, which is behind the last character for the last
+        // word.
+        let mut end = start + first.len_utf8();
+        for (i, c) in self.chars.by_ref() {
+            if newline.separates_words(c) {
+                return Some(start..i);
+            }
+            end = i + c.len_utf8();
+        }
+        Some(start..end)
+    }
+}
+
+/// Iterator for extracting words together with their byte positions out of a text. It yields
+/// `(start, end, word)`, where `word == &text[start..end]`.
+#[derive(Clone, Debug)]
+pub struct WordSpanIterator<'r> {
     /// Source text to be iterated.
     text: &'r str,
     /// Internal iterator.
-    iter: std::str::CharIndices<'r>,
+    words: WordIterator<std::str::CharIndices<'r>>,
+}
+
+impl<'r> WordSpanIterator<'r> {
+    pub fn new(text: &'r str) -> WordSpanIterator<'r> {
+        WordSpanIterator::with_newline(text, Newline::default())
+    }
+
+    /// New type pattern: generates a new iterator, whose words are also separated by the line
+    /// terminators of `newline`.
+    pub fn with_newline(text: &'r str, newline: Newline) -> WordSpanIterator<'r> {
+        let words = WordIterator::with_newline(text.char_indices(), newline);
+        WordSpanIterator { text, words }
+    }
 }
 
-impl<'r> WordIterator<'r> {
-    pub fn new(text: &'r str) -> WordIterator<'r> {
-        let iter = text.char_indices();
-        WordIterator { text, iter }
+impl<'r> Iterator for WordSpanIterator<'r> {
+    type Item = (usize, usize, &'r str);
+
+    fn next(&mut self) -> Option<(usize, usize, &'r str)> {
+        let range = self.words.next()?;
+        Some((range.start, range.end, &self.text[range]))
     }
 }
 
-impl<'r> Iterator for WordIterator<'r> {
+/// All words of the text.
+pub fn words(text: &str) -> impl Iterator<Item = &str> {
+    words_with(text, Newline::default())
+}
+
+/// All words of the text, which are also separated by the line terminators of `newline`.
+pub fn words_with(text: &str, newline: Newline) -> impl Iterator<Item = &str> {
+    WordSpanIterator::with_newline(text, newline).map(|(_, _, word)| word)
+}
+
+/// Iterator for extracting lines out of a text, with their terminators preserved. A final line
+/// without terminator is yielded as well.
+#[derive(Clone, Debug)]
+pub struct LineIterator<'r> {
+    /// Source text to be iterated.
+    text: &'r str,
+    /// Definition of line terminators.
+    newline: Newline,
+}
+
+impl<'r> LineIterator<'r> {
+    pub fn new(text: &'r str, newline: Newline) -> LineIterator<'r> {
+        LineIterator { text, newline }
+    }
+}
+
+impl<'r> Iterator for LineIterator<'r> {
     type Item = &'r str;
 
     fn next(&mut self) -> Option<&'r str> {
-        let mut set = false;
-        let mut start = 0;
+        if self.text.is_empty() {
+            return None;
+        }
+        let end = self.newline.find_end(self.text).unwrap_or(self.text.len());
+        let (line, rest) = self.text.split_at(end);
+        self.text = rest;
+        Some(line)
+    }
+}
 
-        // Step 1: Search for next beginning word.
-        for (i, c) in self.iter.by_ref() {
-            // if c.is_alphanumeric() {
-            if !c.is_whitespace() {
-                start = i;
-                set = true;
-                break;
-            }
+/// Iterator for reading a text piece by piece out of a reader, decoded by the given encoding.
+/// Every piece ends behind a line break (or at least behind a whitespace), so that lines and words
+/// are not split between two pieces.
+pub struct ChunkIterator<R> {
+    /// Source of the raw data.
+    reader: R,
+    /// Encoding of the raw data.
+    encoding: Encoding,
+    /// Handling of invalid raw data.
+    invalid: Invalid,
+    /// Minimum number of bytes of one piece.
+    chunk_size: usize,
+    /// Raw bytes, which could not be decoded yet.
+    raw: Vec<u8>,
+    /// Decoded text, which has not been returned yet.
+    text: String,
+    /// Number of raw bytes read so far.
+    consumed: u64,
+    /// Reader reached its end.
+    eof: bool,
+    /// Error, which stopped the iteration.
+    error: Option<io::Error>,
+}
+
+impl<R: Read> ChunkIterator<R> {
+    pub fn new(reader: R, encoding: Encoding) -> ChunkIterator<R> {
+        ChunkIterator::with_chunk_size(reader, encoding, CHUNK_SIZE)
+    }
+
+    pub fn with_chunk_size(reader: R, encoding: Encoding, chunk_size: usize) -> ChunkIterator<R> {
+        ChunkIterator {
+            reader,
+            encoding,
+            invalid: Invalid::Strict,
+            chunk_size,
+            raw: Vec::new(),
+            text: String::new(),
+            consumed: 0,
+            eof: false,
+            error: None,
         }
-        if !set {
-            return None;
+    }
+
+    /// Sets the handling of invalid raw data, which is strict by default.
+    pub fn with_invalid(mut self, invalid: Invalid) -> ChunkIterator<R> {
+        self.invalid = invalid;
+        self
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Number of raw bytes, which have been read from the reader so far.
+    pub fn consumed(&self) -> u64 {
+        self.consumed
+    }
+
+    /// Returns the error, which stopped the iteration, if there was one.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    /// Reads and decodes data until `len` bytes of text are available or the reader is exhausted.
+    fn fill(&mut self, len: usize) -> io::Result<()> {
+        while !self.eof && self.text.len() < len {
+            let len = self.raw.len();
+            self.raw.resize(len + self.chunk_size, 0);
+            let n = match self.reader.read(&mut self.raw[len..]) {
+                Ok(n) => n,
+                Err(err) => {
+                    self.raw.truncate(len);
+                    if err.kind() == io::ErrorKind::Interrupted {
+                        continue;
+                    }
+                    return Err(err);
+                }
+            };
+            self.raw.truncate(len + n);
+            self.consumed += n as u64;
+            self.eof = n == 0;
+
+            let base = self.consumed - self.raw.len() as u64;
+            let (text, consumed) = self
+                .encoding
+                .decode_with(&self.raw, self.eof, self.invalid)
+                .map_err(|err| encoding::shift_offset(err, base))?;
+            self.text.push_str(&text);
+            self.raw.drain(..consumed);
         }
+        Ok(())
+    }
+}
 
-        // Step 2: Search for end of this word.
-        let mut stop = start;
-        for (i, c) in self.iter.by_ref() {
-            stop = i;
-            // if !(c.is_alphanumeric() || c == '-' || c == '.') {
-            if c.is_whitespace() {
-                break;
+impl<R> std::fmt::Debug for ChunkIterator<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChunkIterator")
+            .field("encoding", &self.encoding)
+            .field("chunk_size", &self.chunk_size)
+            .field("consumed", &self.consumed)
+            .field("eof", &self.eof)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<R: Read + Seek> ChunkIterator<R> {
+    /// Restarts the iteration at the beginning of the reader.
+    pub fn rewind(&mut self) -> io::Result<()> {
+        self.reader.rewind()?;
+        self.raw.clear();
+        self.text.clear();
+        self.consumed = 0;
+        self.eof = false;
+        self.error = None;
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for ChunkIterator<R> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        // A chunk without any split position would break a word, so more is read until there is
+        // one, like a partial line is kept until its terminator arrives.
+        let mut len = self.chunk_size;
+        let split = loop {
+            if let Err(err) = self.fill(len) {
+                self.error = Some(err);
+                self.eof = true;
+                self.raw.clear();
+                self.text.clear();
+                return None;
             }
+            if self.eof {
+                break self.text.len();
+            }
+            match split_position(&self.text) {
+                Some(split) => break split,
+                None => len = self.text.len() + self.chunk_size,
+            }
+        };
+        if self.text.is_empty() {
+            return None;
         }
+        let rest = self.text.split_off(split);
+        Some(std::mem::replace(&mut self.text, rest))
+    }
+}
 
-        Some(&self.text[start..stop])
+/// Position behind the last line break or NUL character, or the last whitespace if there is none
+/// of them. A carriage return is not used as split position, since it could be followed by a line
+/// feed.
+pub(crate) fn split_position(text: &str) -> Option<usize> {
+    if let Some(i) = text.rfind(['\n', '\0']) {
+        Some(i + 1)
+    } else {
+        text.char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace() && *c != '\r')
+            .map(|(i, c)| i + c.len_utf8())
     }
 }
 
@@ -57,14 +350,112 @@ mod tests {
     #[test]
     fn worditer_simple_line() {
         let text: &str = "This is a simple, single line of text.";
-        let iter = WordIterator::new(text);
+        let iter = WordIterator::new(text.char_indices());
         assert_eq!(iter.count(), 8);
     }
 
     #[test]
     fn worditer_special_characters() {
         let text: &str = "\u{feff}This is a simple,\nvery simple\t line of text.";
-        let iter = WordIterator::new(text);
+        let iter = WordIterator::new(text.char_indices());
         assert_eq!(iter.count(), 9);
     }
+
+    #[test]
+    fn worditer_last_word() {
+        let text: &str = "This is a line of text.";
+        assert_eq!(words(text).last(), Some("text."));
+        assert_eq!(words("a b").last(), Some("b"));
+        assert_eq!(
+            WordIterator::new("a b\u{e4}".char_indices()).last(),
+            Some(2..5)
+        );
+    }
+
+    #[test]
+    fn worditer_zero_terminated() {
+        let text = "x\0y\0";
+        assert_eq!(words(text).collect::<Vec<_>>(), vec!["x\0y\0"]);
+        assert_eq!(
+            words_with(text, Newline::Nul).collect::<Vec<_>>(),
+            vec!["x", "y"]
+        );
+        assert_eq!(crate::count_ascii_words(text, Newline::Nul), 2);
+    }
+
+    #[test]
+    fn worditer_chained_chunks() {
+        // Chunks, whose positions continue each other, e.g. decoded pieces of a stream.
+        let second = "d e\u{a0}".char_indices().map(|(i, c)| (i + 4, c));
+        let chars = "ab c".char_indices().chain(second);
+        let ranges: Vec<Range<usize>> = WordIterator::new(chars).collect();
+        assert_eq!(ranges, vec![0..2, 3..5, 6..7]);
+    }
+
+    #[test]
+    fn wordspaniter_positions() {
+        let text: &str = "  ab c\tdäf";
+        let spans: Vec<(usize, usize, &str)> = WordSpanIterator::new(text).collect();
+        assert_eq!(spans, vec![(2, 4, "ab"), (5, 6, "c"), (7, 11, "däf")]);
+        assert!(spans.iter().all(|(s, e, w)| &text[*s..*e] == *w));
+    }
+
+    #[test]
+    fn lineiter_terminators() {
+        let text: &str = "first\r\nsecond\n\nlast";
+        let lines: Vec<&str> = LineIterator::new(text, Newline::Lf).collect();
+        assert_eq!(lines, vec!["first\r\n", "second\n", "\n", "last"]);
+        let lines: Vec<&str> = LineIterator::new(text, Newline::Crlf).collect();
+        assert_eq!(lines, vec!["first\r\n", "second\n\nlast"]);
+
+        let text: &str = "a\rb\r\nc\n";
+        let lines: Vec<&str> = LineIterator::new(text, Newline::Any).collect();
+        assert_eq!(lines, vec!["a\r", "b\r\n", "c\n"]);
+        let terminators: Vec<usize> = lines
+            .iter()
+            .map(|l| Newline::Any.terminator_len(l))
+            .collect();
+        assert_eq!(terminators, vec![1, 2, 1]);
+    }
+
+    #[test]
+    fn chunkiter_line_aligned() {
+        let text = "first line\nsecond line\nthird line without break";
+        let mut iter = ChunkIterator::with_chunk_size(io::Cursor::new(text), Encoding::Utf8, 8);
+        let pieces: Vec<String> = iter.by_ref().collect();
+        assert_eq!(iter.consumed(), text.len() as u64);
+        assert_eq!(pieces.concat(), text);
+        assert_eq!(pieces[0], "first ");
+        assert_eq!(pieces[1], "line\n");
+        assert!(pieces
+            .iter()
+            .rev()
+            .skip(1)
+            .all(|p| p.ends_with(char::is_whitespace)));
+    }
+
+    #[test]
+    fn chunkiter_long_words() {
+        // Words longer than a chunk are kept whole and grapheme clusters are not broken.
+        let text = "abcdefghijklmnop qrstuvwxyz e\u{301}e\u{301}e\u{301}e\u{301}e\u{301} end";
+        let iter = ChunkIterator::with_chunk_size(io::Cursor::new(text), Encoding::Utf8, 4);
+        let pieces: Vec<String> = iter.collect();
+        assert_eq!(pieces.concat(), text);
+        assert_eq!(pieces[0], "abcdefghijklmnop ");
+        let words: usize = pieces.iter().map(|p| words(p).count()).sum();
+        assert_eq!(words, 4);
+        let graphemes: usize = pieces.iter().map(|p| crate::count_graphemes(p)).sum();
+        assert_eq!(graphemes, text.chars().count() - 5);
+    }
+
+    #[test]
+    fn chunkiter_rewind_and_error() {
+        let raw = [b'a', b' ', 0xFF, b'b'];
+        let mut iter = ChunkIterator::with_chunk_size(io::Cursor::new(raw), Encoding::Utf8, 2);
+        assert_eq!(iter.next(), Some(String::from("a ")));
+        assert_eq!(iter.next(), None);
+        assert!(iter.take_error().is_some());
+        iter.rewind().unwrap();
+        assert_eq!(iter.next(), Some(String::from("a ")));
+    }
 }
@@ -1,56 +1,105 @@
 //! Coding challenge: Own version of word count (wc).
 
+#[cfg(feature = "tokio")]
+pub mod async_count;
+pub mod binary;
+pub mod classes;
 pub mod command;
+pub mod encoding;
+pub mod follow;
+pub mod format;
+pub mod frequency;
+pub mod gzip;
 pub mod iterators;
+pub mod normalize;
+pub mod options;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod pattern;
+pub mod progress;
+pub mod stats;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub mod uring;
+#[cfg(unix)]
+pub mod verify;
+pub mod visitor;
+pub mod watch;
 
-use std::{error, str};
+use std::{collections::HashSet, error, fs};
+use unicode_segmentation::UnicodeSegmentation;
 
-pub use command::{CcWcArgs, CcWcInput, Content};
+#[cfg(feature = "tokio")]
+pub use async_count::count_async;
+pub use binary::BinaryMode;
+pub use command::{Buffering, CcWcArgs, CcWcInput, Content};
+pub use encoding::{Encoding, Invalid};
+pub use format::{OutputFormat, Total};
+pub use frequency::WordFrequency;
+pub use iterators::{LineIterator, Newline, WordIterator, WordSpanIterator};
+pub use normalize::{Normalization, WordForm};
+pub use options::{CcWcOptions, Counts};
+pub use stats::TextStats;
+pub use visitor::{for_each_piece, CountVisitor};
 
 /// Common Result type definition.
 pub type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
 
-/// Checks if next character in iterator is equal to c, without modifying it.
-fn check_next_is(chars: &str::Chars, c: char) -> bool {
-    let mut cpy = chars.clone();
-    Some(c) == cpy.next()
-}
-
-fn count_lines(piece: &str) -> usize {
-    let mut lines = 0;
-    let mut iter = piece.chars();
-    while let Some(c) = iter.next() {
-        if c == '\n' && !check_next_is(&iter, '\n') {
-            lines += 1;
-        }
+/// Counts line terminators bytewise, which is vectorized by `memchr`. A terminator byte can never
+/// be part of a multi-byte UTF-8 sequence, so this is exact for any `str`. Terminators of more
+/// than one kind fall back to iterating the lines.
+fn count_lines(piece: &str, newline: Newline) -> usize {
+    let bytes = piece.as_bytes();
+    match newline {
+        Newline::Lf => memchr::memchr_iter(b'\n', bytes).count(),
+        Newline::Cr => memchr::memchr_iter(b'\r', bytes).count(),
+        Newline::Nul => memchr::memchr_iter(b'\0', bytes).count(),
+        Newline::Crlf => memchr::memmem::find_iter(bytes, b"\r\n").count(),
+        Newline::Any => LineIterator::new(piece, newline)
+            .filter(|line| newline.terminator_len(line) > 0)
+            .count(),
     }
-    lines
 }
 
 fn count_chars(piece: &str) -> usize {
     piece.char_indices().count()
 }
 
+fn count_graphemes(piece: &str) -> usize {
+    piece.graphemes(true).count()
+}
+
 fn count_bytes(piece: &str) -> usize {
-    piece.as_bytes().len()
+    piece.len()
 }
 
-fn count_words(piece: &str) -> usize {
-    iterators::WordIterator::new(piece).count()
+fn count_words(piece: &str, newline: Newline) -> usize {
+    WordIterator::with_newline(piece.char_indices(), newline).count()
 }
 
-fn iterate_pieces(content: &mut Content, f: fn(&str) -> usize) -> Result<usize> {
-    let mut cnt: usize = 0;
-    for piece in &mut *content {
-        cnt += f(&piece);
+fn count_ascii_words(piece: &str, newline: Newline) -> usize {
+    match newline {
+        Newline::Nul => piece
+            .split(|c: char| c.is_ascii_whitespace() || c == '\0')
+            .filter(|word| !word.is_empty())
+            .count(),
+        _ => piece.split_ascii_whitespace().count(),
     }
-    content.rewind()?;
+}
+
+fn iterate_pieces(content: &mut Content, mut f: impl FnMut(&str) -> usize) -> Result<usize> {
+    let mut cnt: usize = 0;
+    for_each_piece(content, |piece| cnt += f(piece))?;
     Ok(cnt)
 }
 
-/// Main count function for lines in text.
+/// Main count function for lines in text, terminated by line feeds.
 pub fn lines(content: &mut Content) -> Result<usize> {
-    iterate_pieces(content, count_lines)
+    lines_with(content, Newline::Lf)
+}
+
+/// Count function for lines in text, terminated as defined by `newline`.
+pub fn lines_with(content: &mut Content, newline: Newline) -> Result<usize> {
+    iterate_pieces(content, |piece| count_lines(piece, newline))
 }
 
 /// Main count function for characters in text.
@@ -58,70 +107,550 @@ pub fn chars(content: &mut Content) -> Result<usize> {
     iterate_pieces(content, count_chars)
 }
 
+/// Main count function for extended grapheme clusters (user-perceived characters) in text.
+pub fn graphemes(content: &mut Content) -> Result<usize> {
+    iterate_pieces(content, count_graphemes)
+}
+
 /// Main count function for number of bytes of this text.
 pub fn bytes(content: &mut Content) -> Result<usize> {
     iterate_pieces(content, count_bytes)
 }
 
+/// Count function for number of bytes, the text occupies in the given encoding.
+pub fn encoded_bytes(content: &mut Content, encoding: Encoding) -> Result<usize> {
+    iterate_pieces(content, |piece| encoding.encoded_len(piece))
+}
+
 /// Main count function for number of words in text.
 pub fn words(content: &mut Content) -> Result<usize> {
-    iterate_pieces(content, count_words)
-}
-
-/// Formats output for cli.
-fn format_output(dvec: &Vec<usize>, digits: usize) -> String {
-    match dvec.len() {
-        1 => format!("{:>digit$}", dvec[0], digit = digits),
-        2 => format!("{:>digit$} {:>digit$}", dvec[0], dvec[1], digit = digits),
-        3 => format!(
-            "{:>digit$} {:>digit$} {:>digit$}",
-            dvec[0],
-            dvec[1],
-            dvec[2],
-            digit = digits
-        ),
-        4 => format!(
-            "{:>digit$} {:>digit$} {:>digit$} {:>digit$}",
-            dvec[0],
-            dvec[1],
-            dvec[2],
-            dvec[3],
-            digit = digits
-        ),
-        _ => panic!("number of outputs not supported"),
+    iterate_pieces(content, |piece| count_words(piece, Newline::default()))
+}
+
+/// Count function for number of distinct words in text, which are compared as defined by `form`.
+pub fn unique_words(content: &mut Content, form: WordForm) -> Result<usize> {
+    let mut words = HashSet::<String>::new();
+    iterate_pieces(content, |piece| {
+        insert_words(&mut words, piece, form, Newline::default());
+        0
+    })?;
+    Ok(words.len())
+}
+
+/// Inserts all words of the piece into the set of distinct words.
+fn insert_words(words: &mut HashSet<String>, piece: &str, form: WordForm, newline: Newline) {
+    for word in iterators::words_with(piece, newline) {
+        let key = form.key(word);
+        if !words.contains(key.as_ref()) {
+            words.insert(key.into_owned());
+        }
     }
 }
 
-/// This is the main entry function for ccwc.
-pub fn ccwc(input: &mut command::CcWcInput) -> Result<String> {
-    let no_flags = !(input.args.chars || input.args.bytes || input.args.words || input.args.lines);
+/// Count function for number of words in text, which are separated by ASCII whitespace only.
+pub fn ascii_words(content: &mut Content) -> Result<usize> {
+    iterate_pieces(content, |piece| {
+        count_ascii_words(piece, Newline::default())
+    })
+}
+
+/// Runs ccwc on the content of a reader, like `cat FILE | ccwc ARGS` does on stdin.
+pub fn ccwc_from_reader(
+    args: CcWcArgs,
+    reader: impl std::io::Read + 'static,
+) -> Result<CcWcOutput> {
+    ccwc(&mut CcWcInput::from_reader(args, reader)?)
+}
+
+/// Counts the selected counters of a text, which is already held in memory.
+pub fn count_str(text: &str, options: &CcWcOptions) -> Counts {
+    let mut visitor = options.visitor();
+    visitor.visit(text);
+    visitor.finish()
+}
+
+/// Counts the selected counters of a UTF-8 encoded buffer, which is already held in memory.
+pub fn count_slice(buffer: &[u8], options: &CcWcOptions) -> Result<Counts> {
+    let (text, _) = Encoding::Utf8.decode(buffer, true)?;
+    Ok(count_str(&text, options))
+}
+
+/// The individual counters, which can be requested.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Counter {
+    Lines,
+    Words,
+    Bytes,
+    Chars,
+    Graphemes,
+    Unique,
+    Letters,
+    Digits,
+    Whitespace,
+    Punctuation,
+    Other,
+}
+
+impl Counter {
+    /// All counters, in output order.
+    pub const ALL: [Counter; 11] = [
+        Counter::Lines,
+        Counter::Words,
+        Counter::Bytes,
+        Counter::Chars,
+        Counter::Graphemes,
+        Counter::Unique,
+        Counter::Letters,
+        Counter::Digits,
+        Counter::Whitespace,
+        Counter::Punctuation,
+        Counter::Other,
+    ];
+
+    /// Counters of the character classes, in output order.
+    pub const CLASSES: [Counter; 5] = [
+        Counter::Letters,
+        Counter::Digits,
+        Counter::Whitespace,
+        Counter::Punctuation,
+        Counter::Other,
+    ];
+
+    /// Counter of the given column name.
+    pub fn from_name(name: &str) -> Option<Counter> {
+        Counter::ALL.into_iter().find(|c| c.name() == name)
+    }
+
+    /// Column name of this counter.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Counter::Lines => "lines",
+            Counter::Words => "words",
+            Counter::Bytes => "bytes",
+            Counter::Chars => "chars",
+            Counter::Graphemes => "graphemes",
+            Counter::Unique => "unique",
+            Counter::Letters => "letters",
+            Counter::Digits => "digits",
+            Counter::Whitespace => "whitespace",
+            Counter::Punctuation => "punctuation",
+            Counter::Other => "other",
+        }
+    }
+
+    /// All counters requested by the CLI arguments, in output order. An output template
+    /// defines the counters on its own.
+    pub fn selected(args: &CcWcArgs) -> Vec<Counter> {
+        if let Some(template) = &args.format {
+            return template.counters();
+        }
+        let no_flags = !(args.chars
+            || args.graphemes
+            || args.bytes
+            || args.words
+            || args.lines
+            || args.unique
+            || args.classes);
+        let mut counters = Vec::new();
+        if no_flags || args.lines {
+            counters.push(Counter::Lines);
+        }
+        if no_flags || args.words {
+            counters.push(Counter::Words);
+        }
+        if no_flags || args.bytes {
+            counters.push(Counter::Bytes);
+        }
+        if args.chars {
+            counters.push(Counter::Chars);
+        }
+        if args.graphemes {
+            counters.push(Counter::Graphemes);
+        }
+        if args.unique {
+            counters.push(Counter::Unique);
+        }
+        if args.classes {
+            counters.extend(Counter::CLASSES);
+        }
+        counters
+    }
+}
+
+/// An error, which occurred while processing one of the input files.
+#[derive(Debug)]
+pub struct FileError {
+    /// Name of the affected file.
+    pub file: String,
+    /// The error itself.
+    pub error: Box<dyn error::Error>,
+}
+
+impl std::fmt::Display for FileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.file, self.error)
+    }
+}
+
+/// Result of ccwc: the formatted output of all inputs, which could be processed, and the errors
+/// of those, which could not.
+#[derive(Debug)]
+pub struct CcWcOutput {
+    /// Formatted output for the command line.
+    pub output: String,
+    /// Errors of individual files.
+    pub errors: Vec<FileError>,
+    /// Warnings about individual files, which have been skipped.
+    pub warnings: Vec<FileError>,
+}
+
+impl CcWcOutput {
+    /// Checks whether all inputs have been processed without errors.
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Determines the number of bytes from file metadata, without reading the file. This works only
+/// for regular, uncompressed files and if no other counter is requested.
+fn metadata_bytes(args: &CcWcArgs, file: &str) -> Result<Option<usize>> {
+    if !args.only_bytes() || args.is_compressed(file) {
+        return Ok(None);
+    }
+    let metadata = fs::metadata(file)?;
+    Ok(metadata.is_file().then_some(metadata.len() as usize))
+}
+
+/// Formats all rows, either by the output template or in the requested output format.
+pub(crate) fn format_output(args: &CcWcArgs, counters: &[Counter], rows: &[format::Row]) -> String {
+    if args.porcelain {
+        return format::format_porcelain(rows);
+    }
+    match &args.format {
+        Some(template) => rows
+            .iter()
+            .map(|row| template.render(counters, row))
+            .collect::<Vec<String>>()
+            .join("\n"),
+        None if args.human => format::format_human(counters, rows, args.width.unwrap_or(1)),
+        None => {
+            let header: Vec<&str> = counters.iter().map(|c| c.name()).collect();
+            format::format_rows(args.output, &header, rows, column_width(args, counters))
+        }
+    }
+}
 
-    let mut dvec: Vec<usize> = Vec::new();
-    if no_flags || input.args.lines {
-        dvec.push(lines(&mut input.content)?);
+/// Width of the plain output columns, either as requested or computed like GNU wc from the
+/// sizes of the input files. The size of stdin and compressed files is unknown beforehand.
+fn column_width(args: &CcWcArgs, counters: &[Counter]) -> usize {
+    if let Some(width) = args.width {
+        return width;
     }
-    if no_flags || input.args.words {
-        dvec.push(words(&mut input.content)?);
+    if args.total == Total::Only {
+        // A single row needs no alignment.
+        return 1;
     }
-    if no_flags || input.args.bytes {
-        dvec.push(bytes(&mut input.content)?);
+    let sizes: Vec<Option<u64>> = if args.files.is_empty() {
+        vec![None]
+    } else {
+        args.files
+            .iter()
+            .map(|file| match fs::metadata(file) {
+                _ if file == command::STDIN => None,
+                Ok(_) if args.is_compressed(file) => None,
+                Ok(metadata) => metadata.is_file().then_some(metadata.len()),
+                // Files, which could not be read, are left out like in GNU wc.
+                Err(_) => Some(0),
+            })
+            .collect()
+    };
+    format::default_width(counters.len(), &sizes)
+}
+
+/// Options for counting the given counters as requested by the CLI arguments.
+fn options(args: &CcWcArgs, counters: &[Counter]) -> CcWcOptions {
+    let options = counters
+        .iter()
+        .fold(CcWcOptions::new(), |options, c| options.counter(*c))
+        .newline(args.terminator())
+        .encoding(args.encoding)
+        .fold_case(args.fold_case)
+        .normalization(args.normalize)
+        .count_partial_line(args.count_partial_line);
+    match &args.pattern {
+        Some(pattern) => options.pattern(pattern.clone()),
+        None => options,
     }
-    if input.args.chars {
-        dvec.push(chars(&mut input.content)?);
+}
+
+/// Counts all requested counters of one content.
+pub(crate) fn count(
+    args: &CcWcArgs,
+    counters: &[Counter],
+    content: &mut Content,
+) -> Result<Vec<usize>> {
+    if args.progress() {
+        content.enable_progress();
     }
-    let digits = dvec.iter().max().unwrap().to_string().len();
+    let counts = options(args, counters).run(content)?;
+    Ok(counters.iter().map(|c| counts.get(*c)).collect())
+}
 
-    let mut output = format_output(&dvec, digits);
-    if let Some(file) = &input.args.file {
-        output.push(' ');
-        output.push_str(file);
+/// Opens one input file, compressed files get decompressed transparently. `-` reads stdin.
+fn read_file(args: &CcWcArgs, file: &str) -> Result<Content> {
+    if file == command::STDIN {
+        return CcWcInput::read_content(args, std::io::stdin());
+    }
+    if args.is_compressed(file) {
+        return Content::read_gzip(file, args.encoding, args.invalid);
     }
-    Ok(output)
+    let buffering = args.buffering(fs::metadata(file)?.len());
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    if args.io_uring && buffering == Buffering::Stream {
+        return Content::read_uring(file, args.encoding, args.invalid);
+    }
+    Content::read_with_buffering(file, args.encoding, args.invalid, buffering)
+}
+
+/// Opens one input file like `read_file()`, the content of `stdin` stands for the first `-`.
+fn open_input(args: &CcWcArgs, file: &str, stdin: &mut Option<Content>) -> Result<Content> {
+    match stdin.take() {
+        Some(content) if file == command::STDIN => Ok(content),
+        content => {
+            *stdin = content;
+            read_file(args, file)
+        }
+    }
+}
+
+/// Counts all requested counters of stdin, binary input gets skipped with a warning unless only
+/// its bytes are requested.
+fn count_stdin(
+    args: &CcWcArgs,
+    counters: &[Counter],
+    content: &mut Content,
+    file: Option<&str>,
+    warnings: &mut Vec<FileError>,
+) -> Result<Option<format::Row>> {
+    let file = file.map(String::from);
+    if !binary::detect_content(args, content) {
+        Ok(Some(format::Row::new(
+            count(args, counters, content)?,
+            file,
+        )))
+    } else if args.binary == BinaryMode::Bytes {
+        let values = binary_values(counters, bytes(content)?);
+        Ok(Some(format::Row::new(values, file)))
+    } else {
+        warnings.push(FileError {
+            file: String::from(command::STDIN),
+            error: "binary input skipped".into(),
+        });
+        Ok(None)
+    }
+}
+
+/// Counts all requested counters of one file.
+fn count_file(args: &CcWcArgs, counters: &[Counter], file: &str) -> Result<Vec<usize>> {
+    if let Some(bytes) = metadata_bytes(args, file)? {
+        return Ok(vec![bytes]);
+    }
+    #[cfg(feature = "parallel")]
+    if !args.is_compressed(file)
+        && !args.uses_io_uring()
+        && !counters.contains(&Counter::Unique)
+        && args.pattern.is_none()
+        && !args.force_stream
+        && !args.force_memory
+        && !args.count_partial_line
+        && parallel::is_suitable(file, args.encoding)?
+    {
+        let options = options(args, counters);
+        let counts = parallel::count_file(file, &options, args.encoding, args.invalid)?;
+        return Ok(counters.iter().map(|c| counts.get(*c)).collect());
+    }
+    let mut content = read_file(args, file)?;
+    count(args, counters, &mut content)
+}
+
+/// Number of distinct words of all inputs together. Errors of individual inputs are not
+/// reported, since they are part of the normal counting already.
+fn unique_total(args: &CcWcArgs, content: &mut Option<Content>) -> Result<usize> {
+    let freq = word_frequency(args, content, &mut Vec::new())?;
+    Ok(freq.len())
+}
+
+/// Values of a binary input: the number of bytes, all other counters are zero.
+fn binary_values(counters: &[Counter], bytes: usize) -> Vec<usize> {
+    counters
+        .iter()
+        .map(|c| if *c == Counter::Bytes { bytes } else { 0 })
+        .collect()
+}
+
+/// Word frequencies of all inputs together. Files, which could not be read, are collected in
+/// `errors`.
+fn word_frequency(
+    args: &CcWcArgs,
+    content: &mut Option<Content>,
+    errors: &mut Vec<FileError>,
+) -> Result<WordFrequency> {
+    let mut freq = WordFrequency::new(args.word_form());
+    if let Some(content) = content.as_mut().filter(|_| !args.reads_stdin()) {
+        freq.add_content(content)?;
+    }
+    for file in &args.files {
+        let result =
+            open_input(args, file, content).and_then(|mut input| freq.add_content(&mut input));
+        if let Err(error) = result {
+            errors.push(FileError {
+                file: file.clone(),
+                error,
+            });
+        }
+    }
+    Ok(freq)
+}
+
+/// Writes every distinct word of all inputs with its number of occurrences as CSV file. Errors of
+/// individual inputs are not reported, since they are part of the normal counting already.
+fn write_histogram(args: &CcWcArgs, content: &mut Option<Content>, path: &str) -> Result<()> {
+    let freq = word_frequency(args, content, &mut Vec::new())?;
+    let mut csv = format::format_frequencies(OutputFormat::Csv, &freq.top(freq.len()));
+    csv.push('\n');
+    fs::write(path, csv)?;
+    Ok(())
+}
+
+/// Word frequency mode: the most frequent words of all inputs together.
+fn top_words(input: &mut command::CcWcInput, n: usize) -> Result<CcWcOutput> {
+    let command::CcWcInput { args, content } = input;
+    let mut errors = Vec::new();
+    let freq = word_frequency(args, content, &mut errors)?;
+
+    let output = format::format_frequencies(args.output, &freq.top(n));
+    Ok(CcWcOutput {
+        output,
+        errors,
+        warnings: Vec::new(),
+    })
+}
+
+/// Statistics mode: line and word statistics of all inputs together.
+fn text_stats(input: &mut command::CcWcInput) -> Result<CcWcOutput> {
+    let command::CcWcInput { args, content } = input;
+    let mut stats = TextStats::new(args.terminator());
+    let mut errors = Vec::new();
+    if let Some(content) = content.as_mut().filter(|_| !args.reads_stdin()) {
+        stats.add_content(content)?;
+    }
+    for file in &args.files {
+        let result =
+            open_input(args, file, content).and_then(|mut input| stats.add_content(&mut input));
+        if let Err(error) = result {
+            errors.push(FileError {
+                file: file.clone(),
+                error,
+            });
+        }
+    }
+
+    let output = format::format_stats(args.output, &stats);
+    Ok(CcWcOutput {
+        output,
+        errors,
+        warnings: Vec::new(),
+    })
+}
+
+/// This is the main entry function for ccwc.
+pub fn ccwc(input: &mut command::CcWcInput) -> Result<CcWcOutput> {
+    if let Some(n) = input.args.top {
+        return top_words(input, n);
+    }
+    if input.args.stats {
+        return text_stats(input);
+    }
+
+    let command::CcWcInput { args, content } = input;
+    let counters = Counter::selected(args);
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+
+    if let Some(content) = content.as_mut().filter(|_| !args.reads_stdin()) {
+        rows.extend(count_stdin(args, &counters, content, None, &mut warnings)?);
+    }
+    for file in &args.files {
+        if file == command::STDIN {
+            let result = open_input(args, file, content).and_then(|mut input| {
+                count_stdin(args, &counters, &mut input, Some(file), &mut warnings)
+            });
+            match result {
+                Ok(row) => rows.extend(row),
+                Err(error) => errors.push(FileError {
+                    file: file.clone(),
+                    error,
+                }),
+            }
+            continue;
+        }
+        let result = match binary::detect_file(args, file) {
+            Ok(false) => count_file(args, &counters, file).or_else(|error| {
+                if args.invalid == Invalid::Bytes && encoding::is_decode_error(&*error) {
+                    binary::raw_bytes(args, file).map(|bytes| binary_values(&counters, bytes))
+                } else {
+                    Err(error)
+                }
+            }),
+            Ok(true) if args.binary == BinaryMode::Bytes => {
+                binary::raw_bytes(args, file).map(|bytes| binary_values(&counters, bytes))
+            }
+            Ok(true) => {
+                warnings.push(FileError {
+                    file: file.clone(),
+                    error: "binary file skipped".into(),
+                });
+                continue;
+            }
+            Err(error) => Err(error),
+        };
+        match result {
+            Ok(values) => rows.push(format::Row::new(values, Some(file.clone()))),
+            Err(error) => errors.push(FileError {
+                file: file.clone(),
+                error,
+            }),
+        }
+    }
+
+    #[cfg(unix)]
+    if args.verify {
+        warnings.extend(verify::verify(&counters, &rows)?);
+    }
+    if format::apply_total(args.total, counters.len(), args.files.len(), &mut rows) {
+        // Distinct words can not be summed up, they are counted across all inputs instead.
+        if let Some(i) = counters.iter().position(|c| *c == Counter::Unique) {
+            let total = rows.last_mut().expect("total row");
+            total.values[i] = unique_total(args, content)?;
+        }
+    }
+    if let Some(path) = &args.histogram {
+        write_histogram(args, content, path)?;
+    }
+
+    let output = format_output(args, &counters, &rows);
+    Ok(CcWcOutput {
+        output,
+        errors,
+        warnings,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use clap::Parser;
     use totems::assert_ok;
 
     const TESTFILE: &str = "test.txt";
@@ -134,6 +663,26 @@ mod tests {
         assert_ok!(lines, value == 7145);
     }
 
+    #[test]
+    fn fn_lines_newline() {
+        let mut content = Content::SmallFile(String::from("a\n\n\nb\n"), true);
+        assert_ok!(lines(&mut content), value == 4);
+
+        let mut content = Content::SmallFile(String::from("a\r\nb\rc\n\r"), true);
+        assert_ok!(lines_with(&mut content, Newline::Lf), value == 2);
+        assert_ok!(lines_with(&mut content, Newline::Crlf), value == 1);
+        assert_ok!(lines_with(&mut content, Newline::Cr), value == 3);
+        assert_ok!(lines_with(&mut content, Newline::Any), value == 4);
+
+        let mut content = Content::SmallFile(String::from("a b\0c\nd\0e"), true);
+        assert_ok!(lines_with(&mut content, Newline::Nul), value == 2);
+
+        let mut content = Content::SmallFile(String::from("\u{e4}\n\u{20ac}\r\n\u{1F600}\r"), true);
+        assert_ok!(lines_with(&mut content, Newline::Lf), value == 2);
+        assert_ok!(lines_with(&mut content, Newline::Crlf), value == 1);
+        assert_ok!(lines_with(&mut content, Newline::Cr), value == 2);
+    }
+
     #[test]
     fn fn_chars() {
         let mut content = Content::read_to_string(TESTFILE).expect(TESTFILE_MISSING);
@@ -141,6 +690,23 @@ mod tests {
         assert_ok!(chars, value == 339292);
     }
 
+    #[test]
+    fn fn_graphemes() {
+        let mut content =
+            Content::SmallFile(String::from("e\u{301}\u{1F469}\u{200D}\u{1F467}\r\n"), true);
+        assert_ok!(graphemes(&mut content), value == 3);
+
+        let mut content = Content::read_to_string(TESTFILE).expect(TESTFILE_MISSING);
+        assert_ok!(graphemes(&mut content), value == 332147);
+    }
+
+    #[test]
+    fn cc_graphemes() {
+        let mut input = CcWcInput::try_from("ccwc -mg test.txt").unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(result, String::from("339292 332147 test.txt"));
+    }
+
     #[test]
     fn fn_bytes() {
         let mut content = Content::read_to_string(TESTFILE).expect(TESTFILE_MISSING);
@@ -155,41 +721,439 @@ mod tests {
         assert_ok!(words, value == 58164);
     }
 
+    #[test]
+    fn fn_unique_words() {
+        let mut content = Content::SmallFile(String::from("The cat and the dog\nthe end"), true);
+        assert_ok!(unique_words(&mut content, WordForm::default()), value == 6);
+        let form = WordForm::new(true, Normalization::None);
+        assert_ok!(unique_words(&mut content, form), value == 5);
+
+        let mut content = Content::from_string(String::from("Cafe\u{301} caf\u{e9}"));
+        assert_ok!(unique_words(&mut content, WordForm::default()), value == 2);
+        let form = WordForm::new(true, Normalization::Nfc);
+        assert_ok!(unique_words(&mut content, form), value == 1);
+    }
+
+    #[test]
+    fn cc_unique() {
+        let mut input = CcWcInput::try_from("ccwc -wu test.txt test.txt").unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        let unique = result
+            .lines()
+            .next()
+            .unwrap()
+            .split_whitespace()
+            .nth(1)
+            .unwrap();
+        assert_eq!(
+            result.lines().last().unwrap(),
+            format!("116328 {unique:>6} total")
+        );
+    }
+
+    #[test]
+    fn cc_classes() {
+        let fname = "test_classes.txt";
+        std::fs::write(fname, "Hello, world 42!\n").expect("writing testfile failed");
+
+        let cmd = format!("ccwc -l --classes {fname}");
+        let mut input = CcWcInput::try_from(cmd.as_str()).unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(result, format!(" 1 10  2  3  2  0 {fname}"));
+
+        let cmd = format!("ccwc --classes --output csv {fname}");
+        let mut input = CcWcInput::try_from(cmd.as_str()).unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert!(result.starts_with("letters,digits,whitespace,punctuation,other,file\n"));
+
+        std::fs::remove_file(fname).expect("removing testfile failed");
+    }
+
+    #[test]
+    fn cc_stdin() {
+        let file = || std::fs::File::open(TESTFILE).expect(TESTFILE_MISSING);
+        let result = ccwc_from_reader(CcWcArgs::from("ccwc -l"), file()).expect("ccwc error");
+        assert_eq!(result.output, "7145");
+
+        let result = ccwc_from_reader(CcWcArgs::from("ccwc"), file()).expect("ccwc error");
+        assert_eq!(result.output, "   7145   58164  342190");
+
+        let result = ccwc_from_reader(CcWcArgs::from("ccwc -c other.txt"), file());
+        assert_eq!(result.expect("ccwc error").output, "342190");
+
+        // `-` stands for the reader among the files, like for `cat FILE | wc -l - FILE`.
+        let args = CcWcArgs::from(format!("ccwc -l - {TESTFILE}").as_str());
+        let result = ccwc_from_reader(args, file()).expect("ccwc error");
+        assert_eq!(
+            result.output,
+            format!("   7145 -\n   7145 {TESTFILE}\n  14290 total")
+        );
+        let result = ccwc_from_reader(CcWcArgs::from("ccwc -w -"), file()).expect("ccwc error");
+        assert_eq!(result.output, "58164 -");
+    }
+
+    #[test]
+    fn match_pattern() {
+        let fname = "test_match.txt";
+        std::fs::write(fname, "error: one\nok\nerror: two three\nfine error").unwrap();
+
+        let cmd = format!("ccwc -lw --match ^error {fname} {fname}");
+        let mut input = CcWcInput::try_from(cmd.as_str()).unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(result, format!(" 2  3 {fname}\n 2  3 {fname}\n 4  6 total"));
+
+        let cmd = format!("ccwc -l --match error$ {fname}");
+        let mut input = CcWcInput::try_from(cmd.as_str()).unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(result, format!("1 {fname}"));
+
+        std::fs::remove_file(fname).expect("removing testfile failed");
+    }
+
+    #[test]
+    fn forced_buffering() {
+        for cmd in [
+            "ccwc --force-stream test.txt",
+            "ccwc --buffer-threshold 1 test.txt",
+        ] {
+            let mut input = CcWcInput::try_from(cmd).unwrap();
+            let result = ccwc(&mut input).expect("ccwc error").output;
+            assert_eq!(result, "  7145  58164 342190 test.txt");
+        }
+    }
+
+    #[test]
+    fn partial_line() {
+        let args = CcWcArgs::from("ccwc -l");
+        assert_eq!(ccwc_from_reader(args, &b"a\n\nb"[..]).unwrap().output, "2");
+        let args = CcWcArgs::from("ccwc -l --count-partial-line");
+        assert_eq!(ccwc_from_reader(args, &b"a\n\nb"[..]).unwrap().output, "3");
+    }
+
+    #[test]
+    fn human() {
+        let mut input = CcWcInput::try_from("ccwc -H test.txt").unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(result, "    7,145    58,164 334.2 KiB test.txt");
+        assert!(CcWcArgs::try_parse_from(["ccwc", "-H", "--output", "csv"]).is_err());
+    }
+
+    #[test]
+    fn porcelain() {
+        let mut input = CcWcInput::try_from("ccwc --porcelain -wlm test.txt test.txt").unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(
+            result,
+            "7145 58164 339292\n7145 58164 339292\n14290 116328 678584"
+        );
+
+        assert!(CcWcArgs::try_parse_from(["ccwc", "--porcelain", "--output", "csv"]).is_err());
+    }
+
+    #[test]
+    fn content_from_reader() {
+        let text = "Some text\nfrom a reader\n";
+        let reader = std::io::Cursor::new(text);
+        let mut content = Content::from_reader(reader, Buffering::Memory).unwrap();
+        assert_ok!(lines(&mut content), value == 2);
+        assert_ok!(words(&mut content), value == 5);
+
+        let reader = std::io::Cursor::new(text);
+        let mut content = Content::from_reader(reader, Buffering::Stream).unwrap();
+        assert_ok!(words(&mut content), value == 5);
+        assert!(lines(&mut content).is_err());
+
+        // All counters are counted within one pass, so a stream can be counted completely.
+        let reader = std::io::Cursor::new(text);
+        let mut content = Content::from_reader(reader, Buffering::Stream).unwrap();
+        let counts = CcWcOptions::new()
+            .lines()
+            .words()
+            .chars()
+            .unique()
+            .run(&mut content)
+            .unwrap();
+        assert_eq!(
+            (counts.lines, counts.words, counts.chars, counts.unique),
+            (2, 5, 24, 5)
+        );
+    }
+
+    #[test]
+    fn content_from_memory() {
+        let mut content = Content::from_string(String::from("one two\nthree\n"));
+        assert_ok!(lines(&mut content), value == 2);
+        assert_ok!(words(&mut content), value == 3);
+
+        let mut content = Content::from_bytes("caf\u{e9}\n".as_bytes()).unwrap();
+        assert_ok!(chars(&mut content), value == 5);
+        assert!(Content::from_bytes(&[b'a', 0xFF]).is_err());
+
+        let options = CcWcOptions::new().words().bytes().unique();
+        let counts = count_str("to be or not to be", &options);
+        assert_eq!((counts.words, counts.bytes, counts.unique), (6, 18, 4));
+        let counts = count_slice(b"to be\n", &options).unwrap();
+        assert_eq!((counts.words, counts.bytes, counts.unique), (2, 6, 2));
+        assert!(count_slice(&[0xC3], &options).is_err());
+    }
+
     #[test]
     fn cc_step_1_test() {
         let mut input = CcWcInput::try_from("ccwc -c test.txt").unwrap();
-        let result = ccwc(&mut input).expect("ccwc error");
+        let result = ccwc(&mut input).expect("ccwc error").output;
         assert_eq!(result, String::from("342190 test.txt"));
     }
 
+    #[test]
+    fn bytes_from_metadata() {
+        // Invalid UTF-8 would fail, if the file was read.
+        let fname = "test_metadata.txt";
+        std::fs::write(fname, [0xFF, 0xFE, b'\n']).expect("writing testfile failed");
+
+        let cmd = format!("ccwc -c {fname}");
+        let mut input = CcWcInput::try_from(cmd.as_str()).unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(result, format!("3 {fname}"));
+
+        let cmd = format!("ccwc -cl {fname}");
+        let mut input = CcWcInput::try_from(cmd.as_str()).unwrap();
+        let result = ccwc(&mut input).expect("ccwc error");
+        assert!(!result.is_ok());
+
+        std::fs::remove_file(fname).expect("removing testfile failed");
+    }
+
     #[test]
     fn cc_step_2_test() {
         let mut input = CcWcInput::try_from("ccwc -l test.txt").unwrap();
-        let result = ccwc(&mut input).expect("ccwc error");
+        let result = ccwc(&mut input).expect("ccwc error").output;
         assert_eq!(result, String::from("7145 test.txt"));
     }
 
     #[test]
     fn cc_step_3_test() {
         let mut input = CcWcInput::try_from("ccwc -w test.txt").unwrap();
-        let result = ccwc(&mut input).expect("ccwc error");
+        let result = ccwc(&mut input).expect("ccwc error").output;
         assert_eq!(result, String::from("58164 test.txt"));
     }
 
     #[test]
     fn cc_step_4_test() {
         let mut input = CcWcInput::try_from("ccwc -m test.txt").unwrap();
-        let result = ccwc(&mut input).expect("ccwc error");
+        let result = ccwc(&mut input).expect("ccwc error").output;
         assert_eq!(result, String::from("339292 test.txt"));
     }
 
     #[test]
     fn cc_step_5_test() {
         let mut input = CcWcInput::try_from("ccwc test.txt").unwrap();
-        let result = ccwc(&mut input).expect("ccwc error");
+        let result = ccwc(&mut input).expect("ccwc error").output;
         assert_eq!(result, String::from("  7145  58164 342190 test.txt"));
     }
 
+    #[test]
+    fn output_csv() {
+        let mut input = CcWcInput::try_from("ccwc --output csv test.txt").unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(
+            result,
+            String::from("lines,words,bytes,file\n7145,58164,342190,test.txt")
+        );
+    }
+
+    #[test]
+    fn output_tsv() {
+        let mut input = CcWcInput::try_from("ccwc -lw --output tsv test.txt").unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(
+            result,
+            String::from("lines\twords\tfile\n7145\t58164\ttest.txt")
+        );
+    }
+
+    #[test]
+    fn output_template() {
+        let mut input =
+            CcWcInput::try_from("ccwc -c --format {file}:{lines}:{words} test.txt").unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(result, String::from("test.txt:7145:58164"));
+
+        let args = CcWcArgs::try_parse_from(["ccwc", "--format", "{line}", "test.txt"]);
+        assert!(args.is_err());
+    }
+
+    #[test]
+    fn top_words() {
+        let mut input = CcWcInput::try_from("ccwc --top 3 test.txt").unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(result, String::from("3455 the\n2123 of\n1675 to"));
+    }
+
+    #[test]
+    fn stats() {
+        let mut input = CcWcInput::try_from("ccwc --stats --newline crlf test.txt").unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        let mut lines = result
+            .lines()
+            .map(|l| l.split_whitespace().collect::<Vec<_>>());
+        assert_eq!(lines.next(), Some(vec!["lines:", "7145"]));
+        assert_eq!(lines.next(), Some(vec!["words:", "58164"]));
+    }
+
+    #[test]
+    fn histogram() {
+        let fname = "test_histogram.csv";
+        let cmd = format!("ccwc -w --fold-case --histogram {fname} test.txt");
+        let mut input = CcWcInput::try_from(cmd.as_str()).unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(result, String::from("58164 test.txt"));
+
+        let csv = std::fs::read_to_string(fname).expect("reading histogram failed");
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("word,count"));
+        assert!(lines.next().is_some_and(|l| l.starts_with("the,")));
+        let total: usize = csv
+            .lines()
+            .skip(1)
+            .map(|l| l.rsplit(',').next().unwrap().parse::<usize>().unwrap())
+            .sum();
+        assert_eq!(total, 58164);
+
+        std::fs::remove_file(fname).expect("removing testfile failed");
+    }
+
+    #[test]
+    fn encoding_utf16() {
+        let fname = "test_utf16le.txt";
+        let text = std::fs::read_to_string(TESTFILE).expect(TESTFILE_MISSING);
+        let raw: Vec<u8> = text.encode_utf16().flat_map(|u| u.to_le_bytes()).collect();
+        std::fs::write(fname, &raw).expect("writing testfile failed");
+
+        let cmd = format!("ccwc -lwcm --encoding utf16le {fname}");
+        let mut input = CcWcInput::try_from(cmd.as_str()).unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        let reference = format!("  7145  58164 {} 339292 {fname}", raw.len());
+        assert_eq!(result, reference);
+
+        std::fs::remove_file(fname).expect("removing testfile failed");
+    }
+
+    #[test]
+    fn gzip_input() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let fname = "test_input.txt.gz";
+        let text = std::fs::read(TESTFILE).expect(TESTFILE_MISSING);
+        let file = std::fs::File::create(fname).expect("writing testfile failed");
+        let mut encoder = GzEncoder::new(file, Compression::default());
+        encoder.write_all(&text).expect("writing testfile failed");
+        encoder.finish().expect("writing testfile failed");
+
+        let cmd = format!("ccwc {fname}");
+        let mut input = CcWcInput::try_from(cmd.as_str()).unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(result, format!("   7145   58164  342190 {fname}"));
+
+        let cmd = format!("ccwc -c {fname}");
+        let mut input = CcWcInput::try_from(cmd.as_str()).unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(result, format!("342190 {fname}"));
+
+        std::fs::remove_file(fname).expect("removing testfile failed");
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let fname = "test_invalid.txt";
+        std::fs::write(fname, b"valid text\n\xFF\xFEinvalid\n").expect("writing testfile failed");
+
+        let cmd = format!("ccwc {fname}");
+        let mut input = CcWcInput::try_from(cmd.as_str()).unwrap();
+        let result = ccwc(&mut input).expect("ccwc error");
+        assert_eq!(
+            result.errors[0].to_string(),
+            format!("{fname}: invalid UTF-8 sequence (at byte offset 11)")
+        );
+
+        let cmd = format!("ccwc -lwm --invalid lossy {fname}");
+        let mut input = CcWcInput::try_from(cmd.as_str()).unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(result, format!(" 2  3 21 {fname}"));
+
+        let cmd = format!("ccwc --invalid bytes {fname}");
+        let mut input = CcWcInput::try_from(cmd.as_str()).unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(result, format!(" 0  0 21 {fname}"));
+
+        std::fs::remove_file(fname).expect("removing testfile failed");
+    }
+
+    #[test]
+    fn multiple_files_with_error() {
+        let mut input = CcWcInput::try_from("ccwc -l test.txt missing.txt test.txt").unwrap();
+        let result = ccwc(&mut input).expect("ccwc error");
+        assert_eq!(
+            result.output,
+            String::from("  7145 test.txt\n  7145 test.txt\n 14290 total")
+        );
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].file, "missing.txt");
+    }
+
+    #[test]
+    fn total_modes() {
+        let mut input = CcWcInput::try_from("ccwc -l --total always test.txt").unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(result, String::from("7145 test.txt\n7145 total"));
+
+        let mut input = CcWcInput::try_from("ccwc -l --total only test.txt test.txt").unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(result, String::from("14290"));
+
+        let mut input = CcWcInput::try_from("ccwc -l --total never test.txt test.txt").unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(result, String::from("  7145 test.txt\n  7145 test.txt"));
+    }
+
+    #[test]
+    fn zero_terminated() {
+        let fname = "test_zero.txt";
+        std::fs::write(fname, b"./a file\0./b\0./c\0").expect("writing testfile failed");
+
+        let cmd = format!("ccwc -z {fname}");
+        let mut input = CcWcInput::try_from(cmd.as_str()).unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(result, format!(" 3  4 17 {fname}"));
+
+        std::fs::remove_file(fname).expect("removing testfile failed");
+    }
+
+    #[test]
+    fn binary_files() {
+        let fname = "test_binary.bin";
+        std::fs::write(fname, b"\x7fELF\0\x02 some\nwords\n").expect("writing testfile failed");
+
+        let cmd = format!("ccwc {fname}");
+        let mut input = CcWcInput::try_from(cmd.as_str()).unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(result, format!(" 0  0 18 {fname}"));
+
+        let cmd = format!("ccwc --binary force {fname}");
+        let mut input = CcWcInput::try_from(cmd.as_str()).unwrap();
+        let result = ccwc(&mut input).expect("ccwc error").output;
+        assert_eq!(result, format!(" 2  3 18 {fname}"));
+
+        let cmd = format!("ccwc --binary skip {fname} test.txt");
+        let mut input = CcWcInput::try_from(cmd.as_str()).unwrap();
+        let result = ccwc(&mut input).expect("ccwc error");
+        assert_eq!(result.output.lines().count(), 2);
+        assert!(result.is_ok());
+        assert_eq!(result.warnings[0].file, fname);
+
+        std::fs::remove_file(fname).expect("removing testfile failed");
+    }
+
     // Integration test, manually via shell...
     // #[test]
     // fn cc_final_step() {
@@ -1,8 +1,47 @@
 //! An own count words version (cw).
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+use std::{
+    io::{self, IsTerminal},
+    process::ExitCode,
+};
+
+/// Prints the output and all warnings and errors of one run.
+fn print(cli_out: &ccwc::CcWcOutput) {
+    for warning in &cli_out.warnings {
+        eprintln!("ccwc: {warning}");
+    }
+    for err in &cli_out.errors {
+        eprintln!("ccwc: {err}");
+    }
+    if !cli_out.output.is_empty() {
+        println!("{}", cli_out.output);
+    }
+}
+
+fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
     let mut args = ccwc::CcWcInput::parse_input()?;
+    if let Some(shell) = args.args.generate_completions {
+        ccwc::command::generate_completions(shell, &mut io::stdout());
+        return Ok(ExitCode::SUCCESS);
+    }
+    if args.args.follow {
+        ccwc::follow::follow(&args, |out| println!("{out}"))?;
+    }
+    if args.args.watch {
+        let clear = io::stdout().is_terminal();
+        ccwc::watch::watch(&mut args, |cli_out| {
+            if clear {
+                // Clears the terminal and moves the cursor to its top left corner.
+                print!("\x1b[2J\x1b[H");
+            }
+            print(cli_out);
+        })?;
+    }
     let cli_out = ccwc::ccwc(&mut args)?;
-    println!("{cli_out}");
-    Ok(())
+    print(&cli_out);
+    Ok(if cli_out.is_ok() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
 }
@@ -0,0 +1,76 @@
+//! Module encapsules the comparison of words, so that different spellings of one word, like
+//! "Word" and "word" or composed and decomposed characters, can be counted as one distinct word.
+
+use clap::ValueEnum;
+use std::borrow::Cow;
+use unicode_normalization::{is_nfc, is_nfkc, UnicodeNormalization};
+
+/// Unicode normalization form, which words are converted to before comparing them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Normalization {
+    /// Words are compared as they are.
+    #[default]
+    None,
+    /// Canonical composition, e.g. "e" followed by a combining accent equals "é".
+    Nfc,
+    /// Compatibility composition, which additionally unifies e.g. ligatures and full-width forms.
+    Nfkc,
+}
+
+/// Definition of when two words are equal, used for distinct words and word frequencies.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WordForm {
+    /// Words are compared case-insensitively.
+    pub fold_case: bool,
+    /// Words are normalized before comparing them.
+    pub normalization: Normalization,
+}
+
+impl WordForm {
+    /// New type pattern: generates a new word form.
+    pub fn new(fold_case: bool, normalization: Normalization) -> WordForm {
+        WordForm {
+            fold_case,
+            normalization,
+        }
+    }
+
+    /// Key of the word, which is equal for all words considered equal. The word is only copied,
+    /// if it needs to be changed.
+    pub fn key<'w>(&self, word: &'w str) -> Cow<'w, str> {
+        let word = match self.normalization {
+            Normalization::Nfc if !is_nfc(word) => Cow::Owned(word.nfc().collect()),
+            Normalization::Nfkc if !is_nfkc(word) => Cow::Owned(word.nfkc().collect()),
+            _ => Cow::Borrowed(word),
+        };
+        if self.fold_case {
+            Cow::Owned(word.to_lowercase())
+        } else {
+            word
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_keys() {
+        let composed = "caf\u{e9}";
+        let decomposed = "cafe\u{301}";
+
+        let form = WordForm::default();
+        assert_ne!(form.key(composed), form.key(decomposed));
+        assert!(matches!(form.key(composed), Cow::Borrowed(_)));
+
+        let form = WordForm::new(false, Normalization::Nfc);
+        assert_eq!(form.key(decomposed), composed);
+        assert!(matches!(form.key(composed), Cow::Borrowed(_)));
+        assert_ne!(form.key("\u{fb01}le"), "file");
+
+        let form = WordForm::new(true, Normalization::Nfkc);
+        assert_eq!(form.key("\u{fb01}LE"), "file");
+        assert_eq!(form.key("Cafe\u{301}"), composed);
+    }
+}
@@ -0,0 +1,385 @@
+//! Module encapsules the builder-style options API, for using the library without command line
+//! arguments.
+
+use crate::{
+    classes::count_classes,
+    command::Content,
+    encoding::Encoding,
+    iterators::Newline,
+    normalize::{Normalization, WordForm},
+    pattern::{MatchingLines, Pattern},
+    visitor::{self, CountVisitor},
+    Counter, Result,
+};
+use std::collections::HashSet;
+
+/// The counted values of one content. Counters, which have not been requested, are zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counts {
+    pub lines: usize,
+    pub words: usize,
+    pub bytes: usize,
+    pub chars: usize,
+    pub graphemes: usize,
+    pub unique: usize,
+    pub letters: usize,
+    pub digits: usize,
+    pub whitespace: usize,
+    pub punctuation: usize,
+    pub other: usize,
+}
+
+impl Counts {
+    /// Value of the given counter.
+    pub fn get(&self, counter: Counter) -> usize {
+        match counter {
+            Counter::Lines => self.lines,
+            Counter::Words => self.words,
+            Counter::Bytes => self.bytes,
+            Counter::Chars => self.chars,
+            Counter::Graphemes => self.graphemes,
+            Counter::Unique => self.unique,
+            Counter::Letters => self.letters,
+            Counter::Digits => self.digits,
+            Counter::Whitespace => self.whitespace,
+            Counter::Punctuation => self.punctuation,
+            Counter::Other => self.other,
+        }
+    }
+}
+
+impl std::ops::AddAssign for Counts {
+    fn add_assign(&mut self, other: Counts) {
+        self.lines += other.lines;
+        self.words += other.words;
+        self.bytes += other.bytes;
+        self.chars += other.chars;
+        self.graphemes += other.graphemes;
+        self.unique += other.unique;
+        self.letters += other.letters;
+        self.digits += other.digits;
+        self.whitespace += other.whitespace;
+        self.punctuation += other.punctuation;
+        self.other += other.other;
+    }
+}
+
+impl std::ops::Add for Counts {
+    type Output = Counts;
+
+    fn add(mut self, other: Counts) -> Counts {
+        self += other;
+        self
+    }
+}
+
+/// Builder for counting a content, e.g.
+/// `CcWcOptions::new().lines().words().unicode_words(true).run(&mut content)`. Without any
+/// selected counter, lines, words and bytes are counted like wc does.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CcWcOptions {
+    /// Selected counters, in order of selection.
+    counters: Vec<Counter>,
+    /// Words are separated by any Unicode whitespace, otherwise by ASCII whitespace only.
+    unicode_words: bool,
+    /// Definition of line terminators.
+    newline: Newline,
+    /// Encoding, which determines the number of bytes.
+    encoding: Encoding,
+    /// Definition of when two words are equal, for counting distinct words.
+    form: WordForm,
+    /// Only lines and words matching this pattern are counted, if set.
+    pattern: Option<Pattern>,
+    /// A final line without terminator counts as a line, which POSIX wc does not.
+    count_partial_line: bool,
+}
+
+impl Default for CcWcOptions {
+    fn default() -> CcWcOptions {
+        CcWcOptions {
+            counters: Vec::new(),
+            unicode_words: true,
+            newline: Newline::Lf,
+            encoding: Encoding::Utf8,
+            form: WordForm::default(),
+            pattern: None,
+            count_partial_line: false,
+        }
+    }
+}
+
+impl CcWcOptions {
+    /// New type pattern: generates new options without any selected counter.
+    pub fn new() -> CcWcOptions {
+        CcWcOptions::default()
+    }
+
+    /// Selects the given counter.
+    pub fn counter(mut self, counter: Counter) -> CcWcOptions {
+        if !self.counters.contains(&counter) {
+            self.counters.push(counter);
+        }
+        self
+    }
+
+    /// Selects counting lines.
+    pub fn lines(self) -> CcWcOptions {
+        self.counter(Counter::Lines)
+    }
+
+    /// Selects counting words.
+    pub fn words(self) -> CcWcOptions {
+        self.counter(Counter::Words)
+    }
+
+    /// Selects counting bytes.
+    pub fn bytes(self) -> CcWcOptions {
+        self.counter(Counter::Bytes)
+    }
+
+    /// Selects counting characters.
+    pub fn chars(self) -> CcWcOptions {
+        self.counter(Counter::Chars)
+    }
+
+    /// Selects counting grapheme clusters.
+    pub fn graphemes(self) -> CcWcOptions {
+        self.counter(Counter::Graphemes)
+    }
+
+    /// Selects counting distinct words.
+    pub fn unique(self) -> CcWcOptions {
+        self.counter(Counter::Unique)
+    }
+
+    /// Selects counting letters, digits, whitespace, punctuation and other characters.
+    pub fn classes(self) -> CcWcOptions {
+        Counter::CLASSES
+            .into_iter()
+            .fold(self, |options, c| options.counter(c))
+    }
+
+    /// Compares distinct words case-insensitively.
+    pub fn fold_case(mut self, fold_case: bool) -> CcWcOptions {
+        self.form.fold_case = fold_case;
+        self
+    }
+
+    /// Normalizes distinct words before comparing them.
+    pub fn normalization(mut self, normalization: Normalization) -> CcWcOptions {
+        self.form.normalization = normalization;
+        self
+    }
+
+    /// Counts only lines and words, which match the pattern.
+    pub fn pattern(mut self, pattern: Pattern) -> CcWcOptions {
+        self.pattern = Some(pattern);
+        self
+    }
+
+    /// Counts a final line without terminator as a line as well. By default only line
+    /// terminators are counted like POSIX wc does.
+    pub fn count_partial_line(mut self, count_partial_line: bool) -> CcWcOptions {
+        self.count_partial_line = count_partial_line;
+        self
+    }
+
+    /// Separates words by any Unicode whitespace (default), or by ASCII whitespace only.
+    pub fn unicode_words(mut self, unicode_words: bool) -> CcWcOptions {
+        self.unicode_words = unicode_words;
+        self
+    }
+
+    /// Sets the line terminator used for counting lines.
+    pub fn newline(mut self, newline: Newline) -> CcWcOptions {
+        self.newline = newline;
+        self
+    }
+
+    /// Sets the encoding, in which the number of bytes gets determined.
+    pub fn encoding(mut self, encoding: Encoding) -> CcWcOptions {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Selected counters, lines, words and bytes if none has been selected.
+    pub fn counters(&self) -> Vec<Counter> {
+        if self.counters.is_empty() {
+            vec![Counter::Lines, Counter::Words, Counter::Bytes]
+        } else {
+            self.counters.clone()
+        }
+    }
+
+    /// Adds the selected counters of one piece of text to `counts`. This allows counting
+    /// content, which can only be iterated once. The number of distinct words, of matching
+    /// lines and a final partial line can not be summed up piece by piece, they are left
+    /// untouched.
+    pub fn add_piece(&self, counts: &mut Counts, piece: &str) {
+        // All character classes are counted within one pass, once one of them is needed.
+        let mut classes = None;
+        let mut classes = || *classes.get_or_insert_with(|| count_classes(piece));
+        for counter in self.counters() {
+            match counter {
+                Counter::Lines if self.pattern.is_some() => {}
+                Counter::Lines => counts.lines += crate::count_lines(piece, self.newline),
+                Counter::Words => match &self.pattern {
+                    Some(pattern) => counts.words += pattern.count_words(piece, self.newline),
+                    None if self.unicode_words => {
+                        counts.words += crate::count_words(piece, self.newline)
+                    }
+                    None => counts.words += crate::count_ascii_words(piece, self.newline),
+                },
+                Counter::Bytes => counts.bytes += self.encoding.encoded_len(piece),
+                Counter::Chars => counts.chars += crate::count_chars(piece),
+                Counter::Graphemes => counts.graphemes += crate::count_graphemes(piece),
+                Counter::Unique => {}
+                Counter::Letters => counts.letters += classes().letters,
+                Counter::Digits => counts.digits += classes().digits,
+                Counter::Whitespace => counts.whitespace += classes().whitespace,
+                Counter::Punctuation => counts.punctuation += classes().punctuation,
+                Counter::Other => counts.other += classes().other,
+            }
+        }
+    }
+
+    /// Counts all selected counters of the content. The content is iterated exactly once, all
+    /// counters are accumulated simultaneously.
+    pub fn run(&self, content: &mut Content) -> Result<Counts> {
+        visitor::visit(content, self.visitor())
+    }
+
+    /// Visitor, which counts all selected counters, e.g. alongside a custom `CountVisitor`.
+    pub fn visitor(&self) -> Accumulator<'_> {
+        Accumulator::new(self)
+    }
+}
+
+/// State of counting one content piece by piece, including the counters, which can not simply be
+/// summed up per piece.
+pub struct Accumulator<'o> {
+    options: &'o CcWcOptions,
+    counts: Counts,
+    /// Distinct words so far, if requested.
+    words: Option<HashSet<String>>,
+    /// Matching lines so far, if a pattern is set and lines are requested.
+    lines: Option<MatchingLines<'o>>,
+    /// Last two characters so far, to check whether the content ends with a line terminator.
+    tail: String,
+}
+
+impl<'o> Accumulator<'o> {
+    fn new(options: &'o CcWcOptions) -> Accumulator<'o> {
+        let counters = options.counters();
+        let lines = match &options.pattern {
+            Some(pattern) if counters.contains(&Counter::Lines) => {
+                Some(MatchingLines::new(pattern, options.newline))
+            }
+            _ => None,
+        };
+        Accumulator {
+            options,
+            counts: Counts::default(),
+            words: counters.contains(&Counter::Unique).then(HashSet::new),
+            lines,
+            tail: String::new(),
+        }
+    }
+}
+
+impl CountVisitor for Accumulator<'_> {
+    type Output = Counts;
+
+    /// Adds the counters of one piece.
+    fn visit(&mut self, piece: &str) {
+        self.options.add_piece(&mut self.counts, piece);
+        if let Some(words) = &mut self.words {
+            crate::insert_words(words, piece, self.options.form, self.options.newline);
+        }
+        if let Some(lines) = &mut self.lines {
+            lines.add(piece);
+        }
+        if self.options.count_partial_line && !piece.is_empty() {
+            // A piece might consist of a single character, so the tail spans several pieces.
+            self.tail.push_str(piece);
+            let start = self.tail.char_indices().rev().nth(1).map_or(0, |(i, _)| i);
+            self.tail.drain(..start);
+        }
+    }
+
+    /// Counts of all pieces.
+    fn finish(mut self) -> Counts {
+        if let Some(words) = self.words {
+            self.counts.unique = words.len();
+        }
+        if let Some(lines) = self.lines {
+            self.counts.lines = lines.finish();
+        } else if self.options.counters().contains(&Counter::Lines)
+            && !self.tail.is_empty()
+            && self.options.newline.terminator_len(&self.tail) == 0
+        {
+            self.counts.lines += 1;
+        }
+        self.counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder() {
+        let text = "one\u{a0}two three\r\n";
+        let mut content = Content::SmallFile(String::from(text), true);
+        let counts = CcWcOptions::new()
+            .lines()
+            .words()
+            .unicode_words(false)
+            .run(&mut content)
+            .unwrap();
+        assert_eq!(
+            counts,
+            Counts {
+                lines: 1,
+                words: 2,
+                ..Default::default()
+            }
+        );
+
+        let counts = CcWcOptions::new().run(&mut content).unwrap();
+        assert_eq!((counts.lines, counts.words, counts.bytes), (1, 3, 16));
+
+        let options = CcWcOptions::new().chars().newline(Newline::Crlf).chars();
+        assert_eq!(options.counters(), vec![Counter::Chars]);
+
+        let mut counts = Counts::default();
+        options.add_piece(&mut counts, "ab ");
+        options.add_piece(&mut counts, "c\r\n");
+        assert_eq!(
+            counts + counts,
+            Counts {
+                chars: 12,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn partial_line() {
+        let options = CcWcOptions::new().lines();
+        assert_eq!(crate::count_str("a\n\nb", &options).lines, 2);
+        assert_eq!(crate::count_str("\n\n", &options).lines, 2);
+
+        let options = options.count_partial_line(true);
+        assert_eq!(crate::count_str("a\n\nb", &options).lines, 3);
+        assert_eq!(crate::count_str("a\n", &options).lines, 1);
+        assert_eq!(crate::count_str("", &options).lines, 0);
+
+        let options = options.newline(Newline::Crlf);
+        let mut accumulator = options.visitor();
+        accumulator.visit("a\r\n");
+        accumulator.visit("b\r");
+        assert_eq!(accumulator.finish().lines, 2);
+    }
+}
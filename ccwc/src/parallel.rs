@@ -0,0 +1,161 @@
+//! Module encapsules parallel counting of huge files, whose byte ranges are counted on a thread
+//! pool.
+
+use crate::{
+    encoding::{self, Encoding, Invalid},
+    iterators::ChunkIterator,
+    options::{CcWcOptions, Counts},
+};
+use rayon::prelude::*;
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    ops::Range,
+};
+
+/// Minimum number of bytes of one range, smaller files are not split at all.
+pub const MIN_RANGE_SIZE: u64 = 1 << 24;
+
+/// Checks whether a file is worth to be counted in parallel. This requires a large regular file
+/// and an encoding, in which an ASCII whitespace byte is always a whitespace character.
+pub fn is_suitable(file: &str, encoding: Encoding) -> io::Result<bool> {
+    let metadata = fs::metadata(file)?;
+    Ok(metadata.is_file()
+        && metadata.len() >= 2 * MIN_RANGE_SIZE
+        && matches!(encoding, Encoding::Utf8 | Encoding::Latin1))
+}
+
+/// Counts the file by splitting it into one range per thread of the thread pool.
+pub fn count_file(
+    file: &str,
+    options: &CcWcOptions,
+    encoding: Encoding,
+    invalid: Invalid,
+) -> io::Result<Counts> {
+    let size = fs::metadata(file)?.len();
+    let threads = rayon::current_num_threads() as u64;
+    let ranges = split_ranges(file, size.div_ceil(threads).max(MIN_RANGE_SIZE))?;
+    count_ranges(file, &ranges, options, encoding, invalid)
+}
+
+/// Splits the file into ranges of about `range_size` bytes. Every range ends behind an ASCII
+/// whitespace other than a carriage return, so that no character, word or line is broken between
+/// two ranges. A range without any whitespace gets merged with the following one.
+pub fn split_ranges(file: &str, range_size: u64) -> io::Result<Vec<Range<u64>>> {
+    let size = fs::metadata(file)?.len();
+    let mut reader = BufReader::new(fs::File::open(file)?);
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while start < size {
+        let end = match start.checked_add(range_size) {
+            Some(nominal) if nominal < size => find_boundary(&mut reader, nominal)?.min(size),
+            _ => size,
+        };
+        ranges.push(start..end);
+        start = end;
+    }
+    Ok(ranges)
+}
+
+/// Position behind the first ASCII whitespace at or after `from`, the end of the file if there is
+/// none. A carriage return is skipped, since it could be followed by a line feed.
+fn find_boundary(reader: &mut BufReader<fs::File>, from: u64) -> io::Result<u64> {
+    let mut position = reader.seek(SeekFrom::Start(from))?;
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(position);
+        }
+        if let Some(i) = buf
+            .iter()
+            .position(|b| b.is_ascii_whitespace() && *b != b'\r')
+        {
+            return Ok(position + i as u64 + 1);
+        }
+        let n = buf.len();
+        reader.consume(n);
+        position += n as u64;
+    }
+}
+
+/// Counts all ranges of the file in parallel and merges their counts.
+pub fn count_ranges(
+    file: &str,
+    ranges: &[Range<u64>],
+    options: &CcWcOptions,
+    encoding: Encoding,
+    invalid: Invalid,
+) -> io::Result<Counts> {
+    ranges
+        .par_iter()
+        .map(|range| count_range(file, range, options, encoding, invalid))
+        .try_reduce(Counts::default, |a, b| Ok(a + b))
+}
+
+fn count_range(
+    file: &str,
+    range: &Range<u64>,
+    options: &CcWcOptions,
+    encoding: Encoding,
+    invalid: Invalid,
+) -> io::Result<Counts> {
+    let mut file = fs::File::open(file)?;
+    file.seek(SeekFrom::Start(range.start))?;
+    let reader = BufReader::new(file).take(range.end - range.start);
+    let mut chunks = ChunkIterator::new(reader, encoding).with_invalid(invalid);
+    let mut counts = Counts::default();
+    for piece in &mut chunks {
+        options.add_piece(&mut counts, &piece);
+    }
+    match chunks.take_error() {
+        Some(err) => Err(encoding::shift_offset(err, range.start)),
+        None => Ok(counts),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_end_behind_whitespace() {
+        let text = fs::read("test.txt").unwrap();
+        let ranges = split_ranges("test.txt", 10_000).unwrap();
+        assert!(ranges.len() > 30);
+        assert_eq!(ranges.last().unwrap().end, text.len() as u64);
+        for range in &ranges[..ranges.len() - 1] {
+            let last = text[range.end as usize - 1];
+            assert!(last.is_ascii_whitespace() && last != b'\r');
+        }
+    }
+
+    #[test]
+    fn count_in_parallel() {
+        let options = CcWcOptions::new()
+            .lines()
+            .words()
+            .bytes()
+            .chars()
+            .graphemes();
+        let ranges = split_ranges("test.txt", 10_000).unwrap();
+        let counts = count_ranges(
+            "test.txt",
+            &ranges,
+            &options,
+            Encoding::Utf8,
+            Invalid::Strict,
+        )
+        .unwrap();
+        assert_eq!(
+            counts,
+            Counts {
+                lines: 7145,
+                words: 58164,
+                bytes: 342190,
+                chars: 339292,
+                graphemes: 332147,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! Module encapsules counting of lines and words, which match a regular expression, like a
+//! built-in `grep -c`.
+
+use crate::iterators::{self, LineIterator, Newline};
+use regex::Regex;
+use std::str::FromStr;
+
+/// Regular expression, which selects the counted lines and words.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    regex: Regex,
+}
+
+impl Pattern {
+    /// Checks whether the text contains a match.
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+
+    /// Number of words of the piece, which contain a match. Words are also separated by the line
+    /// terminators of `newline`.
+    pub fn count_words(&self, piece: &str, newline: Newline) -> usize {
+        iterators::words_with(piece, newline)
+            .filter(|word| self.is_match(word))
+            .count()
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(pattern: &str) -> Result<Pattern, String> {
+        let regex = Regex::new(pattern).map_err(|err| err.to_string())?;
+        Ok(Pattern { regex })
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Pattern) -> bool {
+        self.regex.as_str() == other.regex.as_str()
+    }
+}
+
+impl Eq for Pattern {}
+
+/// Counter of matching lines of a text, which arrives piece by piece. Lines can span several
+/// pieces, so an incomplete line is kept until its terminator arrives.
+#[derive(Debug)]
+pub struct MatchingLines<'p> {
+    pattern: &'p Pattern,
+    newline: Newline,
+    /// Beginning of a line, which has not been terminated so far.
+    pending: String,
+    count: usize,
+}
+
+impl<'p> MatchingLines<'p> {
+    /// New type pattern: generates a new counter of matching lines.
+    pub fn new(pattern: &'p Pattern, newline: Newline) -> MatchingLines<'p> {
+        MatchingLines {
+            pattern,
+            newline,
+            pending: String::new(),
+            count: 0,
+        }
+    }
+
+    /// Counts all complete lines of the piece.
+    pub fn add(&mut self, piece: &str) {
+        let mut text = std::mem::take(&mut self.pending);
+        text.push_str(piece);
+        for line in LineIterator::new(&text, self.newline) {
+            let len = self.newline.terminator_len(line);
+            if len == 0 {
+                self.pending = line.to_string();
+            } else if self.pattern.is_match(&line[..line.len() - len]) {
+                self.count += 1;
+            }
+        }
+    }
+
+    /// Number of matching lines, including a final line without terminator like grep does.
+    pub fn finish(self) -> usize {
+        let last = !self.pending.is_empty() && self.pattern.is_match(&self.pending);
+        self.count + last as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matching_lines_and_words() {
+        let pattern: Pattern = "^a|c$".parse().unwrap();
+        let mut lines = MatchingLines::new(&pattern, Newline::Lf);
+        lines.add("ab\nb");
+        lines.add("c\nbd\n");
+        lines.add("a");
+        assert_eq!(lines.finish(), 3);
+
+        assert_eq!(pattern.count_words("abc bd ac", Newline::Lf), 2);
+        assert!("(unclosed".parse::<Pattern>().is_err());
+    }
+}
@@ -0,0 +1,74 @@
+//! Module encapsules a simple progress bar on stderr, for processing large files.
+
+use std::io::{self, Write};
+
+/// Number of characters of the bar itself.
+const BAR_WIDTH: usize = 30;
+
+/// Progress bar, which shows the number of processed bytes of the total size on stderr.
+#[derive(Clone, Debug)]
+pub struct ProgressBar {
+    /// Total number of bytes to be processed.
+    total: u64,
+    /// Last drawn percentage, `None` if nothing is drawn currently.
+    drawn: Option<u64>,
+}
+
+impl ProgressBar {
+    /// New type pattern: generates a new progress bar for the given total number of bytes.
+    pub fn new(total: u64) -> ProgressBar {
+        ProgressBar { total, drawn: None }
+    }
+
+    /// Updates the bar with the number of processed bytes. It will only be redrawn, if the
+    /// percentage changed.
+    pub fn update(&mut self, done: u64) {
+        let percent = percentage(done, self.total);
+        if self.drawn != Some(percent) {
+            self.drawn = Some(percent);
+            let mut stderr = io::stderr().lock();
+            let _ = write!(stderr, "\r{}", render(done, self.total));
+            let _ = stderr.flush();
+        }
+    }
+
+    /// Removes the bar from the terminal.
+    pub fn finish(&mut self) {
+        if self.drawn.take().is_some() {
+            let blank = " ".repeat(render(self.total, self.total).len());
+            let mut stderr = io::stderr().lock();
+            let _ = write!(stderr, "\r{blank}\r");
+            let _ = stderr.flush();
+        }
+    }
+}
+
+fn percentage(done: u64, total: u64) -> u64 {
+    (done.min(total) * 100).checked_div(total).unwrap_or(100)
+}
+
+/// Renders one line of the progress bar, e.g. `[#######-------] 50% (1024/2048 bytes)`.
+fn render(done: u64, total: u64) -> String {
+    let percent = percentage(done, total);
+    let filled = percent as usize * BAR_WIDTH / 100;
+    format!(
+        "[{}{}] {percent:>3}% ({done}/{total} bytes)",
+        "#".repeat(filled),
+        "-".repeat(BAR_WIDTH - filled)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_bar() {
+        assert_eq!(
+            render(512, 1024),
+            "[###############---------------]  50% (512/1024 bytes)"
+        );
+        assert_eq!(percentage(0, 0), 100);
+        assert_eq!(percentage(2048, 1024), 100);
+    }
+}
@@ -0,0 +1,147 @@
+//! Module encapsules statistics about the lines and words of a text.
+
+use crate::{
+    command::Content,
+    iterators::{self, LineIterator, Newline},
+};
+use std::collections::BTreeMap;
+
+/// Statistics about the lines and words of a text, collected in one streaming pass. Line lengths
+/// are measured in characters, without the line terminator.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextStats {
+    /// Definition of line terminators.
+    newline: Newline,
+    /// Number of lines, including an unterminated last line.
+    pub lines: usize,
+    /// Number of words.
+    pub words: usize,
+    /// Sum of the lengths of all lines.
+    pub line_chars: usize,
+    /// Sum of the lengths of all words.
+    pub word_chars: usize,
+    /// Length of the longest line.
+    pub longest_line: Option<usize>,
+    /// Length of the shortest line.
+    pub shortest_line: Option<usize>,
+    /// Number of lines per number of words in a line.
+    pub words_per_line: BTreeMap<usize, usize>,
+    /// Length and number of words of the current line, which may be continued by the next piece.
+    partial: Option<(usize, usize)>,
+}
+
+impl TextStats {
+    /// New type pattern: generates new and empty statistics.
+    pub fn new(newline: Newline) -> TextStats {
+        TextStats {
+            newline,
+            ..Default::default()
+        }
+    }
+
+    /// Collects the statistics of the content within a single pass.
+    pub fn from_content(content: &mut Content, newline: Newline) -> crate::Result<TextStats> {
+        let mut stats = TextStats::new(newline);
+        stats.add_content(content)?;
+        Ok(stats)
+    }
+
+    /// Adds the statistics of the content within a single pass.
+    pub fn add_content(&mut self, content: &mut Content) -> crate::Result<()> {
+        content.rewind()?;
+        for piece in &mut *content {
+            self.add(&piece);
+        }
+        self.finish();
+        match content.take_error() {
+            Some(err) => Err(err.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds the next piece of text. A line may be continued by the following piece, until
+    /// `finish()` gets called.
+    pub fn add(&mut self, piece: &str) {
+        for line in LineIterator::new(piece, self.newline) {
+            let terminator = self.newline.terminator_len(line);
+            let text = &line[..line.len() - terminator];
+            let (chars, words) = self.partial.get_or_insert((0, 0));
+            *chars += text.chars().count();
+            for word in iterators::words(text) {
+                *words += 1;
+                self.word_chars += word.chars().count();
+            }
+            if terminator > 0 {
+                self.end_line();
+            }
+        }
+    }
+
+    /// Completes the statistics, an unterminated last line gets counted as well.
+    pub fn finish(&mut self) {
+        if self.partial.is_some() {
+            self.end_line();
+        }
+    }
+
+    fn end_line(&mut self) {
+        let (chars, words) = self.partial.take().unwrap_or_default();
+        self.lines += 1;
+        self.words += words;
+        self.line_chars += chars;
+        self.longest_line = Some(self.longest_line.map_or(chars, |l| l.max(chars)));
+        self.shortest_line = Some(self.shortest_line.map_or(chars, |l| l.min(chars)));
+        *self.words_per_line.entry(words).or_insert(0) += 1;
+    }
+
+    /// Average length of a word in characters.
+    pub fn average_word_length(&self) -> f64 {
+        average(self.word_chars, self.words)
+    }
+
+    /// Average length of a line in characters.
+    pub fn average_line_length(&self) -> f64 {
+        average(self.line_chars, self.lines)
+    }
+}
+
+fn average(sum: usize, n: usize) -> f64 {
+    if n == 0 {
+        0.0
+    } else {
+        sum as f64 / n as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_of_pieces() {
+        let mut stats = TextStats::new(Newline::Lf);
+        stats.add("one two\nthree ");
+        stats.add("four five\n\nsix");
+        stats.finish();
+
+        assert_eq!(stats.lines, 4);
+        assert_eq!(stats.words, 6);
+        assert_eq!(stats.longest_line, Some(15));
+        assert_eq!(stats.shortest_line, Some(0));
+        assert_eq!(stats.line_chars, 25);
+        assert_eq!(stats.average_word_length(), 22.0 / 6.0);
+        assert_eq!(
+            stats.words_per_line,
+            BTreeMap::from([(0, 1), (1, 1), (2, 1), (3, 1)])
+        );
+    }
+
+    #[test]
+    fn stats_of_empty_content() {
+        let mut content = Content::SmallFile(String::new(), true);
+        let stats = TextStats::from_content(&mut content, Newline::Crlf).unwrap();
+        assert_eq!(stats.lines, 0);
+        assert_eq!(stats.longest_line, None);
+        assert_eq!(stats.average_line_length(), 0.0);
+    }
+}
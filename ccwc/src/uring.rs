@@ -0,0 +1,267 @@
+//! Module encapsules reading huge files via io_uring on Linux. Several blocks of the file are
+//! read ahead in parallel by the kernel, while the already completed blocks are being counted.
+
+use io_uring::{opcode, types, IoUring};
+use std::{
+    fs,
+    io::{self, Read, Seek, SeekFrom},
+    os::unix::{fs::FileExt, io::AsRawFd},
+};
+
+/// Number of blocks, which are read ahead at once.
+pub const QUEUE_DEPTH: usize = 4;
+/// Number of bytes of one block.
+pub const BLOCK_SIZE: usize = 1 << 20;
+
+/// State of the read of one block.
+#[derive(Debug)]
+enum State {
+    /// No read is pending, the end of the file has been reached.
+    Idle,
+    /// The read has been submitted and not yet completed.
+    InFlight,
+    /// The read has completed successfully.
+    Done,
+    /// The read has failed.
+    Failed(io::Error),
+}
+
+/// One buffer, into which a block of the file gets read.
+struct Block {
+    /// Buffer, which must neither be moved nor freed, while a read is in flight.
+    data: Box<[u8]>,
+    /// Position of the block in the file.
+    offset: u64,
+    /// Number of bytes of the block.
+    len: usize,
+    /// Number of bytes, which have already been passed to the reader.
+    pos: usize,
+    state: State,
+}
+
+/// Reader, which reads a file block by block via io_uring. Seeking is only supported to the
+/// beginning of the file, which restarts reading ahead.
+pub struct UringFile {
+    file: fs::File,
+    size: u64,
+    ring: IoUring,
+    /// Number of bytes of one block.
+    block_size: usize,
+    /// Blocks in a ring, starting at `front` in order of their offsets.
+    blocks: Vec<Block>,
+    /// Index of the block, which is passed to the reader next.
+    front: usize,
+    /// Offset of the next block, which gets submitted.
+    next_offset: u64,
+    /// Number of submitted, but not yet completed reads.
+    in_flight: usize,
+}
+
+impl UringFile {
+    /// Opens a file and starts reading ahead its first blocks.
+    pub fn open(file: &str) -> io::Result<UringFile> {
+        UringFile::with_block_size(file, BLOCK_SIZE)
+    }
+
+    /// Like `open()`, but with a custom number of bytes per block.
+    pub fn with_block_size(file: &str, block_size: usize) -> io::Result<UringFile> {
+        let file = fs::File::open(file)?;
+        let size = file.metadata()?.len();
+        let blocks = (0..QUEUE_DEPTH)
+            .map(|_| Block {
+                data: vec![0; block_size].into_boxed_slice(),
+                offset: 0,
+                len: 0,
+                pos: 0,
+                state: State::Idle,
+            })
+            .collect();
+        let mut uring = UringFile {
+            file,
+            size,
+            ring: IoUring::new(QUEUE_DEPTH as u32)?,
+            block_size,
+            blocks,
+            front: 0,
+            next_offset: 0,
+            in_flight: 0,
+        };
+        uring.start()?;
+        Ok(uring)
+    }
+
+    /// Submits reading the first blocks of the file into all buffers.
+    fn start(&mut self) -> io::Result<()> {
+        self.front = 0;
+        self.next_offset = 0;
+        for i in 0..QUEUE_DEPTH {
+            self.submit(i)?;
+        }
+        Ok(())
+    }
+
+    /// Submits reading the next block of the file into the given buffer, unless the end of the
+    /// file has been reached.
+    fn submit(&mut self, i: usize) -> io::Result<()> {
+        let block = &mut self.blocks[i];
+        block.pos = 0;
+        if self.next_offset >= self.size {
+            block.state = State::Idle;
+            return Ok(());
+        }
+        block.offset = self.next_offset;
+        block.len = (self.size - self.next_offset).min(self.block_size as u64) as usize;
+        self.next_offset += block.len as u64;
+
+        let entry = opcode::Read::new(
+            types::Fd(self.file.as_raw_fd()),
+            block.data.as_mut_ptr(),
+            block.len as u32,
+        )
+        .offset(block.offset)
+        .build()
+        .user_data(i as u64);
+        // Safety: the buffer of the block stays valid until the read has completed, since it is
+        // neither resubmitted nor dropped while it is in flight.
+        unsafe {
+            self.ring
+                .submission()
+                .push(&entry)
+                .map_err(|_| io::Error::other("io_uring submission queue is full"))?;
+        }
+        block.state = State::InFlight;
+        self.in_flight += 1;
+        self.ring.submit()?;
+        Ok(())
+    }
+
+    /// Waits for at least one read to complete and updates the states of the completed blocks.
+    /// A short read gets completed synchronously. Errors of single reads are kept in the state
+    /// of their block, so that the number of reads in flight stays correct.
+    fn wait(&mut self) -> io::Result<()> {
+        self.ring.submit_and_wait(1)?;
+        let completions: Vec<(u64, i32)> = self
+            .ring
+            .completion()
+            .map(|cqe| (cqe.user_data(), cqe.result()))
+            .collect();
+        for (i, result) in completions {
+            self.in_flight -= 1;
+            let block = &mut self.blocks[i as usize];
+            let read = result as usize;
+            block.state = if result < 0 {
+                State::Failed(io::Error::from_raw_os_error(-result))
+            } else if read < block.len {
+                let rest = &mut block.data[read..block.len];
+                match self.file.read_exact_at(rest, block.offset + read as u64) {
+                    Ok(()) => State::Done,
+                    Err(err) => State::Failed(err),
+                }
+            } else {
+                State::Done
+            };
+        }
+        Ok(())
+    }
+
+    /// Waits until no read is in flight anymore.
+    fn drain(&mut self) -> io::Result<()> {
+        while self.in_flight > 0 {
+            match self.wait() {
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for UringFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UringFile")
+            .field("size", &self.size)
+            .field("in_flight", &self.in_flight)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Read for UringFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let block = &mut self.blocks[self.front];
+            match block.state {
+                // Blocks are submitted in order, so all following blocks are idle as well.
+                State::Idle => return Ok(0),
+                State::InFlight => self.wait()?,
+                State::Failed(_) => {
+                    // The error is reported once, afterwards the reader is at its end.
+                    if let State::Failed(err) = std::mem::replace(&mut block.state, State::Idle) {
+                        return Err(err);
+                    }
+                }
+                State::Done if block.pos < block.len => {
+                    let n = buf.len().min(block.len - block.pos);
+                    buf[..n].copy_from_slice(&block.data[block.pos..block.pos + n]);
+                    block.pos += n;
+                    return Ok(n);
+                }
+                State::Done => {
+                    // The block has been passed completely, its buffer reads ahead again.
+                    let i = self.front;
+                    self.front = (i + 1) % QUEUE_DEPTH;
+                    self.submit(i)?;
+                }
+            }
+        }
+    }
+}
+
+impl Seek for UringFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        if pos != SeekFrom::Start(0) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "io_uring input can only be rewound to its beginning",
+            ));
+        }
+        self.drain()?;
+        self.size = self.file.metadata()?.len();
+        self.start()?;
+        Ok(0)
+    }
+}
+
+impl Drop for UringFile {
+    fn drop(&mut self) {
+        if self.drain().is_err() {
+            // The kernel might still write into the buffers, so they must not be freed.
+            std::mem::forget(std::mem::take(&mut self.blocks));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_and_rewind() {
+        let expected = fs::read("test.txt").unwrap();
+        // Small blocks, so that the buffers are reused several times.
+        let mut file = match UringFile::with_block_size("test.txt", 10_000) {
+            Ok(file) => file,
+            // io_uring may be disabled by the kernel or a sandbox.
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return,
+            Err(err) => panic!("{err}"),
+        };
+        let mut text = Vec::new();
+        file.read_to_end(&mut text).unwrap();
+        assert_eq!(text, expected);
+
+        file.rewind().unwrap();
+        let mut start = [0; 3];
+        file.read_exact(&mut start).unwrap();
+        assert_eq!(start, expected[..3]);
+        assert!(file.seek(SeekFrom::Start(1)).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+//! Module encapsules the comparison of the counts with the system's wc, which turns ccwc into its
+//! own conformance harness when investigating counting differences.
+
+use crate::{command::STDIN, format::Row, Counter, FileError, Result};
+use std::{collections::HashMap, process::Command};
+
+/// Name of the system's wc executable.
+pub const WC: &str = "wc";
+
+/// Option of the system's wc, which outputs the given counter, if there is one.
+fn wc_option(counter: Counter) -> Option<&'static str> {
+    match counter {
+        Counter::Lines => Some("-l"),
+        Counter::Words => Some("-w"),
+        Counter::Bytes => Some("-c"),
+        Counter::Chars => Some("-m"),
+        _ => None,
+    }
+}
+
+/// Counts of one counter by the system's wc, per file.
+fn run_wc(option: &str, files: &[&str]) -> Result<HashMap<String, usize>> {
+    let output = Command::new(WC)
+        .arg(option)
+        .arg("--")
+        .args(files)
+        .output()
+        .map_err(|err| format!("running the system's {WC} failed: {err}"))?;
+    // wc fails for unreadable files, but still prints the counts of all other files.
+    Ok(parse_wc(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses lines like `  7145 test.txt` of wc's output.
+fn parse_wc(output: &str) -> HashMap<String, usize> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (value, file) = line.trim_start().split_once(' ')?;
+            Some((file.to_string(), value.parse().ok()?))
+        })
+        .collect()
+}
+
+/// Compares the counts of all file rows with the system's wc, and describes every discrepancy.
+/// Counters, which wc does not support, are not compared, neither is stdin, which has been read.
+pub fn verify(counters: &[Counter], rows: &[Row]) -> Result<Vec<FileError>> {
+    let files: Vec<&str> = rows
+        .iter()
+        .filter_map(|row| row.file.as_deref())
+        .filter(|file| *file != STDIN)
+        .collect();
+    let mut discrepancies = Vec::new();
+    if files.is_empty() {
+        return Ok(discrepancies);
+    }
+    for (i, counter) in counters.iter().enumerate() {
+        let Some(option) = wc_option(*counter) else {
+            continue;
+        };
+        let expected = run_wc(option, &files)?;
+        for row in rows {
+            let Some(file) = row.file.as_ref().filter(|file| *file != STDIN) else {
+                continue;
+            };
+            match expected.get(file) {
+                Some(value) if *value == row.values[i] => {}
+                Some(value) => discrepancies.push(FileError {
+                    file: file.clone(),
+                    error: format!(
+                        "{} differ: ccwc {}, {WC} {value}",
+                        counter.name(),
+                        row.values[i]
+                    )
+                    .into(),
+                }),
+                None => discrepancies.push(FileError {
+                    file: file.clone(),
+                    error: format!("{} not reported by {WC}", counter.name()).into(),
+                }),
+            }
+        }
+    }
+    Ok(discrepancies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_output() {
+        let counts = parse_wc("  7145 test.txt\n    12 a file.txt\n  7157 total\n");
+        assert_eq!(counts.get("test.txt"), Some(&7145));
+        assert_eq!(counts.get("a file.txt"), Some(&12));
+    }
+
+    #[test]
+    fn compare_with_wc() {
+        if Command::new(WC).arg("--version").output().is_err() {
+            return;
+        }
+        let counters = [Counter::Lines, Counter::Words, Counter::Graphemes];
+        let rows = [Row::new(vec![7145, 1, 1], Some(String::from("test.txt")))];
+        let discrepancies = verify(&counters, &rows).unwrap();
+        assert_eq!(discrepancies.len(), 1);
+        assert!(discrepancies[0]
+            .error
+            .to_string()
+            .starts_with("words differ"));
+    }
+}
@@ -0,0 +1,85 @@
+//! Module encapsules the callback API over the pieces of a content, so that custom metrics can be
+//! counted by the same streaming machinery as the built-in counters.
+
+use crate::{command::Content, Result};
+
+/// Metric, which is counted piece by piece. Pieces end behind a line break or a whitespace, so
+/// lines and words are never split between two pieces.
+pub trait CountVisitor {
+    /// Result of the metric.
+    type Output;
+
+    /// Counts one piece of the content.
+    fn visit(&mut self, piece: &str);
+
+    /// Result after all pieces have been visited.
+    fn finish(self) -> Self::Output;
+}
+
+/// Two metrics are counted within one pass over the content.
+impl<A: CountVisitor, B: CountVisitor> CountVisitor for (A, B) {
+    type Output = (A::Output, B::Output);
+
+    fn visit(&mut self, piece: &str) {
+        self.0.visit(piece);
+        self.1.visit(piece);
+    }
+
+    fn finish(self) -> Self::Output {
+        (self.0.finish(), self.1.finish())
+    }
+}
+
+/// Calls `f` for every piece of the content, beginning at its start.
+pub fn for_each_piece(content: &mut Content, mut f: impl FnMut(&str)) -> Result<()> {
+    content.rewind()?;
+    for piece in &mut *content {
+        f(&piece);
+    }
+    match content.take_error() {
+        Some(err) => Err(err.into()),
+        None => Ok(()),
+    }
+}
+
+/// Passes all pieces of the content to the visitor and returns its result.
+pub fn visit<V: CountVisitor>(content: &mut Content, mut visitor: V) -> Result<V::Output> {
+    for_each_piece(content, |piece| visitor.visit(piece))?;
+    Ok(visitor.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CcWcOptions, Counts};
+
+    /// Counts the vowels of a text, as an example of a custom metric.
+    struct Vowels(usize);
+
+    impl CountVisitor for Vowels {
+        type Output = usize;
+
+        fn visit(&mut self, piece: &str) {
+            self.0 += piece.chars().filter(|c| "aeiou".contains(*c)).count();
+        }
+
+        fn finish(self) -> usize {
+            self.0
+        }
+    }
+
+    #[test]
+    fn custom_metric() {
+        let mut content = Content::from_string(String::from("one two\nthree\n"));
+        assert_eq!(visit(&mut content, Vowels(0)).unwrap(), 5);
+
+        let options = CcWcOptions::new().lines().words();
+        let visitor = (Vowels(0), options.visitor());
+        let (vowels, counts): (usize, Counts) = visit(&mut content, visitor).unwrap();
+        assert_eq!((vowels, counts.lines, counts.words), (5, 2, 3));
+
+        let mut pieces = 0;
+        for_each_piece(&mut content, |_| pieces += 1).unwrap();
+        assert_eq!(pieces, 1);
+    }
+}
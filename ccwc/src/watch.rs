@@ -0,0 +1,72 @@
+//! Module encapsules the watch mode, which counts the input files again whenever they change on
+//! disk.
+
+use crate::{CcWcInput, CcWcOutput, Result};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::{
+    path::{self, Path, PathBuf},
+    sync::mpsc,
+    time::Duration,
+};
+
+/// Time to wait for further events after a change, so that one save results in one report.
+pub const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Checks whether the event is a change of one of the watched files.
+fn is_relevant(event: &Event, files: &[PathBuf]) -> bool {
+    matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+    ) && event.paths.iter().any(|p| files.contains(p))
+}
+
+/// Counts all input files of `input` and reports the result initially and after every change of
+/// one of the files. The directories of the files are watched, since editors often replace a file
+/// when saving it. This function only returns in case of an error.
+pub fn watch(input: &mut CcWcInput, mut report: impl FnMut(&CcWcOutput)) -> Result<()> {
+    if input.content.is_some() || input.args.files.is_empty() {
+        return Err("watch mode requires input files".into());
+    }
+    let files = input
+        .args
+        .files
+        .iter()
+        .map(path::absolute)
+        .collect::<std::io::Result<Vec<PathBuf>>>()?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    let mut dirs: Vec<&Path> = files.iter().filter_map(|f| f.parent()).collect();
+    dirs.dedup();
+    for dir in dirs {
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    }
+
+    report(&crate::ccwc(input)?);
+    loop {
+        let event = rx.recv()??;
+        if !is_relevant(&event, &files) {
+            continue;
+        }
+        while rx.recv_timeout(DEBOUNCE).is_ok() {}
+        report(&crate::ccwc(input)?);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, ModifyKind};
+
+    #[test]
+    fn relevant_events() {
+        let files = vec![PathBuf::from("/tmp/a.txt")];
+        let event = |kind, path: &str| Event::new(kind).add_path(PathBuf::from(path));
+
+        let modify = EventKind::Modify(ModifyKind::Any);
+        assert!(is_relevant(&event(modify, "/tmp/a.txt"), &files));
+        assert!(!is_relevant(&event(modify, "/tmp/b.txt"), &files));
+        let access = EventKind::Access(AccessKind::Any);
+        assert!(!is_relevant(&event(access, "/tmp/a.txt"), &files));
+    }
+}
@@ -161,6 +161,7 @@ fn string_to_invalid_data_err(s: String) -> io::Error {
     io::Error::new(io::ErrorKind::InvalidData, err)
 }
 
+#[allow(dead_code)]
 #[derive(Clone, Debug)]
 pub enum ScInformational {
     Continue,